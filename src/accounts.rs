@@ -0,0 +1,198 @@
+//! Human-readable names for AWS account IDs: nicknames from the `[accounts]`
+//! table of the settings file, and each account's IAM alias, looked up with
+//! `iam:ListAccountAliases` through one of its profiles and cached for
+//! [`CACHE_TTL`].
+
+use crate::aws::{get_account_alias, load_sdk_config};
+use crate::cache::Cache;
+use crate::check::{DEFAULT_CONCURRENCY, DEFAULT_TIMEOUT};
+use crate::config::{AwsConfig, ConfigPaths, Profile};
+use crate::environment::now;
+use crate::tasks::{Background, Update, run_bounded};
+use crate::ui::account_groups;
+use anyhow::Result;
+use chrono::TimeDelta;
+use regex::Regex;
+use serde::Serialize;
+use std::collections::BTreeMap;
+use std::sync::LazyLock;
+
+const CACHE_NAME: &str = "account-aliases";
+pub const CACHE_TTL: TimeDelta = TimeDelta::days(1);
+
+/// Account ID → name.
+pub type AccountNames = BTreeMap<String, String>;
+
+/// The name of the profile's account, if `account_names` has one.
+pub fn account_name<'a>(account_names: &'a AccountNames, profile: &Profile) -> Option<&'a str> {
+    let account_id = profile.get_account_id()?;
+    account_names.get(account_id).map(String::as_str)
+}
+
+/// `payments-prod (123456789012)`, or just the ID when there is no name.
+pub fn describe_account(account_id: &str, account_name: Option<&str>) -> String {
+    match account_name {
+        Some(account_name) => format!("{account_name} ({account_id})"),
+        None => account_id.to_string(),
+    }
+}
+
+/// Masks every account ID in `text` but for its last four digits
+/// (`********9012`), ARNs included.
+pub fn redact(text: &str) -> String {
+    static ACCOUNT_ID: LazyLock<Regex> =
+        LazyLock::new(|| Regex::new(r"\b\d{8}(\d{4})\b").expect("valid regex"));
+    ACCOUNT_ID.replace_all(text, "********$1").into_owned()
+}
+
+/// `value` for structured output, with [`redact`] applied to every string.
+pub fn redacted<T: Serialize>(value: &T) -> Result<serde_json::Value> {
+    let mut value = serde_json::to_value(value)?;
+    redact_json(&mut value);
+    Ok(value)
+}
+
+fn redact_json(value: &mut serde_json::Value) {
+    match value {
+        serde_json::Value::String(text) => *text = redact(text),
+        serde_json::Value::Array(values) => values.iter_mut().for_each(redact_json),
+        serde_json::Value::Object(map) => map.values_mut().for_each(redact_json),
+        _ => {}
+    }
+}
+
+/// An account whose alias isn't cached, with the profiles that could look it
+/// up, in config order.
+#[derive(Debug, Clone)]
+pub struct Unresolved {
+    pub account_id: String,
+    pub profile_names: Vec<String>,
+}
+
+/// The cached aliases of the accounts in `config`, and the accounts still to
+/// be looked up. Accounts known to have no alias are in neither, and those
+/// in `nicknames` are skipped.
+pub fn cached_aliases(
+    config: &AwsConfig,
+    nicknames: &AccountNames,
+) -> Result<(AccountNames, Vec<Unresolved>)> {
+    let cache: Cache<Option<String>> = Cache::load(CACHE_NAME, CACHE_TTL)?;
+    let now = now();
+    let mut names = AccountNames::new();
+    let mut unresolved = Vec::new();
+
+    for group in account_groups(config) {
+        let Some(account_id) = group.account_id.filter(|id| !nicknames.contains_key(*id)) else {
+            continue;
+        };
+        match cache.get(account_id, now) {
+            Some(Some(alias)) => {
+                names.insert(account_id.to_string(), alias.clone());
+            }
+            Some(None) => {}
+            None => unresolved.push(Unresolved {
+                account_id: account_id.to_string(),
+                profile_names: group
+                    .profiles
+                    .iter()
+                    .map(|profile| profile.name.clone())
+                    .collect(),
+            }),
+        }
+    }
+
+    Ok((names, unresolved))
+}
+
+/// Starts the lookups on `background`, which reports each alias as an
+/// [`Update::AccountAlias`].
+pub fn spawn_lookups(
+    background: &Background,
+    paths: &ConfigPaths,
+    accounts: Vec<Unresolved>,
+) -> Result<()> {
+    let paths = paths.clone();
+
+    background.spawn(|sender| async move {
+        let _ = run_lookups(&paths, accounts, |account_id, alias| {
+            let _ = sender.send(Update::AccountAlias {
+                account_id: account_id.to_string(),
+                alias: alias.to_string(),
+            });
+        })
+        .await;
+    })
+}
+
+/// Tries the account's profiles in turn until one is allowed to list the
+/// aliases. `None` when none could.
+async fn lookup(paths: &ConfigPaths, profile_names: &[String]) -> Option<Option<String>> {
+    for profile_name in profile_names {
+        let lookup = async {
+            let sdk_config = load_sdk_config(paths, profile_name).await;
+            get_account_alias(&sdk_config).await
+        };
+        if let Ok(Ok(alias)) = tokio::time::timeout(DEFAULT_TIMEOUT, lookup).await {
+            return Some(alias);
+        }
+    }
+    None
+}
+
+async fn run_lookups(
+    paths: &ConfigPaths,
+    accounts: Vec<Unresolved>,
+    mut on_alias: impl FnMut(&str, &str),
+) -> Result<()> {
+    let mut cache: Cache<Option<String>> = Cache::load(CACHE_NAME, CACHE_TTL)?;
+    let mut changed = false;
+    let look_up = |account: Unresolved| {
+        let paths = paths.clone();
+        async move {
+            let alias = lookup(&paths, &account.profile_names).await;
+            (account.account_id, alias)
+        }
+    };
+    run_bounded(accounts, DEFAULT_CONCURRENCY, look_up, |finished| {
+        // Accounts none of the profiles could look up are tried again next
+        // time rather than cached.
+        let (account_id, Some(alias)) = finished else {
+            return;
+        };
+        if let Some(alias) = &alias {
+            on_alias(&account_id, alias);
+        }
+        cache.insert(&account_id, alias, now());
+        changed = true;
+    })
+    .await?;
+
+    if changed {
+        cache.save(now())?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_account_ids() {
+        assert_eq!(
+            redact("dev (123456789012) [us-west-2]"),
+            "dev (********9012) [us-west-2]"
+        );
+        assert_eq!(
+            redact("arn:aws:sts::123456789012:assumed-role/Admin/me"),
+            "arn:aws:sts::********9012:assumed-role/Admin/me"
+        );
+        assert_eq!(redact("build-1234567890123"), "build-1234567890123");
+
+        let value = serde_json::json!({"account_id": "123456789012", "ids": ["987654321098"]});
+        assert_eq!(
+            redacted(&value).unwrap(),
+            serde_json::json!({"account_id": "********9012", "ids": ["********1098"]})
+        );
+    }
+}
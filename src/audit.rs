@@ -0,0 +1,230 @@
+//! The audit log: with `[audit] enabled = true` in the settings file, every
+//! activation, `exec`, and `console` appends a line of JSON (the time, the
+//! profile with its account and role, and the command) to `audit.jsonl` in
+//! the state directory. Lines are only ever appended, and only the owner
+//! can read the file. Once it passes `max-size-mb` it is rotated to
+//! `audit.jsonl.1` and the older copies move up, keeping `keep` of them.
+//!
+//! Dry runs and `--read-only` runs record nothing. A log that can't be
+//! written is warned about but doesn't fail the command.
+
+use crate::config::Profile;
+use crate::dry_run::{dry_run, read_only};
+use crate::environment::now;
+use crate::files::append_private;
+use crate::state::get_state_dir;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+
+const LOG_NAME: &str = "audit.jsonl";
+const MEGABYTE: u64 = 1024 * 1024;
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct AuditSettings {
+    /// Record activations, `exec`, and `console` in the audit log.
+    pub enabled: bool,
+    /// Rotate the log once it is bigger than this.
+    pub max_size_mb: u64,
+    /// Rotated logs kept besides the current one.
+    pub keep: usize,
+}
+
+impl Default for AuditSettings {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            max_size_mb: 10,
+            keep: 5,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditEvent {
+    Activate,
+    Exec,
+    Console,
+}
+
+impl AuditEvent {
+    pub fn name(self) -> &'static str {
+        match self {
+            AuditEvent::Activate => "activate",
+            AuditEvent::Exec => "exec",
+            AuditEvent::Console => "console",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub at: DateTime<Utc>,
+    pub event: AuditEvent,
+    pub profile: String,
+    /// `sso_account_id`, or the account in `role_arn`.
+    pub account_id: Option<String>,
+    /// `sso_role_name`, or the role in `role_arn`.
+    pub role: Option<String>,
+    /// The login name the command ran as.
+    pub user: Option<String>,
+    /// The arguments the tool was run with.
+    pub command: String,
+}
+
+impl AuditEntry {
+    fn new(event: AuditEvent, profile_name: &str, profile: Option<&Profile>) -> Self {
+        let role_arn = profile.and_then(Profile::role_arn_parts);
+        Self {
+            at: now(),
+            event,
+            profile: profile_name.to_string(),
+            account_id: profile
+                .and_then(Profile::get_account_id)
+                .or(role_arn.map(|(account_id, _)| account_id))
+                .map(str::to_string),
+            role: profile
+                .and_then(Profile::get_role_name)
+                .or(role_arn.map(|(_, role)| role))
+                .map(str::to_string),
+            user: ["USER", "USERNAME"]
+                .into_iter()
+                .find_map(|var| std::env::var(var).ok().filter(|name| !name.is_empty())),
+            command: std::env::args().skip(1).collect::<Vec<_>>().join(" "),
+        }
+    }
+}
+
+pub fn log_path() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join(LOG_NAME))
+}
+
+/// Appends `event` for `profile_name` to the log when it's enabled.
+pub fn record(
+    settings: &AuditSettings,
+    event: AuditEvent,
+    profile_name: &str,
+    profile: Option<&Profile>,
+) {
+    if !settings.enabled || dry_run() || read_only() {
+        return;
+    }
+    let entry = AuditEntry::new(event, profile_name, profile);
+    if let Err(e) = log_path().and_then(|path| append(settings, &path, &entry)) {
+        tracing::warn!("Failed to write the audit log: {e:#}");
+    }
+}
+
+fn append(settings: &AuditSettings, path: &Path, entry: &AuditEntry) -> Result<()> {
+    if fs::metadata(path).is_ok_and(|metadata| metadata.len() >= settings.max_size_mb * MEGABYTE) {
+        rotate(path, settings.keep)?;
+    }
+
+    // One write per line, so concurrent runs' lines don't interleave.
+    let line = serde_json::to_string(entry)? + "\n";
+    append_private(path)?
+        .write_all(line.as_bytes())
+        .with_context(|| format!("Failed to append to {}", path.display()))
+}
+
+/// `audit.jsonl.<n>`, the `n`th newest rotated log.
+fn rotated(path: &Path, n: usize) -> PathBuf {
+    let mut name = path.as_os_str().to_owned();
+    name.push(format!(".{n}"));
+    PathBuf::from(name)
+}
+
+/// Moves each rotated log one up, dropping the oldest, and the current log
+/// to `.1`; with nothing to keep, the log is just removed.
+fn rotate(path: &Path, keep: usize) -> Result<()> {
+    let _ = fs::remove_file(rotated(path, keep.max(1)));
+    if keep == 0 {
+        return fs::remove_file(path)
+            .with_context(|| format!("Failed to rotate {}", path.display()));
+    }
+    for n in (1..keep).rev() {
+        let from = rotated(path, n);
+        if from.exists() {
+            fs::rename(&from, rotated(path, n + 1))
+                .with_context(|| format!("Failed to rotate {}", from.display()))?;
+        }
+    }
+    fs::rename(path, rotated(path, 1))
+        .with_context(|| format!("Failed to rotate {}", path.display()))
+}
+
+/// Every entry in the log and its rotated copies, oldest first. Lines that
+/// aren't entries are skipped with a warning.
+pub fn entries(settings: &AuditSettings) -> Result<Vec<AuditEntry>> {
+    read_entries(&log_path()?, settings.keep)
+}
+
+fn read_entries(path: &Path, keep: usize) -> Result<Vec<AuditEntry>> {
+    let files = (1..=keep)
+        .rev()
+        .map(|n| rotated(path, n))
+        .chain([path.to_path_buf()]);
+    let mut entries = Vec::new();
+    for file in files {
+        let Ok(opened) = fs::File::open(&file) else {
+            continue;
+        };
+        let mut skipped = 0;
+        for line in BufReader::new(opened).lines() {
+            let line = line.with_context(|| format!("Failed to read {}", file.display()))?;
+            match serde_json::from_str(&line) {
+                Ok(entry) => entries.push(entry),
+                Err(_) if line.trim().is_empty() => {}
+                Err(_) => skipped += 1,
+            }
+        }
+        if skipped > 0 {
+            tracing::warn!("Skipped {skipped} unreadable lines in {}", file.display());
+        }
+    }
+    Ok(entries)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_append_rotates_and_reads_back_in_order() {
+        let root = temp_dir("audit");
+        let path = root.join(LOG_NAME);
+        // Every append after the first rotates.
+        let settings = AuditSettings {
+            enabled: true,
+            max_size_mb: 0,
+            keep: 2,
+        };
+        let entry = |profile: &str| AuditEntry::new(AuditEvent::Exec, profile, None);
+
+        for profile in ["one", "two", "three", "four"] {
+            append(&settings, &path, &entry(profile)).unwrap();
+        }
+        fs::write(&path, fs::read_to_string(&path).unwrap() + "not json\n").unwrap();
+
+        let profiles: Vec<String> = read_entries(&path, settings.keep)
+            .unwrap()
+            .into_iter()
+            .map(|entry| entry.profile)
+            .collect();
+        assert_eq!(profiles, ["two", "three", "four"]);
+        assert!(!rotated(&path, 3).exists());
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&path).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+    }
+}
@@ -0,0 +1,468 @@
+use crate::config::ConfigPaths;
+use anyhow::{Context, Result};
+use aws_config::{Region, SdkConfig};
+use aws_credential_types::Credentials;
+use aws_credential_types::provider::{ProvideCredentials, SharedCredentialsProvider};
+use aws_runtime::env_config::file::{EnvConfigFileKind, EnvConfigFiles};
+use aws_sdk_sts::error::DisplayErrorContext;
+use base64::Engine;
+use std::future::Future;
+use std::sync::OnceLock;
+use std::time::SystemTime;
+use tokio::runtime::Runtime;
+
+/// STS and IAM are global services, so profiles without a region still work.
+const FALLBACK_REGION: &str = "us-east-1";
+
+/// The Tokio runtime shared by the whole process, started on first use.
+/// Commands wait on it with [`block_on`]; [`crate::tasks`] runs background
+/// work on it while the selector is open.
+pub fn runtime() -> Result<&'static Runtime> {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+
+    if let Some(runtime) = RUNTIME.get() {
+        return Ok(runtime);
+    }
+    let runtime = Runtime::new().context("Failed to start async runtime")?;
+    Ok(RUNTIME.get_or_init(|| runtime))
+}
+
+/// Runs a future to completion on the shared runtime.
+pub fn block_on<F: Future>(future: F) -> Result<F::Output> {
+    Ok(runtime()?.block_on(future))
+}
+
+/// Loads SDK configuration for `profile_name` from the same config and
+/// credentials files the rest of the tool reads. Either may be missing,
+/// which the SDK only allows of the default paths.
+pub async fn load_sdk_config(paths: &ConfigPaths, profile_name: &str) -> SdkConfig {
+    let mut profile_files = EnvConfigFiles::builder();
+    for (kind, path) in [
+        (EnvConfigFileKind::Config, &paths.config),
+        (EnvConfigFileKind::Credentials, &paths.credentials),
+    ] {
+        profile_files = match path.exists() {
+            true => profile_files.with_file(kind, path),
+            false => profile_files.with_contents(kind, ""),
+        };
+    }
+    let profile_files = profile_files.build();
+
+    aws_config::from_env()
+        .profile_files(profile_files)
+        .profile_name(profile_name)
+        .load()
+        .await
+}
+
+/// The caller identity reported by STS.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct CallerIdentity {
+    pub account: String,
+    pub arn: String,
+    pub user_id: String,
+}
+
+fn sts_client(sdk_config: &SdkConfig) -> aws_sdk_sts::Client {
+    let mut sts_config = aws_sdk_sts::config::Builder::from(sdk_config);
+    if sdk_config.region().is_none() {
+        sts_config.set_region(Some(Region::new(FALLBACK_REGION)));
+    }
+
+    aws_sdk_sts::Client::from_conf(sts_config.build())
+}
+
+/// Resolves credentials through the SDK's default chain for the loaded
+/// profile (static keys, SSO, assume-role, credential_process, ...).
+pub async fn resolve_credentials(sdk_config: &SdkConfig) -> Result<Credentials> {
+    let provider = sdk_config
+        .credentials_provider()
+        .ok_or_else(|| anyhow::anyhow!("No credentials provider is configured"))?;
+
+    provider
+        .provide_credentials()
+        .await
+        .map_err(|e| anyhow::anyhow!("Failed to resolve credentials: {}", DisplayErrorContext(e)))
+}
+
+/// Exchanges long-lived credentials for temporary ones via
+/// GetFederationToken, granting the caller's full permissions.
+pub async fn get_federation_token(
+    sdk_config: &SdkConfig,
+    duration_seconds: Option<i32>,
+) -> Result<Credentials> {
+    const ALLOW_ALL_POLICY: &str =
+        r#"{"Version":"2012-10-17","Statement":[{"Effect":"Allow","Action":"*","Resource":"*"}]}"#;
+
+    let response = sts_client(sdk_config)
+        .get_federation_token()
+        .name("aws-profile-selector")
+        .policy(ALLOW_ALL_POLICY)
+        .set_duration_seconds(duration_seconds)
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("GetFederationToken failed: {}", DisplayErrorContext(e)))?;
+
+    let credentials = response
+        .credentials()
+        .ok_or_else(|| anyhow::anyhow!("GetFederationToken returned no credentials"))?;
+
+    Ok(Credentials::new(
+        credentials.access_key_id(),
+        credentials.secret_access_key(),
+        Some(credentials.session_token().to_string()),
+        SystemTime::try_from(*credentials.expiration()).ok(),
+        "GetFederationToken",
+    ))
+}
+
+/// What an MFA-protected profile asks STS for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MfaRequest {
+    /// `role_arn` with `mfa_serial`: AssumeRole with the code.
+    AssumeRole {
+        role_arn: String,
+        session_name: String,
+        external_id: Option<String>,
+    },
+    /// `mfa_serial` alone: GetSessionToken for the profile's own keys.
+    SessionToken,
+}
+
+/// Calls STS with an MFA code, using the credentials in `sdk_config`.
+pub async fn get_mfa_credentials(
+    sdk_config: &SdkConfig,
+    request: &MfaRequest,
+    serial_number: &str,
+    token_code: &str,
+    duration_seconds: Option<i32>,
+) -> Result<Credentials> {
+    let client = sts_client(sdk_config);
+    let (operation, credentials) = match request {
+        MfaRequest::AssumeRole {
+            role_arn,
+            session_name,
+            external_id,
+        } => {
+            let response = client
+                .assume_role()
+                .role_arn(role_arn)
+                .role_session_name(session_name)
+                .set_external_id(external_id.clone())
+                .serial_number(serial_number)
+                .token_code(token_code)
+                .set_duration_seconds(duration_seconds)
+                .send()
+                .await
+                .map_err(|e| anyhow::anyhow!("AssumeRole failed: {}", DisplayErrorContext(e)))?;
+            ("AssumeRole", response.credentials)
+        }
+        MfaRequest::SessionToken => {
+            let response = client
+                .get_session_token()
+                .serial_number(serial_number)
+                .token_code(token_code)
+                .set_duration_seconds(duration_seconds)
+                .send()
+                .await
+                .map_err(|e| {
+                    anyhow::anyhow!("GetSessionToken failed: {}", DisplayErrorContext(e))
+                })?;
+            ("GetSessionToken", response.credentials)
+        }
+    };
+    let credentials =
+        credentials.ok_or_else(|| anyhow::anyhow!("{operation} returned no credentials"))?;
+
+    Ok(Credentials::new(
+        credentials.access_key_id(),
+        credentials.secret_access_key(),
+        Some(credentials.session_token().to_string()),
+        SystemTime::try_from(*credentials.expiration()).ok(),
+        operation,
+    ))
+}
+
+/// Assumes `role_arn` with the credentials in `sdk_config`, for a session of
+/// `duration_seconds`.
+pub async fn assume_role(
+    sdk_config: &SdkConfig,
+    role_arn: &str,
+    session_name: &str,
+    external_id: Option<String>,
+    duration_seconds: i32,
+) -> Result<Credentials> {
+    let response = sts_client(sdk_config)
+        .assume_role()
+        .role_arn(role_arn)
+        .role_session_name(session_name)
+        .set_external_id(external_id)
+        .duration_seconds(duration_seconds)
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("AssumeRole failed: {}", DisplayErrorContext(e)))?;
+    let credentials = response
+        .credentials
+        .ok_or_else(|| anyhow::anyhow!("AssumeRole returned no credentials"))?;
+
+    Ok(Credentials::new(
+        credentials.access_key_id(),
+        credentials.secret_access_key(),
+        Some(credentials.session_token().to_string()),
+        SystemTime::try_from(*credentials.expiration()).ok(),
+        "AssumeRole",
+    ))
+}
+
+/// Exchanges long-lived credentials for a session of `duration_seconds`
+/// via GetSessionToken.
+pub async fn get_session_token(
+    sdk_config: &SdkConfig,
+    duration_seconds: i32,
+) -> Result<Credentials> {
+    let response = sts_client(sdk_config)
+        .get_session_token()
+        .duration_seconds(duration_seconds)
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("GetSessionToken failed: {}", DisplayErrorContext(e)))?;
+    let credentials = response
+        .credentials
+        .ok_or_else(|| anyhow::anyhow!("GetSessionToken returned no credentials"))?;
+
+    Ok(Credentials::new(
+        credentials.access_key_id(),
+        credentials.secret_access_key(),
+        Some(credentials.session_token().to_string()),
+        SystemTime::try_from(*credentials.expiration()).ok(),
+        "GetSessionToken",
+    ))
+}
+
+/// The same configuration with fixed credentials in place of the profile's
+/// provider chain.
+pub fn with_credentials(sdk_config: &SdkConfig, credentials: Credentials) -> SdkConfig {
+    sdk_config
+        .to_builder()
+        .credentials_provider(SharedCredentialsProvider::new(credentials))
+        .build()
+}
+
+pub async fn get_caller_identity(sdk_config: &SdkConfig) -> Result<CallerIdentity> {
+    let response = sts_client(sdk_config)
+        .get_caller_identity()
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("GetCallerIdentity failed: {}", DisplayErrorContext(e)))?;
+
+    Ok(CallerIdentity {
+        account: response.account().unwrap_or_default().to_string(),
+        arn: response.arn().unwrap_or_default().to_string(),
+        user_id: response.user_id().unwrap_or_default().to_string(),
+    })
+}
+
+fn iam_client(sdk_config: &SdkConfig) -> aws_sdk_iam::Client {
+    let mut iam_config = aws_sdk_iam::config::Builder::from(sdk_config);
+    if sdk_config.region().is_none() {
+        iam_config.set_region(Some(Region::new(FALLBACK_REGION)));
+    }
+
+    aws_sdk_iam::Client::from_conf(iam_config.build())
+}
+
+/// The account's IAM alias, if it has one. An account has at most one.
+pub async fn get_account_alias(sdk_config: &SdkConfig) -> Result<Option<String>> {
+    let response = iam_client(sdk_config)
+        .list_account_aliases()
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("ListAccountAliases failed: {}", DisplayErrorContext(e)))?;
+
+    Ok(response.account_aliases().first().cloned())
+}
+
+/// What a kubeconfig needs to reach an EKS cluster.
+#[derive(Debug, Clone)]
+pub struct EksCluster {
+    pub name: String,
+    pub arn: String,
+    pub endpoint: String,
+    /// Base64-encoded, as kubeconfig's `certificate-authority-data` wants it.
+    pub certificate_authority: String,
+}
+
+/// The names of the EKS clusters in the profile's region.
+pub async fn list_eks_clusters(sdk_config: &SdkConfig) -> Result<Vec<String>> {
+    let mut clusters = Vec::new();
+    let mut pages = aws_sdk_eks::Client::new(sdk_config)
+        .list_clusters()
+        .into_paginator()
+        .send();
+
+    while let Some(page) = pages.next().await {
+        let page =
+            page.map_err(|e| anyhow::anyhow!("ListClusters failed: {}", DisplayErrorContext(e)))?;
+        clusters.extend(page.clusters().iter().cloned());
+    }
+    Ok(clusters)
+}
+
+pub async fn describe_eks_cluster(sdk_config: &SdkConfig, name: &str) -> Result<EksCluster> {
+    let response = aws_sdk_eks::Client::new(sdk_config)
+        .describe_cluster()
+        .name(name)
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("DescribeCluster failed: {}", DisplayErrorContext(e)))?;
+    let cluster = response
+        .cluster()
+        .ok_or_else(|| anyhow::anyhow!("DescribeCluster returned no cluster"))?;
+
+    Ok(EksCluster {
+        name: name.to_string(),
+        arn: cluster.arn().unwrap_or(name).to_string(),
+        endpoint: cluster.endpoint().unwrap_or_default().to_string(),
+        certificate_authority: cluster
+            .certificate_authority()
+            .and_then(|authority| authority.data())
+            .unwrap_or_default()
+            .to_string(),
+    })
+}
+
+/// A registry and the password `docker login` needs for it (the user name
+/// is always `AWS`).
+#[derive(Debug, Clone)]
+pub struct EcrAuthorization {
+    pub endpoint: String,
+    pub password: String,
+}
+
+/// GetAuthorizationToken for the caller's own registry, or for
+/// `registry_id`'s in the same region (the password works for any registry
+/// the caller may pull from).
+pub async fn get_ecr_authorization(
+    sdk_config: &SdkConfig,
+    registry_id: Option<&str>,
+) -> Result<EcrAuthorization> {
+    let response = aws_sdk_ecr::Client::new(sdk_config)
+        .get_authorization_token()
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("GetAuthorizationToken failed: {}", DisplayErrorContext(e)))?;
+    let data = response
+        .authorization_data()
+        .first()
+        .ok_or_else(|| anyhow::anyhow!("GetAuthorizationToken returned no token"))?;
+
+    // The token is base64 of `AWS:<password>`.
+    let token = base64::engine::general_purpose::STANDARD
+        .decode(data.authorization_token().unwrap_or_default())
+        .context("GetAuthorizationToken returned a malformed token")?;
+    let token =
+        String::from_utf8(token).context("GetAuthorizationToken returned a malformed token")?;
+    let password = token
+        .split_once(':')
+        .map_or(token.as_str(), |(_, password)| password);
+
+    // `https://<registry id>.dkr.ecr.<region>.<domain>`
+    let mut endpoint = data.proxy_endpoint().unwrap_or_default().to_string();
+    if let Some(registry_id) = registry_id
+        && let Some((_, host)) = endpoint.split_once("://")
+        && let Some((_, rest)) = host.split_once('.')
+    {
+        endpoint = format!("https://{registry_id}.{rest}");
+    }
+
+    Ok(EcrAuthorization {
+        endpoint,
+        password: password.to_string(),
+    })
+}
+
+/// A CodeArtifact repository's package-manager endpoint and a token for it.
+#[derive(Debug, Clone)]
+pub struct CodeArtifactLogin {
+    pub endpoint: String,
+    pub token: String,
+}
+
+/// GetAuthorizationToken for `domain` and GetRepositoryEndpoint for
+/// `repository` in `format` (`npm`, `pypi`, `cargo`, ...).
+pub async fn get_codeartifact_login(
+    sdk_config: &SdkConfig,
+    domain: &str,
+    domain_owner: Option<&str>,
+    repository: &str,
+    format: &str,
+) -> Result<CodeArtifactLogin> {
+    let client = aws_sdk_codeartifact::Client::new(sdk_config);
+
+    let token = client
+        .get_authorization_token()
+        .domain(domain)
+        .set_domain_owner(domain_owner.map(str::to_string))
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("GetAuthorizationToken failed: {}", DisplayErrorContext(e)))?;
+    let endpoint = client
+        .get_repository_endpoint()
+        .domain(domain)
+        .set_domain_owner(domain_owner.map(str::to_string))
+        .repository(repository)
+        .format(aws_sdk_codeartifact::types::PackageFormat::from(format))
+        .send()
+        .await
+        .map_err(|e| anyhow::anyhow!("GetRepositoryEndpoint failed: {}", DisplayErrorContext(e)))?;
+
+    Ok(CodeArtifactLogin {
+        endpoint: endpoint
+            .repository_endpoint()
+            .unwrap_or_default()
+            .to_string(),
+        token: token.authorization_token().unwrap_or_default().to_string(),
+    })
+}
+
+/// Environment variables that hand resolved credentials to other tools.
+pub fn credential_env_vars(
+    credentials: &Credentials,
+    region: Option<&str>,
+) -> Vec<(&'static str, String)> {
+    let mut vars = vec![
+        ("AWS_ACCESS_KEY_ID", credentials.access_key_id().to_string()),
+        (
+            "AWS_SECRET_ACCESS_KEY",
+            credentials.secret_access_key().to_string(),
+        ),
+    ];
+
+    if let Some(session_token) = credentials.session_token() {
+        vars.push(("AWS_SESSION_TOKEN", session_token.to_string()));
+    }
+
+    if let Some(expiry) = credentials.expiry() {
+        let expiry: chrono::DateTime<chrono::Utc> = expiry.into();
+        vars.push(("AWS_CREDENTIAL_EXPIRATION", expiry.to_rfc3339()));
+    }
+
+    if let Some(region) = region {
+        vars.push(("AWS_REGION", region.to_string()));
+        vars.push(("AWS_DEFAULT_REGION", region.to_string()));
+    }
+
+    vars
+}
+
+/// Resolves a profile's credentials and returns them as environment
+/// variables, including the profile's region when it has one.
+pub fn resolve_credential_env(
+    paths: &ConfigPaths,
+    profile_name: &str,
+) -> Result<Vec<(&'static str, String)>> {
+    let sdk_config = crate::mfa::load_sdk_config(paths, profile_name)?;
+    let credentials = block_on(resolve_credentials(&sdk_config))??;
+    let region = sdk_config.region().map(|region| region.to_string());
+    Ok(credential_env_vars(&credentials, region.as_deref()))
+}
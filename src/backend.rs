@@ -0,0 +1,160 @@
+//! aws-vault and granted, which read the same `~/.aws/config` but keep the
+//! credentials themselves. A profile is theirs when its `credential_process`
+//! runs them or, for granted, when it has `granted_sso_*` keys; aws-vault's
+//! keyring also counts, as its profiles often have nothing in the config to
+//! say so. `exec --backend` hands the command to them to run.
+
+use crate::config::{AwsConfig, Profile};
+use clap::ValueEnum;
+use serde::Serialize;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Backend {
+    AwsVault,
+    Granted,
+}
+
+impl Backend {
+    /// The backend the profile's config points at, if any.
+    pub fn detect(profile: &Profile) -> Option<Self> {
+        [Self::AwsVault, Self::Granted]
+            .into_iter()
+            .find(|backend| backend.configured(profile))
+    }
+
+    fn program(self) -> &'static str {
+        match self {
+            Self::AwsVault => "aws-vault",
+            Self::Granted => "granted",
+        }
+    }
+
+    fn configured(self, profile: &Profile) -> bool {
+        let runs_it = profile
+            .attributes
+            .get("credential_process")
+            .and_then(|process| process.split_whitespace().next())
+            .is_some_and(|program| program.rsplit(['/', '\\']).next() == Some(self.program()));
+        runs_it
+            || (self == Self::Granted
+                && profile
+                    .attributes
+                    .keys()
+                    .any(|key| key.starts_with("granted_sso_")))
+    }
+
+    /// The profiles this backend supplies credentials for, in config order.
+    pub fn profiles(self, config: &AwsConfig) -> Vec<&str> {
+        let stored = match self {
+            Self::AwsVault => aws_vault_credentials(),
+            Self::Granted => Vec::new(),
+        };
+        config
+            .profiles
+            .iter()
+            .filter(|profile| self.configured(profile) || stored.contains(&profile.name))
+            .map(|profile| profile.name.as_str())
+            .collect()
+    }
+
+    /// `command_line` run by the backend with the profile's credentials.
+    pub fn exec_command(self, profile_name: &str, command_line: &[&String]) -> Command {
+        match self {
+            Self::AwsVault => {
+                let mut command = Command::new("aws-vault");
+                command
+                    .args(["exec", profile_name, "--"])
+                    .args(command_line);
+                command
+            }
+            // granted's `assume` takes the command as one string.
+            Self::Granted => {
+                let mut command = Command::new("assume");
+                command
+                    .args([profile_name, "--exec"])
+                    .arg(join_command(command_line));
+                command
+            }
+        }
+    }
+}
+
+/// The profiles aws-vault has keys for in its keyring, or none when it
+/// isn't installed.
+fn aws_vault_credentials() -> Vec<String> {
+    let output = Command::new("aws-vault")
+        .args(["list", "--credentials"])
+        .stdin(Stdio::null())
+        .stderr(Stdio::null())
+        .output();
+    match output {
+        Ok(output) if output.status.success() => String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .map(|line| line.trim().to_string())
+            .filter(|line| !line.is_empty())
+            .collect(),
+        Ok(output) => {
+            tracing::warn!("aws-vault list failed with {}", output.status);
+            Vec::new()
+        }
+        Err(e) => {
+            tracing::debug!("Not asking aws-vault for its credentials: {e}");
+            Vec::new()
+        }
+    }
+}
+
+/// `command_line` as a POSIX shell command, with words that need it in
+/// single quotes.
+fn join_command(command_line: &[&String]) -> String {
+    command_line
+        .iter()
+        .map(|word| {
+            let plain = !word.is_empty()
+                && word
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric() || "-_./=:,@%+".contains(c));
+            match plain {
+                true => word.to_string(),
+                false => format!("'{}'", word.replace('\'', "'\\''")),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_aws_config;
+
+    #[test]
+    fn test_detect_backend() {
+        let config = parse_aws_config(
+            r#"
+[profile vault]
+credential_process = /usr/local/bin/aws-vault export --format=json vault
+
+[profile granted]
+granted_sso_start_url = https://corp.awsapps.com/start
+credential_process = granted credential-process --profile granted
+
+[profile plain]
+region = eu-west-1
+"#,
+        )
+        .unwrap();
+        let detected: Vec<_> = config.profiles.iter().map(Backend::detect).collect();
+        assert_eq!(
+            detected,
+            [Some(Backend::AwsVault), Some(Backend::Granted), None]
+        );
+        assert_eq!(Backend::Granted.profiles(&config), ["granted"]);
+
+        let words = ["sh", "-c", "echo 'hi' $HOME"].map(String::from);
+        let words: Vec<&String> = words.iter().collect();
+        assert_eq!(join_command(&words), r#"sh -c 'echo '\''hi'\'' $HOME'"#);
+    }
+}
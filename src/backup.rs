@@ -0,0 +1,232 @@
+//! Copies of the AWS config and credentials files taken before a command
+//! changes them, in `config-backups` under the state directory. Each run
+//! that writes gets one backup, holding every file as it was before that
+//! run's first write to it, so `backup restore` undoes the whole command.
+//! The newest [`KEEP`] backups are kept.
+//!
+//! Only the CLI takes backups; [`crate::store::ProfileStore`] users don't.
+
+use crate::dry_run::{ensure_writable, would, would_write};
+use crate::environment::now;
+use crate::files::{Access, write_atomically};
+use crate::state::get_state_dir;
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+const KEEP: usize = 50;
+const MANIFEST: &str = "manifest.json";
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// This run's backup, once something has been written.
+static CURRENT: Mutex<Option<(PathBuf, Manifest)>> = Mutex::new(None);
+
+pub fn enable_backups() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Manifest {
+    pub created: DateTime<Utc>,
+    /// The arguments of the command that made the changes.
+    pub command: String,
+    pub files: Vec<BackedUpFile>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackedUpFile {
+    pub path: PathBuf,
+    /// The copy's name in the backup's directory; `None` when the file
+    /// didn't exist yet, so restoring removes it.
+    pub copy: Option<String>,
+}
+
+/// A backup's directory name and what it holds.
+#[derive(Debug, Clone, Serialize)]
+pub struct Backup {
+    pub id: String,
+    #[serde(flatten)]
+    pub manifest: Manifest,
+}
+
+pub fn backups_dir() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join("config-backups"))
+}
+
+/// Saves `path` as it is now, unless this run already has. Called before
+/// each write to the AWS config or credentials.
+pub fn before_write(path: &Path) -> Result<()> {
+    if !ENABLED.load(Ordering::Relaxed) {
+        return Ok(());
+    }
+    let mut current = CURRENT.lock().unwrap();
+    if current
+        .as_ref()
+        .is_some_and(|(_, manifest)| manifest.files.iter().any(|file| file.path == path))
+    {
+        return Ok(());
+    }
+    if current.is_none() {
+        *current = Some(create()?);
+    }
+    let (dir, manifest) = current.as_mut().expect("created above");
+    save_copy(dir, manifest, path)
+}
+
+/// Copies `path` into `dir` and records it in the manifest there.
+fn save_copy(dir: &Path, manifest: &mut Manifest, path: &Path) -> Result<()> {
+    let copy = match fs::read(path) {
+        Ok(contents) => {
+            let name = format!(
+                "{}-{}",
+                manifest.files.len(),
+                path.file_name().unwrap_or_default().to_string_lossy()
+            );
+            // The credentials file's copy holds secrets.
+            write_atomically(&dir.join(&name), &contents, Access::Private)?;
+            Some(name)
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e).with_context(|| format!("Failed to back up {}", path.display())),
+    };
+    manifest.files.push(BackedUpFile {
+        path: path.to_path_buf(),
+        copy,
+    });
+    write_atomically(
+        &dir.join(MANIFEST),
+        serde_json::to_string_pretty(manifest)?,
+        Access::Private,
+    )
+}
+
+/// A new, empty backup directory named after the time, and the oldest
+/// backups past [`KEEP`] removed.
+fn create() -> Result<(PathBuf, Manifest)> {
+    let root = backups_dir()?;
+    let created = now();
+    let stamp = created.format("%Y%m%d-%H%M%S").to_string();
+    let mut id = stamp.clone();
+    let mut n = 1;
+    while root.join(&id).exists() {
+        n += 1;
+        id = format!("{stamp}-{n}");
+    }
+    let dir = root.join(&id);
+    fs::create_dir_all(&dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o700))?;
+    }
+
+    // The new directory has no manifest yet, so isn't listed.
+    let backups = list()?;
+    for old in backups.iter().skip(KEEP - 1) {
+        let _ = fs::remove_dir_all(root.join(&old.id));
+    }
+
+    let command = std::env::args().skip(1).collect::<Vec<_>>().join(" ");
+    Ok((
+        dir,
+        Manifest {
+            created,
+            command,
+            files: Vec::new(),
+        },
+    ))
+}
+
+/// The backups, newest first. Directories without a readable manifest are
+/// skipped.
+pub fn list() -> Result<Vec<Backup>> {
+    let root = backups_dir()?;
+    let Ok(entries) = fs::read_dir(&root) else {
+        return Ok(Vec::new());
+    };
+    let mut backups: Vec<Backup> = entries
+        .filter_map(|entry| {
+            let entry = entry.ok()?;
+            let manifest = fs::read_to_string(entry.path().join(MANIFEST)).ok()?;
+            Some(Backup {
+                id: entry.file_name().to_string_lossy().to_string(),
+                manifest: serde_json::from_str(&manifest).ok()?,
+            })
+        })
+        .collect();
+    backups.sort_by_key(|backup| std::cmp::Reverse(backup.manifest.created));
+    Ok(backups)
+}
+
+/// Puts every file in the backup back as it was. The files as they are now
+/// are backed up first, so a restore can be undone too.
+pub fn restore(backup: &Backup) -> Result<()> {
+    restore_files(&backups_dir()?.join(&backup.id), &backup.manifest)
+}
+
+fn restore_files(dir: &Path, manifest: &Manifest) -> Result<()> {
+    for file in &manifest.files {
+        ensure_writable(format_args!("restore {}", file.path.display()))?;
+        match &file.copy {
+            Some(copy) => {
+                let contents = fs::read_to_string(dir.join(copy))
+                    .with_context(|| format!("{} is missing {copy}", dir.display()))?;
+                if would_write(&file.path, &contents) {
+                    continue;
+                }
+                before_write(&file.path)?;
+                write_atomically(&file.path, contents, Access::KeepOrPrivate)?;
+            }
+            None if file.path.exists() => {
+                if would(format_args!("remove {}", file.path.display())) {
+                    continue;
+                }
+                before_write(&file.path)?;
+                fs::remove_file(&file.path)
+                    .with_context(|| format!("Failed to remove {}", file.path.display()))?;
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_restore_undoes_the_changes() {
+        let root = temp_dir("backup");
+        let backup = root.join("backup");
+        fs::create_dir_all(&backup).unwrap();
+        let config = root.join("config");
+        let credentials = root.join("credentials");
+        fs::write(&config, "[profile dev]\nregion = eu-west-1\n").unwrap();
+
+        let mut manifest = Manifest {
+            created: Utc::now(),
+            command: "migrate-sso".to_string(),
+            files: Vec::new(),
+        };
+        save_copy(&backup, &mut manifest, &config).unwrap();
+        save_copy(&backup, &mut manifest, &credentials).unwrap();
+        assert_eq!(manifest.files[0].copy.as_deref(), Some("0-config"));
+        assert_eq!(manifest.files[1].copy, None);
+
+        fs::write(&config, "[profile dev]\nregion = us-east-1\n").unwrap();
+        fs::write(&credentials, "[dev]\n").unwrap();
+        restore_files(&backup, &manifest).unwrap();
+        assert_eq!(
+            fs::read_to_string(&config).unwrap(),
+            "[profile dev]\nregion = eu-west-1\n"
+        );
+        assert!(!credentials.exists());
+    }
+}
@@ -0,0 +1,151 @@
+//! Results of slow network lookups, kept under
+//! `~/.cache/aws-profile-selector/` so later runs can show them instantly.
+//!
+//! Each kind of result lives in its own JSON file with its own time to live;
+//! stale entries are ignored on read and dropped on save. A missing or
+//! unreadable file is an empty cache.
+
+use crate::dry_run::read_only;
+use crate::environment::Environment;
+use crate::files::{Access, write_atomically};
+use anyhow::{Context, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// `$XDG_CACHE_HOME` or `~/.cache`, followed by `aws-profile-selector`.
+pub fn get_cache_dir() -> Result<PathBuf> {
+    Ok(Environment::current()?.cache_dir())
+}
+
+/// Deletes every cached result, returning whether there was anything.
+pub fn clear() -> Result<bool> {
+    let dir = get_cache_dir()?;
+
+    if !dir.exists() {
+        return Ok(false);
+    }
+
+    fs::remove_dir_all(&dir).with_context(|| format!("Failed to remove {}", dir.display()))?;
+    Ok(true)
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Entry<T> {
+    value: T,
+    stored_at: DateTime<Utc>,
+}
+
+/// One cache file, keyed by profile name (or whatever the caller looks up).
+#[derive(Debug)]
+pub struct Cache<T> {
+    path: PathBuf,
+    ttl: TimeDelta,
+    entries: BTreeMap<String, Entry<T>>,
+    /// Whether the file should be readable by its owner only.
+    private: bool,
+}
+
+impl<T: Serialize + DeserializeOwned> Cache<T> {
+    /// Reads `<name>.json` from the cache directory.
+    pub fn load(name: &str, ttl: TimeDelta) -> Result<Self> {
+        let path = get_cache_dir()?.join(format!("{name}.json"));
+        let entries = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| serde_json::from_str(&content).ok())
+            .unwrap_or_default();
+
+        Ok(Self {
+            path,
+            ttl,
+            entries,
+            private: false,
+        })
+    }
+
+    /// Like `load`, but the file is saved readable by its owner only, for
+    /// entries holding secrets.
+    pub fn load_private(name: &str, ttl: TimeDelta) -> Result<Self> {
+        Ok(Self {
+            private: true,
+            ..Self::load(name, ttl)?
+        })
+    }
+
+    /// The value stored for `key`, unless it is older than the time to live.
+    pub fn get(&self, key: &str, now: DateTime<Utc>) -> Option<&T> {
+        self.entries
+            .get(key)
+            .filter(|entry| now - entry.stored_at < self.ttl)
+            .map(|entry| &entry.value)
+    }
+
+    pub fn insert(&mut self, key: &str, value: T, now: DateTime<Utc>) {
+        self.entries.insert(
+            key.to_string(),
+            Entry {
+                value,
+                stored_at: now,
+            },
+        );
+    }
+
+    /// Drops the entry for `key`, returning whether there was one.
+    pub fn remove(&mut self, key: &str) -> bool {
+        self.entries.remove(key).is_some()
+    }
+
+    /// Writes the fresh entries back, unless `--read-only` leaves the cache
+    /// as it was.
+    pub fn save(&mut self, now: DateTime<Utc>) -> Result<()> {
+        if read_only() {
+            return Ok(());
+        }
+        let ttl = self.ttl;
+        self.entries.retain(|_, entry| now - entry.stored_at < ttl);
+
+        let access = match self.private {
+            true => Access::Private,
+            false => Access::KeepOrDefault,
+        };
+        write_atomically(
+            &self.path,
+            serde_json::to_string_pretty(&self.entries)?,
+            access,
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_entries_expire_after_ttl() {
+        let now = Utc::now();
+        let mut cache: Cache<String> = Cache {
+            path: PathBuf::new(),
+            ttl: TimeDelta::minutes(15),
+            entries: BTreeMap::new(),
+            private: false,
+        };
+
+        cache.insert(
+            "dev",
+            "123456789012".to_string(),
+            now - TimeDelta::minutes(20),
+        );
+        cache.insert(
+            "prod",
+            "987654321098".to_string(),
+            now - TimeDelta::minutes(5),
+        );
+
+        assert_eq!(cache.get("dev", now), None);
+        assert_eq!(cache.get("prod", now).unwrap(), "987654321098");
+        assert_eq!(cache.get("prod", now + TimeDelta::minutes(10)), None);
+    }
+}
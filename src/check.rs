@@ -0,0 +1,299 @@
+//! Credential health checks: STS GetCallerIdentity for many profiles at
+//! once, with a bound on how many run concurrently.
+//!
+//! Successful checks are cached for [`CACHE_TTL`], so checking again soon
+//! after only contacts STS for the profiles that failed or weren't checked.
+//! Every outcome is also kept for [`HEALTH_TTL`] for [`last_result`], which
+//! the status line and prompt show without calling STS themselves.
+
+use crate::aws::{CallerIdentity, block_on, get_caller_identity, load_sdk_config};
+use crate::cache::Cache;
+use crate::config::{ConfigPaths, read_aws_config};
+use crate::environment::now;
+use crate::tasks::{Background, Update, run_bounded};
+use anyhow::Result;
+use chrono::TimeDelta;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::time::Duration;
+
+pub const DEFAULT_CONCURRENCY: usize = 8;
+pub const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+const CACHE_NAME: &str = "checks";
+pub const CACHE_TTL: TimeDelta = TimeDelta::minutes(15);
+
+const HEALTH_CACHE_NAME: &str = "health";
+pub const HEALTH_TTL: TimeDelta = TimeDelta::hours(1);
+
+/// How the profile's latest check within [`HEALTH_TTL`] went, whether it
+/// succeeded or not; checks that timed out aren't kept.
+pub fn last_result(profile_name: &str) -> Option<CheckResult> {
+    let cache: Cache<CheckResult> = Cache::load(HEALTH_CACHE_NAME, HEALTH_TTL).ok()?;
+    cache.get(profile_name, now()).cloned()
+}
+
+fn record_health<'a>(results: impl IntoIterator<Item = (&'a str, &'a CheckResult)>) -> Result<()> {
+    let now = now();
+    let mut cache = Cache::load(HEALTH_CACHE_NAME, HEALTH_TTL)?;
+    for (profile_name, result) in results {
+        match result {
+            CheckResult::Timeout => continue,
+            result => cache.insert(profile_name, result.clone(), now),
+        }
+    }
+    cache.save(now)
+}
+
+/// How one profile's check went.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "status", rename_all = "lowercase")]
+pub enum CheckResult {
+    Ok { account: String, arn: String },
+    Failed { error: String },
+    Timeout,
+}
+
+impl CheckResult {
+    pub fn is_ok(&self) -> bool {
+        matches!(self, Self::Ok { .. })
+    }
+
+    /// `✓`, `✗`, or `timeout`.
+    pub fn symbol(&self) -> &'static str {
+        match self {
+            Self::Ok { .. } => "✓",
+            Self::Failed { .. } => "✗",
+            Self::Timeout => "timeout",
+        }
+    }
+
+    /// The identity on success, otherwise what went wrong in one line.
+    pub fn detail(&self) -> String {
+        match self {
+            Self::Ok { arn, .. } => arn.clone(),
+            Self::Failed { error } => summarize_error(error).to_string(),
+            Self::Timeout => "No answer from STS in time".to_string(),
+        }
+    }
+}
+
+/// The first line of an SDK error, without the debug dump of the error
+/// chain that follows it in parentheses.
+pub fn summarize_error(error: &str) -> &str {
+    let line = error.lines().next().unwrap_or_default();
+    line.match_indices(" (")
+        .find(|(index, _)| {
+            line[index + 2..]
+                .chars()
+                .next()
+                .is_some_and(|c| c.is_ascii_uppercase())
+        })
+        .map_or(line, |(index, _)| &line[..index])
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct CheckOptions {
+    /// Profiles checked at the same time.
+    pub concurrency: usize,
+    /// How long one profile may take, credential resolution included.
+    pub timeout: Duration,
+    /// Reuse cached successes instead of checking those profiles again.
+    pub use_cache: bool,
+}
+
+impl Default for CheckOptions {
+    fn default() -> Self {
+        Self {
+            concurrency: DEFAULT_CONCURRENCY,
+            timeout: DEFAULT_TIMEOUT,
+            use_cache: true,
+        }
+    }
+}
+
+/// Caches an identity obtained elsewhere (e.g. by `whoami`) as a successful
+/// check.
+pub fn record_identity(profile_name: &str, identity: &CallerIdentity) -> Result<()> {
+    let now = now();
+    let result = CheckResult::Ok {
+        account: identity.account.clone(),
+        arn: identity.arn.clone(),
+    };
+    let mut cache = Cache::load(CACHE_NAME, CACHE_TTL)?;
+    cache.insert(profile_name, result.clone(), now);
+    cache.save(now)?;
+    record_health([(profile_name, &result)])
+}
+
+async fn check_profile(paths: &ConfigPaths, profile_name: &str, timeout: Duration) -> CheckResult {
+    let check = async {
+        let sdk_config = load_sdk_config(paths, profile_name).await;
+        get_caller_identity(&sdk_config).await
+    };
+
+    match tokio::time::timeout(timeout, check).await {
+        Ok(Ok(identity)) => CheckResult::Ok {
+            account: identity.account,
+            arn: identity.arn,
+        },
+        Ok(Err(e)) => CheckResult::Failed {
+            error: format!("{e:#}"),
+        },
+        Err(_) => CheckResult::Timeout,
+    }
+}
+
+/// Checks each profile, calling `on_result` as the checks finish (so not
+/// necessarily in the order given). Cached results come first.
+pub fn check_profiles(
+    paths: &ConfigPaths,
+    profile_names: &[String],
+    options: CheckOptions,
+    on_result: impl FnMut(&str, CheckResult),
+) -> Result<()> {
+    block_on(run_checks(paths, profile_names, options, on_result))?
+}
+
+/// Checks each profile and returns the results in the order given.
+pub fn check_in_order(
+    paths: &ConfigPaths,
+    profile_names: &[String],
+    options: CheckOptions,
+) -> Result<Vec<(String, CheckResult)>> {
+    let mut results = HashMap::new();
+    check_profiles(paths, profile_names, options, |profile_name, result| {
+        results.insert(profile_name.to_string(), result);
+    })?;
+    Ok(profile_names
+        .iter()
+        .filter_map(|name| Some((name.clone(), results.remove(name)?)))
+        .collect())
+}
+
+/// Starts the checks on `background`, which reports each result as an
+/// [`Update::Checked`].
+pub fn spawn_checks(
+    background: &Background,
+    paths: &ConfigPaths,
+    profile_names: Vec<String>,
+    options: CheckOptions,
+) -> Result<()> {
+    let paths = paths.clone();
+
+    background.spawn(|sender| async move {
+        let _ = run_checks(&paths, &profile_names, options, |profile_name, result| {
+            let _ = sender.send(Update::Checked {
+                profile_name: profile_name.to_string(),
+                result,
+            });
+        })
+        .await;
+    })
+}
+
+async fn run_checks(
+    paths: &ConfigPaths,
+    profile_names: &[String],
+    options: CheckOptions,
+    mut on_result: impl FnMut(&str, CheckResult),
+) -> Result<()> {
+    let mut cache: Cache<CheckResult> = Cache::load(CACHE_NAME, CACHE_TTL)?;
+    let mut changed = false;
+    let mut unchecked = Vec::new();
+    for profile_name in profile_names {
+        match cache.get(profile_name, now()) {
+            Some(result) if options.use_cache => on_result(profile_name, result.clone()),
+            _ => unchecked.push(profile_name.clone()),
+        }
+    }
+
+    // A token file or program that isn't there fails without asking STS.
+    let config = read_aws_config(paths).ok();
+    let mut checked = Vec::new();
+    let check = |profile_name: String| {
+        let paths = paths.clone();
+        let problem = config
+            .as_ref()
+            .and_then(|config| config.credential_problem(&profile_name));
+        async move {
+            let result = match problem {
+                Some(error) => CheckResult::Failed { error },
+                None => check_profile(&paths, &profile_name, options.timeout).await,
+            };
+            (profile_name, result)
+        }
+    };
+    run_bounded(
+        unchecked,
+        options.concurrency,
+        check,
+        |(profile_name, result)| {
+            if result.is_ok() {
+                cache.insert(&profile_name, result.clone(), now());
+                changed = true;
+            } else {
+                changed |= cache.remove(&profile_name);
+            }
+            on_result(&profile_name, result.clone());
+            checked.push((profile_name, result));
+        },
+    )
+    .await?;
+
+    if changed {
+        cache.save(now())?;
+    }
+    if !checked.is_empty() {
+        record_health(checked.iter().map(|(name, result)| (name.as_str(), result)))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+
+    #[test]
+    fn test_summarize_error() {
+        assert_eq!(
+            summarize_error(
+                "GetCallerIdentity failed: dispatch failure: io error: dns error (DispatchFailure(DispatchFailure { source: ConnectorError }))"
+            ),
+            "GetCallerIdentity failed: dispatch failure: io error: dns error"
+        );
+        assert_eq!(
+            summarize_error("no credentials found in chain. Attempted:\n  Environment: not set"),
+            "no credentials found in chain. Attempted:"
+        );
+        assert_eq!(
+            summarize_error("connection reset (os error 104)"),
+            "connection reset (os error 104)"
+        );
+    }
+
+    #[test]
+    fn test_every_profile_reports_once() {
+        let paths = ConfigPaths {
+            config: PathBuf::from("/nonexistent/config"),
+            credentials: PathBuf::from("/nonexistent/credentials"),
+        };
+        let names: Vec<String> = ["a", "b", "c"].map(String::from).to_vec();
+        let options = CheckOptions {
+            concurrency: 2,
+            timeout: Duration::ZERO,
+            use_cache: false,
+        };
+
+        let mut reported = Vec::new();
+        check_profiles(&paths, &names, options, |name, result| {
+            assert!(!result.is_ok());
+            reported.push(name.to_string());
+        })
+        .unwrap();
+
+        reported.sort();
+        assert_eq!(reported, names);
+    }
+}
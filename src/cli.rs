@@ -0,0 +1,1440 @@
+use crate::audit::AuditEvent;
+use crate::backend::Backend;
+use crate::config::{ConfigPaths, read_aws_config};
+use crate::filter::ProfileFilter;
+use crate::matcher::MatchMode;
+use crate::output::{ListField, OutputFormat};
+use crate::regions::KNOWN_REGIONS;
+use crate::session::SessionDuration;
+use crate::settings::Settings;
+use crate::shell::{KeyBinding, SHELL_NAMES, Shell};
+use crate::state::{Scope, SelectorState, Window};
+use crate::template::DisplayFormat;
+use crate::ui::{PageSize, Picker, SortOrder};
+use clap::builder::{PossibleValue, PossibleValuesParser, TypedValueParser};
+use clap::{Arg, ArgAction, Command};
+use clap_complete::engine::{ArgValueCandidates, CompletionCandidate};
+use clap_complete::env::Shells;
+use std::path::PathBuf;
+
+pub fn build_cli() -> Command {
+    Command::new("aws-profile-selector")
+        .version("0.1.0")
+        .author("AWS Profile Selector - Rust Edition")
+        .about("Interactive AWS profile selector")
+        .arg(
+            Arg::new("current")
+                .short('c')
+                .long("current")
+                .help("Output a shell command for the current shell instead of writing the current-profile file")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("config")
+                .long("config")
+                .help("Path to the AWS config file (defaults to $AWS_CONFIG_FILE or ~/.aws/config)")
+                .value_name("PATH")
+                .value_parser(clap::value_parser!(PathBuf))
+                .global(true),
+        )
+        .arg(
+            Arg::new("credentials")
+                .long("credentials")
+                .help("Path to the AWS credentials file (defaults to $AWS_SHARED_CREDENTIALS_FILE or ~/.aws/credentials)")
+                .value_name("PATH")
+                .value_parser(clap::value_parser!(PathBuf))
+                .global(true),
+        )
+        .arg(
+            Arg::new("verbose")
+                .short('v')
+                .long("verbose")
+                .help("Explain what was detected and chosen on stderr (-vv for details, -vvv with the AWS SDK's own events)")
+                .action(ArgAction::Count)
+                .global(true),
+        )
+        .arg(
+            Arg::new("quiet")
+                .short('q')
+                .long("quiet")
+                .help("Leave out warnings and notes")
+                .action(ArgAction::SetTrue)
+                .conflicts_with("verbose")
+                .global(true),
+        )
+        .arg(
+            Arg::new("log-file")
+                .long("log-file")
+                .help("Append log messages to this file instead of printing them")
+                .value_name("PATH")
+                .value_parser(clap::value_parser!(PathBuf))
+                .global(true),
+        )
+        .arg(
+            Arg::new("dry-run")
+                .long("dry-run")
+                .help("Show what would change (file writes as a diff), hooks, and shell commands without doing any of it")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("read-only")
+                .long("read-only")
+                .help("Refuse to write any file: the AWS config, the current profile, kubeconfig, or caches")
+                .env("AWS_PROFILE_SELECTOR_READ_ONLY")
+                .value_parser(clap::builder::FalseyValueParser::new())
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("redact")
+                .long("redact")
+                .help("Mask account IDs except their last 4 digits in the selectors, list, and whoami")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("accessible")
+                .long("accessible")
+                .help("Pick from plain numbered lists instead of drawn prompts, for screen readers and dumb terminals (also set by TERM=dumb)")
+                .action(ArgAction::SetTrue)
+                .global(true),
+        )
+        .arg(
+            Arg::new("scope")
+                .long("scope")
+                .help("Share the current profile between terminals (global) or keep one per tmux pane or tty (terminal) [default: global]")
+                .value_name("SCOPE")
+                .value_parser(clap::value_parser!(Scope))
+                .env("AWS_PROFILE_SELECTOR_SCOPE")
+                .global(true),
+        )
+        .arg(
+            Arg::new("output-shell")
+                .long("shell")
+                .help("Shell syntax for emitted commands and statusline colors, instead of detecting it from $SHELL")
+                .value_name("SHELL")
+                .value_parser(PossibleValuesParser::new(SHELL_NAMES).map(|name| Shell::from_name(&name)))
+                .env("AWS_PROFILE_SELECTOR_SYNTAX")
+                .global(true),
+        )
+        // Pre-subcommand flags, kept working for existing scripts and shell
+        // wrappers.
+        .arg(
+            Arg::new("activate")
+                .short('a')
+                .long("activate")
+                .help("Activate a profile by name, or by a unique prefix or fuzzy match of one")
+                .value_name("PROFILE")
+                .add(profile_candidates())
+                .hide(true),
+        )
+        .arg(
+            Arg::new("deactivate")
+                .short('d')
+                .long("deactivate")
+                .help("Deactivate AWS_PROFILE")
+                .action(ArgAction::SetTrue)
+                .hide(true),
+        )
+        .arg(
+            Arg::new("new")
+                .short('n')
+                .long("new")
+                .help("Set a profile name that is not available in the list")
+                .value_name("PROFILE")
+                .add(profile_candidates())
+                .hide(true),
+        )
+        .args(activation_args())
+        .subcommand(
+            Command::new("activate")
+                .about("Activate a profile, selecting it interactively when no name is given")
+                .arg(
+                    Arg::new("profile")
+                        .help("Name of the profile to activate, - for the previous one, or text to filter the selector by")
+                        .value_name("PROFILE")
+                .add(profile_candidates()),
+                )
+                .args(activation_args()),
+        )
+        .subcommand(Command::new("deactivate").about("Deactivate AWS_PROFILE"))
+        .subcommand(
+            Command::new("switch")
+                .about("Pick from the most recently used profiles, with an entry that opens the full list")
+                .arg(
+                    Arg::new("count")
+                        .short('n')
+                        .long("count")
+                        .help("How many recent profiles to offer [default: 5]")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u64).range(1..).map(|n| n as usize)),
+                )
+                .args(activation_args()),
+        )
+        .subcommand(
+            Command::new("role")
+                .about("Switch to another role in the current profile's account")
+                .arg(
+                    Arg::new("role-name")
+                        .help("Role to switch to, or a glob; picked from a list when omitted")
+                        .value_name("ROLE"),
+                )
+                .args(activation_args()),
+        )
+        .subcommand(
+            Command::new("new")
+                .about("Set a profile name that is not available in the list")
+                .arg(
+                    Arg::new("profile")
+                        .help("Name of the profile to set")
+                        .value_name("PROFILE")
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("add")
+                .about("Create a new SSO, static-key, or assume-role profile interactively")
+                .arg(
+                    Arg::new("profile")
+                        .help("Name of the new profile (prompted for when omitted)")
+                        .value_name("PROFILE"),
+                )
+                .arg(
+                    Arg::new("from-template")
+                        .long("from-template")
+                        .help("Write the profiles of a template from the [templates] settings instead")
+                        .value_name("TEMPLATE")
+                        .conflicts_with("profile"),
+                )
+                .arg(
+                    Arg::new("account")
+                        .long("account")
+                        .help("Account ID the template's {account_id} and {account_name} stand for")
+                        .value_name("ID")
+                        .value_parser(parse_account_id)
+                        .requires("from-template"),
+                )
+                .arg(
+                    Arg::new("var")
+                        .long("var")
+                        .help("Set a template variable, e.g. --var region=eu-west-1; repeatable")
+                        .value_name("KEY=VALUE")
+                        .value_parser(parse_assignment)
+                        .action(ArgAction::Append)
+                        .requires("from-template"),
+                )
+                .arg(yes_arg()),
+        )
+        .subcommand(
+            Command::new("edit")
+                .about("Edit a profile's section in $EDITOR, or answer prompts with --prompt")
+                .arg(
+                    Arg::new("profile")
+                        .help("Profile to edit")
+                        .value_name("PROFILE")
+                        .add(profile_candidates())
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("prompt")
+                        .long("prompt")
+                        .help("Change region, output, and role through prompts instead of an editor")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(yes_arg()),
+        )
+        .subcommand(
+            Command::new("remove")
+                .about("Delete a profile from the config and credentials files")
+                .arg(
+                    Arg::new("profile")
+                        .help("Profile to remove")
+                        .value_name("PROFILE")
+                        .add(profile_candidates())
+                        .required(true),
+                )
+                .arg(yes_arg()),
+        )
+        .subcommand(
+            Command::new("rename")
+                .about("Rename a profile and update source_profile references to it")
+                .args(source_target_args()),
+        )
+        .subcommand(
+            Command::new("copy")
+                .about("Duplicate a profile under a new name")
+                .args(source_target_args())
+                .arg(
+                    Arg::new("set")
+                        .long("set")
+                        .help("Override a setting in the copy, e.g. --set sso_role_name=Admin; repeatable")
+                        .value_name("KEY=VALUE")
+                        .value_parser(parse_assignment)
+                        .action(ArgAction::Append),
+                ),
+        )
+        .subcommand(
+            Command::new("list")
+                .about("List the profiles found in the AWS config")
+                .arg(output_arg())
+                .arg(
+                    Arg::new("names")
+                        .long("names")
+                        .help("Print only profile names, one per line")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("output"),
+                )
+                .arg(
+                    Arg::new("filter")
+                        .long("filter")
+                        .help("Only list profiles matching field=value (the value may be a glob) or field~value (name, region, account, role); repeatable")
+                        .value_name("EXPR")
+                        .value_parser(clap::value_parser!(ProfileFilter))
+                        .action(ArgAction::Append),
+                )
+                .arg(
+                    regex_arg()
+                        .help("Read --filter values as regexes: = has to match the whole field, ~ any part")
+                        .requires("filter"),
+                )
+                .arg(format_arg().conflicts_with_all(["output", "names"]))
+                .arg(
+                    Arg::new("fields")
+                        .long("fields")
+                        .help("Print only these columns, tab-separated, e.g. name,account,region,role,expiry")
+                        .value_name("FIELDS")
+                        .value_parser(clap::value_parser!(ListField))
+                        .value_delimiter(',')
+                        .action(ArgAction::Append)
+                        .conflicts_with_all(["output", "names", "format"]),
+                )
+                .arg(
+                    Arg::new("no-header")
+                        .long("no-header")
+                        .help("Leave out the header row of the table or --fields")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with_all(["output", "names", "format"]),
+                )
+                .arg(
+                    check_arg()
+                        .help("Check each profile's credentials with STS and add the results")
+                        .conflicts_with_all(["names", "format"]),
+                )
+                .arg(sort_arg().help("Order of the profiles [default: name]"))
+                .arg(reverse_arg())
+                .arg(
+                    Arg::new("backend")
+                        .long("backend")
+                        .help("Only list profiles whose credentials aws-vault or granted supplies")
+                        .value_name("BACKEND")
+                        .value_parser(clap::value_parser!(Backend)),
+                )
+                .args(attribute_filter_args()),
+        )
+        .subcommand(
+            Command::new("show")
+                .about("Print every attribute of a profile, with its role chain and SSO session")
+                .arg(output_arg())
+                .arg(
+                    Arg::new("profile")
+                        .help("Profile to show (defaults to the active profile)")
+                        .value_name("PROFILE")
+                        .add(profile_candidates()),
+                ),
+        )
+        .subcommand(
+            Command::new("alias")
+                .about("Manage short aliases that can be used wherever a profile name is expected")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add")
+                        .about("Point an alias at a profile")
+                        .arg(
+                            Arg::new("alias")
+                                .help("Short name, e.g. p")
+                                .value_name("ALIAS")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::new("profile")
+                                .help("Profile the alias stands for")
+                                .value_name("PROFILE")
+                                .add(profile_candidates())
+                                .required(true),
+                        ),
+                )
+                .subcommand(
+                    Command::new("remove").about("Delete an alias").arg(
+                        Arg::new("alias")
+                            .help("Alias to delete")
+                            .value_name("ALIAS")
+                            .required(true),
+                    ),
+                )
+                .subcommand(Command::new("list").about("Print the aliases as alias = profile")),
+        )
+        .subcommand(
+            Command::new("local")
+                .about("Manage the .aws-profile file that sets the profile for a directory tree")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("set")
+                        .about("Write .aws-profile in the current directory")
+                        .arg(
+                            Arg::new("profile")
+                                .help("Profile to use here and below")
+                                .value_name("PROFILE")
+                                .add(profile_candidates())
+                                .required(true),
+                        ),
+                )
+                .subcommand(
+                    Command::new("unset").about("Remove .aws-profile from the current directory"),
+                )
+                .subcommand(
+                    Command::new("show")
+                        .about("Print the profile of the nearest .aws-profile (exits 1 when there is none)"),
+                ),
+        )
+        .subcommand(
+            Command::new("fav")
+                .about("Manage favorite profiles, which are pinned to the top of the selector")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add").about("Mark a profile as a favorite").arg(
+                        Arg::new("profile")
+                            .help("Profile to pin")
+                            .value_name("PROFILE")
+                            .add(profile_candidates())
+                            .required(true),
+                    ),
+                )
+                .subcommand(
+                    Command::new("remove").about("Unmark a favorite profile").arg(
+                        Arg::new("profile")
+                            .help("Profile to unpin")
+                            .value_name("PROFILE")
+                            .add(profile_candidates())
+                            .required(true),
+                    ),
+                )
+                .subcommand(Command::new("list").about("Print the favorite profiles, one per line")),
+        )
+        .subcommand(
+            Command::new("hide")
+                .about("Manage glob patterns for profiles the selector leaves out (they can still be activated by name)")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("add").about("Hide profiles matching a pattern").arg(
+                        Arg::new("pattern")
+                            .help("Profile name or glob, e.g. 'generated-*'")
+                            .value_name("PATTERN")
+                            .add(profile_candidates())
+                            .required(true),
+                    ),
+                )
+                .subcommand(
+                    Command::new("remove").about("Stop hiding a pattern").arg(
+                        Arg::new("pattern")
+                            .help("Pattern as it was added")
+                            .value_name("PATTERN")
+                            .required(true),
+                    ),
+                )
+                .subcommand(Command::new("list").about("Print the hide patterns, one per line")),
+        )
+        .subcommand(
+            Command::new("history")
+                .about("Print the recent profile activations, newest first")
+                .args_conflicts_with_subcommands(true)
+                .arg(
+                    Arg::new("limit")
+                        .short('n')
+                        .long("limit")
+                        .help("How many activations to print")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u64).range(1..).map(|n| n as usize))
+                        .default_value("20"),
+                )
+                .arg(output_arg())
+                .subcommand(
+                    Command::new("clear")
+                        .about("Forget every recorded activation (used by --last and recency sorting)"),
+                ),
+        )
+        .subcommand(
+            Command::new("stats")
+                .about("Print how often each profile was activated, busiest first")
+                .arg(
+                    Arg::new("window")
+                        .short('w')
+                        .long("window")
+                        .help("Count activations within this window, e.g. 24h, 7d, or 4w (repeatable; the first one orders the list)")
+                        .value_name("WINDOW")
+                        .value_parser(clap::value_parser!(Window))
+                        .action(ArgAction::Append)
+                        .default_values(["7d", "30d"]),
+                )
+                .arg(
+                    Arg::new("top")
+                        .long("top")
+                        .help("Print only the first N profiles")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u64).range(1..).map(|n| n as usize)),
+                )
+                .arg(
+                    Arg::new("unused")
+                        .long("unused")
+                        .help("Print only the profiles not activated within the first window")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            Command::new("paths")
+                .about("Print where the AWS files and settings are read from and where state is kept")
+                .arg(
+                    Arg::new("name")
+                        .help("Print only this path, e.g. current-profile")
+                        .value_parser([
+                            "config",
+                            "credentials",
+                            "settings",
+                            "state-dir",
+                            "current-profile",
+                            "state-file",
+                            "local-profile",
+                            "backups-dir",
+                            "audit-log",
+                            "cache-dir",
+                            "socket",
+                        ]),
+                )
+                .arg(output_arg()),
+        )
+        .subcommand(
+            Command::new("current")
+                .about("Print the active profile and where it comes from (exits 1 when none is active)")
+                .arg(output_arg()),
+        )
+        .subcommand(
+            Command::new("status")
+                .about("Show AWS_PROFILE, the current-profile file, and .aws-profile, which one the AWS CLI and SDKs use, and where they disagree")
+                .arg(output_arg()),
+        )
+        .subcommand(
+            Command::new("import")
+                .about("Generate profiles from an external source")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("sso")
+                        .about("Create a profile for every account and role available through an SSO session")
+                        .arg(
+                            Arg::new("session")
+                                .long("session")
+                                .help("sso-session to enumerate (prompted for when there are several)")
+                                .value_name("NAME"),
+                        )
+                        .arg(
+                            Arg::new("template")
+                                .long("template")
+                                .help("Profile name template; supports {account_name}, {account_id}, {role}, {session}")
+                                .value_name("TEMPLATE")
+                                .default_value("{account_name}-{role}"),
+                        )
+                        .arg(
+                            Arg::new("region")
+                                .long("region")
+                                .help("Default region to set on the generated profiles")
+                                .value_name("REGION"),
+                        )
+                        .arg(yes_arg()),
+                )
+                .subcommand(
+                    Command::new("org")
+                        .about("Create an assume-role profile for every account in the AWS Organization (needs the AWS CLI)")
+                        .arg(
+                            Arg::new("role")
+                                .long("role")
+                                .help("Role to assume in each account, e.g. OrganizationAccountAccessRole")
+                                .value_name("ROLE")
+                                .required(true),
+                        )
+                        .arg(
+                            Arg::new("source-profile")
+                                .long("source-profile")
+                                .help("Management account profile to list the accounts with and assume the role from (default: the active profile)")
+                                .value_name("PROFILE")
+                                .add(profile_candidates()),
+                        )
+                        .arg(
+                            Arg::new("template")
+                                .long("template")
+                                .help("Profile name template; supports {account_name}, {account_id}, {role}, {source_profile}")
+                                .value_name("TEMPLATE")
+                                .default_value("{account_name}-{role}"),
+                        )
+                        .arg(
+                            Arg::new("region")
+                                .long("region")
+                                .help("Default region to set on the generated profiles")
+                                .value_name("REGION"),
+                        )
+                        .arg(yes_arg()),
+                ),
+        )
+        .subcommand(
+            Command::new("export-bundle")
+                .about("Write profile and sso-session definitions, without secrets, to a bundle for sharing")
+                .arg(
+                    Arg::new("profiles")
+                        .help("Profiles or globs to export (picked interactively when none are given)")
+                        .value_name("PROFILE")
+                        .num_args(1..)
+                        .add(profile_candidates()),
+                )
+                .arg(
+                    regex_arg()
+                        .help("Match the profile arguments as regexes over the profile names")
+                        .requires("profiles"),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .help("Bundle format")
+                        .value_name("FORMAT")
+                        .value_parser(["yaml", "json"])
+                        .default_value("yaml"),
+                )
+                .arg(
+                    Arg::new("file")
+                        .long("file")
+                        .help("Write to FILE instead of printing")
+                        .value_name("FILE")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                ),
+        )
+        .subcommand(
+            Command::new("import-bundle")
+                .about("Merge a bundle's profiles and sso-sessions into the AWS config, asking about conflicts")
+                .arg(
+                    Arg::new("source")
+                        .help("Bundle file, - for stdin, or a git URL (https, ssh, or ending in .git)")
+                        .value_name("SOURCE")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("path")
+                        .long("path")
+                        .help("Bundle file inside a git repository")
+                        .value_name("PATH")
+                        .default_value("aws-profiles.yaml"),
+                )
+                .arg(yes_arg().help("Take the bundle's values for conflicting entries and merge without asking")),
+        )
+        .subcommand(
+            Command::new("migrate-sso")
+                .about("Move inline sso_start_url/sso_region settings into shared [sso-session] blocks")
+                .arg(yes_arg()),
+        )
+        .subcommand(
+            Command::new("doctor")
+                .about("Check the AWS config for common mistakes (exits 1 when problems are found)")
+                .arg(output_arg())
+                .subcommand(
+                    Command::new("creds")
+                        .about("Check every profile's credentials with STS GetCallerIdentity (exits 1 when any fail)")
+                        .arg(output_arg())
+                        .args(check_tuning_args())
+                        .args(attribute_filter_args()),
+                ),
+        )
+        .subcommand(
+            Command::new("cache")
+                .about("Manage cached STS and IAM results (under ~/.cache/aws-profile-selector)")
+                .subcommand_required(true)
+                .subcommand(Command::new("clear").about("Delete every cached result")),
+        )
+        .subcommand(
+            Command::new("backup")
+                .about("List or restore the copies of the AWS config and credentials taken before each change")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("list")
+                        .about("List the backups, newest first")
+                        .arg(output_arg()),
+                )
+                .subcommand(
+                    Command::new("restore")
+                        .about("Put the files back as they were before the command that took a backup")
+                        .arg(
+                            Arg::new("id")
+                                .help("Backup to restore, from backup list, or latest")
+                                .value_name("ID")
+                                .required(true),
+                        )
+                        .arg(yes_arg()),
+                ),
+        )
+        .subcommand(
+            Command::new("audit")
+                .about("Read the audit log of activations, exec, and console")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("export")
+                        .about("Print the audit log, oldest first, as one JSON object per line")
+                        .arg(
+                            Arg::new("since")
+                                .long("since")
+                                .help("Only entries from this recent window, e.g. 24h, 7d, or 4w")
+                                .value_name("WINDOW")
+                                .value_parser(clap::value_parser!(Window)),
+                        )
+                        .arg(
+                            Arg::new("profile")
+                                .long("profile")
+                                .help("Only entries for this profile, or profiles matching this glob")
+                                .value_name("PROFILE"),
+                        )
+                        .arg(
+                            Arg::new("event")
+                                .long("event")
+                                .help("Only entries for this event; repeatable")
+                                .value_name("EVENT")
+                                .value_parser(clap::value_parser!(AuditEvent))
+                                .action(ArgAction::Append),
+                        )
+                        .arg(
+                            output_arg()
+                                .default_value(None::<&str>)
+                                .help("Output format instead of JSON lines"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("statusline")
+                .about("Print the active profile for a tmux status line, colored by its credentials' health")
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .help("Template for the profile, e.g. '{name} {account_name}'")
+                        .value_name("TEMPLATE")
+                        .value_parser(clap::value_parser!(DisplayFormat)),
+                ),
+        )
+        .subcommand(
+            Command::new("prompt")
+                .about("Print the active profile as a colored prompt segment (for starship or PS1)")
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .help("Template for the profile, e.g. '{name} {account_name}'")
+                        .value_name("TEMPLATE")
+                        .value_parser(clap::value_parser!(DisplayFormat)),
+                )
+                .arg(
+                    Arg::new("plain")
+                        .long("plain")
+                        .help("Leave out the colors (also set by a non-empty NO_COLOR)")
+                        .action(ArgAction::SetTrue),
+                )
+        )
+        .subcommand(
+            Command::new("serve")
+                .about("Answer newline-delimited JSON requests for editor integrations")
+                .arg(
+                    Arg::new("transport")
+                        .help("Where requests come from and responses go")
+                        .value_parser(["stdio", "socket"])
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("regions")
+                .about("Pick an AWS region; with -c, print the commands that set AWS_REGION and AWS_DEFAULT_REGION")
+                .arg(
+                    Arg::new("region")
+                        .help("Region to use (selected interactively when omitted)")
+                        .value_name("REGION")
+                        .add(region_candidates()),
+                )
+                .arg(
+                    Arg::new("names")
+                        .long("names")
+                        .help("Print the regions one per line, those the config uses first")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("region"),
+                ),
+        )
+        .subcommand(
+            Command::new("whoami")
+                .about("Show the AWS identity (account, ARN, user id) of a profile via STS")
+                .arg(
+                    Arg::new("profile")
+                        .help("Profile to check (defaults to the active profile)")
+                        .value_name("PROFILE")
+                .add(profile_candidates()),
+                )
+                .arg(output_arg())
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .help("Resolve every profile's identity at once and report each (exits 1 when any fail)")
+                        .action(ArgAction::SetTrue)
+                        .conflicts_with("profile"),
+                )
+                .args(check_tuning_args()),
+        )
+        .subcommand(
+            Command::new("login")
+                .about("Log in to AWS SSO for a profile using the device-authorization flow")
+                .arg(
+                    Arg::new("profile")
+                        .help("Profile to log in with (defaults to the active profile)")
+                        .value_name("PROFILE")
+                .add(profile_candidates()),
+                )
+                .arg(
+                    Arg::new("force")
+                        .long("force")
+                        .help("Log in even when the cached SSO token is still valid")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("logout")
+                .about("Remove the cached SSO token for a profile to force re-authentication")
+                .arg(
+                    Arg::new("profile")
+                        .help("Profile to log out (defaults to the active profile)")
+                        .value_name("PROFILE")
+                .add(profile_candidates())
+                        .conflicts_with("all"),
+                )
+                .arg(
+                    Arg::new("all")
+                        .long("all")
+                        .help("Remove the cached tokens of every SSO profile in the config")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("refresh")
+                .about("Renew the SSO tokens of profiles used in the last week before they expire, without a browser")
+                .arg(
+                    Arg::new("once")
+                        .long("once")
+                        .help("Check once and exit, failing when a token couldn't be renewed (for a systemd timer or cron)")
+                        .action(ArgAction::SetTrue)
+                        .required_unless_present("daemon")
+                        .conflicts_with("daemon"),
+                )
+                .arg(
+                    Arg::new("daemon")
+                        .long("daemon")
+                        .help("Keep running, checking every minute")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("console")
+                .about("Open the AWS web console signed in as a profile")
+                .arg(
+                    Arg::new("profile")
+                        .help("Profile to sign in with (defaults to the active profile)")
+                        .value_name("PROFILE")
+                .add(profile_candidates()),
+                )
+                .arg(
+                    Arg::new("print")
+                        .long("print")
+                        .help("Print the sign-in URL instead of opening a browser")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(duration_arg()),
+        )
+        .subcommand(
+            Command::new("ecr-login")
+                .about("Log Docker in to an ECR registry with a profile's credentials")
+                .arg(
+                    Arg::new("profile")
+                        .help("Profile to log in with (defaults to the active profile)")
+                        .value_name("PROFILE")
+                .add(profile_candidates()),
+                )
+                .arg(
+                    Arg::new("registry")
+                        .long("registry")
+                        .help("Registry (account) ID, in the profile's region (defaults to the profile's own)")
+                        .value_name("ID"),
+                )
+                .arg(
+                    Arg::new("print")
+                        .long("print")
+                        .help("Print the registry password instead of running docker login")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("codeartifact-login")
+                .about("Point npm, pip, or cargo at a CodeArtifact repository with a profile's credentials")
+                .arg(
+                    Arg::new("profile")
+                        .help("Profile to log in with (defaults to the active profile)")
+                        .value_name("PROFILE")
+                .add(profile_candidates()),
+                )
+                .arg(
+                    Arg::new("domain")
+                        .long("domain")
+                        .help("CodeArtifact domain")
+                        .value_name("DOMAIN")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("domain-owner")
+                        .long("domain-owner")
+                        .help("Account ID owning the domain (defaults to the profile's own)")
+                        .value_name("ID"),
+                )
+                .arg(
+                    Arg::new("repo")
+                        .long("repo")
+                        .help("Repository in the domain")
+                        .value_name("REPO")
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("tool")
+                        .long("tool")
+                        .help("Package manager to configure")
+                        .value_name("TOOL")
+                        .value_parser(["npm", "pip", "cargo"])
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("print")
+                        .long("print")
+                        .help("Print the token instead of configuring the tool")
+                        .action(ArgAction::SetTrue),
+                ),
+        )
+        .subcommand(
+            Command::new("credential-process")
+                .about("Print a profile's credentials as the JSON a credential_process helper returns")
+                .arg(
+                    Arg::new("profile")
+                        .long("profile")
+                        .help("Profile to resolve")
+                        .value_name("PROFILE")
+                        .required(true)
+                        .add(profile_candidates()),
+                ),
+        )
+        .subcommand(
+            Command::new("secure")
+                .about("Keep static access keys in the OS keychain instead of the credentials file")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("import")
+                        .about("Move profiles' static keys from the credentials file into the OS keychain")
+                        .arg(
+                            Arg::new("profiles")
+                                .help("Profiles or globs to move (defaults to every profile with static keys)")
+                                .value_name("PROFILE")
+                                .num_args(1..)
+                                .add(profile_candidates()),
+                        )
+                        .arg(
+                            regex_arg()
+                                .help("Match the profile arguments as regexes over the profile names")
+                                .requires("profiles"),
+                        ),
+                ),
+        )
+        .subcommand(
+            Command::new("creds")
+                .about("Copy temporary credentials into the credentials file for tools that only read static keys")
+                .subcommand_required(true)
+                .subcommand(
+                    Command::new("write")
+                        .about("Resolve a profile's temporary credentials and write them to a credentials file section")
+                        .arg(
+                            Arg::new("profile")
+                                .help("Profile to resolve (defaults to the active profile)")
+                                .value_name("PROFILE")
+                                .add(profile_candidates()),
+                        )
+                        .arg(
+                            Arg::new("as")
+                                .long("as")
+                                .help("Section to write (defaults to the profile's name)")
+                                .value_name("NAME"),
+                        )
+                        .arg(
+                            Arg::new("force")
+                                .long("force")
+                                .help("Replace a section that wasn't written by creds write")
+                                .action(ArgAction::SetTrue),
+                        )
+                        .arg(duration_arg()),
+                )
+                .subcommand(
+                    Command::new("clean")
+                        .about("Remove the sections written by creds write whose credentials have expired"),
+                ),
+        )
+        .subcommand(
+            Command::new("exec")
+                .about("Run a command with AWS_PROFILE set for that process only")
+                .arg(
+                    Arg::new("profile")
+                        .help("Profile to run the command under, or a glob to run it under each match")
+                        .value_name("PROFILE")
+                .add(profile_candidates())
+                        .required_unless_present("multi"),
+                )
+                .arg(with_credentials_arg())
+                .arg(
+                    Arg::new("backend")
+                        .long("backend")
+                        .help("Let aws-vault or granted supply the credentials and run the command")
+                        .value_name("BACKEND")
+                        .value_parser(clap::value_parser!(Backend))
+                        .conflicts_with_all(["with-credentials", "docker"]),
+                )
+                .arg(
+                    Arg::new("docker")
+                        .long("docker")
+                        .help("Pass the resolved credentials and region into a docker run, docker compose run, or docker exec")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("multi")
+                        .long("multi")
+                        .help("Run the command once per profile, picked interactively when none is given")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("parallel")
+                        .long("parallel")
+                        .help("With several profiles, run this many at once [default: 1]")
+                        .value_name("N")
+                        .value_parser(clap::value_parser!(u64).range(1..).map(|n| n as usize)),
+                )
+                .arg(regex_arg().help("Match the profile as a regex over the profile names"))
+                .arg(duration_arg().conflicts_with("backend"))
+                .arg(
+                    Arg::new("command")
+                        .help("Command to run, after --")
+                        .value_name("COMMAND")
+                        .num_args(1..)
+                        .last(true)
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("env")
+                .about("Print the profile's resolved credentials as shell export commands")
+                .arg(
+                    Arg::new("profile")
+                        .help("Profile to resolve (defaults to the active profile)")
+                        .value_name("PROFILE")
+                .add(profile_candidates()),
+                )
+                .arg(
+                    Arg::new("format")
+                        .long("format")
+                        .help("Shell exports, a .env file, or a file for docker run --env-file")
+                        .value_name("FORMAT")
+                        .value_parser(["shell", "dotenv", "docker"])
+                        .default_value("shell"),
+                )
+                .arg(
+                    Arg::new("file")
+                        .long("file")
+                        .help("Write to FILE (readable only by you) instead of printing")
+                        .value_name("FILE")
+                        .value_parser(clap::value_parser!(PathBuf)),
+                )
+                .arg(duration_arg()),
+        )
+        .subcommand(
+            Command::new("init")
+                .about("Print a shell function that switches profiles in the current shell")
+                .arg(
+                    Arg::new("shell")
+                        .help("Shell to generate the integration for")
+                        .value_name("SHELL")
+                        .value_parser(PossibleValuesParser::new([
+                            PossibleValue::new("bash"),
+                            PossibleValue::new("zsh"),
+                            PossibleValue::new("fish"),
+                            PossibleValue::new("nushell").alias("nu"),
+                            PossibleValue::new("elvish"),
+                            PossibleValue::new("xonsh"),
+                            PossibleValue::new("tcsh"),
+                        ]))
+                        .required(true),
+                )
+                .arg(
+                    Arg::new("cmd")
+                        .long("cmd")
+                        .help("Name of the generated function")
+                        .value_name("NAME")
+                        .default_value("awsp"),
+                )
+                .arg(
+                    Arg::new("hook")
+                        .long("hook")
+                        .help("Also set AWS_PROFILE from .aws-profile files when changing directory")
+                        .action(ArgAction::SetTrue),
+                )
+                .arg(
+                    Arg::new("bind")
+                        .long("bind")
+                        .help("Also open the selector with KEY, e.g. ctrl-o or alt-p (bash, zsh, and fish)")
+                        .value_name("KEY")
+                        .num_args(0..=1)
+                        .default_missing_value("ctrl-o")
+                        .value_parser(clap::value_parser!(KeyBinding)),
+                ),
+        )
+        .subcommand(
+            Command::new("hook-env")
+                .about("Print the commands that apply the nearest .aws-profile (used by init --hook)")
+                .hide(true),
+        )
+        .subcommand(
+            Command::new("completions")
+                .about("Print a completion script that completes profile names from the AWS config")
+                .arg(
+                    Arg::new("shell")
+                        .help("Shell to generate completions for")
+                        .value_name("SHELL")
+                        .value_parser(Shells::builtins().names().collect::<Vec<_>>())
+                        .required(true),
+                ),
+        )
+        .subcommand(
+            Command::new("shell")
+                .about("Start a subshell with AWS_PROFILE set, without touching the active profile")
+                .arg(
+                    Arg::new("profile")
+                        .help("Profile to pin the shell to, or text to filter the selector by (selected interactively when omitted)")
+                        .value_name("PROFILE")
+                .add(profile_candidates()),
+                )
+                .arg(with_credentials_arg())
+                .args(selection_args()),
+        )
+}
+
+/// Options accepted wherever a profile gets activated: the top-level
+/// interactive mode and the `activate` subcommand.
+fn activation_args() -> Vec<Arg> {
+    let mut args = vec![
+        Arg::new("auto-login")
+            .long("auto-login")
+            .help("Log in to SSO first if the profile's cached token is missing or expired")
+            .action(ArgAction::SetTrue),
+        Arg::new("last")
+            .long("last")
+            .help("Switch back to the previously active profile (same as `activate -`)")
+            .action(ArgAction::SetTrue),
+        Arg::new("with-region")
+            .long("with-region")
+            .help("With -c, also set AWS_REGION and AWS_DEFAULT_REGION from the profile's region")
+            .action(ArgAction::SetTrue),
+        output_arg().help(
+            "Report the activated profile as json or yaml (with -c, instead of the shell commands)",
+        ),
+        Arg::new("copy")
+            .long("copy")
+            .help("Copy the command that sets AWS_PROFILE to the clipboard instead of activating")
+            .action(ArgAction::SetTrue),
+        Arg::new("copy-creds")
+            .long("copy-creds")
+            .help("Copy export commands for the profile's temporary credentials to the clipboard instead of activating")
+            .action(ArgAction::SetTrue)
+            .conflicts_with("copy"),
+    ];
+    // An explicitly named profile takes `--region` as an override rather
+    // than a filter.
+    args.extend(selection_args().into_iter().map(|arg| {
+        if arg.get_id() != "region" {
+            return arg;
+        }
+        arg.help("Also set AWS_REGION to this region; only filters the interactive selector")
+            .add(region_candidates())
+    }));
+    args
+}
+
+/// Flags for the interactive selector, shared by every command that can
+/// prompt for a profile.
+fn selection_args() -> Vec<Arg> {
+    let mut args = vec![
+        Arg::new("tui")
+            .long("tui")
+            .help("Pick the profile in a full-screen browser with a details pane")
+            .action(ArgAction::SetTrue),
+        Arg::new("picker")
+            .long("picker")
+            .help("Pick with builtin, fzf, skim, or a command that reads the lines on stdin and prints the chosen one [default: builtin]")
+            .value_name("PICKER")
+            .value_parser(clap::value_parser!(Picker))
+            .env("AWS_PROFILE_SELECTOR_PICKER"),
+        Arg::new("match")
+            .long("match")
+            .help("How typed text filters the list [default: fuzzy]")
+            .value_name("MODE")
+            .value_parser(clap::value_parser!(MatchMode))
+            .env("AWS_PROFILE_SELECTOR_MATCH"),
+        sort_arg().help("Order of the list before anything is typed [default: recent]"),
+        reverse_arg(),
+        Arg::new("page-size")
+            .long("page-size")
+            .help("Rows shown at once by the inline prompt, or 'auto' to fill the terminal [default: 10]")
+            .value_name("ROWS")
+            .value_parser(clap::value_parser!(PageSize))
+            .env("AWS_PROFILE_SELECTOR_PAGE_SIZE"),
+        format_arg(),
+        Arg::new("no-color")
+            .long("no-color")
+            .help("Don't color the selector (also set by a non-empty NO_COLOR)")
+            .action(ArgAction::SetTrue),
+        check_arg()
+            .help("Check each profile's credentials with STS and tag the results (live in --tui)"),
+        Arg::new("account-aliases")
+            .long("account-aliases")
+            .help("Show each account's IAM alias next to its ID (looked up once a day)")
+            .action(ArgAction::SetTrue),
+        Arg::new("show-hidden")
+            .long("show-hidden")
+            .help("Include profiles matched by the hide list")
+            .action(ArgAction::SetTrue),
+        Arg::new("by-account")
+            .long("by-account")
+            .help("Pick an account first, then one of its profiles")
+            .action(ArgAction::SetTrue),
+        Arg::new("pipe")
+            .long("pipe")
+            .help("Print the profile lines for a picker like fzf, or take the picked line on stdin")
+            .action(ArgAction::SetTrue)
+            .conflicts_with_all(["tui", "by-account"]),
+        Arg::new("selected")
+            .long("selected")
+            .help("The line picked from --pipe output, instead of reading it from stdin")
+            .value_name("LINE")
+            .requires("pipe"),
+        Arg::new("confirm")
+            .long("confirm")
+            .help(
+                "Show the picked profile's account, role, and region and ask before activating it",
+            )
+            .action(ArgAction::SetTrue),
+        Arg::new("no-auto")
+            .long("no-auto")
+            .help("Open the selector even when the name given as a filter matches one profile")
+            .action(ArgAction::SetTrue),
+        regex_arg().help("Match a name that isn't a profile as a regex over the profile names"),
+        Arg::new("select-index")
+            .long("select-index")
+            .help("Pick the Nth profile the selector would list (from 1) without prompting")
+            .value_name("N")
+            .value_parser(clap::value_parser!(u64).range(1..))
+            .conflicts_with_all(["tui", "by-account", "pipe", "select-match"]),
+        Arg::new("select-match")
+            .long("select-match")
+            .help("Pick the profile whose line (as --pipe prints it) is exactly this")
+            .value_name("LINE")
+            .conflicts_with_all(["tui", "by-account", "pipe"]),
+    ];
+    args.extend(attribute_filter_args());
+    args
+}
+
+/// `--regex`, shared by everything that matches profile names or filters
+/// with [`crate::filter::Pattern`].
+fn regex_arg() -> Arg {
+    Arg::new("regex").long("regex").action(ArgAction::SetTrue)
+}
+
+/// `--concurrency` and `--timeout`, for commands that check every profile.
+fn check_tuning_args() -> [Arg; 2] {
+    [
+        Arg::new("concurrency")
+            .long("concurrency")
+            .help("Profiles checked at the same time")
+            .value_name("N")
+            .value_parser(clap::builder::RangedU64ValueParser::<usize>::new().range(1..))
+            .default_value("8"),
+        Arg::new("timeout")
+            .long("timeout")
+            .help("Seconds to wait for each profile")
+            .value_name("SECONDS")
+            .value_parser(clap::value_parser!(u64).range(1..))
+            .default_value("10"),
+    ]
+}
+
+/// `--sort`, shared by `list` and the selector.
+fn sort_arg() -> Arg {
+    Arg::new("sort")
+        .long("sort")
+        .value_name("ORDER")
+        .value_parser(clap::value_parser!(SortOrder))
+        .env("AWS_PROFILE_SELECTOR_SORT")
+}
+
+/// `--reverse`, shared by `list` and the selector.
+fn reverse_arg() -> Arg {
+    Arg::new("reverse")
+        .long("reverse")
+        .help("Reverse the --sort order")
+        .action(ArgAction::SetTrue)
+}
+
+/// `--check`, shared by `list` and the selector.
+fn check_arg() -> Arg {
+    Arg::new("check").long("check").action(ArgAction::SetTrue)
+}
+
+/// `--format`, shared by `list` and the selector.
+fn format_arg() -> Arg {
+    Arg::new("format")
+        .long("format")
+        .help("Template for profile lines, e.g. '{name:<30} {account_id} {region} {role}'")
+        .value_name("TEMPLATE")
+        .value_parser(clap::value_parser!(DisplayFormat))
+        .env("AWS_PROFILE_SELECTOR_FORMAT")
+}
+
+/// `--region`, `--account`, and `--role` shorthands for `field=value`
+/// filters, shared by `list` and the selector.
+fn attribute_filter_args() -> Vec<Arg> {
+    [
+        ("region", "REGION", "Only include profiles in this region"),
+        (
+            "account",
+            "ACCOUNT_ID",
+            "Only include profiles for this account id",
+        ),
+        (
+            "role",
+            "ROLE",
+            "Only include profiles with this role name (sso_role_name or the role_arn's role)",
+        ),
+        (
+            "tag",
+            "TAG",
+            "Only include profiles with this tag from the settings file",
+        ),
+    ]
+    .into_iter()
+    .map(|(name, value_name, help)| Arg::new(name).long(name).help(help).value_name(value_name))
+    .collect()
+}
+
+/// Completes the known region names.
+fn region_candidates() -> ArgValueCandidates {
+    ArgValueCandidates::new(|| {
+        KNOWN_REGIONS
+            .iter()
+            .map(|region| CompletionCandidate::new(*region))
+            .collect::<Vec<_>>()
+    })
+}
+
+/// Completes profile names by reading the AWS config at completion time. The
+/// `--config`/`--credentials` flags aren't visible here, so only the
+/// environment variables and default paths are honoured.
+fn profile_candidates() -> ArgValueCandidates {
+    ArgValueCandidates::new(|| {
+        let mut candidates: Vec<CompletionCandidate> = ConfigPaths::resolve(None, None)
+            .and_then(|paths| read_aws_config(&paths))
+            .map(|config| {
+                config
+                    .profiles
+                    .iter()
+                    .map(|profile| CompletionCandidate::new(&profile.name))
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if let Ok(state) = SelectorState::load() {
+            let state = state.with_settings(&Settings::load().unwrap_or_default());
+            candidates.extend(state.all_aliases().iter().map(|(alias, profile_name)| {
+                CompletionCandidate::new(alias)
+                    .help(Some(format!("alias for {profile_name}").into()))
+            }));
+        }
+        candidates
+    })
+}
+
+fn source_target_args() -> [Arg; 2] {
+    [
+        Arg::new("source")
+            .help("Existing profile")
+            .value_name("PROFILE")
+            .add(profile_candidates())
+            .required(true),
+        Arg::new("target")
+            .help("New profile name")
+            .value_name("NEW_NAME")
+            .required(true),
+    ]
+}
+
+fn parse_assignment(value: &str) -> Result<(String, String), String> {
+    match value.split_once('=') {
+        Some((key, value)) if !key.trim().is_empty() => {
+            Ok((key.trim().to_string(), value.trim().to_string()))
+        }
+        _ => Err(format!("expected KEY=VALUE, got '{value}'")),
+    }
+}
+
+fn parse_account_id(value: &str) -> Result<String, String> {
+    match value.len() == 12 && value.chars().all(|c| c.is_ascii_digit()) {
+        true => Ok(value.to_string()),
+        false => Err("account IDs are 12 digits".to_string()),
+    }
+}
+
+fn yes_arg() -> Arg {
+    Arg::new("yes")
+        .short('y')
+        .long("yes")
+        .help("Don't ask for confirmation")
+        .action(ArgAction::SetTrue)
+}
+
+fn with_credentials_arg() -> Arg {
+    Arg::new("with-credentials")
+        .long("with-credentials")
+        .help("Also resolve the profile's credentials and pass them as AWS_ACCESS_KEY_ID/AWS_SECRET_ACCESS_KEY/AWS_SESSION_TOKEN")
+        .action(ArgAction::SetTrue)
+}
+
+fn duration_arg() -> Arg {
+    Arg::new("duration")
+        .long("duration")
+        .help("How long the temporary credentials last, e.g. 2h or 90m (default: the profile's [session-durations] entry, else STS's)")
+        .value_name("DURATION")
+        .value_parser(clap::value_parser!(SessionDuration))
+}
+
+fn output_arg() -> Arg {
+    Arg::new("output")
+        .short('o')
+        .long("output")
+        .help("Output format")
+        .value_name("FORMAT")
+        .value_parser(clap::value_parser!(OutputFormat))
+        .default_value("table")
+}
@@ -0,0 +1,97 @@
+//! Copying to the system clipboard, for `--copy`, `--copy-creds`, and the
+//! browser's copy key. Like [`crate::notify`], this goes through the
+//! desktop's own tool: `pbcopy` on macOS, `clip` on Windows, and `wl-copy`,
+//! `xclip`, or `xsel` elsewhere. Without one, the text is sent to the
+//! terminal as an OSC 52 sequence, which most terminals (over SSH too) turn
+//! into a clipboard write.
+
+use crate::dry_run::would;
+use anyhow::{Context, Result};
+use base64::Engine;
+use base64::engine::general_purpose::STANDARD;
+use std::io::{IsTerminal, Write};
+use std::process::{Command, Stdio};
+
+/// Puts `text` on the clipboard. `what` names it for a dry run.
+pub fn copy(text: &str, what: &str) -> Result<()> {
+    if would(format_args!("copy {what} to the clipboard")) {
+        return Ok(());
+    }
+    for mut command in clipboard_commands() {
+        let program = command.get_program().to_string_lossy().to_string();
+        let Ok(mut child) = command
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null())
+            .spawn()
+        else {
+            continue;
+        };
+        child
+            .stdin
+            .take()
+            .expect("stdin is piped")
+            .write_all(text.as_bytes())
+            .with_context(|| format!("Failed to write to '{program}'"))?;
+        let status = child.wait()?;
+        if !status.success() {
+            return Err(anyhow::anyhow!("'{program}' failed with {status}"));
+        }
+        return Ok(());
+    }
+
+    let mut stderr = std::io::stderr();
+    if !stderr.is_terminal() {
+        return Err(anyhow::anyhow!(
+            "No clipboard tool found; install wl-clipboard, xclip, or xsel"
+        ));
+    }
+    write!(stderr, "{}", osc52(text))?;
+    stderr.flush()?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn clipboard_commands() -> Vec<Command> {
+    vec![Command::new("pbcopy")]
+}
+
+#[cfg(windows)]
+fn clipboard_commands() -> Vec<Command> {
+    vec![Command::new("clip")]
+}
+
+/// Wayland's tool first when a Wayland session is running, then X11's.
+#[cfg(not(any(target_os = "macos", windows)))]
+fn clipboard_commands() -> Vec<Command> {
+    let mut commands = Vec::new();
+    if std::env::var_os("WAYLAND_DISPLAY").is_some() {
+        commands.push(Command::new("wl-copy"));
+    }
+    if std::env::var_os("DISPLAY").is_some() {
+        let mut xclip = Command::new("xclip");
+        xclip.args(["-selection", "clipboard"]);
+        let mut xsel = Command::new("xsel");
+        xsel.args(["--clipboard", "--input"]);
+        commands.extend([xclip, xsel]);
+    }
+    commands
+}
+
+/// The OSC 52 sequence that sets the clipboard to `text`.
+fn osc52(text: &str) -> String {
+    format!("\x1b]52;c;{}\x07", STANDARD.encode(text))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_osc52() {
+        assert_eq!(
+            osc52("export AWS_PROFILE=dev"),
+            "\x1b]52;c;ZXhwb3J0IEFXU19QUk9GSUxFPWRldg==\x07"
+        );
+    }
+}
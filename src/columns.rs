@@ -0,0 +1,267 @@
+//! Lines the default selector layout up in columns that fit the terminal.
+//!
+//! Each field gets a column as wide as its widest value. When the lines are
+//! wider than the room there is, the SSO portal, then the role, then the
+//! region are cut short with an ellipsis, or left out once they would be
+//! narrower than [`MIN_WIDTH`]. Only what is shown changes: the filter still
+//! matches the whole line.
+
+use crate::template::{Field, Label, LabelPart, Tag};
+
+/// The default layout's fields, in the order it shows them.
+const ORDER: [Field; 6] = [
+    Field::Name,
+    Field::Aliases,
+    Field::AccountId,
+    Field::Region,
+    Field::Role,
+    Field::SsoStartUrl,
+];
+
+/// Least important first.
+const SHRINKABLE: [Field; 3] = [Field::SsoStartUrl, Field::Role, Field::Region];
+
+/// A shrunk column is never narrower than this.
+const MIN_WIDTH: usize = 8;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Columns {
+    /// The columns shown, left to right, with their widths.
+    widths: Vec<(Field, usize)>,
+}
+
+impl Columns {
+    /// Columns for `labels` that fit in `width` characters, if shrinking the
+    /// less important ones is enough.
+    pub fn new(labels: &[Label], width: usize) -> Self {
+        let mut widths: Vec<(Field, usize)> = ORDER
+            .into_iter()
+            .filter_map(|field| {
+                let widest = labels
+                    .iter()
+                    .flat_map(cells)
+                    .filter(|cell| cell.field == Some(field))
+                    .map(|cell| cell.width())
+                    .max()?;
+                Some((field, widest))
+            })
+            .collect();
+
+        for field in SHRINKABLE {
+            let total = widths.iter().map(|(_, width)| width).sum::<usize>()
+                + widths.len().saturating_sub(1);
+            if total <= width {
+                break;
+            }
+            let Some(index) = widths.iter().position(|(column, _)| *column == field) else {
+                continue;
+            };
+            let excess = total - width;
+            match widths[index].1.checked_sub(excess) {
+                Some(shrunk) if shrunk >= MIN_WIDTH => widths[index].1 = shrunk,
+                _ => {
+                    widths.remove(index);
+                }
+            }
+        }
+
+        Self { widths }
+    }
+
+    fn shows(&self, field: Option<Field>) -> bool {
+        self.widths.iter().any(|(column, _)| Some(*column) == field)
+    }
+
+    /// `label` laid out in these columns, and `matched` (positions in
+    /// [`Label::plain`]) moved to where those characters now are. Matches in
+    /// text that was cut off are dropped.
+    pub fn fit(&self, label: &Label, matched: &[usize]) -> (Label, Vec<usize>) {
+        let mut fitted = Label {
+            parts: Vec::new(),
+            protected: label.protected,
+        };
+        // Where each character of the plain text ended up, if anywhere.
+        let mut moved: Vec<Option<usize>> = Vec::new();
+        let mut position = 0;
+        // Padding held back until something follows it, so lines don't end
+        // in spaces.
+        let mut pending = 0;
+
+        let mut cells = cells(label).into_iter().peekable();
+        for (index, (field, width)) in self.widths.iter().enumerate() {
+            if index > 0 {
+                pending += 1;
+            }
+            // Cells of columns that aren't shown.
+            while let Some(cell) = cells.next_if(|cell| !self.shows(cell.field)) {
+                moved.extend(std::iter::repeat_n(None, cell.plain_width()));
+            }
+            let Some(cell) = cells.next_if(|cell| cell.field == Some(*field)) else {
+                pending += width;
+                continue;
+            };
+            if cell.separator {
+                moved.push(None);
+            }
+
+            if pending > 0 {
+                fitted.parts.push(padding(None, " ".repeat(pending)));
+            }
+            let cut = cell.width() > *width;
+            let mut room = if cut { width - 1 } else { *width };
+            for part in cell.parts {
+                let shown: String = part.text.chars().take(room).collect();
+                let count = shown.chars().count();
+                room -= count;
+                if part.tag.is_none() {
+                    let hidden = part.text.chars().count() - count;
+                    moved.extend((position..position + count).map(Some));
+                    moved.extend(std::iter::repeat_n(None, hidden));
+                    position += count;
+                }
+                if !shown.is_empty() {
+                    fitted.parts.push(LabelPart {
+                        text: shown,
+                        ..part.clone()
+                    });
+                }
+            }
+            if cut {
+                fitted.parts.push(padding(Some(*field), "…".to_string()));
+            }
+            pending = room;
+        }
+
+        let matched = matched
+            .iter()
+            .filter_map(|&index| moved.get(index).copied().flatten())
+            .collect();
+        (fitted, matched)
+    }
+}
+
+/// A run of a label's parts between the single spaces that separate the
+/// fields, together with that space.
+struct Cell<'a> {
+    /// The first field in the run; the name's run starts with the badge and
+    /// goes on with the tags.
+    field: Option<Field>,
+    separator: bool,
+    parts: Vec<&'a LabelPart>,
+}
+
+impl Cell<'_> {
+    fn width(&self) -> usize {
+        self.parts
+            .iter()
+            .map(|part| part.text.chars().count())
+            .sum()
+    }
+
+    /// Characters of [`Label::plain`] in the cell, separator included.
+    fn plain_width(&self) -> usize {
+        let text: usize = self
+            .parts
+            .iter()
+            .filter(|part| part.tag.is_none())
+            .map(|part| part.text.chars().count())
+            .sum();
+        text + usize::from(self.separator)
+    }
+}
+
+fn cells(label: &Label) -> Vec<Cell<'_>> {
+    let mut cells = vec![Cell {
+        field: None,
+        separator: false,
+        parts: Vec::new(),
+    }];
+    for part in &label.parts {
+        let cell = cells.last_mut().unwrap();
+        if part.field.is_none() && part.tag.is_none() && part.text == " " {
+            cells.push(Cell {
+                field: None,
+                separator: true,
+                parts: Vec::new(),
+            });
+        } else {
+            cell.field = cell.field.or(part.field);
+            cell.parts.push(part);
+        }
+    }
+    cells
+}
+
+fn padding(field: Option<Field>, text: String) -> LabelPart {
+    LabelPart {
+        field,
+        tag: Some(Tag::Padding),
+        text,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn label(fields: &[(Field, &str)]) -> Label {
+        let mut label = Label::default();
+        for (index, (field, text)) in fields.iter().enumerate() {
+            if index > 0 {
+                label.push(None, " ");
+            }
+            label.push(Some(*field), *text);
+        }
+        label
+    }
+
+    #[test]
+    fn test_columns_shrink_role_then_region() {
+        let labels = [
+            label(&[
+                (Field::Name, "dev"),
+                (Field::AccountId, "(111)"),
+                (Field::Region, "[eu-west-1]"),
+                (Field::Role, "{AdministratorAccess}"),
+            ]),
+            label(&[(Field::Name, "production"), (Field::Region, "[us-east-1]")]),
+        ];
+        let fit = |width| {
+            let columns = Columns::new(&labels, width);
+            labels
+                .iter()
+                .map(|label| columns.fit(label, &[]).0.text())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            fit(200),
+            [
+                "dev        (111) [eu-west-1] {AdministratorAccess}",
+                "production       [us-east-1]"
+            ]
+        );
+        assert_eq!(fit(40)[0], "dev        (111) [eu-west-1] {Administr…");
+        assert_eq!(fit(30)[0], "dev        (111) [eu-west-1]");
+        assert_eq!(fit(20), ["dev        (111)", "production"]);
+    }
+
+    #[test]
+    fn test_fit_moves_matches() {
+        let dev = label(&[
+            (Field::Name, "dev"),
+            (Field::AccountId, "(111)"),
+            (Field::Region, "[eu-west-1]"),
+        ]);
+        let prod = label(&[(Field::Name, "production")]);
+        let labels = [dev.clone(), prod];
+        assert_eq!(dev.plain().find("eu-west"), Some(11));
+
+        let (fitted, matched) = Columns::new(&labels, 200).fit(&dev, &[0, 11]);
+        assert_eq!(fitted.plain().find("eu-west"), Some(9));
+        assert_eq!(matched, [0, 9]);
+
+        let (_, matched) = Columns::new(&labels, 20).fit(&dev, &[0, 11]);
+        assert_eq!(matched, [0]);
+    }
+}
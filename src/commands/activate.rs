@@ -0,0 +1,1179 @@
+use super::env::credential_vars;
+use super::login::{ensure_logged_in, warn_if_expiring};
+use super::{Context, attribute_filters, output_format};
+use crate::accounts::{account_name, cached_aliases, redact, redacted, spawn_lookups};
+use crate::audit::AuditEvent;
+use crate::check::{CheckOptions, spawn_checks, summarize_error};
+use crate::clipboard::copy;
+use crate::config::{AwsConfig, Profile, SectionStyle, profile_conflicts};
+use crate::dry_run::{dry_run, read_only};
+use crate::eks::sync_kubeconfig;
+use crate::environment::now;
+use crate::error::Error;
+use crate::filter::{FilterField, Pattern, ProfileFilter, matches_all};
+use crate::hooks::{Event, run_hooks};
+use crate::logging;
+use crate::matcher::MatchMode;
+use crate::notify::{notify, switch_message};
+use crate::output::{OutputFormat, print_structured};
+use crate::regions::is_known_region;
+use crate::settings::Settings;
+use crate::shell::{
+    env_commands, export_commands, print_for_shell, print_shell_command, shell_command,
+};
+use crate::sso::spawn_token_expiries;
+use crate::state::{
+    SelectorState, active_profile, clear_current_profile, read_current_profile, read_local_profile,
+    write_current_profile,
+};
+use crate::tasks::{Background, watch_config};
+use crate::template::DisplayFormat;
+use crate::theme::{Theme, no_color_env};
+use crate::tui;
+use crate::ui::{
+    self, DEFAULT_PAGE_SIZE, Markers, PageSize, Picker, ProfileSelector, SortOrder, answer,
+};
+use anyhow::Result;
+use clap::ArgMatches;
+use inquire::{Confirm, Text};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::io::IsTerminal;
+use std::sync::mpsc::Receiver;
+
+/// What `--output json` reports about an activation.
+#[derive(Serialize)]
+pub(super) struct Activation {
+    profile: String,
+    account_id: Option<String>,
+    /// `--region`, or else the profile's own.
+    region: Option<String>,
+    /// Whether the current-profile file was written, which `-c` leaves to
+    /// the shell.
+    written: bool,
+}
+
+impl Activation {
+    fn new(context: &Context, profile_name: &str, region: Option<&str>, written: bool) -> Self {
+        let config = context.load_config().ok();
+        let profile = config
+            .as_ref()
+            .and_then(|config| config.find_profile(profile_name));
+        Self {
+            profile: profile_name.to_string(),
+            account_id: profile
+                .and_then(Profile::get_account_id)
+                .map(str::to_string),
+            region: region
+                .or_else(|| profile.and_then(Profile::get_region))
+                .map(str::to_string),
+            written,
+        }
+    }
+}
+
+/// Flags that tune how a profile is activated.
+pub struct ActivateOptions {
+    /// Log in to SSO first when the profile's cached token is missing or
+    /// expired.
+    pub auto_login: bool,
+    /// `--last`: switch back to the previously active profile.
+    pub last: bool,
+    /// `--region`: used in place of the profile's own region. It still
+    /// narrows the interactive selector, but a named profile needn't match.
+    pub region: Option<String>,
+    /// In `-c` mode, also set `AWS_REGION` from the profile's `region`.
+    pub with_region: bool,
+    /// Report the result as JSON or YAML instead of text (or, with `-c`,
+    /// instead of the shell commands).
+    pub output: OutputFormat,
+    /// `--copy` or `--copy-creds`: put this on the clipboard instead of
+    /// activating.
+    pub copy: Option<CopyWhat>,
+    pub select: SelectOptions,
+}
+
+/// What `--copy` and `--copy-creds` put on the clipboard.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CopyWhat {
+    /// The command `-c` would print.
+    Command,
+    /// Export commands for the resolved temporary credentials, as `env`
+    /// prints them.
+    Credentials,
+}
+
+impl ActivateOptions {
+    pub fn from_matches(matches: &ArgMatches, settings: &Settings) -> Self {
+        Self {
+            auto_login: matches.get_flag("auto-login"),
+            last: matches.get_flag("last"),
+            region: matches.get_one::<String>("region").cloned(),
+            with_region: matches.get_flag("with-region") || settings.with_region,
+            output: output_format(matches),
+            copy: match (matches.get_flag("copy"), matches.get_flag("copy-creds")) {
+                (_, true) => Some(CopyWhat::Credentials),
+                (true, false) => Some(CopyWhat::Command),
+                (false, false) => None,
+            },
+            select: SelectOptions {
+                deactivate_entry: settings
+                    .deactivate_entry
+                    .clone()
+                    .filter(|entry| !entry.trim().is_empty()),
+                ..SelectOptions::from_matches(matches, settings)
+            },
+        }
+    }
+}
+
+/// Flags that tune the interactive selection.
+pub struct SelectOptions {
+    /// Use the full-screen browser instead of the inline prompt.
+    pub tui: bool,
+    pub match_mode: MatchMode,
+    /// Pick the account first, then one of its profiles.
+    pub by_account: bool,
+    /// Offer profiles matched by the hide list too.
+    pub show_hidden: bool,
+    /// Profiles must match all of these to be offered or accepted.
+    pub filters: Vec<ProfileFilter>,
+    pub sort: SortOrder,
+    /// Turn the `sort` order around.
+    pub reverse: bool,
+    /// Rows shown at once by the inline prompt.
+    pub page_size: usize,
+    pub display_format: Option<DisplayFormat>,
+    pub theme: Theme,
+    /// Run `--check` and show the results in the selector.
+    pub check: bool,
+    /// Look up IAM account aliases and show them in the selector.
+    pub account_aliases: bool,
+    /// Leave the picking to an external tool: print the lines, or resolve
+    /// the one handed back.
+    pub pipe: bool,
+    /// The line picked from `--pipe` output, instead of reading stdin.
+    pub selected: Option<String>,
+    /// Pick the Nth profile the selector lists (from 1) without prompting.
+    pub select_index: Option<usize>,
+    /// Pick the profile whose line is exactly this, without prompting.
+    pub select_match: Option<String>,
+    /// Open the selector even when a name used as a filter matches only one
+    /// profile.
+    pub no_auto: bool,
+    /// Read a name that isn't a profile as a regex over the profile names.
+    pub regex: bool,
+    /// Show what was picked and ask before activating it.
+    pub confirm: bool,
+    /// Vim-style keys in the selectors.
+    pub vim: bool,
+    /// The text of an entry above the profiles that deactivates when picked.
+    /// Only `activate` offers one.
+    pub deactivate_entry: Option<String>,
+    /// `switch`: offer only this many of the most recently used profiles,
+    /// and an entry that opens the full list.
+    pub recent: Option<usize>,
+    /// What shows the profiles when the selector opens inline.
+    pub picker: Picker,
+}
+
+impl SelectOptions {
+    /// Flags (or their environment variables) win over the settings file.
+    pub fn from_matches(matches: &ArgMatches, settings: &Settings) -> Self {
+        Self {
+            tui: matches.get_flag("tui"),
+            match_mode: matches
+                .get_one::<MatchMode>("match")
+                .copied()
+                .or(settings.match_mode)
+                .unwrap_or_default(),
+            by_account: matches.get_flag("by-account"),
+            show_hidden: matches.get_flag("show-hidden"),
+            filters: attribute_filters(matches),
+            sort: matches
+                .get_one::<SortOrder>("sort")
+                .copied()
+                .or(settings.sort)
+                .unwrap_or_default(),
+            reverse: matches.get_flag("reverse"),
+            page_size: matches
+                .get_one::<PageSize>("page-size")
+                .copied()
+                .or(settings.page_size)
+                .map_or(DEFAULT_PAGE_SIZE, PageSize::rows),
+            display_format: matches
+                .get_one::<DisplayFormat>("format")
+                .or(settings.display_format.as_ref())
+                .cloned(),
+            check: matches.get_flag("check"),
+            account_aliases: matches.get_flag("account-aliases") || settings.account_aliases,
+            pipe: matches.get_flag("pipe"),
+            selected: matches.get_one::<String>("selected").cloned(),
+            select_index: matches
+                .get_one::<u64>("select-index")
+                .map(|&index| index as usize),
+            select_match: matches.get_one::<String>("select-match").cloned(),
+            no_auto: matches.get_flag("no-auto"),
+            regex: matches.get_flag("regex"),
+            confirm: matches.get_flag("confirm") || settings.confirm,
+            vim: settings.vim,
+            deactivate_entry: None,
+            recent: None,
+            picker: matches
+                .get_one::<Picker>("picker")
+                .or(settings.picker.as_ref())
+                .cloned()
+                .unwrap_or_default(),
+            theme: Theme::new(
+                &settings.theme,
+                matches.get_flag("no-color") || no_color_env(),
+            ),
+        }
+    }
+}
+
+/// `switch`: the selector with only the most recently used profiles, `count`
+/// of them, or else the `switch-count` setting's number.
+pub fn switch(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let mut options = ActivateOptions::from_matches(matches, &context.settings);
+    options.select.recent = Some(
+        matches
+            .get_one::<usize>("count")
+            .copied()
+            .or(context.settings.switch_count)
+            .unwrap_or(DEFAULT_SWITCH_COUNT),
+    );
+    activate(context, None, options)
+}
+
+/// How many recent profiles `switch` offers by default.
+const DEFAULT_SWITCH_COUNT: usize = 5;
+
+/// `role`: the selector with only the profiles for the current profile's
+/// account, so switching keeps the account. A role name (or glob) narrows
+/// them further, and the only profile it matches is activated outright.
+pub fn role(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let config = context.load_config()?;
+    let current = current_profile(context)?
+        .ok_or_else(|| Error::NotFound("No active AWS profile".to_string()))?;
+    let profile = config
+        .find_profile(&current)
+        .ok_or_else(|| Error::profile_not_found(&current))?;
+    let account_id = profile
+        .get_account_id()
+        .or_else(|| profile.role_arn_parts().map(|(account_id, _)| account_id))
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "'{current}' has no sso_account_id or role_arn to take the account from"
+            )
+        })?;
+    let shown = match context.redact {
+        true => redact(account_id),
+        false => account_id.to_string(),
+    };
+
+    let mut options = ActivateOptions::from_matches(matches, &context.settings);
+    options
+        .select
+        .filters
+        .push(ProfileFilter::equals(FilterField::Account, account_id));
+    let role = matches.get_one::<String>("role-name");
+    if let Some(role) = role {
+        options
+            .select
+            .filters
+            .push(ProfileFilter::equals(FilterField::Role, role));
+    }
+
+    let annotations = context.annotations();
+    let candidates: Vec<&Profile> = config
+        .profiles
+        .iter()
+        .filter(|profile| matches_all(&options.select.filters, profile, &annotations))
+        .collect();
+    match (candidates.as_slice(), role) {
+        ([], Some(role)) => Err(Error::NotFound(format!(
+            "No profile for account {shown} has the role '{role}'"
+        ))
+        .into()),
+        ([only], Some(_)) => activate(context, Some(&only.name), options),
+        (others, None) if others.iter().all(|profile| profile.name == current) => {
+            Err(Error::NotFound(format!(
+                "'{current}' is the only profile for account {shown}"
+            ))
+            .into())
+        }
+        _ => activate(context, None, options),
+    }
+}
+
+/// Activates `profile_name`, or runs the interactive selector when no name
+/// is given. `-` (or `--last`) switches back to the previous profile.
+pub fn activate(
+    context: &Context,
+    profile_name: Option<&String>,
+    mut options: ActivateOptions,
+) -> Result<()> {
+    let config = context.load_config()?;
+    let region = options.region.as_deref();
+    if let Some(region) = region {
+        if !is_known_region(region) {
+            tracing::warn!("'{region}' is not a known region");
+        }
+        if profile_name.is_some() {
+            let filter = ProfileFilter::equals(FilterField::Region, region);
+            options.select.filters.retain(|f| *f != filter);
+        }
+    }
+
+    if options.last || profile_name.is_some_and(|name| name == "-") {
+        if options.last && profile_name.is_some() {
+            return Err(anyhow::anyhow!(
+                "--last can't be combined with a profile name"
+            ));
+        }
+
+        let profile_name = previous_profile(context)?;
+        if options.auto_login && config.find_profile(&profile_name).is_some() {
+            ensure_logged_in(&config, &profile_name)?;
+        }
+        if let Some(what) = options.copy {
+            return copy_profile(context, &profile_name, region, options.with_region, what);
+        }
+        return apply(
+            context,
+            &profile_name,
+            region,
+            options.with_region,
+            options.output,
+        );
+    }
+
+    let profile_name = choose_profile(context, &config, profile_name, &options.select)?;
+    if is_deactivate_entry(&options.select, &config, &profile_name) {
+        if options.copy.is_some() {
+            return copy(&shell_command(None), "the command that unsets AWS_PROFILE");
+        }
+        return deactivate(context);
+    }
+
+    if options.auto_login {
+        ensure_logged_in(&config, &profile_name)?;
+    }
+    if let Some(what) = options.copy {
+        return copy_profile(context, &profile_name, region, options.with_region, what);
+    }
+
+    apply(
+        context,
+        &profile_name,
+        region,
+        options.with_region,
+        options.output,
+    )
+}
+
+/// Validates an explicitly named profile, or runs the interactive selector
+/// when no name is given. A name that is neither a profile nor an alias
+/// narrows the selector, as a glob or regex over the names or else as its
+/// starting filter, and the only profile it matches is chosen outright.
+/// Fails when nothing matches or the user cancels the selection, and with
+/// `--confirm` when the user turns down what was picked.
+pub(super) fn choose_profile(
+    context: &Context,
+    config: &AwsConfig,
+    profile_name: Option<&String>,
+    options: &SelectOptions,
+) -> Result<String> {
+    let mut chosen = pick_profile(context, config, profile_name, options, options.recent)?;
+    if options.recent.is_some() && is_all_profiles_entry(config, &chosen) {
+        chosen = pick_profile(context, config, profile_name, options, None)?;
+    }
+
+    let state = context.selector_state()?;
+    let named =
+        profile_name.is_some_and(|name| *name == chosen || state.resolve_alias(name) == chosen);
+    let scripted = options.pipe || options.select_index.is_some() || options.select_match.is_some();
+    let deactivating = is_deactivate_entry(options, config, &chosen);
+    if options.confirm && !named && !scripted && !deactivating && ui::has_terminal() {
+        confirm_choice(context, config, &chosen)?;
+    }
+    Ok(chosen)
+}
+
+/// `--confirm`: shows the picked profile's account, role, and region and
+/// asks before going on. Protected profiles skip this, as they are confirmed
+/// by name later anyway.
+fn confirm_choice(context: &Context, config: &AwsConfig, profile_name: &str) -> Result<()> {
+    if context.settings.is_protected(profile_name) {
+        return Ok(());
+    }
+    let profile = config.find_profile(profile_name);
+    let account = profile.and_then(Profile::get_account_id).map(|account_id| {
+        let account_id = match context.redact {
+            true => redact(account_id),
+            false => account_id.to_string(),
+        };
+        match profile.and_then(|profile| account_name(&context.settings.accounts, profile)) {
+            Some(name) => format!("{account_id} ({name})"),
+            None => account_id,
+        }
+    });
+    let rows = [
+        ("Account", account),
+        (
+            "Role",
+            profile.and_then(Profile::get_role_name).map(str::to_string),
+        ),
+        (
+            "Region",
+            profile.and_then(Profile::get_region).map(str::to_string),
+        ),
+    ];
+    eprintln!("{profile_name}");
+    for (label, value) in rows {
+        eprintln!("  {label:<8} {}", value.as_deref().unwrap_or("-"));
+    }
+
+    let confirmed = answer(
+        Confirm::new(&format!("Activate '{profile_name}'?"))
+            .with_default(true)
+            .prompt(),
+    )?;
+    match confirmed {
+        true => Ok(()),
+        false => Err(Error::Cancelled("Not activated").into()),
+    }
+}
+
+/// [`choose_profile`] up to the pick, before any `--confirm`, offering only
+/// the `recent` most recently used profiles when given.
+fn pick_profile(
+    context: &Context,
+    config: &AwsConfig,
+    profile_name: Option<&String>,
+    options: &SelectOptions,
+    recent: Option<usize>,
+) -> Result<String> {
+    let mut config = config.clone();
+    let annotations = context.annotations();
+    config
+        .profiles
+        .retain(|profile| matches_all(&options.filters, profile, &annotations));
+
+    let selector_state = context.selector_state()?;
+
+    // Handle direct profile activation, falling back to aliases. Any other
+    // name is a pattern the profile names have to match when it has
+    // wildcards (or with `--regex`), and otherwise a starting filter for the
+    // selector.
+    let mut unmatched = None;
+    if let Some(profile_name) = profile_name {
+        if config.find_profile(profile_name).is_some() {
+            return Ok(profile_name.clone());
+        }
+        let target = selector_state.resolve_alias(profile_name);
+        if config.find_profile(target).is_some() {
+            return Ok(target.to_string());
+        }
+        unmatched = Some(profile_name.as_str());
+    }
+    let is_pattern = |name: &str| options.regex || Pattern::has_wildcards(name);
+    let initial_filter = unmatched.filter(|name| !is_pattern(name));
+    let unfiltered = config.clone();
+
+    if config.profiles.is_empty() && !options.filters.is_empty() {
+        return Err(Error::NotFound("No profiles match the given filters".to_string()).into());
+    }
+
+    if !options.show_hidden {
+        config
+            .profiles
+            .retain(|profile| !selector_state.is_hidden(&profile.name));
+    }
+    let recent = recent
+        .filter(|_| profile_name.is_none())
+        .and_then(|count| recent_profiles(&config, &selector_state, count));
+    if let Some(recent) = &recent {
+        config
+            .profiles
+            .retain(|profile| recent.contains(&profile.name));
+    }
+
+    if let Some(name) = unmatched.filter(|name| is_pattern(name)) {
+        let pattern = Pattern::new(name, options.regex)?;
+        config
+            .profiles
+            .retain(|profile| pattern.matches(&profile.name));
+        match config.profiles.as_slice() {
+            [] => return Err(not_found(name, &unfiltered, options)),
+            [only] if !options.no_auto => return Ok(only.name.clone()),
+            _ => {}
+        }
+    }
+
+    // Run interactive selector
+    if options.by_account {
+        match ui::select_account(
+            &config,
+            &context.settings.accounts,
+            context.redact,
+            options.match_mode,
+            options.page_size,
+        )? {
+            Some(account_config) => config = account_config,
+            None => return Err(Error::Cancelled("No profile selected").into()),
+        }
+    }
+
+    ui::sort_profiles(&mut config, options.sort, options.reverse, &selector_state);
+    let mut markers = markers(context, &config)?;
+    let profile_names: Vec<String> = config
+        .profiles
+        .iter()
+        .map(|profile| profile.name.clone())
+        .collect();
+    markers.annotations = context.annotations();
+    let deactivate_entry = deactivate_entry(options, unmatched);
+    if let Some(entry) = &deactivate_entry {
+        add_deactivate_entry(&mut config, entry);
+    }
+    if recent.is_some() {
+        add_all_profiles_entry(&mut config);
+    }
+    let unresolved = if options.account_aliases {
+        let (aliases, unresolved) = cached_aliases(&config, &markers.annotations.account_names)?;
+        markers.annotations.account_names.extend(aliases);
+        unresolved
+    } else {
+        Vec::new()
+    };
+    let mut selector = ProfileSelector::new(config.clone())
+        .with_match_mode(options.match_mode)
+        .with_page_size(options.page_size)
+        .with_display_format(options.display_format.clone())
+        .with_theme(options.theme)
+        .with_markers(markers.clone())
+        .with_aliases(selector_state.all_aliases())
+        .with_favorites(selector_state.favorites.clone())
+        .with_filter(initial_filter.unwrap_or_default())
+        .with_vim(options.vim);
+    if let Some(filter) = initial_filter {
+        if selector.plain_lines().is_empty() {
+            return Err(not_found(filter, &unfiltered, options));
+        }
+        if let Some(profile_name) = selector.sole_match().filter(|_| !options.no_auto) {
+            return Ok(profile_name);
+        }
+    }
+    // The list goes up without the token expiries, alias lookups, and
+    // checks; they run side by side and fill it in as they finish.
+    let background = Background::default();
+    spawn_token_expiries(&background, config.clone())?;
+    let lookups = unresolved.len();
+    if !unresolved.is_empty() {
+        spawn_lookups(&background, &context.config_paths, unresolved)?;
+    }
+    let checks = profile_names.len();
+    if options.check {
+        spawn_checks(
+            &background,
+            &context.config_paths,
+            profile_names,
+            CheckOptions::default(),
+        )?;
+        markers.checking = true;
+    }
+    let selection = if options.tui && !ui::accessible() {
+        let pattern = unmatched
+            .filter(|name| is_pattern(name))
+            .map(|name| Pattern::new(name, options.regex))
+            .transpose()?;
+        let reloads = watch_selection(
+            context,
+            &config,
+            pattern,
+            options,
+            deactivate_entry,
+            recent,
+            &selector_state,
+        );
+        tui::select_profile(
+            &config,
+            options.match_mode,
+            options.display_format.as_ref(),
+            options.theme,
+            &markers,
+            background.into_updates(),
+            reloads,
+            selector_state,
+            initial_filter.unwrap_or_default(),
+            options.vim,
+            &context.settings.keys,
+            &context.config_paths,
+        )?
+    } else {
+        // This selector can't change its lines once shown, so it waits.
+        if lookups > 0 {
+            eprintln!("Looking up aliases of {lookups} accounts...");
+        }
+        if options.check {
+            eprintln!("Checking credentials of {checks} profiles...");
+        }
+        for update in background.into_updates() {
+            markers.apply(update);
+        }
+        markers.checking = false;
+        selector = selector.with_markers(markers);
+        if options.pipe {
+            pipe_selection(&mut selector, options.selected.as_deref())?
+        } else if options.select_index.is_some() || options.select_match.is_some() {
+            Some(scripted_selection(&selector.plain_lines(), options)?)
+        } else {
+            if let Some(name) = unmatched
+                && !ui::has_terminal()
+            {
+                return Err(ambiguous(name, &selector.plain_lines()));
+            }
+            match options.picker.command() {
+                Some(command) if ui::has_terminal() => {
+                    let lines = selector.plain_lines();
+                    let texts: Vec<String> = lines.iter().map(|(_, line)| line.clone()).collect();
+                    ui::external_pick(command, &texts)?
+                        .map(|chosen| {
+                            ui::resolve_line(&lines, &chosen).map(str::to_string).ok_or_else(|| {
+                                anyhow::anyhow!("The picker chose '{}', which isn't one of the profile lines", chosen.trim())
+                            })
+                        })
+                        .transpose()?
+                }
+                _ => selector.run()?,
+            }
+        }
+    };
+
+    selection.ok_or_else(|| Error::Cancelled("No profile selected").into())
+}
+
+/// Sends the selector the config again each time its files change, narrowed
+/// as `choose_profile` narrowed `config`: by the filters, the hide list,
+/// `pattern`, the `recent` profiles, and with `--by-account` the chosen
+/// account.
+fn watch_selection(
+    context: &Context,
+    config: &AwsConfig,
+    pattern: Option<Pattern>,
+    options: &SelectOptions,
+    deactivate_entry: Option<String>,
+    recent: Option<Vec<String>>,
+    selector_state: &SelectorState,
+) -> Receiver<AwsConfig> {
+    let filters = options.filters.clone();
+    let annotations = context.annotations();
+    let show_hidden = options.show_hidden;
+    let account = options.by_account.then(|| {
+        config
+            .profiles
+            .first()
+            .and_then(Profile::get_account_id)
+            .map(str::to_string)
+    });
+    let (sort, reverse) = (options.sort, options.reverse);
+    let state = selector_state.clone();
+
+    watch_config(context.config_paths.clone(), move |mut config| {
+        config.profiles.retain(|profile| {
+            matches_all(&filters, profile, &annotations)
+                && (show_hidden || !state.is_hidden(&profile.name))
+                && pattern
+                    .as_ref()
+                    .is_none_or(|pattern| pattern.matches(&profile.name))
+                && account
+                    .as_ref()
+                    .is_none_or(|account| profile.get_account_id() == account.as_deref())
+                && recent
+                    .as_ref()
+                    .is_none_or(|recent| recent.contains(&profile.name))
+        });
+        ui::sort_profiles(&mut config, sort, reverse, &state);
+        if let Some(entry) = &deactivate_entry {
+            add_deactivate_entry(&mut config, entry);
+        }
+        if recent.is_some() {
+            add_all_profiles_entry(&mut config);
+        }
+        config
+    })
+}
+
+/// The deactivate entry to offer: only when the selector is opened without a
+/// name to narrow it or a line to pick by number or text, and when no
+/// profile has the entry's name.
+fn deactivate_entry(options: &SelectOptions, unmatched: Option<&str>) -> Option<String> {
+    let scripted = options.select_index.is_some() || options.select_match.is_some();
+    options
+        .deactivate_entry
+        .clone()
+        .filter(|_| unmatched.is_none() && !scripted)
+}
+
+/// Whether `chosen` is the deactivate entry rather than a profile.
+fn is_deactivate_entry(options: &SelectOptions, config: &AwsConfig, chosen: &str) -> bool {
+    options.deactivate_entry.as_deref() == Some(chosen) && config.find_profile(chosen).is_none()
+}
+
+/// Puts the deactivate entry first, as a profile without attributes, unless
+/// a real profile goes by that name.
+fn add_deactivate_entry(config: &mut AwsConfig, entry: &str) {
+    if config.find_profile(entry).is_some() {
+        return;
+    }
+    config.profiles.insert(
+        0,
+        Profile {
+            name: entry.to_string(),
+            attributes: HashMap::new(),
+            subsections: HashMap::new(),
+            section_style: SectionStyle::Bare,
+        },
+    );
+}
+
+/// The entry below the recent profiles in `switch` that opens the full list.
+const ALL_PROFILES_ENTRY: &str = "All profiles…";
+
+/// The `count` most recently used profiles in `config`, or `None` when there
+/// is no history yet or it already covers every profile, so `switch` shows
+/// the full list.
+fn recent_profiles(config: &AwsConfig, state: &SelectorState, count: usize) -> Option<Vec<String>> {
+    let recent: Vec<String> = state
+        .recent_profiles()
+        .into_iter()
+        .filter(|name| config.find_profile(name).is_some())
+        .take(count)
+        .map(str::to_string)
+        .collect();
+    (!recent.is_empty() && recent.len() < config.profiles.len()).then_some(recent)
+}
+
+fn is_all_profiles_entry(config: &AwsConfig, chosen: &str) -> bool {
+    chosen == ALL_PROFILES_ENTRY && config.find_profile(chosen).is_none()
+}
+
+/// Puts the entry that opens the full list last, unless a real profile goes
+/// by its name.
+fn add_all_profiles_entry(config: &mut AwsConfig) {
+    if config.find_profile(ALL_PROFILES_ENTRY).is_some() {
+        return;
+    }
+    config.profiles.push(Profile {
+        name: ALL_PROFILES_ENTRY.to_string(),
+        attributes: HashMap::new(),
+        subsections: HashMap::new(),
+        section_style: SectionStyle::Bare,
+    });
+}
+
+/// The error for a profile name that neither names a profile nor matches any
+/// as a filter, listing the profiles there are.
+fn not_found(profile_name: &str, config: &AwsConfig, options: &SelectOptions) -> anyhow::Error {
+    let mut message = match options.filters.is_empty() {
+        true => format!("Profile '{profile_name}' not found in AWS config"),
+        false => format!("Profile '{profile_name}' not found among the filtered profiles"),
+    };
+    message.push_str("\nAvailable profiles:");
+    for profile in &config.profiles {
+        message.push_str(&format!("\n  {}", profile.name));
+    }
+    Error::NotFound(message).into()
+}
+
+/// Without a terminal to ask on, the error listing the profiles a name used
+/// as a filter matches.
+fn ambiguous(profile_name: &str, lines: &[(String, String)]) -> anyhow::Error {
+    let mut message = format!("'{profile_name}' matches more than one profile:");
+    for (name, _) in lines {
+        message.push_str(&format!("\n  {name}"));
+    }
+    Error::NotFound(message).into()
+}
+
+/// `--pipe`: with no line picked (from `--selected` or a piped stdin),
+/// prints every line for an external picker and exits; otherwise returns the
+/// profile the picked line stands for.
+fn pipe_selection(
+    selector: &mut ProfileSelector,
+    selected: Option<&str>,
+) -> Result<Option<String>> {
+    let lines = selector.plain_lines();
+    let selection = match selected {
+        Some(selected) => selected.to_string(),
+        None if !std::io::stdin().is_terminal() => {
+            let mut line = String::new();
+            std::io::stdin().read_line(&mut line)?;
+            line
+        }
+        None => String::new(),
+    };
+
+    if selection.trim().is_empty() {
+        for (_, line) in &lines {
+            println!("{line}");
+        }
+        std::process::exit(0);
+    }
+    match ui::resolve_line(&lines, &selection) {
+        Some(profile_name) => Ok(Some(profile_name.to_string())),
+        None => Err(anyhow::anyhow!(
+            "'{}' isn't one of the profile lines",
+            selection.trim()
+        )),
+    }
+}
+
+/// `--select-index` or `--select-match`: the profile picked from the
+/// selector's lines without showing it.
+fn scripted_selection(lines: &[(String, String)], options: &SelectOptions) -> Result<String> {
+    let picked = match (options.select_index, &options.select_match) {
+        (Some(index), _) => index
+            .checked_sub(1)
+            .and_then(|index| lines.get(index))
+            .ok_or_else(|| {
+                Error::NotFound(format!(
+                    "--select-index {index} is out of range (1-{})",
+                    lines.len()
+                ))
+            })?,
+        (None, Some(text)) => lines
+            .iter()
+            .find(|(_, line)| line.trim() == text.trim())
+            .ok_or_else(|| {
+                Error::NotFound(format!("No profile line is exactly '{}'", text.trim()))
+            })?,
+        (None, None) => unreachable!("called with --select-index or --select-match"),
+    };
+    Ok(picked.0.clone())
+}
+
+/// The most recently activated profile other than the current one. In `-c`
+/// mode the shell's own `AWS_PROFILE` counts as current.
+fn previous_profile(context: &Context) -> Result<String> {
+    let current = current_profile(context)?;
+    let state = SelectorState::load()?;
+    state
+        .previous_profile(current.as_deref())
+        .map(str::to_string)
+        .ok_or_else(|| anyhow::anyhow!("No previous profile in the activation history"))
+}
+
+/// The profile in use where the command runs: in `-c` mode that is the
+/// shell's `AWS_PROFILE`, otherwise [`active_profile`].
+fn current_profile(context: &Context) -> Result<Option<String>> {
+    match std::env::var("AWS_PROFILE") {
+        Ok(profile_name) if context.current_shell_mode && !profile_name.is_empty() => {
+            Ok(Some(profile_name))
+        }
+        _ => active_profile(),
+    }
+}
+
+/// The active profile, `AWS_PROFILE` when it disagrees with the
+/// current-profile file, the SSO token expiries, broken role chains, and
+/// when each profile was last used.
+fn markers(context: &Context, config: &AwsConfig) -> Result<Markers> {
+    let env = std::env::var("AWS_PROFILE")
+        .ok()
+        .filter(|profile_name| !profile_name.is_empty());
+    let file = read_current_profile()?;
+
+    Ok(Markers {
+        active: current_profile(context)?,
+        env: env.filter(|env| file.as_ref().is_some_and(|file| file != env)),
+        redact: context.redact,
+        protected: context.settings.protected.clone(),
+        broken_chains: config
+            .broken_chains()
+            .into_iter()
+            .filter_map(|(name, chain)| Some((name.to_string(), chain.problem?)))
+            .collect(),
+        conflicts: profile_conflicts(&context.config_paths)?,
+        endpoint_overrides: config
+            .profiles
+            .iter()
+            .filter(|profile| !config.endpoint_overrides(profile).is_empty())
+            .map(|profile| profile.name.clone())
+            .collect(),
+        last_used: SelectorState::load()?.last_used(),
+        ..Markers::default()
+    })
+}
+
+/// Sets a profile name that is not necessarily present in the AWS config.
+pub fn new(context: &Context, profile_name: &str) -> Result<()> {
+    apply(context, profile_name, None, false, OutputFormat::Table)
+}
+
+pub fn deactivate(context: &Context) -> Result<()> {
+    let previous = current_profile(context)?;
+
+    if context.current_shell_mode {
+        // Output shell-specific unset command
+        print_shell_command(None);
+    } else if clear_current_profile()? {
+        println!("AWS profile deactivated");
+    } else {
+        println!("No active AWS profile to deactivate");
+    }
+
+    let env = hook_env(context, None, None, previous);
+    run_hooks(&context.settings.hooks, Event::PostDeactivate, &env)
+}
+
+/// What hooks see: `AWS_PROFILE` with its account and region (all unset when
+/// deactivating), and the profile that was active before.
+fn hook_env(
+    context: &Context,
+    profile_name: Option<&str>,
+    region: Option<&str>,
+    previous: Option<String>,
+) -> Vec<(&'static str, Option<String>)> {
+    let config = match profile_name {
+        Some(_) => context.read_config().unwrap_or_default(),
+        None => AwsConfig::default(),
+    };
+    let profile = profile_name.and_then(|name| config.find_profile(name));
+
+    vec![
+        ("AWS_PROFILE", profile_name.map(str::to_string)),
+        (
+            "AWS_ACCOUNT_ID",
+            profile.and_then(|p| p.get_account_id()).map(str::to_string),
+        ),
+        (
+            "AWS_REGION",
+            region
+                .or_else(|| profile.and_then(|p| p.get_region()))
+                .map(str::to_string),
+        ),
+        ("AWS_PROFILE_SELECTOR_PREVIOUS", previous),
+    ]
+}
+
+/// Asks for a protected profile's name to be typed back before it is
+/// activated; any other profile passes straight through.
+pub(super) fn confirm_protected(context: &Context, profile_name: &str) -> Result<()> {
+    if !context.settings.is_protected(profile_name) {
+        return Ok(());
+    }
+
+    eprintln!("'{profile_name}' is a protected profile.");
+    let typed = answer(Text::new("Type the profile name to confirm:").prompt())?;
+    if typed.trim() != profile_name {
+        return Err(anyhow::anyhow!(
+            "Confirmation didn't match; '{profile_name}' was not activated"
+        ));
+    }
+    Ok(())
+}
+
+/// Activates `profile_name`, with `region` in place of its own when given.
+/// `with_region` has `-c` set `AWS_REGION` to the profile's own otherwise.
+fn apply(
+    context: &Context,
+    profile_name: &str,
+    region: Option<&str>,
+    with_region: bool,
+    output: OutputFormat,
+) -> Result<()> {
+    confirm_protected(context, profile_name)?;
+
+    let hooks = &context.settings.hooks;
+    let env = hook_env(
+        context,
+        Some(profile_name),
+        region,
+        current_profile(context)?,
+    );
+    run_hooks(hooks, Event::PreActivate, &env)
+        .map_err(|e| anyhow::anyhow!("{e}; '{profile_name}' was not activated"))?;
+
+    if output != OutputFormat::Table {
+        if !context.current_shell_mode {
+            write_current_profile(profile_name, region)?;
+        }
+        let activation =
+            Activation::new(context, profile_name, region, !context.current_shell_mode);
+        match context.redact {
+            true => print_structured(&redacted(&activation)?, output)?,
+            false => print_structured(&activation, output)?,
+        }
+    } else if context.current_shell_mode || export_instead(profile_name)? {
+        print_for_shell(&activation_commands(
+            context,
+            profile_name,
+            region,
+            with_region,
+        ));
+    } else {
+        write_current_profile(profile_name, region)?;
+        match region {
+            Some(region) => println!("AWS profile activated: {profile_name} (region {region})"),
+            None => println!("AWS profile activated: {profile_name}"),
+        }
+        if let Some((local, path)) = read_local_profile()?
+            && local != profile_name
+            && !logging::quiet()
+        {
+            eprintln!("Note: {} still selects '{local}' here", path.display());
+        }
+    }
+    if let Some(problem) = context
+        .load_config()
+        .ok()
+        .and_then(|config| config.role_chain(profile_name))
+        .and_then(|chain| chain.problem_description())
+    {
+        tracing::warn!("'{profile_name}' can't get credentials: {problem}");
+    }
+    if let Ok(config) = context.read_config() {
+        warn_if_expiring(&config, profile_name, &context.settings.expiry)?;
+    }
+    notify_switch(context, profile_name);
+
+    if !read_only() {
+        SelectorState::update(|state| state.record_activation(profile_name, now()))?;
+    }
+    context.audit(AuditEvent::Activate, profile_name);
+
+    if context.settings.eks.sync {
+        match sync_kubeconfig(&context.config_paths, &context.settings.eks, profile_name) {
+            Ok(Some(kube_context)) => eprintln!("kubectl context: {kube_context}"),
+            Ok(None) => eprintln!("No EKS clusters for '{profile_name}'; kubeconfig unchanged"),
+            Err(e) => tracing::warn!(
+                "kubeconfig not updated: {}",
+                summarize_error(&format!("{e:#}"))
+            ),
+        }
+    }
+
+    run_hooks(hooks, Event::PostActivate, &env)
+}
+
+/// Without `-c`, warns when `AWS_PROFILE` in the environment names another
+/// profile, as the shell and the AWS CLI go by it rather than the
+/// current-profile file, and asks whether to print the export command for
+/// this shell instead of writing the file.
+fn export_instead(profile_name: &str) -> Result<bool> {
+    let Some(env) = std::env::var("AWS_PROFILE")
+        .ok()
+        .filter(|env| !env.is_empty() && env != profile_name)
+    else {
+        return Ok(false);
+    };
+    if logging::quiet() {
+        return Ok(false);
+    }
+    tracing::warn!(
+        "AWS_PROFILE={env} is set in this shell and overrides the current-profile file, so '{profile_name}' won't apply here; `-c` or the `init` shell function change AWS_PROFILE itself"
+    );
+    if !ui::has_terminal() {
+        return Ok(false);
+    }
+    answer(
+        Confirm::new("Print the command that sets AWS_PROFILE instead?")
+            .with_default(true)
+            .prompt(),
+    )
+}
+
+/// The shell commands `-c` prints for `profile_name`: `AWS_PROFILE`, and
+/// with a region to set, `AWS_REGION` and `AWS_DEFAULT_REGION`.
+fn activation_commands(
+    context: &Context,
+    profile_name: &str,
+    region: Option<&str>,
+    with_region: bool,
+) -> String {
+    if region.is_none() && !with_region {
+        return shell_command(Some(profile_name));
+    }
+    // A profile without a region unsets the previous profile's.
+    let region = region.map(str::to_string).or_else(|| {
+        let config = context.load_config().ok()?;
+        config
+            .find_profile(profile_name)?
+            .get_region()
+            .map(str::to_string)
+    });
+    env_commands(&[
+        ("AWS_PROFILE", Some(profile_name.to_string())),
+        ("AWS_REGION", region.clone()),
+        ("AWS_DEFAULT_REGION", region),
+    ])
+}
+
+/// `--copy` and `--copy-creds`: the profile's commands on the clipboard, for
+/// another machine's shell. Nothing is activated here, but protected
+/// profiles are still confirmed.
+fn copy_profile(
+    context: &Context,
+    profile_name: &str,
+    region: Option<&str>,
+    with_region: bool,
+    what: CopyWhat,
+) -> Result<()> {
+    confirm_protected(context, profile_name)?;
+    let (text, what) = match what {
+        CopyWhat::Command => (
+            activation_commands(context, profile_name, region, with_region),
+            format!("the command that selects '{profile_name}'"),
+        ),
+        CopyWhat::Credentials => (
+            export_commands(&credential_vars(context, profile_name, None)?),
+            format!("the temporary credentials of '{profile_name}'"),
+        ),
+    };
+    copy(&text, &what)?;
+    if !dry_run() && !logging::quiet() {
+        eprintln!("Copied {what} to the clipboard");
+    }
+    Ok(())
+}
+
+/// Activates `profile_name` for a `serve` client: as without `-c`, but with
+/// nothing printed to stdout, which carries the protocol. Protected profiles
+/// are the client's to confirm.
+pub(super) fn activate_quietly(
+    context: &Context,
+    profile_name: &str,
+    region: Option<&str>,
+) -> Result<Activation> {
+    let hooks = &context.settings.hooks;
+    let env = hook_env(context, Some(profile_name), region, active_profile()?);
+    run_hooks(hooks, Event::PreActivate, &env)
+        .map_err(|e| anyhow::anyhow!("{e}; '{profile_name}' was not activated"))?;
+
+    write_current_profile(profile_name, region)?;
+    SelectorState::update(|state| state.record_activation(profile_name, now()))?;
+    context.audit(AuditEvent::Activate, profile_name);
+
+    notify_switch(context, profile_name);
+    run_hooks(hooks, Event::PostActivate, &env)?;
+    Ok(Activation::new(context, profile_name, region, true))
+}
+
+/// The desktop notification `notify = true` asks for. A desktop without a
+/// notifier only gets a warning.
+fn notify_switch(context: &Context, profile_name: &str) {
+    if !context.settings.notify {
+        return;
+    }
+    let activation = Activation::new(context, profile_name, None, true);
+    let mut message = switch_message(profile_name, activation.account_id.as_deref());
+    if context.redact {
+        message = redact(&message);
+    }
+    if let Err(e) = notify(&message) {
+        tracing::warn!("no desktop notification: {e:#}");
+    }
+}
@@ -0,0 +1,568 @@
+use super::Context;
+use crate::config::{AwsConfig, config_section_header, mask_secrets};
+use crate::diff::Preview;
+use crate::editor::IniDocument;
+use crate::error::Error;
+use crate::keychain::{self, StaticKeys, helper_command};
+use crate::profile_templates::Variables;
+use crate::settings::get_settings_path;
+use crate::store::ProfileStore;
+use crate::ui::{answer, choose};
+use anyhow::Result;
+use clap::ArgMatches;
+use inquire::validator::Validation;
+use inquire::{Confirm, Password, Text};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy)]
+enum ProfileKind {
+    Sso,
+    Static,
+    AssumeRole,
+}
+
+impl ProfileKind {
+    const ALL: [ProfileKind; 3] = [Self::Sso, Self::Static, Self::AssumeRole];
+}
+
+impl fmt::Display for ProfileKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Sso => "SSO (IAM Identity Center)",
+            Self::Static => "Static access keys",
+            Self::AssumeRole => "Assume role from another profile",
+        })
+    }
+}
+
+/// An `[sso-session]` block created alongside the profile.
+struct NewSession {
+    name: String,
+    entries: Vec<(String, String)>,
+}
+
+/// Everything the wizard collected, ready to be written out.
+struct NewProfile {
+    name: String,
+    config_entries: Vec<(String, String)>,
+    credential_entries: Vec<(String, String)>,
+    session: Option<NewSession>,
+}
+
+impl NewProfile {
+    /// Appends the profile (and its new sso-session, if any) to the config
+    /// and credentials documents, for the preview. Empty sections are not
+    /// written.
+    fn write_to(&self, config: &mut IniDocument, credentials: &mut IniDocument) {
+        if let Some(session) = &self.session {
+            config.append_section(
+                &format!("sso-session {}", session.name),
+                &as_refs(&session.entries),
+            );
+        }
+
+        if !self.config_entries.is_empty() {
+            config.append_section(
+                &config_section_header(&self.name),
+                &as_refs(&self.config_entries),
+            );
+        }
+
+        if !self.credential_entries.is_empty() {
+            credentials.append_section(&self.name, &as_refs(&self.credential_entries));
+        }
+    }
+
+    /// Writes the profile (and its new sso-session, if any) to the store's
+    /// files.
+    fn save(&self, store: &mut ProfileStore) -> Result<()> {
+        if let Some(session) = &self.session {
+            store.add_sso_session(&session.name, &as_refs(&session.entries))?;
+        }
+        store.add_profile(
+            &self.name,
+            &as_refs(&self.config_entries),
+            &as_refs(&self.credential_entries),
+        )
+    }
+
+    /// Takes the static keys out of the credentials entries and points the
+    /// profile at the keychain helper instead.
+    fn take_static_keys(&mut self) -> Option<StaticKeys> {
+        let value = |key: &str| {
+            self.credential_entries
+                .iter()
+                .find(|(entry_key, _)| entry_key == key)
+                .map(|(_, value)| value.clone())
+        };
+        let keys = StaticKeys {
+            access_key_id: value("aws_access_key_id")?,
+            secret_access_key: value("aws_secret_access_key")?,
+        };
+
+        self.credential_entries
+            .retain(|(key, _)| key != "aws_access_key_id" && key != "aws_secret_access_key");
+        self.config_entries.insert(
+            0,
+            ("credential_process".to_string(), helper_command(&self.name)),
+        );
+        Some(keys)
+    }
+}
+
+fn as_refs(entries: &[(String, String)]) -> Vec<(&str, &str)> {
+    entries
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect()
+}
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    if let Some(template_name) = matches.get_one::<String>("from-template") {
+        return add_from_template(context, matches, template_name);
+    }
+
+    let paths = &context.config_paths;
+    // Adding the first profile creates the files.
+    let mut store = ProfileStore::open(paths.clone())?;
+    let config = store.config().clone();
+
+    let name = match matches.get_one::<String>("profile") {
+        Some(name) => name.clone(),
+        None => answer(Text::new("Profile name:").with_validator(required).prompt())?,
+    };
+
+    if config.find_profile(&name).is_some() {
+        return Err(anyhow::anyhow!("Profile '{name}' already exists"));
+    }
+
+    let kind = choose("Profile type", ProfileKind::ALL.to_vec())?;
+    let mut profile = match kind {
+        ProfileKind::Sso => prompt_sso(&name, &config)?,
+        ProfileKind::Static => prompt_static(&name)?,
+        ProfileKind::AssumeRole => prompt_assume_role(&name, &config)?,
+    };
+
+    let region = answer(
+        Text::new("Default region (optional):")
+            .with_help_message("e.g. us-east-1; leave empty to skip")
+            .prompt(),
+    )?;
+    if !region.trim().is_empty() {
+        profile
+            .config_entries
+            .push(("region".to_string(), region.trim().to_string()));
+    }
+
+    let output = answer(
+        Text::new("Default output format (optional):")
+            .with_help_message("json, yaml, text, or table; leave empty to skip")
+            .prompt(),
+    )?;
+    if !output.trim().is_empty() {
+        profile
+            .config_entries
+            .push(("output".to_string(), output.trim().to_string()));
+    }
+
+    let keychain_keys = match context.settings.keychain {
+        true => profile.take_static_keys(),
+        false => None,
+    };
+
+    let mut preview_config = IniDocument::parse("");
+    let mut preview_credentials = IniDocument::parse("");
+    profile.write_to(&mut preview_config, &mut preview_credentials);
+
+    if !preview_config.to_string().is_empty() {
+        eprintln!("\n{}:\n{preview_config}", paths.config.display());
+    }
+    if !preview_credentials.to_string().is_empty() {
+        eprintln!(
+            "\n{}:\n{}",
+            paths.credentials.display(),
+            mask_secrets(&preview_credentials.to_string())
+        );
+    }
+
+    if !matches.get_flag("yes")
+        && !answer(
+            Confirm::new("Write this profile?")
+                .with_default(true)
+                .prompt(),
+        )?
+    {
+        return Err(Error::cancelled().into());
+    }
+
+    if let Some(keys) = &keychain_keys {
+        keychain::store(&name, keys)?;
+    }
+    profile.save(&mut store)?;
+
+    println!("Profile '{name}' added");
+    Ok(())
+}
+
+/// Stamps out a template's profiles for the account, shows them as a diff,
+/// and writes them once confirmed. Profiles that already exist are skipped.
+fn add_from_template(context: &Context, matches: &ArgMatches, template_name: &str) -> Result<()> {
+    let template = context
+        .settings
+        .templates
+        .get(template_name)
+        .ok_or_else(|| {
+            let path = get_settings_path().unwrap_or_default();
+            Error::NotFound(format!(
+                "Template '{template_name}' not found in the [templates] of {}",
+                path.display()
+            ))
+        })?;
+
+    let mut variables = Variables::new();
+    if let Some(account_id) = matches.get_one::<String>("account") {
+        let account_name = context
+            .settings
+            .accounts
+            .get(account_id)
+            .unwrap_or(account_id);
+        variables.insert("account_id".to_string(), account_id.clone());
+        variables.insert("account_name".to_string(), account_name.clone());
+    }
+    for (key, value) in matches
+        .get_many::<(String, String)>("var")
+        .into_iter()
+        .flatten()
+    {
+        variables.insert(key.clone(), value.clone());
+    }
+
+    let paths = &context.config_paths;
+    let store = ProfileStore::open(paths.clone())?;
+    let mut profiles = Vec::new();
+    for profile in template.stamp(&variables)? {
+        if store.get(&profile.name).is_some() {
+            eprintln!("Skipping '{}': the profile already exists", profile.name);
+        } else {
+            profiles.push(profile);
+        }
+    }
+    if profiles.is_empty() {
+        println!("Every profile in template '{template_name}' already exists");
+        return Ok(());
+    }
+
+    let original = IniDocument::load(&paths.config)?;
+    let mut document = original.clone();
+    for profile in &profiles {
+        document.append_section(
+            &config_section_header(&profile.name),
+            &as_refs(&profile.entries),
+        );
+    }
+    let mut preview = Preview::new();
+    preview.file(&paths.config, &original.to_string(), &document.to_string());
+    preview.confirm(
+        Confirm::new(&format!("Add {} profile(s)?", profiles.len())).with_default(true),
+        matches.get_flag("yes"),
+    )?;
+    document.save(&paths.config)?;
+
+    let names: Vec<&str> = profiles
+        .iter()
+        .map(|profile| profile.name.as_str())
+        .collect();
+    println!("Added {} profile(s): {}", names.len(), names.join(", "));
+    Ok(())
+}
+
+fn prompt_sso(name: &str, config: &AwsConfig) -> Result<NewProfile> {
+    const NEW_SESSION: &str = "Create a new sso-session";
+
+    let mut choices: Vec<&str> = config
+        .sso_sessions
+        .iter()
+        .map(|session| session.name.as_str())
+        .collect();
+    choices.push(NEW_SESSION);
+
+    let choice = if choices.len() == 1 {
+        NEW_SESSION
+    } else {
+        choose("SSO session", choices)?
+    };
+
+    let session = if choice == NEW_SESSION {
+        Some(prompt_sso_session(config)?)
+    } else {
+        None
+    };
+    let session_name = session
+        .as_ref()
+        .map_or(choice.to_string(), |session| session.name.clone());
+
+    let account_id = answer(
+        Text::new("AWS account id:")
+            .with_validator(account_id)
+            .prompt(),
+    )?;
+    let role_name = answer(
+        Text::new("Permission set (role) name:")
+            .with_validator(required)
+            .prompt(),
+    )?;
+
+    Ok(NewProfile {
+        name: name.to_string(),
+        config_entries: vec![
+            ("sso_session".to_string(), session_name),
+            ("sso_account_id".to_string(), account_id.trim().to_string()),
+            ("sso_role_name".to_string(), role_name.trim().to_string()),
+        ],
+        credential_entries: Vec::new(),
+        session,
+    })
+}
+
+fn prompt_sso_session(config: &AwsConfig) -> Result<NewSession> {
+    let existing: Vec<String> = config
+        .sso_sessions
+        .iter()
+        .map(|session| session.name.clone())
+        .collect();
+
+    let name = answer(
+        Text::new("Session name:")
+            .with_validator(required)
+            .with_validator(move |input: &str| {
+                Ok(if existing.iter().any(|name| name == input.trim()) {
+                    Validation::Invalid("A session with that name already exists".into())
+                } else {
+                    Validation::Valid
+                })
+            })
+            .prompt(),
+    )?;
+    let start_url = answer(
+        Text::new("SSO start URL:")
+            .with_help_message("e.g. https://my-org.awsapps.com/start")
+            .with_validator(|input: &str| {
+                Ok(if input.trim().starts_with("https://") {
+                    Validation::Valid
+                } else {
+                    Validation::Invalid("The start URL must begin with https://".into())
+                })
+            })
+            .prompt(),
+    )?;
+    let region = answer(
+        Text::new("SSO region:")
+            .with_help_message("The region IAM Identity Center is hosted in")
+            .with_validator(required)
+            .prompt(),
+    )?;
+
+    Ok(NewSession {
+        name: name.trim().to_string(),
+        entries: vec![
+            ("sso_start_url".to_string(), start_url.trim().to_string()),
+            ("sso_region".to_string(), region.trim().to_string()),
+            (
+                "sso_registration_scopes".to_string(),
+                "sso:account:access".to_string(),
+            ),
+        ],
+    })
+}
+
+fn prompt_static(name: &str) -> Result<NewProfile> {
+    let access_key_id = answer(
+        Text::new("Access key id:")
+            .with_validator(required)
+            .prompt(),
+    )?;
+    let secret_access_key = answer(
+        Password::new("Secret access key:")
+            .without_confirmation()
+            .with_validator(required)
+            .prompt(),
+    )?;
+
+    Ok(NewProfile {
+        name: name.to_string(),
+        config_entries: Vec::new(),
+        credential_entries: vec![
+            (
+                "aws_access_key_id".to_string(),
+                access_key_id.trim().to_string(),
+            ),
+            (
+                "aws_secret_access_key".to_string(),
+                secret_access_key.trim().to_string(),
+            ),
+        ],
+        session: None,
+    })
+}
+
+fn prompt_assume_role(name: &str, config: &AwsConfig) -> Result<NewProfile> {
+    let sources: Vec<&str> = config
+        .profiles
+        .iter()
+        .map(|profile| profile.name.as_str())
+        .collect();
+
+    if sources.is_empty() {
+        return Err(anyhow::anyhow!(
+            "Assume-role profiles need an existing profile to source credentials from"
+        ));
+    }
+
+    let role_arn = answer(
+        Text::new("Role ARN:")
+            .with_help_message("e.g. arn:aws:iam::123456789012:role/Admin")
+            .with_validator(|input: &str| {
+                Ok(if input.trim().starts_with("arn:") {
+                    Validation::Valid
+                } else {
+                    Validation::Invalid("Expected an ARN starting with arn:".into())
+                })
+            })
+            .prompt(),
+    )?;
+    let source_profile = choose("Source profile", sources)?;
+    let mfa_serial = answer(
+        Text::new("MFA device ARN (optional):")
+            .with_help_message("Leave empty if the role doesn't require MFA")
+            .prompt(),
+    )?;
+
+    let mut config_entries = vec![
+        ("role_arn".to_string(), role_arn.trim().to_string()),
+        ("source_profile".to_string(), source_profile.to_string()),
+    ];
+    if !mfa_serial.trim().is_empty() {
+        config_entries.push(("mfa_serial".to_string(), mfa_serial.trim().to_string()));
+    }
+
+    Ok(NewProfile {
+        name: name.to_string(),
+        config_entries,
+        credential_entries: Vec::new(),
+        session: None,
+    })
+}
+
+fn required(input: &str) -> Result<Validation, inquire::CustomUserError> {
+    Ok(if input.trim().is_empty() {
+        Validation::Invalid("A value is required".into())
+    } else {
+        Validation::Valid
+    })
+}
+
+fn account_id(input: &str) -> Result<Validation, inquire::CustomUserError> {
+    let input = input.trim();
+    Ok(
+        if input.len() == 12 && input.chars().all(|c| c.is_ascii_digit()) {
+            Validation::Valid
+        } else {
+            Validation::Invalid("Account ids are 12 digits".into())
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_write_to_appends_session_profile_and_credentials() {
+        let profile = NewProfile {
+            name: "dev".to_string(),
+            config_entries: vec![
+                ("sso_session".to_string(), "corp".to_string()),
+                ("region".to_string(), "eu-west-1".to_string()),
+            ],
+            credential_entries: Vec::new(),
+            session: Some(NewSession {
+                name: "corp".to_string(),
+                entries: vec![(
+                    "sso_start_url".to_string(),
+                    "https://corp.awsapps.com/start".to_string(),
+                )],
+            }),
+        };
+
+        let mut config = IniDocument::parse("[default]\nregion = us-east-1\n");
+        let mut credentials = IniDocument::parse("");
+        profile.write_to(&mut config, &mut credentials);
+
+        assert_eq!(
+            config.to_string(),
+            r#"[default]
+region = us-east-1
+
+[sso-session corp]
+sso_start_url = https://corp.awsapps.com/start
+
+[profile dev]
+sso_session = corp
+region = eu-west-1
+"#
+        );
+        assert_eq!(credentials.to_string(), "");
+    }
+
+    #[test]
+    fn test_write_to_static_profile_goes_to_credentials() {
+        let profile = NewProfile {
+            name: "default".to_string(),
+            config_entries: vec![("region".to_string(), "us-east-1".to_string())],
+            credential_entries: vec![
+                ("aws_access_key_id".to_string(), "AKIA".to_string()),
+                ("aws_secret_access_key".to_string(), "secret".to_string()),
+            ],
+            session: None,
+        };
+
+        let mut config = IniDocument::parse("");
+        let mut credentials = IniDocument::parse("");
+        profile.write_to(&mut config, &mut credentials);
+
+        assert_eq!(config.to_string(), "[default]\nregion = us-east-1\n");
+        assert_eq!(
+            credentials.to_string(),
+            "[default]\naws_access_key_id = AKIA\naws_secret_access_key = secret\n"
+        );
+        assert_eq!(
+            mask_secrets(&credentials.to_string()),
+            "[default]\naws_access_key_id = AKIA\naws_secret_access_key = ********"
+        );
+    }
+
+    #[test]
+    fn test_take_static_keys_points_profile_at_keychain() {
+        let mut profile = NewProfile {
+            name: "ci".to_string(),
+            config_entries: vec![("region".to_string(), "us-east-1".to_string())],
+            credential_entries: vec![
+                ("aws_access_key_id".to_string(), "AKIA".to_string()),
+                ("aws_secret_access_key".to_string(), "secret".to_string()),
+            ],
+            session: None,
+        };
+
+        let keys = profile.take_static_keys().unwrap();
+
+        assert_eq!(keys.access_key_id, "AKIA");
+        let mut config = IniDocument::parse("");
+        let mut credentials = IniDocument::parse("");
+        profile.write_to(&mut config, &mut credentials);
+        assert_eq!(
+            config.to_string(),
+            "[profile ci]\ncredential_process = aws-profile-selector credential-process --profile ci\nregion = us-east-1\n"
+        );
+        assert_eq!(credentials.to_string(), "");
+    }
+}
@@ -0,0 +1,64 @@
+use super::Context;
+use crate::error::Error;
+use crate::state::SelectorState;
+use anyhow::Result;
+use clap::ArgMatches;
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("add", sub_matches)) => {
+            let alias = sub_matches
+                .get_one::<String>("alias")
+                .expect("alias is required");
+            let profile_name = sub_matches
+                .get_one::<String>("profile")
+                .expect("profile is required");
+            let config = context.load_config()?;
+
+            if alias == "-" || alias.is_empty() || alias.contains(char::is_whitespace) {
+                return Err(anyhow::anyhow!("'{alias}' can't be used as an alias"));
+            }
+            if config.find_profile(alias).is_some() {
+                return Err(anyhow::anyhow!(
+                    "'{alias}' is already the name of a profile"
+                ));
+            }
+            if config.find_profile(profile_name).is_none() {
+                return Err(Error::profile_not_found(profile_name).into());
+            }
+
+            match SelectorState::update(|state| {
+                state.aliases.insert(alias.clone(), profile_name.clone())
+            })? {
+                Some(previous) if previous != *profile_name => {
+                    println!("Alias '{alias}' now points to '{profile_name}' (was '{previous}')");
+                }
+                _ => println!("Alias '{alias}' points to '{profile_name}'"),
+            }
+        }
+        Some(("remove", sub_matches)) => {
+            let alias = sub_matches
+                .get_one::<String>("alias")
+                .expect("alias is required");
+            if SelectorState::update(|state| state.aliases.remove(alias))?.is_some() {
+                println!("Alias '{alias}' removed");
+            } else {
+                println!("No alias named '{alias}'");
+            }
+        }
+        Some(("list", _)) => {
+            let state = context.selector_state()?;
+            for (alias, profile_name) in &state.aliases {
+                println!("{alias} = {profile_name}");
+            }
+            for (alias, profile_name) in &state.configured_aliases {
+                if !state.aliases.contains_key(alias) {
+                    println!("{alias} = {profile_name} (settings file)");
+                }
+            }
+        }
+        _ => unreachable!("clap requires a subcommand"),
+    }
+
+    Ok(())
+}
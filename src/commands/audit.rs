@@ -0,0 +1,92 @@
+//! `audit export`: the audit log's entries, oldest first, optionally only
+//! the recent ones, one profile's, or one kind of event.
+
+use super::Context;
+use crate::accounts::{redact, redacted};
+use crate::audit::{self, AuditEvent};
+use crate::environment::now;
+use crate::filter::glob_match;
+use crate::output::{OutputFormat, print_structured, print_table};
+use crate::state::Window;
+use anyhow::Result;
+use chrono::Local;
+use clap::ArgMatches;
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("export", sub_matches)) => export(context, sub_matches),
+        _ => unreachable!("clap requires a subcommand"),
+    }
+}
+
+fn export(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let settings = &context.settings.audit;
+    if !settings.enabled {
+        tracing::warn!(
+            "The audit log is off, so nothing new is recorded; `enabled = true` under [audit] in the settings file turns it on"
+        );
+    }
+
+    let since = matches
+        .get_one::<Window>("since")
+        .map(|window| now() - window.length());
+    let profile = matches.get_one::<String>("profile");
+    let events: Vec<AuditEvent> = matches
+        .get_many::<AuditEvent>("event")
+        .map(|events| events.copied().collect())
+        .unwrap_or_default();
+    let entries: Vec<_> = audit::entries(settings)?
+        .into_iter()
+        .filter(|entry| since.is_none_or(|since| entry.at >= since))
+        .filter(|entry| profile.is_none_or(|pattern| glob_match(pattern, &entry.profile)))
+        .filter(|entry| events.is_empty() || events.contains(&entry.event))
+        .collect();
+
+    match matches.get_one::<OutputFormat>("output") {
+        None => {
+            for entry in &entries {
+                let line = match context.redact {
+                    true => serde_json::to_string(&redacted(entry)?)?,
+                    false => serde_json::to_string(entry)?,
+                };
+                println!("{line}");
+            }
+            Ok(())
+        }
+        Some(OutputFormat::Table) => {
+            let rows: Vec<Vec<String>> = entries
+                .iter()
+                .map(|entry| {
+                    let account = entry.account_id.clone().unwrap_or_default();
+                    vec![
+                        entry
+                            .at
+                            .with_timezone(&Local)
+                            .format("%Y-%m-%d %H:%M:%S")
+                            .to_string(),
+                        entry.event.name().to_string(),
+                        entry.profile.clone(),
+                        match context.redact {
+                            true => redact(&account),
+                            false => account,
+                        },
+                        entry.role.clone().unwrap_or_default(),
+                        entry.user.clone().unwrap_or_default(),
+                        entry.command.clone(),
+                    ]
+                })
+                .collect();
+            print_table(
+                &[
+                    "WHEN", "EVENT", "PROFILE", "ACCOUNT", "ROLE", "USER", "COMMAND",
+                ],
+                &rows,
+            );
+            Ok(())
+        }
+        Some(&format) => match context.redact {
+            true => print_structured(&redacted(&entries)?, format),
+            false => print_structured(&entries, format),
+        },
+    }
+}
@@ -0,0 +1,88 @@
+use super::output_format;
+use crate::backup::{self, Backup};
+use crate::error::Error;
+use crate::output::{OutputFormat, print_structured, print_table};
+use crate::ui::answer;
+use anyhow::Result;
+use chrono::Local;
+use clap::ArgMatches;
+use inquire::Confirm;
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("list", sub_matches)) => list(sub_matches),
+        Some(("restore", sub_matches)) => restore(sub_matches),
+        _ => unreachable!("clap requires a subcommand"),
+    }
+}
+
+fn list(matches: &ArgMatches) -> Result<()> {
+    let format = output_format(matches);
+    let backups = backup::list()?;
+    if format != OutputFormat::Table {
+        return print_structured(&backups, format);
+    }
+    if backups.is_empty() {
+        println!("No backups yet");
+        return Ok(());
+    }
+
+    let rows: Vec<Vec<String>> = backups
+        .iter()
+        .map(|backup| {
+            let files: Vec<String> = backup
+                .manifest
+                .files
+                .iter()
+                .map(|file| file.path.display().to_string())
+                .collect();
+            vec![
+                backup.id.clone(),
+                backup
+                    .manifest
+                    .created
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+                backup.manifest.command.clone(),
+                files.join(", "),
+            ]
+        })
+        .collect();
+    print_table(&["ID", "TAKEN", "COMMAND", "FILES"], &rows);
+    Ok(())
+}
+
+fn restore(matches: &ArgMatches) -> Result<()> {
+    let id = matches.get_one::<String>("id").expect("id is required");
+    let backups = backup::list()?;
+    let backup: &Backup = match id.as_str() {
+        "latest" => backups.first(),
+        id => backups.iter().find(|backup| backup.id == id),
+    }
+    .ok_or_else(|| Error::NotFound(format!("Backup '{id}' not found; see `backup list`")))?;
+
+    println!(
+        "Backup {} was taken before `{}` changed:",
+        backup.id, backup.manifest.command
+    );
+    for file in &backup.manifest.files {
+        match file.copy {
+            Some(_) => println!("  {}", file.path.display()),
+            None => println!("  {} (didn't exist, so it is removed)", file.path.display()),
+        }
+    }
+    if !matches.get_flag("yes")
+        && !answer(
+            Confirm::new("Restore these files?")
+                .with_default(false)
+                .prompt(),
+        )?
+    {
+        return Err(Error::cancelled().into());
+    }
+
+    backup::restore(backup)?;
+    println!("Restored backup {}", backup.id);
+    Ok(())
+}
@@ -0,0 +1,388 @@
+//! `export-bundle` and `import-bundle`: profile and sso-session definitions
+//! shared as a YAML or JSON file, for a team to keep one canonical set.
+//! Bundles never carry secrets: access keys, session tokens, and
+//! `credential_process` commands are left out, and so are nested settings
+//! such as `s3 =`.
+
+use super::Context;
+use super::exec::{matching_profiles, pick_profiles};
+use crate::config::{AwsConfig, config_section_header, credentials_file_wins};
+use crate::diff::Preview;
+use crate::dry_run::{ensure_writable, would_write};
+use crate::editor::IniDocument;
+use crate::error::Error;
+use crate::files::TempDir;
+use crate::filter::Pattern;
+use crate::output::print_table;
+use crate::store::ProfileStore;
+use crate::ui::answer;
+use anyhow::{Context as _, Result};
+use clap::ArgMatches;
+use inquire::Confirm;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fs;
+use std::io::Read;
+use std::path::PathBuf;
+use std::process::Command;
+
+type Settings = BTreeMap<String, String>;
+
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct Bundle {
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    sso_sessions: BTreeMap<String, Settings>,
+    #[serde(default)]
+    profiles: BTreeMap<String, Settings>,
+}
+
+impl Bundle {
+    /// The named profiles and the sso-sessions they use, without secrets.
+    fn from_config(config: &AwsConfig, profile_names: &[String]) -> Result<Self> {
+        let mut bundle = Self::default();
+        for name in profile_names {
+            let profile = config
+                .find_profile(name)
+                .ok_or_else(|| Error::profile_not_found(name))?;
+            if let Some(session) = profile.get_sso_session()
+                && let Some(session) = config.sso_sessions.iter().find(|s| s.name == session)
+            {
+                bundle
+                    .sso_sessions
+                    .insert(session.name.clone(), sanitized(&session.attributes));
+            }
+            bundle
+                .profiles
+                .insert(name.clone(), sanitized(&profile.attributes));
+        }
+        for (name, settings) in &bundle.profiles {
+            if let Some(source) = settings.get("source_profile")
+                && !bundle.profiles.contains_key(source)
+            {
+                tracing::warn!("'{name}' uses source_profile '{source}', which isn't exported");
+            }
+            if let Some(services) = settings.get("services") {
+                tracing::warn!("'{name}' uses [services {services}], which bundles don't carry");
+            }
+        }
+        Ok(bundle)
+    }
+}
+
+fn sanitized(attributes: &HashMap<String, String>) -> Settings {
+    attributes
+        .iter()
+        .filter(|(key, _)| !credentials_file_wins(key) && *key != "credential_process")
+        .map(|(key, value)| (key.clone(), value.clone()))
+        .collect()
+}
+
+pub fn export(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let config = context.load_config()?;
+    let profile_names = match matches.get_many::<String>("profiles") {
+        Some(names) => {
+            let state = context.selector_state()?;
+            let regex = matches.get_flag("regex");
+            let mut profile_names: Vec<String> = Vec::new();
+            for name in names {
+                let matched = match regex || Pattern::has_wildcards(name) {
+                    true => matching_profiles(context, &config, name, regex)?,
+                    false => vec![state.resolve_alias(name).to_string()],
+                };
+                for name in matched {
+                    if !profile_names.contains(&name) {
+                        profile_names.push(name);
+                    }
+                }
+            }
+            profile_names
+        }
+        None => pick_profiles(context, &config)?,
+    };
+    let bundle = Bundle::from_config(&config, &profile_names)?;
+
+    let text = match matches.get_one::<String>("format").map(String::as_str) {
+        Some("json") => format!("{}\n", serde_json::to_string_pretty(&bundle)?),
+        _ => serde_yaml::to_string(&bundle)?,
+    };
+    let Some(path) = matches.get_one::<PathBuf>("file") else {
+        print!("{text}");
+        return Ok(());
+    };
+    ensure_writable(format_args!("write {}", path.display()))?;
+    if would_write(path, &text) {
+        return Ok(());
+    }
+    fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))?;
+    eprintln!(
+        "Exported {} profile(s) to {}",
+        bundle.profiles.len(),
+        path.display()
+    );
+    Ok(())
+}
+
+/// How a bundle's entry compares with the local one.
+#[derive(Debug, PartialEq, Eq)]
+enum Change {
+    New,
+    Same,
+    /// The keys whose values differ, with the local value (if any) and the
+    /// bundle's. Keys only the local entry has are kept either way.
+    Differs(Vec<(String, Option<String>, String)>),
+}
+
+fn compare(local: Option<&HashMap<String, String>>, theirs: &Settings) -> Change {
+    let Some(local) = local else {
+        return Change::New;
+    };
+    let differences: Vec<_> = theirs
+        .iter()
+        .filter(|(key, value)| local.get(*key) != Some(value))
+        .map(|(key, value)| (key.clone(), local.get(key).cloned(), value.clone()))
+        .collect();
+    match differences.is_empty() {
+        true => Change::Same,
+        false => Change::Differs(differences),
+    }
+}
+
+pub fn import(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let source = matches
+        .get_one::<String>("source")
+        .expect("source is required");
+    let path = matches
+        .get_one::<String>("path")
+        .expect("path has a default");
+    let text = read_source(source, path)?;
+    let bundle: Bundle = serde_yaml::from_str(&text)
+        .with_context(|| format!("'{source}' is not a profile bundle"))?;
+
+    let store = ProfileStore::open(context.config_paths.clone())?;
+    let sessions: Vec<(&String, &Settings, Change)> = bundle
+        .sso_sessions
+        .iter()
+        .map(|(name, settings)| {
+            let local = store.sso_sessions().iter().find(|s| &s.name == name);
+            let change = compare(local.map(|s| &s.attributes), settings);
+            (name, settings, change)
+        })
+        .collect();
+    let profiles: Vec<(&String, &Settings, Change)> = bundle
+        .profiles
+        .iter()
+        .map(|(name, settings)| {
+            let change = compare(store.get(name).map(|p| &p.attributes), settings);
+            (name, settings, change)
+        })
+        .collect();
+
+    let entries: Vec<(&str, &(&String, &Settings, Change))> = sessions
+        .iter()
+        .map(|entry| ("sso-session", entry))
+        .chain(profiles.iter().map(|entry| ("profile", entry)))
+        .collect();
+    let rows: Vec<Vec<String>> = entries
+        .iter()
+        .map(|(kind, (name, _, change))| {
+            let change = match change {
+                Change::New => "new".to_string(),
+                Change::Same => "unchanged".to_string(),
+                Change::Differs(keys) => format!("{} key(s) differ", keys.len()),
+            };
+            vec![kind.to_string(), name.to_string(), change]
+        })
+        .collect();
+    print_table(&["KIND", "NAME", "CHANGE"], &rows);
+
+    // Every question comes before the first write, so cancelling one
+    // leaves the config as it was.
+    let yes = matches.get_flag("yes");
+    let mut chosen = Vec::new();
+    for (kind, entry) in entries {
+        if should_write(&format!("{kind} {}", entry.0), &entry.2, yes)? {
+            chosen.push((kind, entry));
+        }
+    }
+    if chosen.is_empty() {
+        println!("Nothing to merge");
+        return Ok(());
+    }
+
+    let path = &store.paths().config;
+    let original = IniDocument::load(path)?;
+    let mut document = original.clone();
+    for (kind, (name, settings, change)) in &chosen {
+        let header = match (*kind, store.get(name)) {
+            ("sso-session", _) => format!("sso-session {name}"),
+            // A credentials-only profile gets its config section here.
+            (_, Some(profile)) if document.has_section(&profile.section_header()) => {
+                profile.section_header()
+            }
+            _ => config_section_header(name),
+        };
+        match change {
+            Change::New => document.append_section(&header, &pairs(settings)),
+            _ => {
+                for (key, value) in pairs(settings) {
+                    document.set(&header, key, value);
+                }
+            }
+        }
+    }
+
+    let entries = format!(
+        "{} entr{}",
+        chosen.len(),
+        if chosen.len() == 1 { "y" } else { "ies" }
+    );
+    let mut preview = Preview::new();
+    preview.file(path, &original.to_string(), &document.to_string());
+    preview.confirm(
+        Confirm::new(&format!("Merge {entries} into {}?", path.display())).with_default(true),
+        yes,
+    )?;
+    document.save(path)?;
+    println!("Merged {entries} into {}", path.display());
+    Ok(())
+}
+
+fn pairs(settings: &Settings) -> Vec<(&str, &str)> {
+    settings
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect()
+}
+
+/// New entries are always added; differing ones are shown and asked about
+/// unless `yes` is given.
+fn should_write(section: &str, change: &Change, yes: bool) -> Result<bool> {
+    let differences = match change {
+        Change::New => return Ok(true),
+        Change::Same => return Ok(false),
+        Change::Differs(differences) => differences,
+    };
+    if yes {
+        return Ok(true);
+    }
+    println!("[{section}] differs from the bundle:");
+    for (key, local, theirs) in differences {
+        println!(
+            "  {key}: {} -> {theirs}",
+            local.as_deref().unwrap_or("(not set)")
+        );
+    }
+    answer(
+        Confirm::new(&format!("Take the bundle's values for [{section}]?"))
+            .with_default(false)
+            .prompt(),
+    )
+}
+
+/// The bundle's text: `-` reads stdin, a git URL is cloned and `path` read
+/// from it, and anything else is a file.
+fn read_source(source: &str, path: &str) -> Result<String> {
+    if source == "-" {
+        let mut text = String::new();
+        std::io::stdin().read_to_string(&mut text)?;
+        return Ok(text);
+    }
+    if !is_git_url(source) {
+        return fs::read_to_string(source).with_context(|| format!("Failed to read {source}"));
+    }
+
+    // Removed with everything cloned into it however this returns.
+    let dir = TempDir::new("bundle")?;
+    let checkout = dir.join("checkout");
+    // `--` so a source starting with `-` isn't taken for an option.
+    let status = Command::new("git")
+        .args(["clone", "--quiet", "--depth", "1", "--", source])
+        .arg(&checkout)
+        .status()
+        .context("Failed to run git")?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("git clone {source} failed with {status}"));
+    }
+    fs::read_to_string(checkout.join(path)).with_context(|| format!("No {path} in {source}"))
+}
+
+/// Whether `source` names a git remote rather than a local file: any
+/// `https://` or `http://` URL, as hosts serve repositories with or without
+/// `.git`, and the usual SSH and git forms.
+fn is_git_url(source: &str) -> bool {
+    source.ends_with(".git")
+        || ["git@", "git://", "ssh://", "https://", "http://"]
+            .iter()
+            .any(|scheme| source.starts_with(scheme))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::parse_aws_config;
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let config = parse_aws_config(
+            r#"
+[profile dev]
+sso_session = corp
+sso_account_id = 111111111111
+sso_role_name = Admin
+region = eu-west-1
+aws_access_key_id = AKIA
+credential_process = vault dev
+
+[sso-session corp]
+sso_start_url = https://corp.awsapps.com/start
+sso_region = us-east-1
+"#,
+        )
+        .unwrap();
+
+        let bundle = Bundle::from_config(&config, &["dev".to_string()]).unwrap();
+        assert_eq!(
+            serde_yaml::to_string(&bundle).unwrap(),
+            "sso_sessions:
+  corp:
+    sso_region: us-east-1
+    sso_start_url: https://corp.awsapps.com/start
+profiles:
+  dev:
+    region: eu-west-1
+    sso_account_id: '111111111111'
+    sso_role_name: Admin
+    sso_session: corp
+"
+        );
+        let json = serde_json::to_string(&bundle).unwrap();
+        assert_eq!(serde_yaml::from_str::<Bundle>(&json).unwrap(), bundle);
+
+        let local = &config.profiles[0].attributes;
+        assert_eq!(compare(Some(local), &bundle.profiles["dev"]), Change::Same);
+        let mut theirs = bundle.profiles["dev"].clone();
+        theirs.insert("region".to_string(), "us-west-2".to_string());
+        theirs.insert("output".to_string(), "json".to_string());
+        assert_eq!(
+            compare(Some(local), &theirs),
+            Change::Differs(vec![
+                ("output".to_string(), None, "json".to_string()),
+                (
+                    "region".to_string(),
+                    Some("eu-west-1".to_string()),
+                    "us-west-2".to_string()
+                ),
+            ])
+        );
+        assert_eq!(compare(None, &theirs), Change::New);
+    }
+
+    #[test]
+    fn test_git_sources() {
+        assert!(is_git_url("git@github.com:acme/aws-profiles.git"));
+        assert!(is_git_url("https://gitlab.example.com/acme/aws-profiles"));
+        assert!(is_git_url("ssh://git@example.com/acme/aws-profiles"));
+        assert!(!is_git_url("bundles/team.yaml"));
+        assert!(!is_git_url("-u evil"));
+    }
+}
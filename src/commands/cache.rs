@@ -0,0 +1,16 @@
+use anyhow::Result;
+use clap::ArgMatches;
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("clear", _)) => {
+            if crate::cache::clear()? {
+                println!("Cache cleared");
+            } else {
+                println!("Cache is already empty");
+            }
+        }
+        _ => unreachable!("clap requires a subcommand"),
+    }
+    Ok(())
+}
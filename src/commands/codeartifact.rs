@@ -0,0 +1,111 @@
+use super::{Context, profile_or_active};
+use crate::accounts::redact;
+use crate::aws::{block_on, get_codeartifact_login, load_sdk_config};
+use anyhow::{Context as _, Result};
+use clap::ArgMatches;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let profile_name = profile_or_active(context, matches.get_one::<String>("profile"))?;
+    let domain = matches
+        .get_one::<String>("domain")
+        .expect("domain is required");
+    let repository = matches.get_one::<String>("repo").expect("repo is required");
+    let tool = matches.get_one::<String>("tool").expect("tool is required");
+    let format = match tool.as_str() {
+        "pip" => "pypi",
+        other => other,
+    };
+
+    let login = block_on(async {
+        let sdk_config = load_sdk_config(&context.config_paths, &profile_name).await;
+        if sdk_config.region().is_none() {
+            return Err(anyhow::anyhow!(
+                "'{profile_name}' has no region, and CodeArtifact domains are regional"
+            ));
+        }
+        get_codeartifact_login(
+            &sdk_config,
+            domain,
+            matches
+                .get_one::<String>("domain-owner")
+                .map(String::as_str),
+            repository,
+            format,
+        )
+        .await
+    })??;
+
+    if matches.get_flag("print") {
+        println!("{}", login.token);
+        return Ok(());
+    }
+
+    let endpoint = match context.redact {
+        true => redact(&login.endpoint),
+        false => login.endpoint.clone(),
+    };
+    eprintln!("Configuring {tool} for {endpoint} as {profile_name}");
+
+    match tool.as_str() {
+        "npm" => {
+            // Same settings as `aws codeartifact login --tool npm`.
+            let auth_key = format!("{}:_authToken", login.endpoint.trim_start_matches("https:"));
+            run_tool("npm", &["config", "set", "registry", &login.endpoint], None)?;
+            run_tool(
+                "npm",
+                &["config", "set", &format!("{auth_key}={}", login.token)],
+                None,
+            )?;
+        }
+        "pip" => {
+            let index_url = format!(
+                "https://aws:{}@{}simple/",
+                login.token,
+                login.endpoint.trim_start_matches("https://")
+            );
+            run_tool(
+                "pip",
+                &["config", "set", "global.index-url", &index_url],
+                None,
+            )?;
+        }
+        _ => {
+            // cargo keeps the token per registry; the index itself has to be
+            // in the cargo config under the repository's name.
+            let token = format!("Bearer {}", login.token);
+            run_tool("cargo", &["login", "--registry", repository], Some(&token))?;
+            eprintln!(
+                "The registry needs `[registries.{repository}] index = \"sparse+{endpoint}\"` and \
+                 `credential-provider = \"cargo:token\"` in ~/.cargo/config.toml"
+            );
+        }
+    }
+
+    Ok(())
+}
+
+/// Runs the package manager, feeding `stdin` to it when given, and fails if
+/// it does.
+fn run_tool(program: &str, args: &[&str], stdin: Option<&str>) -> Result<()> {
+    let mut command = Command::new(program);
+    command.args(args).stdout(Stdio::from(std::io::stderr()));
+    if stdin.is_some() {
+        command.stdin(Stdio::piped());
+    }
+
+    let mut child = command
+        .spawn()
+        .with_context(|| format!("Failed to run {program}"))?;
+    if let Some(input) = stdin {
+        let mut pipe = child.stdin.take().expect("stdin is piped");
+        writeln!(pipe, "{input}")?;
+    }
+
+    let status = child.wait()?;
+    if !status.success() {
+        return Err(anyhow::anyhow!("`{program} {}` failed", args[0]));
+    }
+    Ok(())
+}
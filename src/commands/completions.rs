@@ -0,0 +1,23 @@
+use anyhow::{Context as _, Result};
+use clap::ArgMatches;
+use clap_complete::env::Shells;
+
+const BIN: &str = "aws-profile-selector";
+
+/// Environment variable the registration script sets when it calls back
+/// into the binary; matches `CompleteEnv`'s default.
+const COMPLETE_VAR: &str = "COMPLETE";
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let shell_name = matches
+        .get_one::<String>("shell")
+        .expect("shell is required");
+    let shells = Shells::builtins();
+    let shell = shells
+        .completer(shell_name)
+        .expect("clap restricts the shell");
+
+    shell
+        .write_registration(COMPLETE_VAR, BIN, BIN, BIN, &mut std::io::stdout())
+        .context("Failed to write the completion script")
+}
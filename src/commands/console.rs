@@ -0,0 +1,48 @@
+use super::{Context, profile_or_active};
+use crate::audit::AuditEvent;
+use crate::aws::{block_on, get_federation_token, load_sdk_config, resolve_credentials};
+use crate::console::signin_url;
+use crate::session::session_credentials;
+use anyhow::Result;
+use clap::ArgMatches;
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let profile_name = profile_or_active(context, matches.get_one::<String>("profile"))?;
+
+    let duration = context.session_duration(matches, &profile_name);
+    let (credentials, region, session_duration) = match duration {
+        // The console session lasts as long as asked for only with
+        // AssumeRole credentials; federation tokens last that long anyway.
+        Some(duration) => {
+            let session =
+                session_credentials(&context.config_paths, &profile_name, duration, true)?;
+            let session_duration = session.assumed_role.then_some(duration);
+            (session.credentials, session.region, session_duration)
+        }
+        None => block_on(async {
+            let sdk_config = load_sdk_config(&context.config_paths, &profile_name).await;
+            let mut credentials = resolve_credentials(&sdk_config).await?;
+
+            // Long-lived access keys can't sign in directly; trade them for a
+            // federation token first.
+            if credentials.session_token().is_none() {
+                credentials = get_federation_token(&sdk_config, None).await?;
+            }
+
+            let region = sdk_config.region().map(|region| region.to_string());
+            anyhow::Ok((credentials, region, None))
+        })??,
+    };
+
+    let url = signin_url(&credentials, region.as_deref(), session_duration)?;
+    context.audit(AuditEvent::Console, &profile_name);
+
+    if matches.get_flag("print") {
+        println!("{url}");
+    } else {
+        eprintln!("Opening the AWS console for {profile_name}");
+        open::that_detached(url.as_str())?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,157 @@
+//! `credential-process`: resolves a profile and prints the JSON document the
+//! AWS SDKs read from a `credential_process` helper, so other profiles can
+//! borrow its credentials:
+//!
+//! ```ini
+//! [profile tools]
+//! credential_process = aws-profile-selector credential-process --profile dev
+//! ```
+//!
+//! A profile pointing at itself this way has its keys in the OS keychain
+//! (see `secure import`) or in 1Password (see `[credential-sources]`), and
+//! those are printed as they are.
+
+use super::Context;
+use crate::aws::{block_on, load_sdk_config, resolve_credentials};
+use crate::cache::Cache;
+use crate::environment::now;
+use crate::keychain::{self, StaticKeys, runs_helper_for};
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, TimeDelta, Utc};
+use clap::ArgMatches;
+use serde::{Deserialize, Serialize};
+
+const CACHE_NAME: &str = "credential-process";
+/// Temporary credentials are kept until shortly before they expire; no AWS
+/// session lasts longer than this.
+const CACHE_TTL: TimeDelta = TimeDelta::hours(12);
+
+/// Cached credentials with less than this left are resolved again, so the
+/// caller doesn't get keys that expire before it can use them.
+const EXPIRY_MARGIN: TimeDelta = TimeDelta::minutes(5);
+
+/// The output format described under "Sourcing credentials with an external
+/// process" in the AWS SDK reference.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct CredentialDocument {
+    version: u8,
+    access_key_id: String,
+    secret_access_key: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    session_token: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    expiration: Option<String>,
+}
+
+impl CredentialDocument {
+    fn is_fresh(&self, now: DateTime<Utc>) -> bool {
+        self.expiration
+            .as_deref()
+            .and_then(|expiration| DateTime::parse_from_rfc3339(expiration).ok())
+            .is_some_and(|expiration| expiration.with_timezone(&Utc) - now > EXPIRY_MARGIN)
+    }
+}
+
+impl From<StaticKeys> for CredentialDocument {
+    fn from(keys: StaticKeys) -> Self {
+        Self {
+            version: 1,
+            access_key_id: keys.access_key_id,
+            secret_access_key: keys.secret_access_key,
+            session_token: None,
+            expiration: None,
+        }
+    }
+}
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let profile_name = matches
+        .get_one::<String>("profile")
+        .expect("profile is required");
+    let profile_name = context
+        .selector_state()?
+        .resolve_alias(profile_name)
+        .to_string();
+
+    if let Some(source) = context.settings.credential_sources.get(&profile_name) {
+        let document = CredentialDocument::from(source.load()?);
+        println!("{}", serde_json::to_string(&document)?);
+        return Ok(());
+    }
+
+    let config = context.read_config()?;
+    if let Some(command) = config
+        .find_profile(&profile_name)
+        .and_then(|profile| profile.attributes.get("credential_process"))
+        && runs_helper_for(command, &profile_name)
+    {
+        let Some(keys) = keychain::load(&profile_name)? else {
+            return Err(anyhow::anyhow!(
+                "'{profile_name}' gets its credentials from `{command}`, but the OS keychain has no keys for it"
+            ));
+        };
+        println!(
+            "{}",
+            serde_json::to_string(&CredentialDocument::from(keys))?
+        );
+        return Ok(());
+    }
+
+    let now = now();
+    let mut cache: Cache<CredentialDocument> = Cache::load_private(CACHE_NAME, CACHE_TTL)?;
+    if let Some(document) = cache
+        .get(&profile_name, now)
+        .filter(|document| document.is_fresh(now))
+    {
+        println!("{}", serde_json::to_string(document)?);
+        return Ok(());
+    }
+
+    let credentials = block_on(async {
+        let sdk_config = load_sdk_config(&context.config_paths, &profile_name).await;
+        resolve_credentials(&sdk_config).await
+    })??;
+    let document = CredentialDocument {
+        version: 1,
+        access_key_id: credentials.access_key_id().to_string(),
+        secret_access_key: credentials.secret_access_key().to_string(),
+        session_token: credentials.session_token().map(str::to_string),
+        expiration: credentials
+            .expiry()
+            .map(|expiry| DateTime::<Utc>::from(expiry).to_rfc3339_opts(SecondsFormat::Secs, true)),
+    };
+    println!("{}", serde_json::to_string(&document)?);
+
+    // Long-lived keys are already on disk; only temporary ones are worth
+    // keeping, and a failed save just means resolving again next time.
+    if document.expiration.is_some() {
+        cache.insert(&profile_name, document, now);
+        let _ = cache.save(now);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_document_matches_sdk_format() {
+        let document = CredentialDocument {
+            version: 1,
+            access_key_id: "ASIA".to_string(),
+            secret_access_key: "secret".to_string(),
+            session_token: None,
+            expiration: Some("2026-10-14T12:00:00Z".to_string()),
+        };
+
+        assert_eq!(
+            serde_json::to_string(&document).unwrap(),
+            r#"{"Version":1,"AccessKeyId":"ASIA","SecretAccessKey":"secret","Expiration":"2026-10-14T12:00:00Z"}"#
+        );
+        let now: DateTime<Utc> = "2026-10-14T11:50:00Z".parse().unwrap();
+        assert!(document.is_fresh(now));
+        assert!(!document.is_fresh(now + TimeDelta::minutes(6)));
+    }
+}
@@ -0,0 +1,162 @@
+use super::{Context, profile_or_active};
+use crate::aws::{block_on, load_sdk_config, resolve_credentials};
+use crate::dry_run::ensure_writable;
+use crate::editor::IniDocument;
+use crate::environment::now;
+use crate::files::{Access, TempDir, write_atomically};
+use crate::session::session_credentials;
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::ArgMatches;
+
+/// Starts the comment line that marks sections written by `creds write`.
+const MARKER: &str = "# Written by aws-profile-selector";
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("write", sub_matches)) => write(context, sub_matches),
+        Some(("clean", _)) => clean(context),
+        _ => unreachable!("clap requires a subcommand"),
+    }
+}
+
+fn write(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let profile_name = profile_or_active(context, matches.get_one::<String>("profile"))?;
+    let section = matches
+        .get_one::<String>("as")
+        .cloned()
+        .unwrap_or_else(|| profile_name.clone());
+    let path = &context.config_paths.credentials;
+    ensure_writable(format_args!("write {}", path.display()))?;
+    let mut document = IniDocument::load(path)?;
+
+    let ours = document
+        .section_text(&section)
+        .map(|text| written_expiry(&text));
+    if let Some(None) = ours
+        && !matches.get_flag("force")
+    {
+        return Err(anyhow::anyhow!(
+            "[{section}] in {} wasn't written by aws-profile-selector; pick another name with --as or replace it with --force",
+            path.display()
+        ));
+    }
+
+    // Resolve without the section being replaced, or the SDK would hand back
+    // the (possibly expired) keys written last time.
+    let mut paths = context.config_paths.clone();
+    let mut temp_dir = None;
+    if ours.is_some() {
+        let mut without_section = IniDocument::load(path)?;
+        without_section.remove_section(&section);
+        // Written even in a dry run: only the SDK reads it, and it goes again
+        // with the directory.
+        let dir = temp_dir.insert(TempDir::new("credentials")?);
+        paths.credentials = dir.join("credentials");
+        write_atomically(
+            &paths.credentials,
+            without_section.to_string(),
+            Access::Private,
+        )?;
+    }
+    let credentials = match context.session_duration(matches, &profile_name) {
+        Some(duration) => session_credentials(&paths, &profile_name, duration, false)
+            .map(|session| Ok(session.credentials)),
+        None => block_on(async {
+            let sdk_config = load_sdk_config(&paths, &profile_name).await;
+            resolve_credentials(&sdk_config).await
+        }),
+    };
+    drop(temp_dir);
+    let credentials = credentials??;
+
+    let Some(session_token) = credentials.session_token() else {
+        return Err(anyhow::anyhow!(
+            "'{profile_name}' resolves to long-lived access keys, which don't need copying"
+        ));
+    };
+    let expiry = credentials.expiry().map(DateTime::<Utc>::from);
+    let expires = match expiry {
+        Some(expiry) => expiry.to_rfc3339_opts(SecondsFormat::Secs, true),
+        None => "unknown".to_string(),
+    };
+
+    let text = format!(
+        "[{section}]\n{MARKER} from '{profile_name}'; expires {expires}\naws_access_key_id = {}\naws_secret_access_key = {}\naws_session_token = {session_token}\n",
+        credentials.access_key_id(),
+        credentials.secret_access_key(),
+    );
+    document.replace_section_text(&section, &text);
+    document.save(path)?;
+
+    println!(
+        "Wrote temporary credentials for '{profile_name}' to [{section}] in {} (expire {expires})",
+        path.display()
+    );
+    Ok(())
+}
+
+fn clean(context: &Context) -> Result<()> {
+    let path = &context.config_paths.credentials;
+    let mut document = IniDocument::load(path)?;
+    let now = now();
+
+    let expired: Vec<String> = document
+        .sections()
+        .into_iter()
+        .filter(|section| {
+            document
+                .section_text(section)
+                .and_then(|text| written_expiry(&text))
+                .is_some_and(|expiry| expiry.is_none_or(|expiry| expiry <= now))
+        })
+        .map(str::to_string)
+        .collect();
+
+    if expired.is_empty() {
+        println!("No expired temporary credentials in {}", path.display());
+        return Ok(());
+    }
+
+    for section in &expired {
+        document.remove_section(section);
+        println!("Removed [{section}]");
+    }
+    document.save(path)
+}
+
+/// For a section written by `creds write`, when its credentials expire
+/// (`None` if that wasn't known); `None` for any other section.
+fn written_expiry(section_text: &str) -> Option<Option<DateTime<Utc>>> {
+    let line = section_text
+        .lines()
+        .find(|line| line.trim_start().starts_with(MARKER))?;
+    let (_, expires) = line.rsplit_once("expires ")?;
+
+    Some(
+        DateTime::parse_from_rfc3339(expires.trim())
+            .ok()
+            .map(|expiry| expiry.with_timezone(&Utc)),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_written_expiry() {
+        let written = "[dev]\n# Written by aws-profile-selector from 'dev'; expires 2026-10-14T12:00:00Z\naws_access_key_id = ASIA\n";
+        assert_eq!(
+            written_expiry(written),
+            Some(Some("2026-10-14T12:00:00Z".parse().unwrap()))
+        );
+        assert_eq!(written_expiry("[legacy]\naws_access_key_id = AKIA\n"), None);
+        assert_eq!(
+            written_expiry(
+                "[dev]\n# Written by aws-profile-selector from 'dev'; expires unknown\n"
+            ),
+            Some(None)
+        );
+    }
+}
@@ -0,0 +1,91 @@
+use super::{Context, output_format};
+use crate::error::Error;
+use crate::output::{OutputFormat, print_structured};
+use crate::state::{
+    get_current_profile_path, read_current_profile, read_current_region, read_local_profile,
+};
+use anyhow::Result;
+use clap::ArgMatches;
+use serde::Serialize;
+
+/// Where the active profile was found.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum Source {
+    LocalFile,
+    StateFile,
+    Environment,
+}
+
+#[derive(Serialize)]
+struct CurrentProfile {
+    profile: String,
+    source: Source,
+    path: Option<String>,
+    env_profile: Option<String>,
+    env_matches: bool,
+    /// Given with `activate --region` in place of the profile's own.
+    region: Option<String>,
+}
+
+pub fn run(_context: &Context, matches: &ArgMatches) -> Result<()> {
+    let format = output_format(matches);
+    let local_profile = read_local_profile()?;
+    let file_profile = read_current_profile()?;
+    let env_profile = std::env::var("AWS_PROFILE")
+        .ok()
+        .filter(|profile| !profile.is_empty());
+
+    let current = match (local_profile, file_profile, &env_profile) {
+        (Some((profile, path)), _, _) => CurrentProfile {
+            env_matches: env_profile.as_ref() == Some(&profile),
+            profile,
+            source: Source::LocalFile,
+            path: Some(path.display().to_string()),
+            env_profile: env_profile.clone(),
+            region: None,
+        },
+        (None, Some(profile), _) => CurrentProfile {
+            env_matches: env_profile.as_ref() == Some(&profile),
+            profile,
+            source: Source::StateFile,
+            path: Some(get_current_profile_path()?.display().to_string()),
+            env_profile: env_profile.clone(),
+            region: read_current_region()?,
+        },
+        (None, None, Some(profile)) => CurrentProfile {
+            profile: profile.clone(),
+            source: Source::Environment,
+            path: None,
+            env_profile: env_profile.clone(),
+            env_matches: true,
+            region: None,
+        },
+        (None, None, None) => {
+            return Err(Error::NotFound("No active AWS profile".to_string()).into());
+        }
+    };
+
+    if format != OutputFormat::Table {
+        return print_structured(&current, format);
+    }
+
+    println!("{}", current.profile);
+
+    match (&current.path, &current.env_profile) {
+        (Some(path), None) => println!("  source: {path} (AWS_PROFILE is not set)"),
+        (Some(path), Some(_)) if current.env_matches => {
+            println!("  source: {path} (AWS_PROFILE agrees)")
+        }
+        (Some(path), Some(env_profile)) => {
+            println!("  source: {path}");
+            println!("  warning: AWS_PROFILE is set to '{env_profile}' in this environment");
+        }
+        (None, _) => println!("  source: AWS_PROFILE environment variable"),
+    }
+    if let Some(region) = &current.region {
+        println!("  region: {region} (from activate --region)");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,317 @@
+use super::{Context, attribute_filters, check_options, output_format};
+use crate::check::{CheckResult, check_in_order};
+use crate::config::{AwsConfig, ProfileConflict, profile_conflicts, read_aws_config};
+use crate::editor::IniDocument;
+use crate::error::EXIT_FAILURE;
+use crate::filter::matches_all;
+use crate::output::{OutputFormat, print_structured, print_table};
+use crate::regions::is_known_region;
+use anyhow::Result;
+use clap::ArgMatches;
+use serde::Serialize;
+use std::collections::HashSet;
+use std::fs;
+use std::path::Path;
+
+/// A single problem found in the AWS config.
+#[derive(Debug, PartialEq, Eq, Serialize)]
+struct Problem {
+    /// What the problem is about, e.g. `profile dev` or a file path.
+    subject: String,
+    message: String,
+}
+
+impl Problem {
+    fn new(subject: impl Into<String>, message: impl Into<String>) -> Self {
+        Self {
+            subject: subject.into(),
+            message: message.into(),
+        }
+    }
+}
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    if let Some(("creds", sub_matches)) = matches.subcommand() {
+        return creds(context, sub_matches);
+    }
+
+    let format = output_format(matches);
+    let paths = &context.config_paths;
+
+    let mut problems = Vec::new();
+    let mut unreadable = false;
+    for path in [&paths.config, &paths.credentials] {
+        match check_file(path) {
+            Ok(duplicates) => problems.extend(duplicates),
+            Err(problem) => {
+                unreadable = true;
+                problems.push(problem);
+            }
+        }
+    }
+
+    // Only lint profiles when both files could be read.
+    if !unreadable {
+        match read_aws_config(paths) {
+            Ok(config) => problems.extend(lint(&config)),
+            Err(e) => problems.push(Problem::new(
+                paths.config.display().to_string(),
+                format!("{e:#}"),
+            )),
+        }
+        problems.extend(
+            profile_conflicts(paths)?
+                .into_iter()
+                // A repeated header is already reported for its file.
+                .filter(|conflict| {
+                    !matches!(conflict, ProfileConflict::Duplicate { headers, .. } if headers.len() == 1)
+                })
+                .map(|conflict| {
+                    Problem::new(format!("profile {}", conflict.profile()), conflict.to_string())
+                }),
+        );
+    }
+
+    if format != OutputFormat::Table {
+        print_structured(&problems, format)?;
+    } else if problems.is_empty() {
+        println!("No problems found");
+    } else {
+        let rows: Vec<Vec<String>> = problems
+            .iter()
+            .map(|problem| vec![problem.subject.clone(), problem.message.clone()])
+            .collect();
+        print_table(&["SUBJECT", "PROBLEM"], &rows);
+    }
+
+    if !problems.is_empty() {
+        std::process::exit(EXIT_FAILURE);
+    }
+
+    Ok(())
+}
+
+#[derive(Serialize)]
+struct CredentialCheck {
+    profile: String,
+    #[serde(flatten)]
+    result: CheckResult,
+}
+
+/// `doctor creds`: checks every profile with STS and reports the results in
+/// config order.
+fn creds(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let format = output_format(matches);
+    let filters = attribute_filters(matches);
+    let config = context.load_config()?;
+    let annotations = context.annotations();
+    let profile_names: Vec<String> = config
+        .profiles
+        .iter()
+        .filter(|profile| matches_all(&filters, profile, &annotations))
+        .map(|profile| profile.name.clone())
+        .collect();
+
+    let checks: Vec<CredentialCheck> = check_in_order(
+        &context.config_paths,
+        &profile_names,
+        check_options(matches),
+    )?
+    .into_iter()
+    .map(|(profile, result)| CredentialCheck { profile, result })
+    .collect();
+    let failed = checks.iter().any(|check| !check.result.is_ok());
+
+    if format != OutputFormat::Table {
+        print_structured(&checks, format)?;
+    } else {
+        let rows: Vec<Vec<String>> = checks
+            .iter()
+            .map(|check| {
+                vec![
+                    check.profile.clone(),
+                    check.result.symbol().to_string(),
+                    check.result.detail(),
+                ]
+            })
+            .collect();
+        print_table(&["PROFILE", "STATUS", "DETAIL"], &rows);
+    }
+
+    if failed {
+        std::process::exit(EXIT_FAILURE);
+    }
+
+    Ok(())
+}
+
+/// Reports duplicated sections in a file, or a problem when it exists but
+/// can't be read.
+fn check_file(path: &Path) -> Result<Vec<Problem>, Problem> {
+    if !path.exists() {
+        return Ok(Vec::new());
+    }
+
+    let content = fs::read_to_string(path)
+        .map_err(|e| Problem::new(path.display().to_string(), format!("cannot be read: {e}")))?;
+
+    Ok(duplicate_sections(&IniDocument::parse(&content))
+        .into_iter()
+        .map(|header| {
+            Problem::new(
+                path.display().to_string(),
+                format!("section [{header}] appears more than once"),
+            )
+        })
+        .collect())
+}
+
+fn duplicate_sections(document: &IniDocument) -> Vec<String> {
+    let mut seen = HashSet::new();
+    let mut duplicates = Vec::new();
+
+    for header in document.sections() {
+        if !seen.insert(header) && !duplicates.iter().any(|d| d == header) {
+            duplicates.push(header.to_string());
+        }
+    }
+
+    duplicates
+}
+
+fn lint(config: &AwsConfig) -> Vec<Problem> {
+    let mut problems = Vec::new();
+
+    for profile in &config.profiles {
+        let subject = format!("profile {}", profile.name);
+        let is_sso = profile.get_sso_session().is_some()
+            || profile.get_account_id().is_some()
+            || profile.get_role_name().is_some();
+
+        if let Some(session) = profile.get_sso_session()
+            && config.sso_session_for(profile).is_none()
+        {
+            problems.push(Problem::new(
+                &subject,
+                format!("sso_session '{session}' has no [sso-session {session}] section"),
+            ));
+        } else if is_sso && config.sso_start_url_for(profile).is_none() {
+            problems.push(Problem::new(&subject, "SSO profile has no sso_start_url"));
+        }
+
+        if let Some(services) = profile.attributes.get("services")
+            && config.services_for(profile).is_none()
+        {
+            problems.push(Problem::new(
+                &subject,
+                format!("services '{services}' has no [services {services}] section"),
+            ));
+        }
+
+        if let Some(problem) = config
+            .role_chain(&profile.name)
+            .and_then(|chain| chain.problem_description())
+        {
+            problems.push(Problem::new(&subject, problem));
+        }
+
+        if let Some(problem) = profile.credential_problem() {
+            problems.push(Problem::new(&subject, problem));
+        }
+
+        for key in ["region", "sso_region"] {
+            if let Some(region) = profile.attributes.get(key)
+                && !is_known_region(region)
+            {
+                problems.push(Problem::new(
+                    &subject,
+                    format!("{key} '{region}' is not a known region"),
+                ));
+            }
+        }
+    }
+
+    for session in &config.sso_sessions {
+        let subject = format!("sso-session {}", session.name);
+
+        if session.get_start_url().is_none() {
+            problems.push(Problem::new(&subject, "missing sso_start_url"));
+        }
+
+        match session.get_region() {
+            None => problems.push(Problem::new(&subject, "missing sso_region")),
+            Some(region) if !is_known_region(region) => problems.push(Problem::new(
+                &subject,
+                format!("sso_region '{region}' is not a known region"),
+            )),
+            Some(_) => {}
+        }
+    }
+
+    problems
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::SsoSession;
+    use crate::test_support::profile;
+    use std::collections::HashMap;
+
+    #[test]
+    fn test_lint_reports_each_kind_of_problem() {
+        let config = AwsConfig {
+            profiles: vec![
+                profile("ok", &[("sso_session", "corp"), ("region", "eu-west-1")]),
+                profile("legacy", &[("sso_account_id", "123456789012")]),
+                profile("missing-session", &[("sso_session", "nope")]),
+                profile(
+                    "admin",
+                    &[("source_profile", "gone"), ("region", "us-east-7")],
+                ),
+                profile(
+                    "ci",
+                    &[
+                        ("role_arn", "arn:aws:iam::123456789012:role/Deploy"),
+                        ("web_identity_token_file", "/nonexistent/token"),
+                    ],
+                ),
+            ],
+            sso_sessions: vec![SsoSession {
+                name: "corp".to_string(),
+                attributes: HashMap::from([
+                    (
+                        "sso_start_url".to_string(),
+                        "https://corp.awsapps.com/start".to_string(),
+                    ),
+                    ("sso_region".to_string(), "us-east-1".to_string()),
+                ]),
+            }],
+            ..AwsConfig::default()
+        };
+
+        assert_eq!(
+            lint(&config),
+            vec![
+                Problem::new("profile legacy", "SSO profile has no sso_start_url"),
+                Problem::new(
+                    "profile missing-session",
+                    "sso_session 'nope' has no [sso-session nope] section"
+                ),
+                Problem::new("profile admin", "source_profile 'gone' does not exist"),
+                Problem::new("profile admin", "region 'us-east-7' is not a known region"),
+                Problem::new(
+                    "profile ci",
+                    "web_identity_token_file /nonexistent/token doesn't exist"
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_duplicate_sections() {
+        let document =
+            IniDocument::parse("[profile a]\n[profile b]\n[profile a]\n[profile a]\n[default]\n");
+        assert_eq!(duplicate_sections(&document), vec!["profile a"]);
+    }
+}
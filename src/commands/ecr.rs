@@ -0,0 +1,59 @@
+use super::{Context, profile_or_active};
+use crate::accounts::redact;
+use crate::aws::{block_on, get_ecr_authorization, load_sdk_config};
+use anyhow::{Context as _, Result};
+use clap::ArgMatches;
+use std::io::Write;
+use std::process::{Command, Stdio};
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let profile_name = profile_or_active(context, matches.get_one::<String>("profile"))?;
+    let registry_id = matches.get_one::<String>("registry");
+    if let Some(registry_id) = registry_id
+        && (registry_id.len() != 12 || !registry_id.bytes().all(|b| b.is_ascii_digit()))
+    {
+        return Err(anyhow::anyhow!(
+            "Registry ID should be a 12-digit account ID, got '{registry_id}'"
+        ));
+    }
+
+    let authorization = block_on(async {
+        let sdk_config = load_sdk_config(&context.config_paths, &profile_name).await;
+        if sdk_config.region().is_none() {
+            return Err(anyhow::anyhow!(
+                "'{profile_name}' has no region, and ECR registries are regional"
+            ));
+        }
+        get_ecr_authorization(&sdk_config, registry_id.map(String::as_str)).await
+    })??;
+
+    if matches.get_flag("print") {
+        println!("{}", authorization.password);
+        return Ok(());
+    }
+
+    let endpoint = match context.redact {
+        true => redact(&authorization.endpoint),
+        false => authorization.endpoint.clone(),
+    };
+    eprintln!("Logging in to {endpoint} as {profile_name}");
+
+    // The password goes through stdin so it never shows up in `ps`.
+    let mut docker = Command::new("docker")
+        .args(["login", "--username", "AWS", "--password-stdin"])
+        .arg(&authorization.endpoint)
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("Failed to run docker")?;
+    docker
+        .stdin
+        .take()
+        .expect("stdin is piped")
+        .write_all(authorization.password.as_bytes())?;
+
+    let status = docker.wait()?;
+    if !status.success() {
+        std::process::exit(status.code().unwrap_or(1));
+    }
+    Ok(())
+}
@@ -0,0 +1,164 @@
+use super::Context;
+use crate::diff::Preview;
+use crate::dry_run::ensure_writable;
+use crate::error::Error;
+use crate::files::{Access, TempDir, write_atomically};
+use crate::store::ProfileStore;
+use crate::ui::answer;
+use anyhow::{Context as _, Result};
+use clap::ArgMatches;
+use inquire::{Confirm, Text};
+use std::fs;
+use std::path::Path;
+use std::process::Command;
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let profile_name = matches
+        .get_one::<String>("profile")
+        .expect("profile is required");
+
+    let mut store = context.profile_store()?;
+    if store.get(profile_name).is_none() {
+        return Err(Error::profile_not_found(profile_name).into());
+    }
+    ensure_writable(format_args!("edit '{profile_name}'"))?;
+
+    let yes = matches.get_flag("yes");
+    if matches.get_flag("prompt") {
+        edit_with_prompts(&mut store, profile_name, yes)
+    } else {
+        edit_in_editor(&mut store, profile_name, yes)
+    }
+}
+
+/// Opens just the profile's section in `$VISUAL`/`$EDITOR` and splices the
+/// result back into the file it came from once the diff is confirmed.
+fn edit_in_editor(store: &mut ProfileStore, profile_name: &str, yes: bool) -> Result<()> {
+    // Credentials-only profiles have no config section to edit.
+    let Some((path, original)) = store.section_texts(profile_name)?.into_iter().next() else {
+        return Err(anyhow::anyhow!(
+            "Profile '{profile_name}' has no section to edit"
+        ));
+    };
+
+    let edited = edit_text(profile_name, &original)?;
+    if edited == original {
+        println!("No changes made to '{profile_name}'");
+        return Ok(());
+    }
+
+    let mut preview = Preview::new();
+    preview.file(path, &original, &edited);
+    preview.confirm(
+        Confirm::new(&format!("Save the changes to '{profile_name}'?")).with_default(true),
+        yes,
+    )?;
+    store.replace_profile_text(profile_name, &edited)?;
+    println!("Profile '{profile_name}' updated");
+    Ok(())
+}
+
+/// Writes `text` to a temporary file, opens it in the user's editor, and
+/// returns what was saved. The section may hold secret keys, so the file is
+/// only readable by its owner, in a directory of its own that is removed
+/// however the editing ends.
+fn edit_text(profile_name: &str, text: &str) -> Result<String> {
+    let dir = TempDir::new("edit")?;
+    let file_name: String = profile_name
+        .chars()
+        .map(|c| match c.is_alphanumeric() || c == '-' || c == '_' {
+            true => c,
+            false => '_',
+        })
+        .collect();
+    let path = dir.join(format!("{file_name}.ini"));
+    write_atomically(&path, text, Access::Private)?;
+
+    run_editor(&path)?;
+    fs::read_to_string(&path).with_context(|| format!("Failed to read {}", path.display()))
+}
+
+fn run_editor(path: &Path) -> Result<()> {
+    let editor = ["VISUAL", "EDITOR"]
+        .into_iter()
+        .find_map(|var| {
+            std::env::var(var)
+                .ok()
+                .filter(|value| !value.trim().is_empty())
+        })
+        .unwrap_or_else(|| "vi".to_string());
+
+    // Editors are often configured with arguments, e.g. `code --wait`.
+    let mut words = editor.split_whitespace();
+    let program = words.next().expect("editor is not empty");
+
+    let status = Command::new(program)
+        .args(words)
+        .arg(path)
+        .status()
+        .with_context(|| format!("Failed to start editor '{editor}'"))?;
+
+    if !status.success() {
+        return Err(anyhow::anyhow!(
+            "Editor '{editor}' exited with {status}; nothing was written"
+        ));
+    }
+
+    Ok(())
+}
+
+/// Prompts for the commonly changed settings, pre-filled with their current
+/// values. Clearing a value removes the key.
+fn edit_with_prompts(store: &mut ProfileStore, profile_name: &str, yes: bool) -> Result<()> {
+    let profile = store.get(profile_name).expect("profile exists");
+    let mut keys = vec![("region", "Region:"), ("output", "Output format:")];
+    if profile.attributes.contains_key("role_arn") {
+        keys.push(("role_arn", "Role ARN:"));
+    } else if profile.get_role_name().is_some() {
+        keys.push(("sso_role_name", "Permission set (role) name:"));
+    }
+
+    let mut changes = Vec::new();
+    for (key, message) in keys {
+        let current = profile.attributes.get(key).cloned().unwrap_or_default();
+        let value = answer(
+            Text::new(message)
+                .with_initial_value(&current)
+                .with_help_message("Clear the value to remove the setting")
+                .prompt(),
+        )?;
+        let value = value.trim();
+
+        if value != current {
+            changes.push((
+                key,
+                Some(value.to_string()).filter(|value| !value.is_empty()),
+            ));
+        }
+    }
+
+    let changes: Vec<(&str, Option<&str>)> = changes
+        .iter()
+        .map(|(key, value)| (*key, value.as_deref()))
+        .collect();
+    let (original, updated) = store.updated_config(profile_name, &changes)?;
+    if updated == original {
+        println!("No changes made to '{profile_name}'");
+        return Ok(());
+    }
+
+    let mut preview = Preview::new();
+    preview.file(
+        &store.paths().config,
+        &original.to_string(),
+        &updated.to_string(),
+    );
+    preview.confirm(
+        Confirm::new(&format!("Save the changes to '{profile_name}'?")).with_default(true),
+        yes,
+    )?;
+    store.update_profile(profile_name, &changes)?;
+
+    println!("Profile '{profile_name}' updated");
+    Ok(())
+}
@@ -0,0 +1,118 @@
+use super::{Context, profile_or_active};
+use crate::dry_run::{ensure_writable, would_write};
+use crate::error::Error;
+use crate::files::{Access, write_atomically};
+use crate::session::SessionDuration;
+use crate::shell::export_commands;
+use crate::state::region_override;
+use anyhow::Result;
+use clap::ArgMatches;
+use std::path::PathBuf;
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let profile_name = profile_or_active(context, matches.get_one::<String>("profile"))?;
+
+    let config = context.load_config()?;
+    if config.find_profile(&profile_name).is_none() {
+        return Err(Error::profile_not_found(&profile_name).into());
+    }
+
+    let duration = context.session_duration(matches, &profile_name);
+    let vars = credential_vars(context, &profile_name, duration)?;
+    let text = match matches.get_one::<String>("format").map(String::as_str) {
+        Some("dotenv") => env_file(&profile_name, &vars, dotenv_value),
+        Some("docker") => env_file(&profile_name, &vars, str::to_string),
+        _ => export_commands(&vars),
+    };
+
+    let Some(path) = matches.get_one::<PathBuf>("file") else {
+        println!("{text}");
+        return Ok(());
+    };
+    let contents = format!("{text}\n");
+    ensure_writable(format_args!("write {}", path.display()))?;
+    if would_write(path, &contents) {
+        return Ok(());
+    }
+    write_atomically(path, contents, Access::Private)?;
+    eprintln!(
+        "Wrote credentials for '{profile_name}' to {}",
+        path.display()
+    );
+    Ok(())
+}
+
+/// `KEY=value` lines, after a comment saying when temporary credentials
+/// expire. `quote` writes each value: `docker run --env-file` takes the rest
+/// of the line as it is, quotes included.
+fn env_file(profile_name: &str, vars: &[(&str, String)], quote: fn(&str) -> String) -> String {
+    let mut lines = vec![match vars
+        .iter()
+        .find(|(key, _)| *key == "AWS_CREDENTIAL_EXPIRATION")
+    {
+        Some((_, expiry)) => format!("# Credentials for '{profile_name}'; expire {expiry}"),
+        None => format!("# Credentials for '{profile_name}'"),
+    }];
+    lines.extend(
+        vars.iter()
+            .map(|(key, value)| format!("{key}={}", quote(value))),
+    );
+    lines.join("\n")
+}
+
+/// Values a `.env` loader would split or expand are double-quoted.
+fn dotenv_value(value: &str) -> String {
+    if value
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "+/=-_.:@".contains(c))
+    {
+        return value.to_string();
+    }
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('$', "\\$");
+    format!("\"{escaped}\"")
+}
+
+/// The profile's resolved credentials and region as environment variables.
+pub(super) fn credential_vars(
+    context: &Context,
+    profile_name: &str,
+    duration: Option<SessionDuration>,
+) -> Result<Vec<(&'static str, String)>> {
+    let mut vars = context.credential_env(profile_name, duration)?;
+    // `activate --region` outranks the profile's own region.
+    if let Some(region) = region_override(profile_name)? {
+        vars.retain(|(key, _)| !matches!(*key, "AWS_REGION" | "AWS_DEFAULT_REGION"));
+        vars.push(("AWS_REGION", region.clone()));
+        vars.push(("AWS_DEFAULT_REGION", region));
+    }
+    Ok(vars)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_file() {
+        let vars = [
+            ("AWS_ACCESS_KEY_ID", "ASIA".to_string()),
+            ("AWS_SESSION_TOKEN", "a+b/c=".to_string()),
+            (
+                "AWS_CREDENTIAL_EXPIRATION",
+                "2026-10-14T12:00:00Z".to_string(),
+            ),
+            ("AWS_REGION", "a \"b\" $c".to_string()),
+        ];
+        assert_eq!(
+            env_file("dev", &vars, dotenv_value),
+            "# Credentials for 'dev'; expire 2026-10-14T12:00:00Z\nAWS_ACCESS_KEY_ID=ASIA\nAWS_SESSION_TOKEN=a+b/c=\nAWS_CREDENTIAL_EXPIRATION=2026-10-14T12:00:00Z\nAWS_REGION=\"a \\\"b\\\" \\$c\""
+        );
+        assert_eq!(
+            env_file("dev", &vars[..1], str::to_string),
+            "# Credentials for 'dev'\nAWS_ACCESS_KEY_ID=ASIA"
+        );
+    }
+}
@@ -0,0 +1,305 @@
+use super::{Context, profile_or_active};
+use crate::audit::AuditEvent;
+use crate::backend::Backend;
+use crate::config::AwsConfig;
+use crate::error::{EXIT_FAILURE, Error};
+use crate::filter::Pattern;
+use crate::mfa::requires_mfa;
+use crate::state::region_override;
+use crate::ui::{self, DEFAULT_PAGE_SIZE, PageSize};
+use anyhow::{Context as _, Result};
+use clap::ArgMatches;
+use std::io::{BufRead, BufReader, Read};
+use std::process::{Command, ExitStatus, Stdio};
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let command_line: Vec<&String> = matches
+        .get_many::<String>("command")
+        .expect("command is required")
+        .collect();
+    let config = context.load_config()?;
+
+    let profile_arg = matches.get_one::<String>("profile");
+    let regex = matches.get_flag("regex");
+    let is_pattern = profile_arg.is_some_and(|name| regex || Pattern::has_wildcards(name));
+    if matches.get_flag("multi") || is_pattern {
+        let profile_names = match profile_arg {
+            Some(name) if is_pattern => matching_profiles(context, &config, name, regex)?,
+            Some(name) => vec![context.selector_state()?.resolve_alias(name).to_string()],
+            None => pick_profiles(context, &config)?,
+        };
+        let parallel = matches.get_one::<usize>("parallel").copied().unwrap_or(1);
+        return run_each(
+            context,
+            matches,
+            &config,
+            &profile_names,
+            &command_line,
+            parallel,
+        );
+    }
+
+    let profile_name = profile_or_active(context, profile_arg)?;
+    let mut command = profile_command(context, matches, &config, &profile_name, &command_line)?;
+    let status = command
+        .status()
+        .with_context(|| format!("Failed to run '{}'", command_line[0]))?;
+
+    // Mirror the child's exit status so callers can rely on it.
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+/// The command to run under `profile_name`, with its environment set up.
+fn profile_command(
+    context: &Context,
+    matches: &ArgMatches,
+    config: &AwsConfig,
+    profile_name: &str,
+    command_line: &[&String],
+) -> Result<Command> {
+    let Some(profile) = config.find_profile(profile_name) else {
+        return Err(Error::profile_not_found(profile_name).into());
+    };
+    context.audit(AuditEvent::Exec, profile_name);
+
+    // The backend sets up the credentials itself, MFA included.
+    if let Some(backend) = matches.get_one::<Backend>("backend") {
+        let mut command = backend.exec_command(profile_name, command_line);
+        if let Some(region) = region_override(profile_name)? {
+            command
+                .env("AWS_REGION", &region)
+                .env("AWS_DEFAULT_REGION", &region);
+        }
+        return Ok(command);
+    }
+
+    // Most tools can't prompt for an MFA code themselves, keys kept in
+    // 1Password are nowhere the SDK looks, a container can't read the host's
+    // config, and a session length needs an STS call of its own, so these
+    // get the credentials resolved here.
+    let docker = matches.get_flag("docker");
+    let duration = context.session_duration(matches, profile_name);
+    let needs_keys = duration.is_some()
+        || requires_mfa(profile)
+        || context
+            .settings
+            .credential_sources
+            .contains_key(profile_name);
+    let mut vars = if matches.get_flag("with-credentials") || docker || needs_keys {
+        context.credential_env(profile_name, duration)?
+    } else {
+        Vec::new()
+    };
+    // `activate --region` outranks the profile's own region.
+    if let Some(region) = region_override(profile_name)? {
+        vars.retain(|(key, _)| !matches!(*key, "AWS_REGION" | "AWS_DEFAULT_REGION"));
+        vars.push(("AWS_REGION", region.clone()));
+        vars.push(("AWS_DEFAULT_REGION", region));
+    }
+
+    let args: Vec<&str> = command_line[1..].iter().map(|arg| arg.as_str()).collect();
+    let args = if docker {
+        let names: Vec<&str> = vars.iter().map(|(key, _)| *key).collect();
+        docker_args(&args, &names)?
+    } else {
+        args.iter().map(|arg| arg.to_string()).collect()
+    };
+    let mut command = Command::new(command_line[0]);
+    command
+        .args(args)
+        .env("AWS_PROFILE", profile_name)
+        .envs(vars);
+    Ok(command)
+}
+
+/// `args` of a docker command with `-e NAME` for each of `names` after its
+/// `run` or `exec`. Without a value, docker copies each from its own
+/// environment, so the secrets stay out of the process list.
+fn docker_args(args: &[&str], names: &[&str]) -> Result<Vec<String>> {
+    let Some(index) = args.iter().position(|arg| matches!(*arg, "run" | "exec")) else {
+        return Err(anyhow::anyhow!(
+            "--docker needs a docker run, docker compose run, or docker exec command"
+        ));
+    };
+    let mut injected: Vec<String> = args[..=index].iter().map(|arg| arg.to_string()).collect();
+    for name in names {
+        injected.extend(["-e".to_string(), name.to_string()]);
+    }
+    injected.extend(args[index + 1..].iter().map(|arg| arg.to_string()));
+    Ok(injected)
+}
+
+/// The profiles whose names match `pattern`, leaving out hidden ones.
+pub(super) fn matching_profiles(
+    context: &Context,
+    config: &AwsConfig,
+    pattern: &str,
+    regex: bool,
+) -> Result<Vec<String>> {
+    let state = context.selector_state()?;
+    let compiled = Pattern::new(pattern, regex)?;
+    let profile_names: Vec<String> = config
+        .profiles
+        .iter()
+        .map(|profile| profile.name.clone())
+        .filter(|name| compiled.matches(name) && !state.is_hidden(name))
+        .collect();
+    if profile_names.is_empty() {
+        return Err(anyhow::anyhow!("No profiles match '{pattern}'"));
+    }
+    Ok(profile_names)
+}
+
+pub(super) fn pick_profiles(context: &Context, config: &AwsConfig) -> Result<Vec<String>> {
+    let state = context.selector_state()?;
+    let mut config = config.clone();
+    config
+        .profiles
+        .retain(|profile| !state.is_hidden(&profile.name));
+    let page_size = context
+        .settings
+        .page_size
+        .map_or(DEFAULT_PAGE_SIZE, PageSize::rows);
+    let profile_names = ui::select_profiles(&config, page_size)?;
+    if profile_names.is_empty() {
+        return Err(Error::Cancelled("No profile selected").into());
+    }
+    Ok(profile_names)
+}
+
+/// Runs the command once per profile, `parallel` at a time, with each line
+/// of output prefixed by the profile it came from. Exits 1 after listing the
+/// failures when any run failed.
+fn run_each(
+    context: &Context,
+    matches: &ArgMatches,
+    config: &AwsConfig,
+    profile_names: &[String],
+    command_line: &[&String],
+    parallel: usize,
+) -> Result<()> {
+    // Set up every environment first, so MFA prompts and credential errors
+    // come before any output.
+    let commands = profile_names
+        .iter()
+        .map(|name| profile_command(context, matches, config, name, command_line))
+        .collect::<Result<Vec<_>>>()?;
+    let width = profile_names.iter().map(String::len).max().unwrap_or(0);
+
+    let commands: Vec<Mutex<Option<Command>>> =
+        commands.into_iter().map(|c| Mutex::new(Some(c))).collect();
+    let outcomes: Vec<Mutex<Option<Result<ExitStatus>>>> =
+        profile_names.iter().map(|_| Mutex::new(None)).collect();
+    let next = AtomicUsize::new(0);
+    std::thread::scope(|scope| {
+        for _ in 0..parallel.min(profile_names.len()) {
+            scope.spawn(|| {
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(command) = commands.get(index) else {
+                        break;
+                    };
+                    let command = command.lock().unwrap().take().expect("each runs once");
+                    let prefix = format!("{:<width$} | ", profile_names[index]);
+                    *outcomes[index].lock().unwrap() = Some(run_prefixed(command, &prefix));
+                }
+            });
+        }
+    });
+
+    let failures: Vec<String> = profile_names
+        .iter()
+        .zip(outcomes)
+        .filter_map(|(name, outcome)| {
+            match outcome.into_inner().unwrap().expect("every command ran") {
+                Ok(status) if status.success() => None,
+                Ok(status) => Some(match status.code() {
+                    Some(code) => format!("{name} (exit status {code})"),
+                    None => format!("{name} (killed by a signal)"),
+                }),
+                Err(e) => Some(format!("{name} ({e})")),
+            }
+        })
+        .collect();
+    if failures.is_empty() {
+        return Ok(());
+    }
+
+    eprintln!(
+        "Failed in {} of {} profiles:",
+        failures.len(),
+        profile_names.len()
+    );
+    for failure in &failures {
+        eprintln!("  {failure}");
+    }
+    std::process::exit(EXIT_FAILURE);
+}
+
+/// Runs `command`, copying its stdout and stderr line by line to ours with
+/// `prefix` in front.
+fn run_prefixed(mut command: Command, prefix: &str) -> Result<ExitStatus> {
+    let program = command.get_program().to_string_lossy().to_string();
+    let mut child = command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run '{program}'"))?;
+    let stdout = child.stdout.take().expect("stdout is piped");
+    let stderr = child.stderr.take().expect("stderr is piped");
+
+    std::thread::scope(|scope| {
+        scope.spawn(|| copy_lines(stdout, |line| println!("{prefix}{line}")));
+        copy_lines(stderr, |line| eprintln!("{prefix}{line}"));
+    });
+    Ok(child.wait()?)
+}
+
+/// Reads to the end even past output that isn't UTF-8, so the child never
+/// blocks on a full pipe.
+fn copy_lines(output: impl Read, mut print: impl FnMut(&str)) {
+    let mut reader = BufReader::new(output);
+    let mut line = Vec::new();
+    while matches!(reader.read_until(b'\n', &mut line), Ok(read) if read > 0) {
+        let text = String::from_utf8_lossy(&line);
+        print(text.trim_end_matches(['\n', '\r']));
+        line.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_copy_lines_reads_past_invalid_utf8() {
+        let mut lines = Vec::new();
+        copy_lines(&b"one\r\ntw\xffo\nthree"[..], |line| {
+            lines.push(line.to_string())
+        });
+        assert_eq!(lines, ["one", "tw\u{fffd}o", "three"]);
+    }
+
+    #[test]
+    fn test_docker_args() {
+        let names = ["AWS_ACCESS_KEY_ID", "AWS_REGION"];
+        assert_eq!(
+            docker_args(&["compose", "run", "--rm", "app", "run"], &names).unwrap(),
+            [
+                "compose",
+                "run",
+                "-e",
+                "AWS_ACCESS_KEY_ID",
+                "-e",
+                "AWS_REGION",
+                "--rm",
+                "app",
+                "run"
+            ]
+        );
+        assert!(docker_args(&["ps"], &names).is_err());
+    }
+}
@@ -0,0 +1,42 @@
+use super::Context;
+use crate::error::Error;
+use crate::state::SelectorState;
+use anyhow::Result;
+use clap::ArgMatches;
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("add", sub_matches)) => {
+            let profile_name = sub_matches
+                .get_one::<String>("profile")
+                .expect("profile is required");
+            if context.load_config()?.find_profile(profile_name).is_none() {
+                return Err(Error::profile_not_found(profile_name).into());
+            }
+
+            if SelectorState::update(|state| state.add_favorite(profile_name))? {
+                println!("Profile '{profile_name}' added to favorites");
+            } else {
+                println!("Profile '{profile_name}' is already a favorite");
+            }
+        }
+        Some(("remove", sub_matches)) => {
+            let profile_name = sub_matches
+                .get_one::<String>("profile")
+                .expect("profile is required");
+            if SelectorState::update(|state| state.remove_favorite(profile_name))? {
+                println!("Profile '{profile_name}' removed from favorites");
+            } else {
+                println!("Profile '{profile_name}' is not a favorite");
+            }
+        }
+        Some(("list", _)) => {
+            for profile_name in &SelectorState::load()?.favorites {
+                println!("{profile_name}");
+            }
+        }
+        _ => unreachable!("clap requires a subcommand"),
+    }
+
+    Ok(())
+}
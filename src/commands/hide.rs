@@ -0,0 +1,41 @@
+use super::Context;
+use crate::state::SelectorState;
+use anyhow::Result;
+use clap::ArgMatches;
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("add", sub_matches)) => {
+            let pattern = sub_matches
+                .get_one::<String>("pattern")
+                .expect("pattern is required");
+            if SelectorState::update(|state| state.add_hidden(pattern))? {
+                println!("Hiding profiles matching '{pattern}'");
+            } else {
+                println!("'{pattern}' is already hidden");
+            }
+        }
+        Some(("remove", sub_matches)) => {
+            let pattern = sub_matches
+                .get_one::<String>("pattern")
+                .expect("pattern is required");
+            if SelectorState::update(|state| state.remove_hidden(pattern))? {
+                println!("No longer hiding profiles matching '{pattern}'");
+            } else {
+                println!("'{pattern}' is not in the hide list");
+            }
+        }
+        Some(("list", _)) => {
+            let state = context.selector_state()?;
+            for pattern in &state.hidden {
+                println!("{pattern}");
+            }
+            for pattern in &state.configured_hidden {
+                println!("{pattern} (settings file)");
+            }
+        }
+        _ => unreachable!("clap requires a subcommand"),
+    }
+
+    Ok(())
+}
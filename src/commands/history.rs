@@ -0,0 +1,92 @@
+//! `history`: the recorded activations, newest first, and `history clear`.
+
+use super::{Context, output_format};
+use crate::accounts::{describe_account, redact, redacted};
+use crate::output::{OutputFormat, print_structured, print_table};
+use crate::state::SelectorState;
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use clap::ArgMatches;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Entry {
+    profile: String,
+    at: DateTime<Utc>,
+    /// The profile's account as the config has it now.
+    account_id: Option<String>,
+}
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    if let Some(("clear", _)) = matches.subcommand() {
+        let cleared = SelectorState::update(|state| std::mem::take(&mut state.history).len())?;
+        println!("Cleared {cleared} activations");
+        return Ok(());
+    }
+
+    let limit = *matches
+        .get_one::<usize>("limit")
+        .expect("limit has a default");
+    // A history of profiles that were removed since is still worth showing.
+    let config = context.read_config().unwrap_or_default();
+    let entries: Vec<Entry> = SelectorState::load()?
+        .history
+        .into_iter()
+        .rev()
+        .take(limit)
+        .map(|activation| Entry {
+            account_id: config
+                .find_profile(&activation.profile)
+                .and_then(|profile| profile.get_account_id())
+                .map(str::to_string),
+            profile: activation.profile,
+            at: activation.at,
+        })
+        .collect();
+
+    let format = output_format(matches);
+    if format != OutputFormat::Table {
+        return match context.redact {
+            true => print_structured(&redacted(&entries)?, format),
+            false => print_structured(&entries, format),
+        };
+    }
+
+    if entries.is_empty() {
+        println!("No activations recorded");
+        return Ok(());
+    }
+    let rows: Vec<Vec<String>> = entries
+        .iter()
+        .map(|entry| {
+            let account = entry
+                .account_id
+                .as_deref()
+                .map(|account_id| {
+                    describe_account(
+                        account_id,
+                        context
+                            .settings
+                            .accounts
+                            .get(account_id)
+                            .map(String::as_str),
+                    )
+                })
+                .unwrap_or_default();
+            vec![
+                entry
+                    .at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M:%S")
+                    .to_string(),
+                entry.profile.clone(),
+                match context.redact {
+                    true => redact(&account),
+                    false => account,
+                },
+            ]
+        })
+        .collect();
+    print_table(&["WHEN", "PROFILE", "ACCOUNT"], &rows);
+    Ok(())
+}
@@ -0,0 +1,438 @@
+use super::{Context, profile_or_active};
+use crate::aws::block_on;
+use crate::config::{AwsConfig, Profile, SsoSession, config_section_header};
+use crate::diff::Preview;
+use crate::editor::IniDocument;
+use crate::error::Error;
+use crate::output::print_table;
+use crate::profile_templates::clean_profile_name;
+use crate::sso::{AccountRole, SsoTarget, list_account_roles, read_cached_token};
+use crate::ui::answer;
+use anyhow::{Context as _, Result};
+use clap::ArgMatches;
+use inquire::{Confirm, Select};
+use serde::Deserialize;
+use std::process::{Command, Stdio};
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("sso", sub_matches)) => import_sso(context, sub_matches),
+        Some(("org", sub_matches)) => import_org(context, sub_matches),
+        _ => unreachable!("clap requires an import source"),
+    }
+}
+
+/// A profile to be generated for one account/role pair.
+#[derive(Debug, PartialEq, Eq)]
+struct ImportedProfile {
+    name: String,
+    account_id: String,
+    role_name: String,
+}
+
+fn import_sso(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let template = matches
+        .get_one::<String>("template")
+        .expect("template has a default");
+    let region = matches.get_one::<String>("region");
+
+    let config = context.load_config()?;
+    let session = choose_session(&config, matches.get_one::<String>("session"))?;
+    let target = SsoTarget::for_session(session).ok_or_else(|| {
+        anyhow::anyhow!(
+            "sso-session '{}' needs both sso_start_url and sso_region",
+            session.name
+        )
+    })?;
+
+    let token = read_cached_token(&target)?
+        .filter(|token| !token.is_expired())
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "No valid SSO token for {}; run `aws-profile-selector login` with one of its profiles first",
+                target.label()
+            )
+        })?;
+
+    let account_roles = block_on(list_account_roles(&target, &token.access_token))??;
+    let profiles = plan_profiles(
+        &config,
+        &account_roles,
+        template,
+        ("session", &session.name),
+        |profile, account_role| {
+            profile.get_sso_session() == Some(session.name.as_str())
+                && profile.get_account_id() == Some(account_role.account_id.as_str())
+                && profile.get_role_name() == Some(account_role.role_name.as_str())
+        },
+    );
+    if profiles.is_empty() {
+        println!(
+            "All {} account/role combinations already have profiles",
+            account_roles.len()
+        );
+        return Ok(());
+    }
+
+    write_profiles(context, matches, &profiles, |profile| {
+        let mut entries = vec![
+            ("sso_session", session.name.clone()),
+            ("sso_account_id", profile.account_id.clone()),
+            ("sso_role_name", profile.role_name.clone()),
+        ];
+        if let Some(region) = region {
+            entries.push(("region", region.clone()));
+        }
+        entries
+    })
+}
+
+/// `import org`: an assume-role profile for every active account in the
+/// organization, through the management profile.
+fn import_org(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let role = matches.get_one::<String>("role").expect("role is required");
+    let template = matches
+        .get_one::<String>("template")
+        .expect("template has a default");
+    let region = matches.get_one::<String>("region");
+
+    let config = context.load_config()?;
+    let source = profile_or_active(context, matches.get_one::<String>("source-profile"))?;
+    let source_profile = config
+        .find_profile(&source)
+        .ok_or_else(|| Error::profile_not_found(&source))?;
+
+    let accounts = list_org_accounts(context, &source)?;
+    // The role to assume is in the member accounts; the management account
+    // is already the source profile's.
+    let account_roles: Vec<AccountRole> = accounts
+        .into_iter()
+        .filter(|account| account.status == "ACTIVE")
+        .filter(|account| source_profile.get_account_id() != Some(account.id.as_str()))
+        .map(|account| AccountRole {
+            account_id: account.id,
+            account_name: account.name,
+            role_name: role.clone(),
+        })
+        .collect();
+    let profiles = plan_profiles(
+        &config,
+        &account_roles,
+        template,
+        ("source_profile", &source),
+        |profile, account_role| {
+            profile.attributes.get("role_arn")
+                == Some(&role_arn(&account_role.account_id, &account_role.role_name))
+        },
+    );
+    if profiles.is_empty() {
+        println!(
+            "All {} accounts already have a profile for {role}",
+            account_roles.len()
+        );
+        return Ok(());
+    }
+
+    write_profiles(context, matches, &profiles, |profile| {
+        let mut entries = vec![
+            (
+                "role_arn",
+                role_arn(&profile.account_id, &profile.role_name),
+            ),
+            ("source_profile", source.clone()),
+        ];
+        if let Some(region) = region {
+            entries.push(("region", region.clone()));
+        }
+        entries
+    })
+}
+
+fn role_arn(account_id: &str, role_name: &str) -> String {
+    format!("arn:aws:iam::{account_id}:role/{role_name}")
+}
+
+/// An account as `aws organizations list-accounts` prints it.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct OrgAccount {
+    id: String,
+    name: String,
+    status: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AccountList {
+    accounts: Vec<OrgAccount>,
+}
+
+/// The organization's accounts, listed by the AWS CLI with the source
+/// profile's credentials. The CLI follows the pages itself.
+fn list_org_accounts(context: &Context, profile_name: &str) -> Result<Vec<OrgAccount>> {
+    let output = Command::new("aws")
+        .args(["organizations", "list-accounts", "--output", "json"])
+        // Organizations is served from us-east-1 whatever the profile's region.
+        .args(["--region", "us-east-1"])
+        .env_remove("AWS_PROFILE")
+        .envs(context.credential_env(profile_name, None)?)
+        .stdin(Stdio::null())
+        .stderr(Stdio::inherit())
+        .output()
+        .context("Failed to run the AWS CLI (aws)")?;
+    if !output.status.success() {
+        return Err(anyhow::anyhow!(
+            "aws organizations list-accounts failed with {}; '{profile_name}' needs to be in the management account",
+            output.status
+        ));
+    }
+    let list: AccountList = serde_json::from_slice(&output.stdout)
+        .context("aws organizations list-accounts printed something other than accounts")?;
+    Ok(list.accounts)
+}
+
+/// Shows the planned profiles and their diff, and appends them to the config
+/// once confirmed.
+fn write_profiles(
+    context: &Context,
+    matches: &ArgMatches,
+    profiles: &[ImportedProfile],
+    entries: impl Fn(&ImportedProfile) -> Vec<(&'static str, String)>,
+) -> Result<()> {
+    let rows: Vec<Vec<String>> = profiles
+        .iter()
+        .map(|profile| {
+            vec![
+                profile.name.clone(),
+                profile.account_id.clone(),
+                profile.role_name.clone(),
+            ]
+        })
+        .collect();
+    print_table(&["NAME", "ACCOUNT", "ROLE"], &rows);
+
+    let path = &context.config_paths.config;
+    let original = IniDocument::load(path)?;
+    let mut document = original.clone();
+    for profile in profiles {
+        let entries = entries(profile);
+        let entries: Vec<(&str, &str)> = entries
+            .iter()
+            .map(|(key, value)| (*key, value.as_str()))
+            .collect();
+        document.append_section(&config_section_header(&profile.name), &entries);
+    }
+    let mut preview = Preview::new();
+    preview.file(path, &original.to_string(), &document.to_string());
+    preview.confirm(
+        Confirm::new(&format!("Add {} profile(s)?", profiles.len())).with_default(true),
+        matches.get_flag("yes"),
+    )?;
+    document.save(path)?;
+
+    println!("Added {} profile(s) to {}", profiles.len(), path.display());
+    Ok(())
+}
+
+fn choose_session<'a>(config: &'a AwsConfig, name: Option<&String>) -> Result<&'a SsoSession> {
+    if let Some(name) = name {
+        return config
+            .sso_sessions
+            .iter()
+            .find(|session| &session.name == name)
+            .ok_or_else(|| {
+                Error::NotFound(format!("sso-session '{name}' not found in AWS config")).into()
+            });
+    }
+
+    match config.sso_sessions.as_slice() {
+        [] => Err(anyhow::anyhow!(
+            "No [sso-session] blocks found; create one with `add` or `migrate-sso`"
+        )),
+        [session] => Ok(session),
+        sessions => {
+            let names: Vec<&str> = sessions
+                .iter()
+                .map(|session| session.name.as_str())
+                .collect();
+            let choice = answer(Select::new("SSO session:", names).prompt())?;
+            Ok(sessions
+                .iter()
+                .find(|session| session.name == choice)
+                .expect("selected from the list"))
+        }
+    }
+}
+
+/// Works out which account/role pairs still need a profile and what to call
+/// them. Pairs a profile already `covers` are skipped, so running an import
+/// again only adds what's new, and names that would clash get the account id
+/// appended. `source` is the template's extra placeholder, such as
+/// `{session}`, and its value.
+fn plan_profiles(
+    config: &AwsConfig,
+    account_roles: &[AccountRole],
+    template: &str,
+    source: (&str, &str),
+    covers: impl Fn(&Profile, &AccountRole) -> bool,
+) -> Vec<ImportedProfile> {
+    let mut planned: Vec<ImportedProfile> = Vec::new();
+
+    for account_role in account_roles {
+        if config
+            .profiles
+            .iter()
+            .any(|profile| covers(profile, account_role))
+        {
+            continue;
+        }
+
+        let taken = |name: &str| {
+            config.find_profile(name).is_some() || planned.iter().any(|p| p.name == name)
+        };
+        let mut name = render_name(template, source, account_role);
+        if taken(&name) {
+            name = format!("{name}-{}", account_role.account_id);
+        }
+        if taken(&name) {
+            eprintln!(
+                "Skipping {} / {}: profile '{name}' already exists",
+                account_role.account_id, account_role.role_name
+            );
+            continue;
+        }
+
+        planned.push(ImportedProfile {
+            name,
+            account_id: account_role.account_id.clone(),
+            role_name: account_role.role_name.clone(),
+        });
+    }
+
+    planned
+}
+
+/// Fills in `{account_name}`, `{account_id}`, `{role}`, and the `source`
+/// placeholder, then replaces characters that don't belong in a profile
+/// name with `-`.
+fn render_name(template: &str, source: (&str, &str), account_role: &AccountRole) -> String {
+    let (placeholder, value) = source;
+    let rendered = template
+        .replace("{account_name}", &account_role.account_name)
+        .replace("{account_id}", &account_role.account_id)
+        .replace("{role}", &account_role.role_name)
+        .replace(&format!("{{{placeholder}}}"), value);
+    clean_profile_name(&rendered)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::{config, profile};
+
+    fn account_role(account_id: &str, account_name: &str, role_name: &str) -> AccountRole {
+        AccountRole {
+            account_id: account_id.to_string(),
+            account_name: account_name.to_string(),
+            role_name: role_name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_render_name() {
+        let role = account_role("111111111111", "Shared Services", "AdministratorAccess");
+
+        assert_eq!(
+            render_name("{account_name}-{role}", ("session", "corp"), &role),
+            "Shared-Services-AdministratorAccess"
+        );
+        assert_eq!(
+            render_name(
+                "{session}/{account_id} ({role})",
+                ("session", "corp"),
+                &role
+            ),
+            "corp-111111111111-AdministratorAccess"
+        );
+    }
+
+    #[test]
+    fn test_plan_profiles_skips_existing_and_resolves_clashes() {
+        let config = config(vec![profile(
+            "existing",
+            &[
+                ("sso_session", "corp"),
+                ("sso_account_id", "111111111111"),
+                ("sso_role_name", "Admin"),
+            ],
+        )]);
+        let account_roles = [
+            account_role("111111111111", "dev", "Admin"),
+            account_role("111111111111", "dev", "ReadOnly"),
+            account_role("222222222222", "dev", "ReadOnly"),
+        ];
+
+        assert_eq!(
+            plan_profiles(
+                &config,
+                &account_roles,
+                "{account_name}-{role}",
+                ("session", "corp"),
+                |profile, account_role| profile.get_account_id()
+                    == Some(account_role.account_id.as_str())
+                    && profile.get_role_name() == Some(account_role.role_name.as_str())
+            ),
+            vec![
+                ImportedProfile {
+                    name: "dev-ReadOnly".to_string(),
+                    account_id: "111111111111".to_string(),
+                    role_name: "ReadOnly".to_string(),
+                },
+                ImportedProfile {
+                    name: "dev-ReadOnly-222222222222".to_string(),
+                    account_id: "222222222222".to_string(),
+                    role_name: "ReadOnly".to_string(),
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_plan_org_profiles_skips_accounts_with_the_role() {
+        let list: AccountList = serde_json::from_str(
+            r#"{"Accounts": [
+                {"Id": "111111111111", "Name": "dev", "Status": "ACTIVE", "Email": "dev@example.com"},
+                {"Id": "222222222222", "Name": "prod", "Status": "ACTIVE"}
+            ]}"#,
+        )
+        .unwrap();
+        let account_roles: Vec<AccountRole> = list
+            .accounts
+            .into_iter()
+            .map(|account| account_role(&account.id, &account.name, "Admin"))
+            .collect();
+        let config = crate::config::parse_aws_config(
+            "[profile dev-admin]\nrole_arn = arn:aws:iam::111111111111:role/Admin\n",
+        )
+        .unwrap();
+
+        let planned = plan_profiles(
+            &config,
+            &account_roles,
+            "{source_profile}-{account_name}",
+            ("source_profile", "mgmt"),
+            |profile, account_role| {
+                profile.attributes.get("role_arn")
+                    == Some(&role_arn(&account_role.account_id, &account_role.role_name))
+            },
+        );
+        assert_eq!(
+            planned,
+            vec![ImportedProfile {
+                name: "mgmt-prod".to_string(),
+                account_id: "222222222222".to_string(),
+                role_name: "Admin".to_string(),
+            }]
+        );
+    }
+}
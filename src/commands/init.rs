@@ -0,0 +1,154 @@
+use crate::shell::{KeyBinding, Shell};
+use anyhow::Result;
+use clap::ArgMatches;
+
+const POSIX: &str = include_str!("init/posix.sh");
+const BASH_COMPLETION: &str = include_str!("init/bash.sh");
+const ZSH_COMPLETION: &str = include_str!("init/zsh.zsh");
+const FISH: &str = include_str!("init/fish.fish");
+const NUSHELL: &str = include_str!("init/nushell.nu");
+const BASH_HOOK: &str = include_str!("init/bash_hook.sh");
+const ZSH_HOOK: &str = include_str!("init/zsh_hook.zsh");
+const FISH_HOOK: &str = include_str!("init/fish_hook.fish");
+const NUSHELL_HOOK: &str = include_str!("init/nushell_hook.nu");
+const ELVISH: &str = include_str!("init/elvish.elv");
+const ELVISH_HOOK: &str = include_str!("init/elvish_hook.elv");
+const XONSH: &str = include_str!("init/xonsh.xsh");
+const XONSH_HOOK: &str = include_str!("init/xonsh_hook.xsh");
+const TCSH: &str = include_str!("init/tcsh.tcsh");
+const TCSH_HOOK: &str = include_str!("init/tcsh_hook.tcsh");
+const BASH_BIND: &str = include_str!("init/bash_bind.sh");
+const ZSH_BIND: &str = include_str!("init/zsh_bind.zsh");
+const FISH_BIND: &str = include_str!("init/fish_bind.fish");
+
+/// Placeholder in the templates that is replaced by the function name.
+const PLACEHOLDER: &str = "AWSPS_CMD";
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let shell = matches
+        .get_one::<String>("shell")
+        .expect("shell is required");
+    let name = matches.get_one::<String>("cmd").expect("cmd has a default");
+
+    if !is_valid_function_name(name) {
+        return Err(anyhow::anyhow!(
+            "'{name}' is not a valid shell function name"
+        ));
+    }
+
+    let bind = matches.get_one::<KeyBinding>("bind").copied();
+    print!(
+        "{}",
+        init_script(shell, name, matches.get_flag("hook"), bind)?
+    );
+    Ok(())
+}
+
+fn init_script(shell: &str, name: &str, hook: bool, bind: Option<KeyBinding>) -> Result<String> {
+    let (mut template, hook_template) = match shell {
+        "bash" => (
+            format!("{POSIX}{BASH_COMPLETION}").replace("SHELL_NAME", "bash"),
+            BASH_HOOK,
+        ),
+        "zsh" => (
+            format!("{POSIX}{ZSH_COMPLETION}").replace("SHELL_NAME", "zsh"),
+            ZSH_HOOK,
+        ),
+        "fish" => (FISH.to_string(), FISH_HOOK),
+        "nushell" | "nu" => (NUSHELL.to_string(), NUSHELL_HOOK),
+        "elvish" => (ELVISH.to_string(), ELVISH_HOOK),
+        "xonsh" => (XONSH.to_string(), XONSH_HOOK),
+        "tcsh" => (TCSH.to_string(), TCSH_HOOK),
+        _ => unreachable!("clap restricts the shell"),
+    };
+    if hook {
+        template.push_str(hook_template);
+    }
+    if let Some(bind) = bind {
+        let (bind_template, syntax) = match shell {
+            "bash" => (BASH_BIND, Shell::Bash),
+            "zsh" => (ZSH_BIND, Shell::Zsh),
+            "fish" => (FISH_BIND, Shell::Fish),
+            _ => return Err(anyhow::anyhow!("--bind works in bash, zsh, and fish")),
+        };
+        let sequence = bind
+            .sequence(syntax)
+            .expect("bash, zsh, and fish bind keys");
+        template.push_str(
+            &bind_template
+                .replace("BIND_SEQUENCE", &sequence)
+                .replace("BIND_KEY_NAME", &bind.to_string()),
+        );
+    }
+
+    Ok(template.replace(PLACEHOLDER, name))
+}
+
+fn is_valid_function_name(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_init_script_substitutes_function_name() {
+        for shell in ["bash", "zsh", "fish", "nushell", "elvish", "xonsh", "tcsh"] {
+            let script = init_script(shell, "awsp", true, None).unwrap();
+            assert!(!script.contains(PLACEHOLDER), "{shell}");
+            assert!(!script.contains("SHELL_NAME"), "{shell}");
+            assert!(script.contains("awsp"), "{shell}");
+            assert!(script.contains("hook-env"), "{shell}");
+            assert!(
+                !init_script(shell, "awsp", false, None)
+                    .unwrap()
+                    .contains("hook-env")
+            );
+        }
+
+        assert!(
+            init_script("bash", "awsp", false, None)
+                .unwrap()
+                .contains("complete -F _awsp_complete awsp")
+        );
+        assert!(
+            init_script("zsh", "awsp", false, None)
+                .unwrap()
+                .contains("compdef _awsp_complete awsp")
+        );
+        assert!(
+            init_script("xonsh", "awsp", false, None)
+                .unwrap()
+                .contains(r#"aliases["awsp"]"#)
+        );
+        assert!(
+            init_script("tcsh", "awsp", false, None)
+                .unwrap()
+                .contains("alias awsp '")
+        );
+        let nushell = init_script("nushell", "awsp", false, None).unwrap();
+        assert!(nushell.contains("def --env awsp ["));
+        assert!(nushell.contains("profile?: string@__awsp_profiles"));
+
+        let ctrl_o = "ctrl-o".parse().ok();
+        let zsh = init_script("zsh", "awsp", false, ctrl_o).unwrap();
+        assert!(zsh.contains("bindkey '^O' _awsp_widget"));
+        let fish = init_script("fish", "awsp", false, ctrl_o).unwrap();
+        assert!(fish.contains(r"bind \co _awsp_widget"));
+        assert!(init_script("tcsh", "awsp", false, ctrl_o).is_err());
+    }
+
+    #[test]
+    fn test_is_valid_function_name() {
+        assert!(is_valid_function_name("awsp"));
+        assert!(is_valid_function_name("aws-switch"));
+        assert!(!is_valid_function_name(""));
+        assert!(!is_valid_function_name("1awsp"));
+        assert!(!is_valid_function_name("awsp; rm"));
+    }
+}
@@ -0,0 +1,9 @@
+
+_AWSPS_CMD_complete() {
+    if [ "$COMP_CWORD" -eq 1 ]; then
+        local profiles
+        profiles="$(command aws-profile-selector list --names 2>/dev/null)"
+        COMPREPLY=($(compgen -W "$profiles" -- "${COMP_WORDS[COMP_CWORD]}"))
+    fi
+}
+complete -F _AWSPS_CMD_complete AWSPS_CMD
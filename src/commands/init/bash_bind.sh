@@ -0,0 +1,3 @@
+
+# Open the selector with BIND_KEY_NAME.
+bind -x '"BIND_SEQUENCE": AWSPS_CMD </dev/tty'
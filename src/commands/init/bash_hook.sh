@@ -0,0 +1,11 @@
+
+# Apply .aws-profile files on every prompt (hook-env prints nothing unless
+# the directory tree changed).
+_AWSPS_CMD_hook() {
+    local __awsps_output
+    __awsps_output="$(SHELL=sh command aws-profile-selector hook-env 2>/dev/null)" &&
+        eval "$__awsps_output"
+}
+if [[ ";${PROMPT_COMMAND:-};" != *";_AWSPS_CMD_hook;"* ]]; then
+    PROMPT_COMMAND="_AWSPS_CMD_hook${PROMPT_COMMAND:+;$PROMPT_COMMAND}"
+fi
@@ -0,0 +1,22 @@
+# aws-profile-selector shell integration.
+# Load it from ~/.config/fish/config.fish with:
+#   aws-profile-selector init fish | source
+
+function AWSPS_CMD --description 'Switch the AWS profile of the current shell'
+    set -l output
+    switch "$argv[1]"
+        case -h --help
+            command aws-profile-selector --help
+            return
+        case -d --deactivate deactivate
+            set output (env SHELL=fish aws-profile-selector -c deactivate); or return
+        case region regions
+            set output (env SHELL=fish aws-profile-selector -c regions $argv[2..]); or return
+        case '*'
+            set output (env SHELL=fish aws-profile-selector -c activate $argv); or return
+    end
+    # One command per line; `eval $output` would join them into one.
+    printf '%s\n' $output | source
+end
+
+complete -c AWSPS_CMD -f -n 'test (count (commandline -opc)) -eq 1' -a '(command aws-profile-selector list --names 2>/dev/null)'
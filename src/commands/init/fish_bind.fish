@@ -0,0 +1,8 @@
+
+# Open the selector with BIND_KEY_NAME and redraw the prompt afterwards.
+function _AWSPS_CMD_widget
+    AWSPS_CMD </dev/tty
+    commandline -f repaint
+end
+bind BIND_SEQUENCE _AWSPS_CMD_widget
+bind -M insert BIND_SEQUENCE _AWSPS_CMD_widget
@@ -0,0 +1,7 @@
+
+# Apply .aws-profile files whenever the directory changes.
+function _AWSPS_CMD_hook --on-variable PWD
+    set -l output (env SHELL=fish aws-profile-selector hook-env 2>/dev/null); or return
+    printf '%s\n' $output | source
+end
+_AWSPS_CMD_hook
@@ -0,0 +1,24 @@
+# aws-profile-selector shell integration.
+# Load it from your shell's rc file with:
+#   eval "$(aws-profile-selector init SHELL_NAME)"
+
+AWSPS_CMD() {
+    local __awsps_output
+    case "${1-}" in
+        -h|--help)
+            command aws-profile-selector --help
+            return
+            ;;
+        -d|--deactivate|deactivate)
+            __awsps_output="$(SHELL=sh command aws-profile-selector -c deactivate)" || return
+            ;;
+        region|regions)
+            shift
+            __awsps_output="$(SHELL=sh command aws-profile-selector -c regions "$@")" || return
+            ;;
+        *)
+            __awsps_output="$(SHELL=sh command aws-profile-selector -c activate "$@")" || return
+            ;;
+    esac
+    eval "$__awsps_output"
+}
@@ -0,0 +1,319 @@
+use super::{Context, attribute_filters, output_format};
+use crate::accounts::{describe_account, redact, redacted};
+use crate::backend::Backend;
+use crate::check::{CheckOptions, CheckResult, check_profiles};
+use crate::config::{AwsConfig, CredentialType, Profile, RoleChain, SECRET_ATTRIBUTES};
+use crate::environment::now;
+use crate::filter::{Annotations, ProfileFilter, matches_all};
+use crate::output::{ListField, OutputFormat, print_columns, print_structured};
+use crate::sso::{describe_expiry, token_expiries};
+use crate::template::DisplayFormat;
+use crate::ui::{SortOrder, sort_profiles};
+use anyhow::Result;
+use chrono::{DateTime, SecondsFormat, Utc};
+use clap::{ArgMatches, ValueEnum};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+#[derive(Serialize)]
+pub(super) struct ProfileEntry<'a> {
+    name: &'a str,
+    credential_type: Option<CredentialType>,
+    /// aws-vault or granted, when the profile's config points at one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    backend: Option<Backend>,
+    account_id: Option<&'a str>,
+    /// From the settings file's `[accounts]` table.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account_name: Option<&'a str>,
+    /// From the settings file's `[tags]` table.
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<&'a str>,
+    region: Option<&'a str>,
+    role_name: Option<&'a str>,
+    /// The `source_profile` chain, for profiles that have one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    role_chain: Option<RoleChain>,
+    sso_session: Option<&'a str>,
+    sso_start_url: Option<&'a str>,
+    sso_region: Option<&'a str>,
+    /// `None` for non-SSO profiles and SSO profiles without a cached token.
+    sso_token_expires_at: Option<DateTime<Utc>>,
+    #[serde(skip)]
+    sso_token: Option<String>,
+    /// The profile's latest activation in the history.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    last_used: Option<DateTime<Utc>>,
+    /// Set with `--check`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    check: Option<CheckResult>,
+    attributes: BTreeMap<&'a str, &'a str>,
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    subsections: BTreeMap<&'a str, BTreeMap<&'a str, &'a str>>,
+}
+
+impl<'a> ProfileEntry<'a> {
+    pub(super) fn new(
+        profile: &'a Profile,
+        config: &'a AwsConfig,
+        annotations: &'a Annotations,
+        token_expiries: &HashMap<String, Option<DateTime<Utc>>>,
+        last_used: &HashMap<String, DateTime<Utc>>,
+    ) -> Self {
+        let attributes = profile
+            .attributes
+            .iter()
+            .map(|(key, value)| {
+                let value = if SECRET_ATTRIBUTES.contains(&key.as_str()) {
+                    "********"
+                } else {
+                    value.as_str()
+                };
+                (key.as_str(), value)
+            })
+            .collect();
+
+        let subsections = profile
+            .subsections
+            .iter()
+            .map(|(name, values)| {
+                let values = values
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.as_str()))
+                    .collect();
+                (name.as_str(), values)
+            })
+            .collect();
+
+        let token_expiry = token_expiries.get(&profile.name);
+        let sso_token = token_expiry.map(|expiry| match expiry {
+            Some(expires_at) => describe_expiry(*expires_at, now()),
+            None => "not logged in".to_string(),
+        });
+
+        Self {
+            name: &profile.name,
+            credential_type: profile.credential_type(),
+            backend: Backend::detect(profile),
+            account_id: profile.get_account_id(),
+            account_name: annotations.account_name(profile),
+            tags: annotations.tags_for(&profile.name),
+            region: profile.get_region(),
+            role_name: profile.get_role_name(),
+            role_chain: config.role_chain(&profile.name),
+            sso_session: profile.get_sso_session(),
+            sso_start_url: config.sso_start_url_for(profile),
+            sso_region: config.sso_region_for(profile),
+            sso_token_expires_at: token_expiry.copied().flatten(),
+            sso_token,
+            last_used: last_used.get(&profile.name).copied(),
+            check: None,
+            attributes,
+            subsections,
+        }
+    }
+}
+
+impl ProfileEntry<'_> {
+    /// The entry's value for a `--fields` column, if it has one.
+    fn field(&self, field: ListField) -> Option<String> {
+        let time = |at: DateTime<Utc>| at.to_rfc3339_opts(SecondsFormat::Secs, true);
+        let value = match field {
+            ListField::Name => Some(self.name),
+            ListField::Type => self.credential_type.map(|kind| kind.badge()),
+            ListField::AccountId => self.account_id,
+            ListField::AccountName => self.account_name,
+            ListField::Tags => return (!self.tags.is_empty()).then(|| self.tags.join(",")),
+            ListField::Region => self.region,
+            ListField::Role => self.role_name,
+            ListField::SsoSession => self.sso_session,
+            ListField::SsoStartUrl => self.sso_start_url,
+            ListField::SsoRegion => self.sso_region,
+            ListField::Expiry => return self.sso_token_expires_at.map(time),
+            ListField::LastUsed => return self.last_used.map(time),
+            ListField::Backend => {
+                return self
+                    .backend
+                    .and_then(|backend| backend.to_possible_value())
+                    .map(|value| value.get_name().to_string());
+            }
+            ListField::Check => self.check.as_ref().map(|check| match check {
+                CheckResult::Ok { .. } => "ok",
+                CheckResult::Failed { .. } => "failed",
+                CheckResult::Timeout => "timeout",
+            }),
+        };
+        value.map(str::to_string)
+    }
+}
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let format = output_format(matches);
+    let mut filters: Vec<ProfileFilter> = matches
+        .get_many::<ProfileFilter>("filter")
+        .unwrap_or_default()
+        .cloned()
+        .map(|filter| match matches.get_flag("regex") {
+            true => filter.into_regex(),
+            false => Ok(filter),
+        })
+        .collect::<Result<_>>()?;
+    filters.extend(attribute_filters(matches));
+
+    let mut config = context.load_config()?;
+    let state = context.selector_state()?;
+    let sort = matches
+        .get_one::<SortOrder>("sort")
+        .copied()
+        .or(context.settings.sort)
+        .unwrap_or(SortOrder::Name);
+    sort_profiles(&mut config, sort, matches.get_flag("reverse"), &state);
+    if let Some(backend) = matches.get_one::<Backend>("backend") {
+        let supplied: Vec<String> = backend
+            .profiles(&config)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        config
+            .profiles
+            .retain(|profile| supplied.contains(&profile.name));
+    }
+    let token_expiries = token_expiries(&config)?;
+    let annotations = context.annotations();
+    let last_used = state.last_used();
+
+    let mut entries: Vec<ProfileEntry> = config
+        .profiles
+        .iter()
+        .filter(|profile| matches_all(&filters, profile, &annotations))
+        .map(|profile| {
+            ProfileEntry::new(profile, &config, &annotations, &token_expiries, &last_used)
+        })
+        .collect();
+
+    let check = matches.get_flag("check");
+    if check {
+        let profile_names: Vec<String> =
+            entries.iter().map(|entry| entry.name.to_string()).collect();
+        let mut results = HashMap::new();
+        check_profiles(
+            &context.config_paths,
+            &profile_names,
+            CheckOptions::default(),
+            |profile_name, result| {
+                results.insert(profile_name.to_string(), result);
+            },
+        )?;
+        for entry in &mut entries {
+            entry.check = results.remove(entry.name);
+        }
+    }
+
+    if matches.get_flag("names") {
+        for entry in &entries {
+            println!("{}", entry.name);
+        }
+        return Ok(());
+    }
+
+    let header = !matches.get_flag("no-header");
+    if let Some(fields) = matches.get_many::<ListField>("fields") {
+        let fields: Vec<ListField> = fields.copied().collect();
+        if header {
+            let names: Vec<String> = fields
+                .iter()
+                .filter_map(|field| field.to_possible_value())
+                .map(|value| value.get_name().to_string())
+                .collect();
+            println!("{}", names.join("\t"));
+        }
+        for entry in &entries {
+            let cells: Vec<String> = fields
+                .iter()
+                .map(|&field| entry.field(field).unwrap_or_default())
+                .map(|cell| if context.redact { redact(&cell) } else { cell })
+                .collect();
+            println!("{}", cells.join("\t"));
+        }
+        return Ok(());
+    }
+
+    if format != OutputFormat::Table {
+        if context.redact {
+            return print_structured(&redacted(&entries)?, format);
+        }
+        return print_structured(&entries, format);
+    }
+
+    let display_format = matches
+        .get_one::<DisplayFormat>("format")
+        .or(context.settings.display_format.as_ref());
+    if let Some(display_format) = display_format {
+        for profile in config
+            .profiles
+            .iter()
+            .filter(|profile| matches_all(&filters, profile, &annotations))
+        {
+            let aliases = state.aliases_for(&profile.name);
+            let account_name = annotations.account_name(profile);
+            let line = display_format.render(
+                profile,
+                &config,
+                &aliases,
+                account_name,
+                last_used.get(&profile.name).copied(),
+            );
+            if context.redact {
+                println!("{}", redact(&line));
+            } else {
+                println!("{line}");
+            }
+        }
+        return Ok(());
+    }
+
+    // TAGS only when the settings file defines some.
+    let tags = !annotations.tags.is_empty();
+    let mut headers = vec!["NAME", "TYPE", "ACCOUNT", "REGION", "ROLE", "SSO TOKEN"];
+    if tags {
+        headers.push("TAGS");
+    }
+    if check {
+        headers.push("CHECK");
+    }
+    let mut rows: Vec<Vec<String>> = entries
+        .iter()
+        .map(|entry| {
+            let account = entry
+                .account_id
+                .map(|account_id| describe_account(account_id, entry.account_name));
+            let tag_list = entry.tags.join(",");
+            let mut cells = vec![
+                Some(entry.name),
+                entry.credential_type.map(|kind| kind.badge()),
+                account.as_deref(),
+                entry.region,
+                entry.role_name,
+                entry.sso_token.as_deref(),
+            ];
+            if tags {
+                cells.push(Some(tag_list.as_str()).filter(|list| !list.is_empty()));
+            }
+            if check {
+                cells.push(entry.check.as_ref().map(CheckResult::symbol));
+            }
+            cells
+                .into_iter()
+                .map(|cell| cell.unwrap_or("-").to_string())
+                .collect()
+        })
+        .collect();
+    if context.redact {
+        for cell in rows.iter_mut().flatten() {
+            *cell = redact(cell);
+        }
+    }
+
+    print_columns(&headers, &rows, header);
+    Ok(())
+}
@@ -0,0 +1,113 @@
+use super::Context;
+use crate::dry_run::{ensure_writable, would, would_write};
+use crate::error::Error;
+use crate::shell::env_commands;
+use crate::state::{LOCAL_PROFILE_FILE, read_local_profile};
+use anyhow::{Context as _, Result};
+use clap::ArgMatches;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Set by `hook-env` to the `.aws-profile` it applied, so it can tell when
+/// the shell has moved to another tree or left it.
+const APPLIED_VAR: &str = "AWS_PROFILE_SELECTOR_LOCAL";
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let path = std::env::current_dir()?.join(LOCAL_PROFILE_FILE);
+
+    match matches.subcommand() {
+        Some(("set", sub_matches)) => {
+            let profile_name = sub_matches
+                .get_one::<String>("profile")
+                .expect("profile is required");
+            let state = context.selector_state()?;
+            let profile_name = state.resolve_alias(profile_name);
+            if context.load_config()?.find_profile(profile_name).is_none() {
+                return Err(Error::profile_not_found(profile_name).into());
+            }
+
+            let contents = format!("{profile_name}\n");
+            ensure_writable(format_args!("write {}", path.display()))?;
+            if !would_write(&path, &contents) {
+                fs::write(&path, contents)
+                    .with_context(|| format!("Failed to write {}", path.display()))?;
+            }
+            println!("Wrote {} ({profile_name})", path.display());
+        }
+        Some(("unset", _)) => {
+            if path.exists() {
+                ensure_writable(format_args!("remove {}", path.display()))?;
+                if !would(format_args!("remove {}", path.display())) {
+                    fs::remove_file(&path)?;
+                }
+                println!("Removed {}", path.display());
+            } else {
+                println!("No {LOCAL_PROFILE_FILE} in this directory");
+            }
+        }
+        Some(("show", _)) => match read_local_profile()? {
+            Some((profile_name, path)) => println!("{profile_name} ({})", path.display()),
+            None => {
+                return Err(Error::NotFound(format!(
+                    "No {LOCAL_PROFILE_FILE} in this directory or above"
+                ))
+                .into());
+            }
+        },
+        _ => unreachable!("clap requires a subcommand"),
+    }
+
+    Ok(())
+}
+
+/// Prints the shell commands that bring `AWS_PROFILE` in line with the
+/// nearest `.aws-profile`, and nothing when it already is. The `init --hook`
+/// integration runs this on every directory change (or prompt).
+pub fn hook_env() -> Result<()> {
+    let applied = std::env::var_os(APPLIED_VAR).map(PathBuf::from);
+    let changes = hook_changes(read_local_profile()?, applied.as_deref());
+
+    if !changes.is_empty() {
+        println!("{}", env_commands(&changes));
+    }
+    Ok(())
+}
+
+/// Entering a tree with an `.aws-profile` (or a nested one) sets its
+/// profile; leaving every such tree unsets `AWS_PROFILE`. Switching
+/// profiles by hand inside the tree is left alone until then.
+fn hook_changes(
+    local: Option<(String, PathBuf)>,
+    applied: Option<&Path>,
+) -> Vec<(&'static str, Option<String>)> {
+    match (local, applied) {
+        (Some((profile_name, path)), applied) if applied != Some(path.as_path()) => vec![
+            ("AWS_PROFILE", Some(profile_name)),
+            (APPLIED_VAR, Some(path.display().to_string())),
+        ],
+        (None, Some(_)) => vec![("AWS_PROFILE", None), (APPLIED_VAR, None)],
+        _ => Vec::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_changes_follow_the_directory() {
+        let project = PathBuf::from("/src/payments/.aws-profile");
+        let local = || Some(("prod".to_string(), project.clone()));
+
+        assert_eq!(
+            hook_changes(local(), None),
+            vec![
+                ("AWS_PROFILE", Some("prod".to_string())),
+                (APPLIED_VAR, Some("/src/payments/.aws-profile".to_string())),
+            ]
+        );
+        assert!(hook_changes(local(), Some(&project)).is_empty());
+        assert_eq!(hook_changes(None, Some(&project)).len(), 2);
+        assert!(hook_changes(None, None).is_empty());
+    }
+}
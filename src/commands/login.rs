@@ -0,0 +1,130 @@
+use super::{Context, profile_or_active};
+use crate::aws::block_on;
+use crate::config::AwsConfig;
+use crate::dry_run::would;
+use crate::environment::now;
+use crate::error::Error;
+use crate::mfa::{requires_mfa, session_expiry};
+use crate::settings::ExpirySettings;
+use crate::sso::{SsoTarget, describe_expiry, login, read_cached_token};
+use crate::ui::{answer, has_terminal};
+use anyhow::Result;
+use chrono::{Local, TimeDelta};
+use clap::ArgMatches;
+use inquire::Confirm;
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let profile_name = profile_or_active(context, matches.get_one::<String>("profile"))?;
+    let config = context.load_config()?;
+    let target = sso_target(&config, &profile_name)?;
+
+    if !matches.get_flag("force")
+        && let Some(token) = read_cached_token(&target)?
+        && !token.is_expired()
+    {
+        println!(
+            "SSO session {} is valid until {}",
+            target.label(),
+            token
+                .expires_at
+                .with_timezone(&Local)
+                .format("%Y-%m-%d %H:%M")
+        );
+        return Ok(());
+    }
+
+    let token = block_on(login(&target))??;
+    println!(
+        "Logged in to {} (valid until {})",
+        target.label(),
+        token
+            .expires_at
+            .with_timezone(&Local)
+            .format("%Y-%m-%d %H:%M")
+    );
+    Ok(())
+}
+
+/// Logs in before activation when `profile_name` uses SSO and its cached
+/// token is missing or expired. Profiles without SSO are left alone.
+pub fn ensure_logged_in(config: &AwsConfig, profile_name: &str) -> Result<()> {
+    let Some(target) = config
+        .find_profile(profile_name)
+        .and_then(|profile| SsoTarget::for_profile(config, profile))
+    else {
+        return Ok(());
+    };
+
+    if read_cached_token(&target)?.is_some_and(|token| !token.is_expired())
+        || would(format_args!("log in to {}", target.label()))
+    {
+        return Ok(());
+    }
+
+    eprintln!(
+        "SSO token for {} is missing or expired, logging in",
+        target.label()
+    );
+    block_on(login(&target))??;
+    Ok(())
+}
+
+/// Warns when `profile_name`'s SSO token or MFA session runs out within the
+/// `[expiry]` threshold, offering to log in to SSO again with
+/// `prompt-login`. Missing credentials are left to `--auto-login` and the
+/// selector's markers.
+pub fn warn_if_expiring(
+    config: &AwsConfig,
+    profile_name: &str,
+    settings: &ExpirySettings,
+) -> Result<()> {
+    let Some(profile) = config.find_profile(profile_name) else {
+        return Ok(());
+    };
+    if settings.warn_minutes == 0 {
+        return Ok(());
+    }
+    let now = now();
+    let threshold = TimeDelta::minutes(settings.warn_minutes.into());
+
+    if let Some(target) = SsoTarget::for_profile(config, profile)
+        && let Some(token) = read_cached_token(&target)?
+        && token.expires_at - now < threshold
+    {
+        tracing::warn!(
+            "SSO session {} {}",
+            target.label(),
+            describe_expiry(token.expires_at, now)
+        );
+        if settings.prompt_login
+            && has_terminal()
+            && answer(
+                Confirm::new("Log in again now?")
+                    .with_default(true)
+                    .prompt(),
+            )?
+        {
+            block_on(login(&target))??;
+        }
+    }
+
+    if requires_mfa(profile)
+        && let Some(expires_at) = session_expiry(profile_name)?
+        && expires_at - now < threshold
+    {
+        tracing::warn!(
+            "the MFA session for '{profile_name}' {}",
+            describe_expiry(expires_at, now)
+        );
+    }
+    Ok(())
+}
+
+pub(super) fn sso_target(config: &AwsConfig, profile_name: &str) -> Result<SsoTarget> {
+    let profile = config
+        .find_profile(profile_name)
+        .ok_or_else(|| Error::profile_not_found(profile_name))?;
+
+    SsoTarget::for_profile(config, profile)
+        .ok_or_else(|| anyhow::anyhow!("Profile '{profile_name}' is not configured for AWS SSO"))
+}
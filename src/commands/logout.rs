@@ -0,0 +1,39 @@
+use super::login::sso_target;
+use super::{Context, profile_or_active};
+use crate::sso::{SsoTarget, remove_cached_token};
+use anyhow::Result;
+use clap::ArgMatches;
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let config = context.load_config()?;
+
+    let targets: Vec<SsoTarget> = if matches.get_flag("all") {
+        let mut targets: Vec<SsoTarget> = config
+            .profiles
+            .iter()
+            .filter_map(|profile| SsoTarget::for_profile(&config, profile))
+            .collect();
+        // Profiles sharing an sso-session or start URL share one cache entry.
+        targets.sort_by_key(SsoTarget::cache_key);
+        targets.dedup_by_key(|target| target.cache_key());
+        targets
+    } else {
+        let profile_name = profile_or_active(context, matches.get_one::<String>("profile"))?;
+        vec![sso_target(&config, &profile_name)?]
+    };
+
+    if targets.is_empty() {
+        println!("No SSO profiles found");
+        return Ok(());
+    }
+
+    for target in &targets {
+        if remove_cached_token(target)? {
+            println!("Logged out of {}", target.label());
+        } else {
+            println!("No cached SSO token for {}", target.label());
+        }
+    }
+
+    Ok(())
+}
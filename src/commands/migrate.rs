@@ -0,0 +1,199 @@
+use super::Context;
+use crate::diff::Preview;
+use crate::editor::IniDocument;
+use anyhow::Result;
+use clap::ArgMatches;
+use inquire::Confirm;
+
+/// Scope the CLI requests for sessions; written to new sso-session blocks.
+const REGISTRATION_SCOPES: &str = "sso:account:access";
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let path = &context.config_paths.config;
+    let original = IniDocument::load(path)?;
+
+    let mut document = original.clone();
+    let migrated = migrate(&mut document);
+    if migrated.is_empty() {
+        println!("No legacy SSO profiles found");
+        return Ok(());
+    }
+
+    let mut preview = Preview::new();
+    preview.file(path, &original.to_string(), &document.to_string());
+    preview.confirm(
+        Confirm::new(&format!("Migrate {} profile(s)?", migrated.len())).with_default(true),
+        matches.get_flag("yes"),
+    )?;
+
+    document.save(path)?;
+    println!(
+        "Migrated {} profile(s): {}",
+        migrated.len(),
+        migrated.join(", ")
+    );
+    println!(
+        "Run `aws-profile-selector login` again; sessions cache tokens separately from legacy profiles"
+    );
+    Ok(())
+}
+
+/// Moves inline `sso_start_url`/`sso_region` settings into shared
+/// `[sso-session]` blocks, one per distinct start URL and region, reusing
+/// blocks that already exist. Returns the headers of the migrated profiles.
+fn migrate(document: &mut IniDocument) -> Vec<String> {
+    let headers: Vec<String> = document
+        .sections()
+        .into_iter()
+        .filter(|header| *header == "default" || header.starts_with("profile "))
+        .map(String::from)
+        .collect();
+
+    let mut migrated = Vec::new();
+    for header in headers {
+        if document.get(&header, "sso_session").is_some() {
+            continue;
+        }
+        let (Some(start_url), Some(region)) = (
+            document.get(&header, "sso_start_url").map(String::from),
+            document.get(&header, "sso_region").map(String::from),
+        ) else {
+            continue;
+        };
+
+        let session = find_session(document, &start_url, &region)
+            .unwrap_or_else(|| create_session(document, &start_url, &region));
+
+        document.replace_key(&header, "sso_start_url", "sso_session", &session);
+        document.remove_key(&header, "sso_region");
+        migrated.push(header);
+    }
+
+    migrated
+}
+
+fn find_session(document: &IniDocument, start_url: &str, region: &str) -> Option<String> {
+    document
+        .sections()
+        .into_iter()
+        .filter_map(|header| header.strip_prefix("sso-session "))
+        .find(|name| {
+            let header = format!("sso-session {name}");
+            document.get(&header, "sso_start_url") == Some(start_url)
+                && document.get(&header, "sso_region") == Some(region)
+        })
+        .map(String::from)
+}
+
+fn create_session(document: &mut IniDocument, start_url: &str, region: &str) -> String {
+    let base = session_name_for(start_url);
+    let mut name = base.clone();
+    let mut suffix = 2;
+    while document.has_section(&format!("sso-session {name}")) {
+        name = format!("{base}-{suffix}");
+        suffix += 1;
+    }
+
+    document.append_section(
+        &format!("sso-session {name}"),
+        &[
+            ("sso_start_url", start_url),
+            ("sso_region", region),
+            ("sso_registration_scopes", REGISTRATION_SCOPES),
+        ],
+    );
+    name
+}
+
+/// Derives a session name from the start URL's subdomain, e.g. `my-org`
+/// for `https://my-org.awsapps.com/start`.
+fn session_name_for(start_url: &str) -> String {
+    url::Url::parse(start_url)
+        .ok()
+        .and_then(|url| {
+            let host = url.host_str()?;
+            let label = host.split('.').next()?;
+            (!label.is_empty()).then(|| label.to_string())
+        })
+        .unwrap_or_else(|| "sso".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_migrate_shares_sessions_between_profiles() {
+        let mut document = IniDocument::parse(
+            r#"[profile dev]
+sso_start_url = https://corp.awsapps.com/start
+sso_region = us-east-1
+sso_account_id = 111111111111
+sso_role_name = Dev
+
+[profile prod]
+sso_start_url = https://corp.awsapps.com/start
+sso_region = us-east-1
+sso_account_id = 222222222222
+sso_role_name = Admin
+
+[profile modern]
+sso_session = corp
+"#,
+        );
+
+        assert_eq!(migrate(&mut document), vec!["profile dev", "profile prod"]);
+        assert_eq!(
+            document.to_string(),
+            r#"[profile dev]
+sso_session = corp
+sso_account_id = 111111111111
+sso_role_name = Dev
+
+[profile prod]
+sso_session = corp
+sso_account_id = 222222222222
+sso_role_name = Admin
+
+[profile modern]
+sso_session = corp
+
+[sso-session corp]
+sso_start_url = https://corp.awsapps.com/start
+sso_region = us-east-1
+sso_registration_scopes = sso:account:access
+"#
+        );
+        assert!(migrate(&mut document).is_empty());
+    }
+
+    #[test]
+    fn test_migrate_reuses_matching_session_and_avoids_name_clashes() {
+        let mut document = IniDocument::parse(
+            r#"[profile a]
+sso_start_url = https://corp.awsapps.com/start
+sso_region = eu-west-1
+
+[profile b]
+sso_start_url = https://other.awsapps.com/start
+sso_region = us-east-1
+
+[sso-session corp]
+sso_start_url = https://corp.awsapps.com/start
+sso_region = us-east-1
+
+[sso-session other]
+sso_start_url = https://other.awsapps.com/start
+sso_region = us-east-1
+"#,
+        );
+
+        migrate(&mut document);
+        assert_eq!(document.get("profile a", "sso_session"), Some("corp-2"));
+        assert_eq!(document.get("profile b", "sso_session"), Some("other"));
+        assert_eq!(
+            document.get("sso-session corp-2", "sso_region"),
+            Some("eu-west-1")
+        );
+    }
+}
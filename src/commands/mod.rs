@@ -0,0 +1,399 @@
+mod activate;
+mod add;
+mod alias;
+mod audit;
+mod backup;
+mod bundle;
+mod cache;
+mod codeartifact;
+mod completions;
+mod console;
+mod credential_process;
+mod creds;
+mod current;
+mod doctor;
+mod ecr;
+mod edit;
+mod env;
+mod exec;
+mod fav;
+mod hide;
+mod history;
+mod import;
+mod init;
+mod list;
+mod local;
+mod login;
+mod logout;
+mod migrate;
+mod paths;
+mod refresh;
+mod regions;
+mod remove;
+mod rename;
+mod secure;
+mod serve;
+mod shell;
+mod show;
+mod stats;
+mod status;
+mod statusline;
+mod whoami;
+
+use crate::audit::{AuditEvent, record};
+use crate::aws::{block_on, credential_env_vars, load_sdk_config, resolve_credential_env};
+use crate::backup::enable_backups;
+use crate::check::CheckOptions;
+use crate::config::{AwsConfig, ConfigCache, ConfigPaths};
+use crate::dry_run::{dry_run, set_dry_run, set_read_only};
+use crate::error::{EXIT_USAGE, Error, ErrorReport, exit_code};
+use crate::filter::{Annotations, FilterField, ProfileFilter};
+use crate::logging;
+use crate::output::OutputFormat;
+use crate::session::{
+    SessionDuration, configured_duration, exchange_static_keys, session_credentials,
+};
+use crate::settings::Settings;
+use crate::shell::{Shell, set_shell};
+use crate::state::{Scope, SelectorState, active_profile, set_scope};
+use crate::store::ProfileStore;
+use crate::ui::set_accessible;
+use activate::ActivateOptions;
+use anyhow::Result;
+use aws_credential_types::Credentials;
+use clap::ArgMatches;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Settings shared by every subcommand, taken from the global flags.
+pub struct Context {
+    pub config_paths: ConfigPaths,
+    /// `-c`: emit shell commands instead of writing the current-profile file.
+    pub current_shell_mode: bool,
+    /// `--redact` (or `redact` in the settings): mask account IDs in output.
+    pub redact: bool,
+    pub settings: Settings,
+    configs: ConfigCache,
+}
+
+impl Context {
+    fn from_matches(matches: &ArgMatches) -> Result<Self> {
+        logging::init(
+            matches.get_count("verbose"),
+            matches.get_flag("quiet"),
+            matches.get_one::<PathBuf>("log-file").map(PathBuf::as_path),
+        )?;
+        set_dry_run(matches.get_flag("dry-run"));
+        enable_backups();
+        let settings = Settings::load().map_err(Error::config)?;
+        set_scope(
+            matches
+                .get_one::<Scope>("scope")
+                .copied()
+                .or(settings.scope)
+                .unwrap_or_default(),
+        );
+        if let Some(&shell) = matches.get_one::<Shell>("output-shell") {
+            set_shell(shell);
+        }
+        set_accessible(matches.get_flag("accessible") || settings.accessible);
+        set_read_only(matches.get_flag("read-only") || settings.read_only);
+
+        let config_paths = ConfigPaths::resolve(
+            matches.get_one::<PathBuf>("config").cloned(),
+            matches.get_one::<PathBuf>("credentials").cloned(),
+        )?;
+        Ok(Self {
+            configs: ConfigCache::new(config_paths.clone()),
+            config_paths,
+            current_shell_mode: matches.get_flag("current"),
+            redact: matches.get_flag("redact") || settings.redact,
+            settings,
+        })
+    }
+
+    /// The account nicknames and tags from the settings file.
+    pub fn annotations(&self) -> Annotations {
+        Annotations {
+            account_names: self.settings.accounts.clone(),
+            tags: self.settings.tags.clone(),
+        }
+    }
+
+    /// The saved selector state with the settings file layered in.
+    pub fn selector_state(&self) -> Result<SelectorState> {
+        Ok(SelectorState::load()?.with_settings(&self.settings))
+    }
+
+    /// The profile's credentials and region as environment variables: read
+    /// from the item `[credential-sources]` maps it to, or resolved through
+    /// the SDK credential chain. With a `duration` they're temporary
+    /// credentials lasting that long (see [`crate::session`]).
+    pub fn credential_env(
+        &self,
+        profile_name: &str,
+        duration: Option<SessionDuration>,
+    ) -> Result<Vec<(&'static str, String)>> {
+        let Some(source) = self.settings.credential_sources.get(profile_name) else {
+            let Some(duration) = duration else {
+                return resolve_credential_env(&self.config_paths, profile_name);
+            };
+            let session = session_credentials(&self.config_paths, profile_name, duration, false)?;
+            return Ok(credential_env_vars(
+                &session.credentials,
+                session.region.as_deref(),
+            ));
+        };
+        let keys = source.load()?;
+        let config = self.read_config()?;
+        let region = config
+            .find_profile(profile_name)
+            .and_then(|profile| profile.get_region());
+        let mut credentials = Credentials::new(
+            keys.access_key_id,
+            keys.secret_access_key,
+            None,
+            None,
+            "credential-sources",
+        );
+        if let Some(duration) = duration {
+            let sdk_config = block_on(load_sdk_config(&self.config_paths, profile_name))?;
+            credentials = exchange_static_keys(&sdk_config, credentials, duration)?;
+        }
+        Ok(credential_env_vars(&credentials, region))
+    }
+
+    /// Adds `event` for `profile_name` to the audit log, when it's on.
+    pub fn audit(&self, event: AuditEvent, profile_name: &str) {
+        if !self.settings.audit.enabled {
+            return;
+        }
+        let config = self.read_config().unwrap_or_default();
+        record(
+            &self.settings.audit,
+            event,
+            profile_name,
+            config.find_profile(profile_name),
+        );
+    }
+
+    /// `--duration`, else the profile's `[session-durations]` entry.
+    pub fn session_duration(
+        &self,
+        matches: &ArgMatches,
+        profile_name: &str,
+    ) -> Option<SessionDuration> {
+        matches
+            .get_one::<SessionDuration>("duration")
+            .copied()
+            .or_else(|| configured_duration(&self.settings.session_durations, profile_name))
+    }
+
+    /// Reads the AWS config, or takes it from the last read when neither
+    /// file has changed since.
+    pub fn read_config(&self) -> Result<AwsConfig> {
+        self.configs.get()
+    }
+
+    /// Reads the AWS config, failing with a config error when it has no
+    /// profiles.
+    pub fn load_config(&self) -> Result<AwsConfig> {
+        let config = self.read_config()?;
+
+        if config.profiles.is_empty() {
+            return Err(Error::Config(format!(
+                "No AWS profiles found in {} or {}",
+                self.config_paths.config.display(),
+                self.config_paths.credentials.display()
+            ))
+            .into());
+        }
+
+        Ok(config)
+    }
+
+    /// The AWS config as a [`ProfileStore`], for commands that change it.
+    pub fn profile_store(&self) -> Result<ProfileStore> {
+        let config = self.load_config()?;
+        Ok(ProfileStore::with_config(self.config_paths.clone(), config))
+    }
+}
+
+pub fn run(matches: &ArgMatches) -> Result<()> {
+    let context = Context::from_matches(matches)?;
+
+    let result = match matches.subcommand() {
+        Some(("activate", sub_matches)) => activate::activate(
+            &context,
+            sub_matches.get_one::<String>("profile"),
+            ActivateOptions::from_matches(sub_matches, &context.settings),
+        ),
+        Some(("deactivate", _)) => activate::deactivate(&context),
+        Some(("switch", sub_matches)) => activate::switch(&context, sub_matches),
+        Some(("role", sub_matches)) => activate::role(&context, sub_matches),
+        Some(("new", sub_matches)) => {
+            let profile_name = sub_matches
+                .get_one::<String>("profile")
+                .expect("profile is required");
+            activate::new(&context, profile_name)
+        }
+        Some(("add", sub_matches)) => add::run(&context, sub_matches),
+        Some(("edit", sub_matches)) => edit::run(&context, sub_matches),
+        Some(("remove", sub_matches)) => remove::run(&context, sub_matches),
+        Some(("rename", sub_matches)) => rename::rename(&context, sub_matches),
+        Some(("copy", sub_matches)) => rename::copy(&context, sub_matches),
+        Some(("list", sub_matches)) => list::run(&context, sub_matches),
+        Some(("show", sub_matches)) => show::run(&context, sub_matches),
+        Some(("alias", sub_matches)) => alias::run(&context, sub_matches),
+        Some(("local", sub_matches)) => local::run(&context, sub_matches),
+        Some(("hook-env", _)) => local::hook_env(),
+        Some(("fav", sub_matches)) => fav::run(&context, sub_matches),
+        Some(("hide", sub_matches)) => hide::run(&context, sub_matches),
+        Some(("current", sub_matches)) => current::run(&context, sub_matches),
+        Some(("status", sub_matches)) => status::run(&context, sub_matches),
+        Some(("history", sub_matches)) => history::run(&context, sub_matches),
+        Some(("stats", sub_matches)) => stats::run(&context, sub_matches),
+        Some(("paths", sub_matches)) => paths::run(&context, sub_matches),
+        Some(("import", sub_matches)) => import::run(&context, sub_matches),
+        Some(("export-bundle", sub_matches)) => bundle::export(&context, sub_matches),
+        Some(("import-bundle", sub_matches)) => bundle::import(&context, sub_matches),
+        Some(("migrate-sso", sub_matches)) => migrate::run(&context, sub_matches),
+        Some(("doctor", sub_matches)) => doctor::run(&context, sub_matches),
+        Some(("cache", sub_matches)) => cache::run(sub_matches),
+        Some(("backup", sub_matches)) => backup::run(sub_matches),
+        Some(("audit", sub_matches)) => audit::run(&context, sub_matches),
+        Some(("statusline", sub_matches)) => statusline::run(&context, sub_matches),
+        Some(("prompt", sub_matches)) => statusline::run_prompt(&context, sub_matches),
+        Some(("regions", sub_matches)) => regions::run(&context, sub_matches),
+        Some(("whoami", sub_matches)) => whoami::run(&context, sub_matches),
+        Some(("login", sub_matches)) => login::run(&context, sub_matches),
+        Some(("logout", sub_matches)) => logout::run(&context, sub_matches),
+        Some(("refresh", sub_matches)) => refresh::run(&context, sub_matches),
+        Some(("console", sub_matches)) => console::run(&context, sub_matches),
+        Some(("ecr-login", sub_matches)) => ecr::run(&context, sub_matches),
+        Some(("creds", sub_matches)) => creds::run(&context, sub_matches),
+        Some(("secure", sub_matches)) => secure::run(&context, sub_matches),
+        Some(("credential-process", sub_matches)) => credential_process::run(&context, sub_matches),
+        Some(("codeartifact-login", sub_matches)) => codeartifact::run(&context, sub_matches),
+        Some(("exec", sub_matches)) => exec::run(&context, sub_matches),
+        Some(("env", sub_matches)) => env::run(&context, sub_matches),
+        Some(("shell", sub_matches)) => shell::run(&context, sub_matches),
+        Some(("serve", sub_matches)) => serve::run(&context, sub_matches),
+        Some(("init", sub_matches)) => init::run(sub_matches),
+        Some(("completions", sub_matches)) => completions::run(sub_matches),
+        _ => run_legacy(&context, matches),
+    };
+    if result.is_ok() && dry_run() && !logging::quiet() {
+        eprintln!("Dry run: nothing was changed");
+    }
+    result
+}
+
+/// Prints the error a command failed with, as a JSON object when the
+/// command was asked for `--output json`, and returns the status to exit
+/// with (see [`crate::error`]).
+pub fn report_error(matches: &ArgMatches, error: &anyhow::Error) -> i32 {
+    let json = matches.subcommand().is_some_and(|(_, sub_matches)| {
+        matches!(
+            sub_matches.try_get_one::<OutputFormat>("output"),
+            Ok(Some(OutputFormat::Json))
+        )
+    });
+    match json {
+        true => eprintln!(
+            "{}",
+            serde_json::json!({ "error": ErrorReport::new(error) })
+        ),
+        false => {
+            eprintln!("Error: {error}");
+            let causes: Vec<_> = error.chain().skip(1).collect();
+            if !causes.is_empty() {
+                eprintln!("\nCaused by:");
+                for cause in causes {
+                    eprintln!("    {cause}");
+                }
+            }
+        }
+    }
+    exit_code(error)
+}
+
+/// Exits after a command line clap rejected, with [`EXIT_USAGE`]. `--help`
+/// and `--version` exit 0 as usual.
+pub fn usage_error(error: clap::Error) -> ! {
+    if !error.use_stderr() {
+        error.exit();
+    }
+    let _ = error.print();
+    std::process::exit(EXIT_USAGE)
+}
+
+fn output_format(matches: &ArgMatches) -> OutputFormat {
+    *matches
+        .get_one::<OutputFormat>("output")
+        .expect("output has a default")
+}
+
+/// `--concurrency` and `--timeout` for checking many profiles at once,
+/// always asking STS afresh.
+fn check_options(matches: &ArgMatches) -> CheckOptions {
+    CheckOptions {
+        concurrency: *matches
+            .get_one::<usize>("concurrency")
+            .expect("concurrency has a default"),
+        timeout: Duration::from_secs(
+            *matches
+                .get_one::<u64>("timeout")
+                .expect("timeout has a default"),
+        ),
+        use_cache: false,
+    }
+}
+
+/// The filters given through `--region`, `--account`, and `--role`.
+fn attribute_filters(matches: &ArgMatches) -> Vec<ProfileFilter> {
+    [
+        ("region", FilterField::Region),
+        ("account", FilterField::Account),
+        ("role", FilterField::Role),
+        ("tag", FilterField::Tag),
+    ]
+    .into_iter()
+    .filter_map(|(name, field)| {
+        let value = matches.get_one::<String>(name)?;
+        Some(ProfileFilter::equals(field, value))
+    })
+    .collect()
+}
+
+/// The named profile (resolving aliases), or the active one when no name is
+/// given. Fails with a not-found error when neither is available.
+fn profile_or_active(context: &Context, profile_name: Option<&String>) -> Result<String> {
+    if let Some(profile_name) = profile_name {
+        let state = context.selector_state()?;
+        return Ok(state.resolve_alias(profile_name).to_string());
+    }
+
+    match active_profile()? {
+        Some(profile_name) => Ok(profile_name),
+        None => Err(Error::NotFound("No active AWS profile".to_string()).into()),
+    }
+}
+
+/// Handles invocations without a subcommand, including the original
+/// `-a`/`-d`/`-n` flags.
+fn run_legacy(context: &Context, matches: &ArgMatches) -> Result<()> {
+    if matches.get_flag("deactivate") {
+        return activate::deactivate(context);
+    }
+
+    if let Some(profile_name) = matches.get_one::<String>("new") {
+        return activate::new(context, profile_name);
+    }
+
+    activate::activate(
+        context,
+        matches.get_one::<String>("activate"),
+        ActivateOptions::from_matches(matches, &context.settings),
+    )
+}
@@ -0,0 +1,88 @@
+//! `paths`: where this run reads the AWS files and the settings from, and
+//! where it keeps its own state, for debugging setups that share dotfiles
+//! across machines.
+
+use super::{Context, output_format};
+use crate::audit::log_path;
+use crate::backup::backups_dir;
+use crate::cache::get_cache_dir;
+use crate::daemon::socket_path;
+use crate::error::Error;
+use crate::output::{OutputFormat, print_structured, print_table};
+use crate::settings::get_settings_path;
+use crate::state::{get_current_profile_path, get_state_dir, get_state_path, read_local_profile};
+use anyhow::Result;
+use clap::ArgMatches;
+use serde::Serialize;
+use std::path::PathBuf;
+
+#[derive(Serialize)]
+struct Location {
+    name: &'static str,
+    path: String,
+    exists: bool,
+}
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let locations = locations(context)?;
+
+    if let Some(name) = matches.get_one::<String>("name") {
+        let Some(location) = locations.iter().find(|location| location.name == name) else {
+            // Only the local profile file can be missing from the list.
+            return Err(Error::NotFound("No .aws-profile file applies here".to_string()).into());
+        };
+        println!("{}", location.path);
+        return Ok(());
+    }
+
+    let format = output_format(matches);
+    if format != OutputFormat::Table {
+        return print_structured(&locations, format);
+    }
+
+    let rows: Vec<Vec<String>> = locations
+        .iter()
+        .map(|location| {
+            vec![
+                location.name.to_string(),
+                location.path.clone(),
+                match location.exists {
+                    true => "yes".to_string(),
+                    false => "no".to_string(),
+                },
+            ]
+        })
+        .collect();
+    print_table(&["NAME", "PATH", "EXISTS"], &rows);
+    Ok(())
+}
+
+fn locations(context: &Context) -> Result<Vec<Location>> {
+    let mut paths: Vec<(&'static str, PathBuf)> = vec![
+        ("config", context.config_paths.config.clone()),
+        ("credentials", context.config_paths.credentials.clone()),
+        ("settings", get_settings_path()?),
+        ("state-dir", get_state_dir()?),
+    ];
+    // The terminal scope has no file outside a terminal.
+    if let Ok(path) = get_current_profile_path() {
+        paths.push(("current-profile", path));
+    }
+    paths.push(("state-file", get_state_path()?));
+    if let Some((_, path)) = read_local_profile()? {
+        paths.push(("local-profile", path));
+    }
+    paths.push(("backups-dir", backups_dir()?));
+    paths.push(("audit-log", log_path()?));
+    paths.push(("cache-dir", get_cache_dir()?));
+    paths.push(("socket", socket_path()?));
+
+    Ok(paths
+        .into_iter()
+        .map(|(name, path)| Location {
+            name,
+            exists: path.exists(),
+            path: path.display().to_string(),
+        })
+        .collect())
+}
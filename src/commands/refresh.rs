@@ -0,0 +1,114 @@
+//! `refresh`: renews the SSO tokens of recently used profiles with their
+//! refresh tokens shortly before they expire, so the next command doesn't
+//! need a browser login. `--once` checks once, for a timer; `--daemon` keeps
+//! checking.
+
+use super::Context;
+use crate::aws::block_on;
+use crate::environment::now;
+use crate::error::EXIT_FAILURE;
+use crate::sso::{SsoTarget, read_cached_token, refresh};
+use crate::state::SelectorState;
+use anyhow::Result;
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use clap::ArgMatches;
+use std::collections::HashSet;
+use std::time::Duration;
+
+/// Tokens with less than this left are refreshed.
+const REFRESH_MARGIN: TimeDelta = TimeDelta::minutes(15);
+
+/// Profiles activated within this long count as recently used.
+const RECENT: TimeDelta = TimeDelta::weeks(1);
+
+/// How often `--daemon` looks at the tokens.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    if matches.get_flag("daemon") {
+        // Each failure is reported once per token rather than every minute.
+        let mut reported = HashSet::new();
+        loop {
+            if let Err(e) = refresh_due(context, &mut reported) {
+                tracing::warn!("{e:#}");
+            }
+            std::thread::sleep(CHECK_INTERVAL);
+        }
+    }
+
+    if refresh_due(context, &mut HashSet::new())? {
+        std::process::exit(EXIT_FAILURE);
+    }
+    Ok(())
+}
+
+/// Refreshes the tokens that are due, returning whether any couldn't be.
+fn refresh_due(context: &Context, reported: &mut HashSet<String>) -> Result<bool> {
+    let now = now();
+    let mut failed = false;
+
+    for target in recent_targets(context, now)? {
+        // Never logged in, so there is nothing to renew.
+        let Some(token) = read_cached_token(&target)? else {
+            continue;
+        };
+        if token.expires_at - now > REFRESH_MARGIN {
+            tracing::debug!(
+                "{} is valid until {}; not refreshing yet",
+                target.label(),
+                local_time(token.expires_at)
+            );
+            continue;
+        }
+        tracing::info!("Refreshing {}, which expires soon", target.label());
+
+        match block_on(refresh(&target, &token))? {
+            Ok(token) => println!(
+                "Refreshed {} (valid until {})",
+                target.label(),
+                local_time(token.expires_at)
+            ),
+            Err(e) => {
+                failed = true;
+                if reported.insert(format!("{} {}", target.cache_key(), token.expires_at)) {
+                    eprintln!("{e:#}");
+                }
+            }
+        }
+    }
+    Ok(failed)
+}
+
+/// The SSO portals of the profiles activated within [`RECENT`], most
+/// recently used first, each listed once.
+fn recent_targets(context: &Context, now: DateTime<Utc>) -> Result<Vec<SsoTarget>> {
+    // Checked on every pass, so a long-running daemon follows edits.
+    let config = context.read_config()?;
+    let state = SelectorState::load()?;
+
+    let mut targets: Vec<SsoTarget> = Vec::new();
+    for activation in state.history.iter().rev() {
+        if now - activation.at > RECENT {
+            break;
+        }
+        let Some(target) = config
+            .find_profile(&activation.profile)
+            .and_then(|profile| SsoTarget::for_profile(&config, profile))
+        else {
+            continue;
+        };
+        if !targets
+            .iter()
+            .any(|known| known.cache_key() == target.cache_key())
+        {
+            targets.push(target);
+        }
+    }
+    Ok(targets)
+}
+
+fn local_time(at: DateTime<Utc>) -> String {
+    at.with_timezone(&Local)
+        .format("%Y-%m-%d %H:%M")
+        .to_string()
+}
@@ -0,0 +1,165 @@
+//! `regions`: picks a region the way the selector picks a profile. With `-c`
+//! it prints the commands that set `AWS_REGION` and `AWS_DEFAULT_REGION`;
+//! otherwise it prints the region's name. `--names` lists them for shell
+//! completion.
+
+use super::Context;
+use crate::config::AwsConfig;
+use crate::error::Error;
+use crate::regions::{KNOWN_REGIONS, is_known_region};
+use crate::shell::{export_commands, print_for_shell};
+use crate::ui::{DEFAULT_PAGE_SIZE, PageSize, answer, draws_prompts, numbered_choice};
+use anyhow::Result;
+use clap::ArgMatches;
+use inquire::Select;
+use std::fmt;
+
+/// A row of the region picker.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RegionChoice {
+    name: String,
+    /// Named by at least one profile or sso-session in the config.
+    in_config: bool,
+}
+
+impl fmt::Display for RegionChoice {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self.in_config {
+            true => write!(f, "{} (in config)", self.name),
+            false => f.write_str(&self.name),
+        }
+    }
+}
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    if matches.get_flag("names") {
+        let config = context.load_config().unwrap_or_default();
+        for choice in region_choices(&config) {
+            println!("{}", choice.name);
+        }
+        return Ok(());
+    }
+
+    let region = match matches.get_one::<String>("region") {
+        Some(region) => {
+            if !is_known_region(region) {
+                tracing::warn!("'{region}' is not a known region");
+            }
+            region.clone()
+        }
+        None => pick_region(context)?,
+    };
+
+    if context.current_shell_mode {
+        print_for_shell(&export_commands(&[
+            ("AWS_REGION", region.clone()),
+            ("AWS_DEFAULT_REGION", region),
+        ]));
+    } else {
+        println!("{region}");
+    }
+    Ok(())
+}
+
+fn pick_region(context: &Context) -> Result<String> {
+    let config = context.load_config().unwrap_or_default();
+    let choices = region_choices(&config);
+    let current = std::env::var("AWS_REGION")
+        .or_else(|_| std::env::var("AWS_DEFAULT_REGION"))
+        .ok();
+    let cursor = current
+        .and_then(|current| choices.iter().position(|choice| choice.name == current))
+        .unwrap_or(0);
+    let page_size = context
+        .settings
+        .page_size
+        .map_or(DEFAULT_PAGE_SIZE, PageSize::rows);
+
+    if !draws_prompts() {
+        let lines: Vec<String> = choices.iter().map(RegionChoice::to_string).collect();
+        return match numbered_choice("Select AWS region", &lines)? {
+            Some(index) => Ok(choices[index].name.clone()),
+            None => Err(Error::cancelled().into()),
+        };
+    }
+    let choice = answer(
+        Select::new("Select AWS region:", choices)
+            .with_starting_cursor(cursor)
+            .with_page_size(page_size)
+            .prompt(),
+    )?;
+    Ok(choice.name)
+}
+
+/// The regions the config uses, most used first, followed by the rest of
+/// the known regions in name order.
+fn region_choices(config: &AwsConfig) -> Vec<RegionChoice> {
+    let mut counts: Vec<(&str, usize)> = Vec::new();
+    let attributes = config
+        .profiles
+        .iter()
+        .map(|profile| &profile.attributes)
+        .chain(
+            config
+                .sso_sessions
+                .iter()
+                .map(|session| &session.attributes),
+        );
+    for attributes in attributes {
+        for key in ["region", "sso_region"] {
+            let Some(region) = attributes.get(key) else {
+                continue;
+            };
+            match counts.iter_mut().find(|(name, _)| name == region) {
+                Some((_, count)) => *count += 1,
+                None => counts.push((region, 1)),
+            }
+        }
+    }
+    // Stable, so ties keep the order the config mentions them in.
+    counts.sort_by_key(|&(_, count)| std::cmp::Reverse(count));
+
+    let mut choices: Vec<RegionChoice> = counts
+        .iter()
+        .map(|(name, _)| RegionChoice {
+            name: name.to_string(),
+            in_config: true,
+        })
+        .collect();
+    choices.extend(
+        KNOWN_REGIONS
+            .iter()
+            .filter(|region| !counts.iter().any(|(name, _)| name == *region))
+            .map(|region| RegionChoice {
+                name: region.to_string(),
+                in_config: false,
+            }),
+    );
+    choices
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::profile;
+
+    #[test]
+    fn test_config_regions_come_first() {
+        let config = AwsConfig {
+            profiles: vec![
+                profile("dev", &[("region", "us-west-2")]),
+                profile("prod", &[("region", "eu-west-1")]),
+                profile("prod-admin", &[("region", "eu-west-1")]),
+            ],
+            ..AwsConfig::default()
+        };
+
+        let choices = region_choices(&config);
+
+        let names: Vec<&str> = choices.iter().map(|choice| choice.name.as_str()).collect();
+        assert_eq!(&names[..3], ["eu-west-1", "us-west-2", "af-south-1"]);
+        assert_eq!(names.len(), KNOWN_REGIONS.len());
+        assert_eq!(choices[0].to_string(), "eu-west-1 (in config)");
+        assert_eq!(choices[2].to_string(), "af-south-1");
+    }
+}
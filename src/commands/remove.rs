@@ -0,0 +1,48 @@
+use super::Context;
+use crate::diff::Preview;
+use crate::state::{SelectorState, clear_current_profile, read_current_profile};
+use anyhow::Result;
+use clap::ArgMatches;
+use inquire::Confirm;
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let profile_name = matches
+        .get_one::<String>("profile")
+        .expect("profile is required");
+
+    let mut store = context.profile_store()?;
+    let mut preview = Preview::new();
+    for (path, text) in store.section_texts(profile_name)? {
+        preview.file(path, &text, "");
+    }
+
+    let dependents: Vec<&str> = store
+        .profiles()
+        .iter()
+        .filter(|other| other.attributes.get("source_profile") == Some(profile_name))
+        .map(|other| other.name.as_str())
+        .collect();
+    if !dependents.is_empty() {
+        tracing::warn!(
+            "{} use '{profile_name}' as their source_profile",
+            dependents.join(", ")
+        );
+    }
+
+    preview.confirm(
+        Confirm::new(&format!("Remove profile '{profile_name}'?")).with_default(false),
+        matches.get_flag("yes"),
+    )?;
+
+    store.remove_profile(profile_name)?;
+
+    if read_current_profile()?.as_ref() == Some(profile_name) {
+        clear_current_profile()?;
+        println!("AWS profile deactivated");
+    }
+
+    SelectorState::update(|state| state.forget_profile(profile_name))?;
+
+    println!("Profile '{profile_name}' removed");
+    Ok(())
+}
@@ -0,0 +1,49 @@
+use super::Context;
+use crate::state::{
+    SelectorState, read_current_profile, read_current_region, write_current_profile,
+};
+use anyhow::Result;
+use clap::ArgMatches;
+
+pub fn rename(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let (old_name, new_name) = names(matches);
+    let mut store = context.profile_store()?;
+    let updated = store.rename_profile(old_name, new_name)?;
+
+    if read_current_profile()?.as_deref() == Some(old_name) {
+        write_current_profile(new_name, read_current_region()?.as_deref())?;
+    }
+
+    SelectorState::update(|state| state.rename_profile(old_name, new_name))?;
+
+    println!("Profile '{old_name}' renamed to '{new_name}'");
+    if !updated.is_empty() {
+        println!("Updated source_profile in: {}", updated.join(", "));
+    }
+    Ok(())
+}
+
+pub fn copy(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let (source_name, target_name) = names(matches);
+    let overrides: Vec<(&str, &str)> = matches
+        .get_many::<(String, String)>("set")
+        .unwrap_or_default()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+
+    let mut store = context.profile_store()?;
+    store.copy_profile(source_name, target_name, &overrides)?;
+
+    println!("Profile '{source_name}' copied to '{target_name}'");
+    Ok(())
+}
+
+fn names(matches: &ArgMatches) -> (&str, &str) {
+    let source = matches
+        .get_one::<String>("source")
+        .expect("source is required");
+    let target = matches
+        .get_one::<String>("target")
+        .expect("target is required");
+    (source, target)
+}
@@ -0,0 +1,137 @@
+//! `secure import`: moves static access keys out of the credentials file and
+//! into the OS keychain.
+
+use super::Context;
+use crate::config::config_section_header;
+use crate::editor::IniDocument;
+use crate::filter::Pattern;
+use crate::keychain::{self, StaticKeys, helper_command};
+use anyhow::Result;
+use clap::ArgMatches;
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    match matches.subcommand() {
+        Some(("import", sub_matches)) => import(context, sub_matches),
+        _ => unreachable!("clap requires a subcommand"),
+    }
+}
+
+fn import(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let paths = &context.config_paths;
+    let mut credentials = IniDocument::load(&paths.credentials)?;
+    let mut config = IniDocument::load(&paths.config)?;
+
+    let importable: Vec<String> = credentials
+        .sections()
+        .into_iter()
+        .filter(|section| static_keys(&credentials, section).is_some())
+        .map(str::to_string)
+        .collect();
+    let profile_names: Vec<String> = match matches.get_many::<String>("profiles") {
+        Some(names) => {
+            let state = context.selector_state()?;
+            let regex = matches.get_flag("regex");
+            let mut profile_names = Vec::new();
+            for name in names {
+                if !regex && !Pattern::has_wildcards(name) {
+                    profile_names.push(state.resolve_alias(name).to_string());
+                    continue;
+                }
+                let pattern = Pattern::new(name, regex)?;
+                profile_names.extend(
+                    importable
+                        .iter()
+                        .filter(|section| pattern.matches(section))
+                        .filter(|section| !profile_names.contains(section))
+                        .cloned()
+                        .collect::<Vec<_>>(),
+                );
+            }
+            profile_names
+        }
+        None => importable,
+    };
+    if profile_names.is_empty() {
+        println!("No static access keys in {}", paths.credentials.display());
+        return Ok(());
+    }
+
+    for profile_name in &profile_names {
+        let keys = static_keys(&credentials, profile_name).ok_or_else(|| {
+            anyhow::anyhow!(
+                "[{profile_name}] in {} has no long-lived access keys to import",
+                paths.credentials.display()
+            )
+        })?;
+        keychain::store(profile_name, &keys)?;
+
+        // Point the profile at the keychain before the plain-text keys go,
+        // so an interruption never leaves it without credentials.
+        config.set(
+            &config_section_header(profile_name),
+            "credential_process",
+            &helper_command(profile_name),
+        );
+        config.save(&paths.config)?;
+
+        credentials.remove_key(profile_name, "aws_access_key_id");
+        credentials.remove_key(profile_name, "aws_secret_access_key");
+        if credentials
+            .section_text(profile_name)
+            .is_some_and(|text| !has_entries(&text))
+        {
+            credentials.remove_section(profile_name);
+        }
+        credentials.save(&paths.credentials)?;
+
+        println!("Moved the keys for '{profile_name}' into the OS keychain");
+    }
+    Ok(())
+}
+
+/// A section's access keys, unless they come with a session token (and so
+/// expire, as `creds write` output does).
+fn static_keys(credentials: &IniDocument, section: &str) -> Option<StaticKeys> {
+    if credentials.get(section, "aws_session_token").is_some() {
+        return None;
+    }
+
+    Some(StaticKeys {
+        access_key_id: credentials.get(section, "aws_access_key_id")?.to_string(),
+        secret_access_key: credentials
+            .get(section, "aws_secret_access_key")?
+            .to_string(),
+    })
+}
+
+/// Whether a section's text has anything besides its header, blank lines,
+/// and comments.
+fn has_entries(section_text: &str) -> bool {
+    section_text.lines().skip(1).any(|line| {
+        let line = line.trim();
+        !line.is_empty() && !line.starts_with('#') && !line.starts_with(';')
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_static_keys_skips_temporary_credentials() {
+        let credentials = IniDocument::parse(
+            "[static]\naws_access_key_id = AKIA\naws_secret_access_key = secret\n\n[temp]\naws_access_key_id = ASIA\naws_secret_access_key = secret\naws_session_token = token\n",
+        );
+
+        assert_eq!(
+            static_keys(&credentials, "static"),
+            Some(StaticKeys {
+                access_key_id: "AKIA".to_string(),
+                secret_access_key: "secret".to_string(),
+            })
+        );
+        assert_eq!(static_keys(&credentials, "temp"), None);
+        assert!(!has_entries("[static]\n# moved to the keychain\n\n"));
+        assert!(has_entries("[static]\nregion = eu-west-1\n"));
+    }
+}
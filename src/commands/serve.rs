@@ -0,0 +1,347 @@
+//! `serve stdio`: answers newline-delimited JSON requests on stdin, one
+//! response line per request on stdout, so editors can list and switch
+//! profiles without driving the selector. `serve socket` answers the same
+//! requests on the daemon's Unix socket (see [`crate::daemon`]), keeping the
+//! parsed config between them.
+//!
+//! ```text
+//! > {"id": 1, "method": "activate", "params": {"profile": "dev"}}
+//! < {"id":1,"result":{"profile":"dev","account_id":"123456789012","region":"us-west-2","written":true}}
+//! ```
+//!
+//! After `subscribe`, `{"event": "current", ...}` and `{"event": "config"}`
+//! lines arrive whenever the current profile or the AWS config files change.
+
+use super::Context;
+use super::activate::activate_quietly;
+use super::list::ProfileEntry;
+use super::statusline::build_segment;
+use crate::accounts::redacted;
+use crate::config::{ConfigCache, ConfigPaths};
+use crate::environment::now;
+use crate::error::Error;
+use crate::sso::token_expiries;
+use crate::state::{active_profile, region_override};
+use crate::template::DisplayFormat;
+use crate::ui::{SortOrder, sort_profiles};
+use anyhow::Result;
+use clap::ArgMatches;
+use serde::{Deserialize, Serialize};
+use serde_json::{Value, json};
+use std::io::{BufRead, Write};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// How often a subscription looks for changes.
+const POLL_INTERVAL: Duration = Duration::from_millis(500);
+
+#[derive(Debug, Deserialize)]
+struct Request {
+    /// Echoed back so the client can match responses to requests.
+    #[serde(default)]
+    id: Value,
+    method: String,
+    #[serde(default)]
+    params: Params,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct Params {
+    profile: Option<String>,
+    region: Option<String>,
+    /// Stands in for typing a protected profile's name back.
+    #[serde(default)]
+    confirmed: bool,
+    /// `segment`: the display format and `--redact` of the asking command.
+    format: Option<String>,
+    #[serde(default)]
+    redact: bool,
+    /// `segment`: the asking command's files, which have to be the ones
+    /// this process reads.
+    config: Option<PathBuf>,
+    credentials: Option<PathBuf>,
+}
+
+/// The current profile as `current` reports it and `current` events carry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct Current {
+    profile: Option<String>,
+    region: Option<String>,
+}
+
+type Output = Arc<Mutex<Box<dyn Write + Send>>>;
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let configs = ConfigCache::new(context.config_paths.clone());
+    match matches.get_one::<String>("transport").map(String::as_str) {
+        Some("socket") => serve_socket(context, &configs),
+        _ => serve(
+            context,
+            &configs,
+            std::io::stdin().lock(),
+            Arc::new(Mutex::new(Box::new(std::io::stdout()))),
+        ),
+    }
+}
+
+/// Answers requests from `input` until it ends.
+fn serve(
+    context: &Context,
+    configs: &ConfigCache,
+    input: impl BufRead,
+    output: Output,
+) -> Result<()> {
+    let mut subscribed = false;
+
+    for line in input.lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let response = match serde_json::from_str::<Request>(&line) {
+            Ok(request) => {
+                if request.method == "subscribe" && !subscribed {
+                    subscribed = true;
+                    subscribe(context.config_paths.clone(), Arc::clone(&output));
+                }
+                response(request.id.clone(), handle(context, configs, &request))
+            }
+            Err(e) => response(Value::Null, Err(anyhow::anyhow!("Invalid request: {e}"))),
+        };
+        send(&output, &response)?;
+    }
+    Ok(())
+}
+
+/// Answers each connection to the daemon's socket on its own thread.
+#[cfg(unix)]
+fn serve_socket(context: &Context, configs: &ConfigCache) -> Result<()> {
+    use anyhow::Context as _;
+    use std::io::BufReader;
+    use std::os::unix::fs::PermissionsExt;
+    use std::os::unix::net::{UnixListener, UnixStream};
+
+    let path = crate::daemon::socket_path()?;
+    if UnixStream::connect(&path).is_ok() {
+        return Err(anyhow::anyhow!(
+            "A daemon is already listening on {}",
+            path.display()
+        ));
+    }
+    // Left behind by a daemon that didn't get to clean up.
+    let _ = std::fs::remove_file(&path);
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+    let listener = UnixListener::bind(&path)
+        .with_context(|| format!("Failed to listen on {}", path.display()))?;
+    std::fs::set_permissions(&path, std::fs::Permissions::from_mode(0o600))?;
+    eprintln!("Listening on {}", path.display());
+
+    std::thread::scope(|scope| {
+        for stream in listener.incoming() {
+            let Ok(stream) = stream else {
+                continue;
+            };
+            scope.spawn(move || {
+                let Ok(reader) = stream.try_clone() else {
+                    return;
+                };
+                // A client that hangs up mid-request only ends its own
+                // connection.
+                let _ = serve(
+                    context,
+                    configs,
+                    BufReader::new(reader),
+                    Arc::new(Mutex::new(Box::new(stream))),
+                );
+            });
+        }
+    });
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn serve_socket(_context: &Context, _configs: &ConfigCache) -> Result<()> {
+    Err(anyhow::anyhow!("serve socket needs Unix domain sockets"))
+}
+
+fn handle(context: &Context, configs: &ConfigCache, request: &Request) -> Result<Value> {
+    match request.method.as_str() {
+        "list" => list(context, configs),
+        "current" => Ok(serde_json::to_value(current()?)?),
+        "activate" => activate(context, configs, &request.params),
+        "segment" => segment(context, configs, &request.params),
+        "subscribe" => Ok(Value::Bool(true)),
+        method => Err(anyhow::anyhow!("Unknown method '{method}'")),
+    }
+}
+
+/// The profiles as `list --output json` prints them.
+fn list(context: &Context, configs: &ConfigCache) -> Result<Value> {
+    let mut config = configs.get()?;
+    let state = context.selector_state()?;
+    sort_profiles(
+        &mut config,
+        context.settings.sort.unwrap_or(SortOrder::Name),
+        false,
+        &state,
+    );
+    let token_expiries = token_expiries(&config)?;
+    let annotations = context.annotations();
+    let last_used = state.last_used();
+    let entries: Vec<ProfileEntry> = config
+        .profiles
+        .iter()
+        .map(|profile| {
+            ProfileEntry::new(profile, &config, &annotations, &token_expiries, &last_used)
+        })
+        .collect();
+    match context.redact {
+        true => redacted(&entries),
+        false => Ok(serde_json::to_value(&entries)?),
+    }
+}
+
+fn current() -> Result<Current> {
+    let profile = active_profile()?;
+    let region = match &profile {
+        Some(profile_name) => region_override(profile_name)?,
+        None => None,
+    };
+    Ok(Current { profile, region })
+}
+
+fn activate(context: &Context, configs: &ConfigCache, params: &Params) -> Result<Value> {
+    let Some(profile_name) = &params.profile else {
+        return Err(anyhow::anyhow!("activate needs a profile"));
+    };
+    let profile_name = context
+        .selector_state()?
+        .resolve_alias(profile_name)
+        .to_string();
+    let config = configs.get()?;
+    if config.find_profile(&profile_name).is_none() {
+        return Err(Error::profile_not_found(&profile_name).into());
+    }
+    if context.settings.is_protected(&profile_name) && !params.confirmed {
+        return Err(anyhow::anyhow!(
+            "'{profile_name}' is a protected profile; send \"confirmed\": true to activate it"
+        ));
+    }
+
+    let activation = activate_quietly(context, &profile_name, params.region.as_deref())?;
+    match context.redact {
+        true => redacted(&activation),
+        false => Ok(serde_json::to_value(&activation)?),
+    }
+}
+
+/// A profile's `statusline` segment, for a `statusline` or `prompt` asking
+/// the daemon.
+fn segment(context: &Context, configs: &ConfigCache, params: &Params) -> Result<Value> {
+    let Some(profile_name) = &params.profile else {
+        return Err(anyhow::anyhow!("segment needs a profile"));
+    };
+    if params.config.as_ref() != Some(&configs.paths().config)
+        || params.credentials.as_ref() != Some(&configs.paths().credentials)
+    {
+        return Err(anyhow::anyhow!("This daemon reads other AWS config files"));
+    }
+    let format = params
+        .format
+        .as_deref()
+        .map(str::parse::<DisplayFormat>)
+        .transpose()?;
+
+    let config = configs.get().unwrap_or_default();
+    let segment = build_segment(
+        context,
+        &config,
+        profile_name,
+        format.as_ref(),
+        params.redact,
+        now(),
+    );
+    Ok(serde_json::to_value(segment)?)
+}
+
+fn response(id: Value, result: Result<Value>) -> Value {
+    match result {
+        Ok(result) => json!({ "id": id, "result": result }),
+        Err(e) => json!({ "id": id, "error": { "message": format!("{e:#}") } }),
+    }
+}
+
+fn send(output: &Output, message: &Value) -> Result<()> {
+    let mut output = output.lock().unwrap();
+    writeln!(output, "{}", serde_json::to_string(message)?)?;
+    output.flush()?;
+    Ok(())
+}
+
+/// Watches for changes in the background until the process exits, which it
+/// does when the client closes stdin.
+fn subscribe(paths: ConfigPaths, output: Output) {
+    std::thread::spawn(move || {
+        let mut last_current = current().ok();
+        let mut last_modified = paths.modified();
+        loop {
+            std::thread::sleep(POLL_INTERVAL);
+
+            let now_modified = paths.modified();
+            if now_modified != last_modified {
+                last_modified = now_modified;
+                if send(&output, &json!({ "event": "config" })).is_err() {
+                    return;
+                }
+            }
+
+            let now_current = current().ok();
+            if now_current != last_current {
+                last_current = now_current.clone();
+                let event = match now_current {
+                    Some(current) => json!({
+                        "event": "current",
+                        "profile": current.profile,
+                        "region": current.region,
+                    }),
+                    None => continue,
+                };
+                if send(&output, &event).is_err() {
+                    return;
+                }
+            }
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_requests_and_responses() {
+        let request: Request = serde_json::from_str(
+            r#"{"id": 7, "method": "activate", "params": {"profile": "prod", "confirmed": true}}"#,
+        )
+        .unwrap();
+        assert_eq!(request.method, "activate");
+        assert_eq!(request.params.profile.as_deref(), Some("prod"));
+        assert!(request.params.confirmed);
+
+        let request: Request = serde_json::from_str(r#"{"method": "list"}"#).unwrap();
+        assert_eq!(request.id, Value::Null);
+        assert_eq!(request.params.profile, None);
+
+        assert_eq!(
+            response(json!(7), Ok(json!(true))).to_string(),
+            r#"{"id":7,"result":true}"#
+        );
+        assert_eq!(
+            response(json!("a"), Err(anyhow::anyhow!("Unknown method 'x'"))).to_string(),
+            r#"{"error":{"message":"Unknown method 'x'"},"id":"a"}"#
+        );
+    }
+}
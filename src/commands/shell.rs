@@ -0,0 +1,58 @@
+use super::Context;
+use super::activate::{SelectOptions, choose_profile, confirm_protected};
+use crate::logging;
+use anyhow::{Context as _, Result};
+use clap::ArgMatches;
+use std::process::Command;
+
+/// Set in the spawned shell so prompts can show which profile it is pinned
+/// to.
+const INDICATOR_VAR: &str = "AWS_PROFILE_SELECTOR_SHELL";
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let config = context.load_config()?;
+    let profile_name = choose_profile(
+        context,
+        &config,
+        matches.get_one::<String>("profile"),
+        &SelectOptions::from_matches(matches, &context.settings),
+    )?;
+    confirm_protected(context, &profile_name)?;
+
+    if let Ok(outer) = std::env::var(INDICATOR_VAR)
+        && !logging::quiet()
+    {
+        eprintln!("Note: already inside a subshell for '{outer}'; starting a nested one");
+    }
+
+    let shell = default_shell();
+    let mut command = Command::new(&shell);
+    command
+        .env("AWS_PROFILE", &profile_name)
+        .env(INDICATOR_VAR, &profile_name);
+
+    if matches.get_flag("with-credentials") {
+        command.envs(context.credential_env(&profile_name, None)?);
+    }
+
+    eprintln!("Starting {shell} with AWS_PROFILE={profile_name} (exit the shell to return)");
+
+    let status = command
+        .status()
+        .with_context(|| format!("Failed to start shell '{shell}'"))?;
+
+    std::process::exit(status.code().unwrap_or(1));
+}
+
+fn default_shell() -> String {
+    let fallback = if cfg!(windows) {
+        std::env::var("COMSPEC").unwrap_or_else(|_| "cmd.exe".to_string())
+    } else {
+        "/bin/sh".to_string()
+    };
+
+    std::env::var("SHELL")
+        .ok()
+        .filter(|shell| !shell.is_empty())
+        .unwrap_or(fallback)
+}
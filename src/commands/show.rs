@@ -0,0 +1,67 @@
+//! `show`: everything known about one profile, as the `--tui` details pane
+//! has it: where its credentials come from, its `source_profile` chain,
+//! every attribute (secrets masked), and the values of its `sso-session`.
+
+use super::list::ProfileEntry;
+use super::{Context, output_format, profile_or_active};
+use crate::accounts::{redact, redacted};
+use crate::error::Error;
+use crate::output::{OutputFormat, print_structured};
+use crate::sso::token_expiries;
+use crate::state::SelectorState;
+use crate::tui::details_text;
+use anyhow::Result;
+use clap::ArgMatches;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+/// `show --output json`: the profile as `list` has it, and its session's
+/// attributes.
+#[derive(Serialize)]
+struct Details<'a> {
+    #[serde(flatten)]
+    profile: ProfileEntry<'a>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    sso_session_attributes: Option<BTreeMap<&'a str, &'a str>>,
+}
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let format = output_format(matches);
+    let profile_name = profile_or_active(context, matches.get_one::<String>("profile"))?;
+    let config = context.load_config()?;
+    let Some(profile) = config.find_profile(&profile_name) else {
+        return Err(Error::profile_not_found(&profile_name).into());
+    };
+
+    if format != OutputFormat::Table {
+        let annotations = context.annotations();
+        let details = Details {
+            profile: ProfileEntry::new(
+                profile,
+                &config,
+                &annotations,
+                &token_expiries(&config)?,
+                &SelectorState::load()?.last_used(),
+            ),
+            sso_session_attributes: config.sso_session_for(profile).map(|session| {
+                session
+                    .attributes
+                    .iter()
+                    .map(|(key, value)| (key.as_str(), value.as_str()))
+                    .collect()
+            }),
+        };
+        if context.redact {
+            return print_structured(&redacted(&details)?, format);
+        }
+        return print_structured(&details, format);
+    }
+
+    for line in details_text(&config, profile) {
+        match context.redact {
+            true => println!("{}", redact(&line)),
+            false => println!("{line}"),
+        }
+    }
+    Ok(())
+}
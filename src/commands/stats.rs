@@ -0,0 +1,190 @@
+//! `stats`: how often and how recently each profile was activated, from the
+//! activation history, to find the profiles nobody uses.
+
+use super::{Context, output_format};
+use crate::environment::now;
+use crate::output::{OutputFormat, print_structured, print_table};
+use crate::state::{Activation, SelectorState, Window};
+use anyhow::Result;
+use chrono::{DateTime, Local, Utc};
+use clap::ArgMatches;
+use serde::Serialize;
+use std::collections::BTreeMap;
+
+#[derive(Debug, PartialEq, Serialize)]
+struct ProfileStats {
+    profile: String,
+    /// Activations within each window, in the order the windows were given.
+    counts: Vec<WindowCount>,
+    total: usize,
+    last_used: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, PartialEq, Serialize)]
+struct WindowCount {
+    window: String,
+    activations: usize,
+}
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let windows: Vec<Window> = matches
+        .get_many::<Window>("window")
+        .expect("window has a default")
+        .copied()
+        .collect();
+    let config = context.read_config().unwrap_or_default();
+    let profile_names: Vec<&str> = config
+        .profiles
+        .iter()
+        .map(|profile| profile.name.as_str())
+        .collect();
+    let history = SelectorState::load()?.history;
+
+    let mut stats = profile_stats(&history, &profile_names, &windows, now());
+    if matches.get_flag("unused") {
+        stats.retain(|stats| {
+            stats
+                .counts
+                .first()
+                .is_some_and(|count| count.activations == 0)
+        });
+    }
+    if let Some(&top) = matches.get_one::<usize>("top") {
+        stats.truncate(top);
+    }
+
+    let format = output_format(matches);
+    if format != OutputFormat::Table {
+        return print_structured(&stats, format);
+    }
+
+    let mut headers = vec!["PROFILE".to_string()];
+    headers.extend(
+        windows
+            .iter()
+            .map(|window| format!("LAST {window}").to_uppercase()),
+    );
+    headers.extend(["TOTAL".to_string(), "LAST USED".to_string()]);
+    let rows: Vec<Vec<String>> = stats
+        .iter()
+        .map(|stats| {
+            let mut row = vec![stats.profile.clone()];
+            row.extend(
+                stats
+                    .counts
+                    .iter()
+                    .map(|count| count.activations.to_string()),
+            );
+            row.push(stats.total.to_string());
+            row.push(match stats.last_used {
+                Some(at) => at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M")
+                    .to_string(),
+                None => "never".to_string(),
+            });
+            row
+        })
+        .collect();
+    let headers: Vec<&str> = headers.iter().map(String::as_str).collect();
+    print_table(&headers, &rows);
+    Ok(())
+}
+
+/// Counts for every configured profile and every profile in the history,
+/// busiest in the first window first.
+fn profile_stats(
+    history: &[Activation],
+    profile_names: &[&str],
+    windows: &[Window],
+    now: DateTime<Utc>,
+) -> Vec<ProfileStats> {
+    let mut by_profile: BTreeMap<&str, Vec<DateTime<Utc>>> = profile_names
+        .iter()
+        .map(|&name| (name, Vec::new()))
+        .collect();
+    for activation in history {
+        by_profile
+            .entry(activation.profile.as_str())
+            .or_default()
+            .push(activation.at);
+    }
+
+    let mut stats: Vec<ProfileStats> = by_profile
+        .into_iter()
+        .map(|(profile, times)| ProfileStats {
+            profile: profile.to_string(),
+            counts: windows
+                .iter()
+                .map(|window| {
+                    let since = now - window.length();
+                    WindowCount {
+                        window: window.to_string(),
+                        activations: times.iter().filter(|&&at| at > since).count(),
+                    }
+                })
+                .collect(),
+            total: times.len(),
+            last_used: times.iter().max().copied(),
+        })
+        .collect();
+
+    // The sort is stable and `by_profile` is ordered by name, so ties stay
+    // alphabetical.
+    let in_first = |stats: &ProfileStats| stats.counts.first().map_or(0, |count| count.activations);
+    stats.sort_by(|a, b| {
+        in_first(b)
+            .cmp(&in_first(a))
+            .then(b.total.cmp(&a.total))
+            .then(b.last_used.cmp(&a.last_used))
+    });
+    stats
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeDelta;
+
+    #[test]
+    fn test_profile_stats() {
+        let now = Utc::now();
+        let activation = |profile: &str, days_ago: i64| Activation {
+            profile: profile.to_string(),
+            at: now - TimeDelta::days(days_ago),
+        };
+        let history = [
+            activation("old", 60),
+            activation("old", 45),
+            activation("old", 40),
+            activation("removed", 20),
+            activation("daily", 2),
+            activation("daily", 1),
+        ];
+        let windows = ["7d".parse().unwrap(), "4w".parse().unwrap()];
+
+        let stats = profile_stats(&history, &["daily", "old", "spare"], &windows, now);
+
+        let summary: Vec<(&str, Vec<usize>, usize)> = stats
+            .iter()
+            .map(|stats| {
+                (
+                    stats.profile.as_str(),
+                    stats.counts.iter().map(|count| count.activations).collect(),
+                    stats.total,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("daily", vec![2, 2], 2),
+                ("old", vec![0, 0], 3),
+                ("removed", vec![0, 1], 1),
+                ("spare", vec![0, 0], 0),
+            ]
+        );
+        assert_eq!(stats[0].last_used, Some(now - TimeDelta::days(1)));
+        assert_eq!(stats[3].last_used, None);
+    }
+}
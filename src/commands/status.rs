@@ -0,0 +1,272 @@
+//! `status`: every place the profile can come from, the one the AWS CLI and
+//! SDKs actually use, and where they disagree. This tool reads the nearest
+//! `.aws-profile` first, then the current-profile file; the CLI and SDKs only
+//! see the environment, which the shell integration keeps in step.
+
+use super::{Context, output_format};
+use crate::output::{OutputFormat, print_structured, print_table};
+use crate::state::{get_current_profile_path, read_current_profile, read_local_profile};
+use anyhow::Result;
+use clap::ArgMatches;
+use serde::Serialize;
+
+/// A profile named in a file.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+struct FromFile {
+    profile: String,
+    path: String,
+}
+
+/// Where the SDK's profile comes from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+enum SdkSource {
+    AwsProfile,
+    /// `AWS_DEFAULT_PROFILE`, which the AWS CLI still reads.
+    AwsDefaultProfile,
+    /// Nothing set: the `default` profile.
+    Default,
+}
+
+#[derive(Debug, Serialize)]
+struct Status {
+    env_profile: Option<String>,
+    env_default_profile: Option<String>,
+    state_file: Option<FromFile>,
+    local_file: Option<FromFile>,
+    /// `AWS_ACCESS_KEY_ID` is set, so the SDK uses it before any profile.
+    env_credentials: bool,
+    sdk_profile: String,
+    sdk_source: SdkSource,
+    /// The profile this tool acts on: the `.aws-profile`, the current-profile
+    /// file, or `AWS_PROFILE`, in that order.
+    active_profile: Option<String>,
+    mismatches: Vec<String>,
+}
+
+impl Status {
+    fn new(
+        env: impl Fn(&str) -> Option<String>,
+        state_file: Option<FromFile>,
+        local_file: Option<FromFile>,
+        known_profile: impl Fn(&str) -> bool,
+    ) -> Self {
+        let env = |name: &str| env(name).filter(|value| !value.is_empty());
+        let env_profile = env("AWS_PROFILE");
+        let env_default_profile = env("AWS_DEFAULT_PROFILE");
+        let (sdk_profile, sdk_source) = match (&env_profile, &env_default_profile) {
+            (Some(profile), _) => (profile.clone(), SdkSource::AwsProfile),
+            (None, Some(profile)) => (profile.clone(), SdkSource::AwsDefaultProfile),
+            (None, None) => ("default".to_string(), SdkSource::Default),
+        };
+        let active_profile = local_file
+            .as_ref()
+            .or(state_file.as_ref())
+            .map(|file| file.profile.clone())
+            .or_else(|| env_profile.clone());
+
+        let mut status = Self {
+            env_profile,
+            env_default_profile,
+            state_file,
+            local_file,
+            env_credentials: env("AWS_ACCESS_KEY_ID").is_some(),
+            sdk_profile,
+            sdk_source,
+            active_profile,
+            mismatches: Vec::new(),
+        };
+        status.mismatches = status.find_mismatches(known_profile);
+        status
+    }
+
+    fn find_mismatches(&self, known_profile: impl Fn(&str) -> bool) -> Vec<String> {
+        let mut mismatches = Vec::new();
+        let sdk = &self.sdk_profile;
+        let uses = match self.sdk_source {
+            SdkSource::AwsProfile => format!("AWS_PROFILE is '{sdk}'"),
+            SdkSource::AwsDefaultProfile => format!("AWS_DEFAULT_PROFILE is '{sdk}'"),
+            SdkSource::Default => "AWS_PROFILE is not set, so the SDK uses 'default'".to_string(),
+        };
+
+        if let Some(local) = &self.local_file
+            && local.profile != *sdk
+        {
+            mismatches.push(format!(
+                "{} names '{}', but {uses}; the shell hook (`init --hook`) exports it on cd",
+                local.path, local.profile
+            ));
+        } else if self.local_file.is_none()
+            && let Some(state) = &self.state_file
+            && state.profile != *sdk
+        {
+            mismatches.push(format!(
+                "The current-profile file names '{0}', but {uses}; the shell integration exports it, or run `export AWS_PROFILE={0}`",
+                state.profile
+            ));
+        }
+        if self.env_profile.is_some()
+            && let Some(ignored) = &self.env_default_profile
+            && ignored != sdk
+        {
+            mismatches.push(format!(
+                "AWS_DEFAULT_PROFILE is '{ignored}', which is ignored while AWS_PROFILE is set"
+            ));
+        }
+        if self.sdk_source != SdkSource::Default && !known_profile(sdk) {
+            mismatches.push(format!("Profile '{sdk}' is not in the AWS config"));
+        }
+        if self.env_credentials {
+            mismatches.push(format!(
+                "AWS_ACCESS_KEY_ID is set, so the SDK uses those keys and not the credentials of '{sdk}'"
+            ));
+        }
+        mismatches
+    }
+}
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let format = output_format(matches);
+    let state_file = match read_current_profile()? {
+        Some(profile) => Some(FromFile {
+            profile,
+            path: get_current_profile_path()?.display().to_string(),
+        }),
+        None => None,
+    };
+    let local_file = read_local_profile()?.map(|(profile, path)| FromFile {
+        profile,
+        path: path.display().to_string(),
+    });
+    // Without a config every profile is unknown, which doctor reports.
+    let config = context.load_config().ok();
+    let status = Status::new(
+        |name| std::env::var(name).ok(),
+        state_file,
+        local_file,
+        |name| {
+            config
+                .as_ref()
+                .is_some_and(|config| config.find_profile(name).is_some())
+        },
+    );
+
+    if format != OutputFormat::Table {
+        return print_structured(&status, format);
+    }
+
+    let row = |source: &str, profile: Option<&str>, place: Option<&str>| {
+        vec![
+            source.to_string(),
+            profile.unwrap_or("-").to_string(),
+            place.unwrap_or_default().to_string(),
+        ]
+    };
+    let from_file = |source: &str, file: &Option<FromFile>| {
+        row(
+            source,
+            file.as_ref().map(|file| file.profile.as_str()),
+            file.as_ref().map(|file| file.path.as_str()),
+        )
+    };
+    let rows = vec![
+        row(
+            "AWS_PROFILE",
+            status.env_profile.as_deref(),
+            Some("environment"),
+        ),
+        row(
+            "AWS_DEFAULT_PROFILE",
+            status.env_default_profile.as_deref(),
+            Some("environment"),
+        ),
+        from_file("current-profile", &status.state_file),
+        from_file(".aws-profile", &status.local_file),
+    ];
+    print_table(&["SOURCE", "PROFILE", "WHERE"], &rows);
+    println!();
+    let source = match status.sdk_source {
+        SdkSource::AwsProfile => "from AWS_PROFILE",
+        SdkSource::AwsDefaultProfile => "from AWS_DEFAULT_PROFILE",
+        SdkSource::Default => "nothing set",
+    };
+    println!("AWS CLI and SDKs use: {} ({source})", status.sdk_profile);
+    if let Some(active) = &status.active_profile {
+        println!("This tool acts on: {active}");
+    }
+    for mismatch in &status.mismatches {
+        println!("warning: {mismatch}");
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn file(profile: &str, path: &str) -> Option<FromFile> {
+        Some(FromFile {
+            profile: profile.to_string(),
+            path: path.to_string(),
+        })
+    }
+
+    fn status(env: &[(&str, &str)], state: Option<FromFile>, local: Option<FromFile>) -> Status {
+        Status::new(
+            |name| {
+                env.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            },
+            state,
+            local,
+            |name| ["dev", "prod", "default"].contains(&name),
+        )
+    }
+
+    #[test]
+    fn test_status_mismatches() {
+        let agreed = status(&[("AWS_PROFILE", "dev")], file("dev", "/state"), None);
+        assert_eq!(agreed.sdk_source, SdkSource::AwsProfile);
+        assert!(agreed.mismatches.is_empty());
+
+        let stale = status(&[("AWS_PROFILE", "dev")], file("prod", "/state"), None);
+        assert_eq!(stale.active_profile.as_deref(), Some("prod"));
+        assert_eq!(stale.mismatches.len(), 1);
+        assert!(
+            stale.mismatches[0]
+                .starts_with("The current-profile file names 'prod', but AWS_PROFILE is 'dev'")
+        );
+
+        let unset = status(&[], None, file("prod", "/repo/.aws-profile"));
+        assert_eq!(unset.sdk_profile, "default");
+        assert_eq!(
+            unset.mismatches,
+            [
+                "/repo/.aws-profile names 'prod', but AWS_PROFILE is not set, so the SDK uses 'default'; the shell hook (`init --hook`) exports it on cd"
+            ]
+        );
+
+        let keys = status(
+            &[
+                ("AWS_PROFILE", "gone"),
+                ("AWS_DEFAULT_PROFILE", "dev"),
+                ("AWS_ACCESS_KEY_ID", "AKIA"),
+            ],
+            None,
+            None,
+        );
+        assert_eq!(
+            keys.mismatches,
+            [
+                "AWS_DEFAULT_PROFILE is 'dev', which is ignored while AWS_PROFILE is set",
+                "Profile 'gone' is not in the AWS config",
+                "AWS_ACCESS_KEY_ID is set, so the SDK uses those keys and not the credentials of 'gone'",
+            ]
+        );
+
+        let fallback = status(&[("AWS_DEFAULT_PROFILE", "dev")], None, None);
+        assert_eq!(fallback.sdk_profile, "dev");
+        assert_eq!(fallback.sdk_source, SdkSource::AwsDefaultProfile);
+    }
+}
@@ -0,0 +1,227 @@
+//! `statusline` and `prompt`: the active profile in one short line for tmux
+//! and shell prompts, which call it every few seconds.
+
+use super::Context;
+use crate::accounts::{self, account_name};
+use crate::cache::Cache;
+use crate::check::{CheckResult, last_result};
+use crate::config::AwsConfig;
+use crate::daemon;
+use crate::environment::now;
+use crate::mfa::{requires_mfa, session_expiry};
+use crate::shell::Shell;
+use crate::sso::{SsoTarget, read_cached_token, time_left};
+use crate::state::active_profile;
+use crate::template::DisplayFormat;
+use crate::theme::no_color_env;
+use anyhow::Result;
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::ArgMatches;
+use serde::{Deserialize, Serialize};
+
+/// Segments are rebuilt at most this often.
+const CACHE_NAME: &str = "statusline";
+const CACHE_TTL: TimeDelta = TimeDelta::seconds(10);
+
+/// Tokens with less than this left are shown in yellow.
+const EXPIRY_WARNING: TimeDelta = TimeDelta::hours(1);
+
+/// The active profile as the status line and prompt show it.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub(super) struct Segment {
+    text: String,
+    /// How usable the credentials are, from what is on disk: the time left
+    /// on the SSO token or MFA session (`3h 12m`, `expired`), or else `✓` or
+    /// `✗` from the last check. `None` when nothing is known.
+    badge: Option<(Health, String)>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Health {
+    Good,
+    Expiring,
+    Expired,
+}
+
+impl Health {
+    fn tmux_color(self) -> &'static str {
+        match self {
+            Health::Good => "green",
+            Health::Expiring => "yellow",
+            Health::Expired => "red",
+        }
+    }
+
+    fn ansi_color(self) -> &'static str {
+        match self {
+            Health::Good => "32",
+            Health::Expiring => "33",
+            Health::Expired => "31",
+        }
+    }
+}
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let Some(segment) = active_segment(context, matches)? else {
+        return Ok(());
+    };
+
+    match &segment.badge {
+        Some((health, remaining)) => println!(
+            "#[fg={}]{} {remaining}#[default]",
+            health.tmux_color(),
+            escape_tmux(&segment.text)
+        ),
+        None => println!("{}", escape_tmux(&segment.text)),
+    }
+    Ok(())
+}
+
+/// `prompt`: the same segment with ANSI colors, or plain text.
+pub fn run_prompt(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let Some(segment) = active_segment(context, matches)? else {
+        return Ok(());
+    };
+    let (text, badge) = (segment.text, segment.badge);
+
+    if matches.get_flag("plain") || no_color_env() {
+        match badge {
+            Some((_, remaining)) => println!("{text} {remaining}"),
+            None => println!("{text}"),
+        }
+        return Ok(());
+    }
+
+    // Shells count prompt width themselves, so the escape codes have to be
+    // marked as taking no space.
+    // Detection is left out: tmux wants no markers at all.
+    let (open, close) = match matches.get_one::<Shell>("output-shell") {
+        Some(Shell::Bash) => ("\\[", "\\]"),
+        Some(Shell::Zsh) => ("%{", "%}"),
+        _ => ("", ""),
+    };
+    let color = |code: &str| format!("{open}\x1b[{code}m{close}");
+    match badge {
+        Some((health, remaining)) => println!(
+            "{}{text} {remaining}{}",
+            color(health.ansi_color()),
+            color("0")
+        ),
+        None => println!("{}{text}{}", color("1"), color("0")),
+    }
+    Ok(())
+}
+
+/// The active profile's segment, from the daemon when one is running and
+/// otherwise from the cache while it is fresh. `None` when no profile is
+/// active, so the segment disappears.
+fn active_segment(context: &Context, matches: &ArgMatches) -> Result<Option<Segment>> {
+    let Some(profile_name) = active_profile()? else {
+        return Ok(None);
+    };
+    let format = matches
+        .get_one::<DisplayFormat>("format")
+        .or(context.settings.statusline_format.as_ref());
+
+    if let Some(segment) = daemon::request(
+        "segment",
+        serde_json::json!({
+            "profile": profile_name,
+            "format": format.map(DisplayFormat::template),
+            "redact": context.redact,
+            "config": context.config_paths.config,
+            "credentials": context.config_paths.credentials,
+        }),
+    )
+    .and_then(|segment| serde_json::from_value(segment).ok())
+    {
+        tracing::debug!("The daemon answered for '{profile_name}'");
+        return Ok(Some(segment));
+    }
+
+    let now = now();
+    let key = format!("{profile_name} {format:?} {}", context.redact);
+    let mut cache: Cache<Segment> = Cache::load(CACHE_NAME, CACHE_TTL)?;
+    if let Some(segment) = cache.get(&key, now) {
+        return Ok(Some(segment.clone()));
+    }
+
+    let config = context.read_config().unwrap_or_default();
+    let segment = build_segment(context, &config, &profile_name, format, context.redact, now);
+    cache.insert(&key, segment.clone(), now);
+    // A read-only home shouldn't blank the status bar.
+    let _ = cache.save(now);
+    Ok(Some(segment))
+}
+
+pub(super) fn build_segment(
+    context: &Context,
+    config: &AwsConfig,
+    profile_name: &str,
+    format: Option<&DisplayFormat>,
+    redact: bool,
+    now: DateTime<Utc>,
+) -> Segment {
+    let Some(profile) = config.find_profile(profile_name) else {
+        return Segment {
+            text: profile_name.to_string(),
+            badge: None,
+        };
+    };
+    let account_name = account_name(&context.settings.accounts, profile);
+
+    let mut text = match format {
+        Some(format) => format.render(profile, config, &[], account_name, None),
+        None => match account_name {
+            Some(account_name) => format!("{profile_name} {account_name}"),
+            None => profile_name.to_string(),
+        },
+    };
+    if redact {
+        text = accounts::redact(&text);
+    }
+
+    let expiry = |expires_at: Option<DateTime<Utc>>, missing: &str| match expires_at {
+        Some(expires_at) if expires_at - now > EXPIRY_WARNING => {
+            (Health::Good, time_left(expires_at, now))
+        }
+        Some(expires_at) if expires_at > now => (Health::Expiring, time_left(expires_at, now)),
+        Some(_) => (Health::Expired, "expired".to_string()),
+        None => (Health::Expired, missing.to_string()),
+    };
+    let badge = if let Some(target) = SsoTarget::for_profile(config, profile) {
+        let expires_at = read_cached_token(&target)
+            .ok()
+            .flatten()
+            .map(|token| token.expires_at);
+        Some(expiry(expires_at, "logged out"))
+    } else if requires_mfa(profile) {
+        Some(expiry(
+            session_expiry(profile_name).ok().flatten(),
+            "needs MFA",
+        ))
+    } else {
+        last_result(profile_name).map(|result| match result {
+            CheckResult::Ok { .. } => (Health::Good, "✓".to_string()),
+            _ => (Health::Expired, "✗".to_string()),
+        })
+    };
+
+    Segment { text, badge }
+}
+
+/// Doubles `#`, which tmux would otherwise read as the start of a format.
+fn escape_tmux(text: &str) -> String {
+    text.replace('#', "##")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_escape_tmux_formats() {
+        assert_eq!(escape_tmux("team#1 #[fg=red]"), "team##1 ##[fg=red]");
+    }
+}
@@ -0,0 +1,136 @@
+use super::{Context, check_options, output_format, profile_or_active};
+use crate::accounts::{describe_account, redact, redacted};
+use crate::aws::{block_on, get_caller_identity};
+use crate::check::{CheckResult, check_in_order, record_identity};
+use crate::error::EXIT_FAILURE;
+use crate::mfa::load_sdk_config;
+use crate::output::{OutputFormat, print_structured, print_table};
+use anyhow::Result;
+use clap::ArgMatches;
+use serde::Serialize;
+
+#[derive(Serialize)]
+struct Identity {
+    profile: String,
+    account: String,
+    /// From the settings file's `[accounts]` table.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account_name: Option<String>,
+    arn: String,
+    user_id: String,
+}
+
+/// One row of `whoami --all`.
+#[derive(Serialize)]
+struct Report {
+    profile: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    account_name: Option<String>,
+    #[serde(flatten)]
+    result: CheckResult,
+}
+
+pub fn run(context: &Context, matches: &ArgMatches) -> Result<()> {
+    let format = output_format(matches);
+    if matches.get_flag("all") {
+        return all(context, matches, format);
+    }
+    let profile_name = profile_or_active(context, matches.get_one::<String>("profile"))?;
+
+    let sdk_config = load_sdk_config(&context.config_paths, &profile_name)?;
+    let caller_identity = block_on(get_caller_identity(&sdk_config))??;
+    record_identity(&profile_name, &caller_identity)?;
+
+    let identity = Identity {
+        profile: profile_name,
+        account_name: context
+            .settings
+            .accounts
+            .get(&caller_identity.account)
+            .cloned(),
+        account: caller_identity.account,
+        arn: caller_identity.arn,
+        user_id: caller_identity.user_id,
+    };
+
+    if format != OutputFormat::Table {
+        if context.redact {
+            return print_structured(&redacted(&identity)?, format);
+        }
+        return print_structured(&identity, format);
+    }
+
+    let mut row = vec![
+        identity.profile,
+        describe_account(&identity.account, identity.account_name.as_deref()),
+        identity.arn,
+        identity.user_id,
+    ];
+    if context.redact {
+        row = row.iter().map(|cell| redact(cell)).collect();
+    }
+    print_table(&["PROFILE", "ACCOUNT", "ARN", "USER ID"], &[row]);
+    Ok(())
+}
+
+/// `--all`: every profile's identity, checked concurrently and reported in
+/// config order.
+fn all(context: &Context, matches: &ArgMatches, format: OutputFormat) -> Result<()> {
+    let config = context.load_config()?;
+    let profile_names: Vec<String> = config
+        .profiles
+        .iter()
+        .map(|profile| profile.name.clone())
+        .collect();
+    let reports: Vec<Report> = check_in_order(
+        &context.config_paths,
+        &profile_names,
+        check_options(matches),
+    )?
+    .into_iter()
+    .map(|(profile, result)| Report {
+        account_name: match &result {
+            CheckResult::Ok { account, .. } => context.settings.accounts.get(account).cloned(),
+            _ => None,
+        },
+        profile,
+        result,
+    })
+    .collect();
+    let failed = reports.iter().any(|report| !report.result.is_ok());
+
+    if format != OutputFormat::Table {
+        match context.redact {
+            true => print_structured(&redacted(&reports)?, format)?,
+            false => print_structured(&reports, format)?,
+        }
+    } else {
+        let rows: Vec<Vec<String>> = reports
+            .iter()
+            .map(|report| {
+                let (account, arn) = match &report.result {
+                    CheckResult::Ok { account, arn } => (
+                        describe_account(account, report.account_name.as_deref()),
+                        arn.clone(),
+                    ),
+                    _ => ("-".to_string(), "-".to_string()),
+                };
+                let status = match report.result.is_ok() {
+                    true => report.result.symbol().to_string(),
+                    false => format!("{} {}", report.result.symbol(), report.result.detail()),
+                };
+                let row = vec![report.profile.clone(), account, arn, status];
+                match context.redact {
+                    true => row.iter().map(|cell| redact(cell)).collect(),
+                    false => row,
+                }
+            })
+            .collect();
+        print_table(&["PROFILE", "ACCOUNT", "ARN", "STATUS"], &rows);
+    }
+
+    if failed {
+        std::process::exit(EXIT_FAILURE);
+    }
+    Ok(())
+}
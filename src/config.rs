@@ -1,13 +1,326 @@
+use crate::environment::Environment;
+use crate::error::Error;
 use anyhow::{Context, Result};
-use regex::Regex;
+use serde::Serialize;
 use std::collections::HashMap;
+use std::fmt;
 use std::fs;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+mod merge;
+
+pub(crate) use merge::credentials_file_wins;
+use merge::merge_profiles;
+
+/// Attributes whose values are never printed.
+pub const SECRET_ATTRIBUTES: &[&str] = &["aws_secret_access_key", "aws_session_token"];
+
+/// Masks the values of secret keys in raw INI text before it is shown.
+/// Lines of a diff, `+ key = value`, are masked too.
+pub(crate) fn mask_secrets(text: &str) -> String {
+    text.lines()
+        .map(|line| match line.split_once('=') {
+            Some((key, _))
+                if SECRET_ATTRIBUTES
+                    .contains(&key.trim().trim_start_matches(['+', '-']).trim_start()) =>
+            {
+                format!("{key}= ********")
+            }
+            _ => line.to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The section header syntax a profile was declared with, so it can be
+/// written back the same way.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SectionStyle {
+    /// `[profile name]`, the form the config file uses for named profiles.
+    Prefixed,
+    /// `[name]`, used for `[default]` in the config file and for every
+    /// section in the credentials file.
+    Bare,
+}
 
 #[derive(Debug, Clone)]
 pub struct Profile {
     pub name: String,
     pub attributes: HashMap<String, String>,
+    /// Nested settings such as `s3 =` followed by indented key/value lines,
+    /// keyed by the subsection name.
+    pub subsections: HashMap<String, HashMap<String, String>>,
+    pub section_style: SectionStyle,
+}
+
+/// How a profile gets its credentials, judged from its attributes in the
+/// order the AWS CLI's credential providers check them.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum CredentialType {
+    /// `role_arn` with `web_identity_token_file`.
+    WebIdentity,
+    /// `role_arn` with `source_profile` or `credential_source`.
+    AssumeRole,
+    /// IAM Identity Center, inline or through an `sso_session`.
+    Sso,
+    CredentialProcess,
+    /// `aws_access_key_id`, with or without a session token.
+    StaticKeys,
+}
+
+impl CredentialType {
+    /// A short tag for the selector and `list`.
+    pub fn badge(&self) -> &'static str {
+        match self {
+            Self::WebIdentity => "web",
+            Self::AssumeRole => "role",
+            Self::Sso => "sso",
+            Self::CredentialProcess => "proc",
+            Self::StaticKeys => "keys",
+        }
+    }
+}
+
+/// The profiles a role profile's credentials pass through, from the one
+/// supplying the base credentials to the profile itself.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RoleChain {
+    pub profiles: Vec<String>,
+    /// Why the chain stops short of a profile with credentials of its own.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub problem: Option<ChainProblem>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ChainProblem {
+    /// The first profile in the chain doesn't exist.
+    MissingSource,
+    /// The chain leads back to a profile already in it, which is repeated
+    /// at the start.
+    Cycle,
+}
+
+impl RoleChain {
+    /// What's wrong with the chain, as `doctor` reports it.
+    pub fn problem_description(&self) -> Option<String> {
+        let first = &self.profiles[0];
+        let direct = self.profiles.len() == 2;
+
+        Some(match self.problem? {
+            ChainProblem::MissingSource if direct => {
+                format!("source_profile '{first}' does not exist")
+            }
+            ChainProblem::MissingSource => {
+                format!("source_profile chain {self} reaches '{first}', which does not exist")
+            }
+            ChainProblem::Cycle if direct => "source_profile refers to itself".to_string(),
+            ChainProblem::Cycle => format!("source_profile chain {self} loops at '{first}'"),
+        })
+    }
+}
+
+/// `base → security-audit → target-admin`, with the first profile marked
+/// `(missing)` or `(cycle)` when the chain is broken there.
+impl fmt::Display for RoleChain {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.profiles.join(" → ");
+        let mark = match self.problem {
+            Some(ChainProblem::MissingSource) => " (missing)",
+            Some(ChainProblem::Cycle) => " (cycle)",
+            None => return f.write_str(&path),
+        };
+        let (first, rest) = path.split_at(self.profiles[0].len());
+        write!(f, "{first}{mark}{rest}")
+    }
+}
+
+/// A shared `[sso-session name]` block referenced by profiles through their
+/// `sso_session` attribute.
+#[derive(Debug, Clone)]
+pub struct SsoSession {
+    pub name: String,
+    pub attributes: HashMap<String, String>,
+}
+
+impl SsoSession {
+    pub fn get_start_url(&self) -> Option<&str> {
+        self.attributes.get("sso_start_url").map(String::as_str)
+    }
+
+    pub fn get_region(&self) -> Option<&str> {
+        self.attributes.get("sso_region").map(String::as_str)
+    }
+}
+
+/// A `[services name]` block of per-service settings, such as the
+/// `endpoint_url`s of a LocalStack, referenced by profiles through their
+/// `services` attribute.
+#[derive(Debug, Clone)]
+pub struct Services {
+    pub name: String,
+    /// Settings per service, keyed by the service's config name (`s3`,
+    /// `dynamodb`).
+    pub services: HashMap<String, HashMap<String, String>>,
+}
+
+/// An `endpoint_url` a profile sends requests to instead of AWS's own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EndpointOverride {
+    /// The service it applies to, from a `[services]` block; `None` for the
+    /// profile's own `endpoint_url`, which applies to all of them.
+    pub service: Option<String>,
+    pub url: String,
+}
+
+/// Everything parsed from the AWS config and credentials files.
+#[derive(Debug, Clone, Default)]
+pub struct AwsConfig {
+    pub profiles: Vec<Profile>,
+    pub sso_sessions: Vec<SsoSession>,
+    pub services: Vec<Services>,
+}
+
+impl AwsConfig {
+    pub fn find_profile(&self, name: &str) -> Option<&Profile> {
+        self.profiles.iter().find(|profile| profile.name == name)
+    }
+
+    /// Looks up the `[sso-session]` block a profile refers to, if any.
+    pub fn sso_session_for(&self, profile: &Profile) -> Option<&SsoSession> {
+        let session_name = profile.get_sso_session()?;
+        self.sso_sessions
+            .iter()
+            .find(|session| session.name == session_name)
+    }
+
+    /// Looks up the `[services]` block a profile refers to, if any.
+    pub fn services_for(&self, profile: &Profile) -> Option<&Services> {
+        let name = profile.attributes.get("services")?;
+        self.services.iter().find(|services| services.name == *name)
+    }
+
+    /// The profile's own `endpoint_url` followed by those of its
+    /// `[services]` block by service; none when the profile sets
+    /// `ignore_configured_endpoint_urls`.
+    pub fn endpoint_overrides(&self, profile: &Profile) -> Vec<EndpointOverride> {
+        if profile
+            .attributes
+            .get("ignore_configured_endpoint_urls")
+            .is_some_and(|value| value.eq_ignore_ascii_case("true"))
+        {
+            return Vec::new();
+        }
+        let mut overrides: Vec<EndpointOverride> = profile
+            .attributes
+            .get("endpoint_url")
+            .map(|url| EndpointOverride {
+                service: None,
+                url: url.clone(),
+            })
+            .into_iter()
+            .collect();
+        if let Some(services) = self.services_for(profile) {
+            let mut by_service: Vec<EndpointOverride> = services
+                .services
+                .iter()
+                .filter_map(|(service, settings)| {
+                    Some(EndpointOverride {
+                        service: Some(service.clone()),
+                        url: settings.get("endpoint_url")?.clone(),
+                    })
+                })
+                .collect();
+            by_service.sort_by(|a, b| a.service.cmp(&b.service));
+            overrides.extend(by_service);
+        }
+        overrides
+    }
+
+    /// The SSO start URL for a profile, whether it is declared inline
+    /// (legacy format) or through a referenced sso-session.
+    pub fn sso_start_url_for<'a>(&'a self, profile: &'a Profile) -> Option<&'a str> {
+        profile
+            .attributes
+            .get("sso_start_url")
+            .map(String::as_str)
+            .or_else(|| self.sso_session_for(profile)?.get_start_url())
+    }
+
+    /// Each profile's `source_profile`, by profile name.
+    pub fn source_graph(&self) -> HashMap<&str, &str> {
+        self.profiles
+            .iter()
+            .filter_map(|profile| {
+                let source = profile.attributes.get("source_profile")?;
+                Some((profile.name.as_str(), source.as_str()))
+            })
+            .collect()
+    }
+
+    /// Follows `source_profile` links back from a profile; `None` when it has
+    /// no `source_profile`.
+    pub fn role_chain(&self, profile_name: &str) -> Option<RoleChain> {
+        let graph = self.source_graph();
+        graph.get(profile_name)?;
+
+        let mut profiles = vec![profile_name.to_string()];
+        let mut current = profile_name;
+        let problem = loop {
+            let Some(&source) = graph.get(current) else {
+                break None;
+            };
+            let seen = profiles.iter().any(|name| name == source);
+            profiles.push(source.to_string());
+            if seen {
+                break Some(ChainProblem::Cycle);
+            }
+            if self.find_profile(source).is_none() {
+                break Some(ChainProblem::MissingSource);
+            }
+            current = source;
+        };
+
+        profiles.reverse();
+        Some(RoleChain { profiles, problem })
+    }
+
+    /// [`Profile::credential_problem`] for the profile or, for a role, the
+    /// profile its `source_profile` chain starts from.
+    pub fn credential_problem(&self, profile_name: &str) -> Option<String> {
+        let base = self
+            .role_chain(profile_name)
+            .map_or(profile_name.to_string(), |chain| chain.profiles[0].clone());
+        let problem = self.find_profile(&base)?.credential_problem()?;
+        Some(match base == profile_name {
+            true => problem,
+            false => format!("{problem} (for its source profile '{base}')"),
+        })
+    }
+
+    /// Profiles whose `source_profile` chain never reaches credentials.
+    pub fn broken_chains(&self) -> Vec<(&str, RoleChain)> {
+        self.profiles
+            .iter()
+            .filter_map(|profile| {
+                let chain = self.role_chain(&profile.name)?;
+                chain.problem.map(|_| (profile.name.as_str(), chain))
+            })
+            .collect()
+    }
+
+    /// The SSO region for a profile, whether it is declared inline (legacy
+    /// format) or through a referenced sso-session.
+    pub fn sso_region_for<'a>(&'a self, profile: &'a Profile) -> Option<&'a str> {
+        profile
+            .attributes
+            .get("sso_region")
+            .map(String::as_str)
+            .or_else(|| self.sso_session_for(profile)?.get_region())
+    }
 }
 
 impl Profile {
@@ -22,82 +335,611 @@ impl Profile {
     pub fn get_role_name(&self) -> Option<&str> {
         self.attributes.get("sso_role_name").map(String::as_str)
     }
+
+    /// The account ID and role name in `role_arn`
+    /// (`arn:aws:iam::123456789012:role/path/Name`).
+    pub fn role_arn_parts(&self) -> Option<(&str, &str)> {
+        let arn = self.attributes.get("role_arn")?;
+        let mut parts = arn.splitn(6, ':');
+        let (Some("arn"), _, Some("iam"), _, Some(account_id), Some(resource)) = (
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+            parts.next(),
+        ) else {
+            return None;
+        };
+        let role = resource.strip_prefix("role/")?.rsplit('/').next()?;
+        (!account_id.is_empty() && !role.is_empty()).then_some((account_id, role))
+    }
+
+    pub fn get_sso_session(&self) -> Option<&str> {
+        self.attributes.get("sso_session").map(String::as_str)
+    }
+
+    /// `None` when the profile configures no credentials of its own, e.g. a
+    /// `[default]` that only sets a region.
+    pub fn credential_type(&self) -> Option<CredentialType> {
+        let has = |key: &str| self.attributes.contains_key(key);
+
+        if has("role_arn") && has("web_identity_token_file") {
+            Some(CredentialType::WebIdentity)
+        } else if has("role_arn") {
+            Some(CredentialType::AssumeRole)
+        } else if has("sso_session") || has("sso_start_url") {
+            Some(CredentialType::Sso)
+        } else if has("credential_process") {
+            Some(CredentialType::CredentialProcess)
+        } else if has("aws_access_key_id") {
+            Some(CredentialType::StaticKeys)
+        } else {
+            None
+        }
+    }
+
+    /// Why the profile's web identity token or `credential_process` can't
+    /// work on this machine: the SDK only reports "No such file or
+    /// directory" for a token file that isn't there, and the shell's "not
+    /// found" for a missing program goes to stderr.
+    pub fn credential_problem(&self) -> Option<String> {
+        match self.credential_type()? {
+            CredentialType::WebIdentity => {
+                let file = &self.attributes["web_identity_token_file"];
+                (!Path::new(file).is_file())
+                    .then(|| format!("web_identity_token_file {file} doesn't exist"))
+            }
+            CredentialType::CredentialProcess => {
+                match command_program(&self.attributes["credential_process"]) {
+                    None => Some("credential_process is empty".to_string()),
+                    Some(program) if !program_exists(program) => Some(format!(
+                        "credential_process runs {program}, which isn't installed"
+                    )),
+                    Some(_) => None,
+                }
+            }
+            _ => None,
+        }
+    }
+
+    /// The header the profile's section was declared with, e.g.
+    /// `profile dev` in the config file or `dev` in the credentials file.
+    pub fn section_header(&self) -> String {
+        match self.section_style {
+            SectionStyle::Prefixed => format!("profile {}", self.name),
+            SectionStyle::Bare => self.name.clone(),
+        }
+    }
+}
+
+/// The program a shell command line starts with, unquoted.
+pub(crate) fn command_program(command: &str) -> Option<&str> {
+    let command = command.trim_start();
+    let program = match command.chars().next()? {
+        quote @ ('"' | '\'') => command[1..].split(quote).next()?,
+        _ => command.split_whitespace().next()?,
+    };
+    (!program.is_empty()).then_some(program)
+}
+
+/// Whether `program` is a file, when it's a path, or on `PATH`.
+fn program_exists(program: &str) -> bool {
+    if program.contains(['/', '\\']) {
+        return Path::new(program).is_file();
+    }
+    std::env::var_os("PATH").is_some_and(|path| {
+        std::env::split_paths(&path).any(|dir| {
+            dir.join(program).is_file()
+                || (cfg!(windows) && dir.join(format!("{program}.exe")).is_file())
+        })
+    })
+}
+
+/// The header of a profile's section in the config file; `default` is the
+/// only profile written without the `profile` prefix.
+pub fn config_section_header(profile_name: &str) -> String {
+    if profile_name == "default" {
+        profile_name.to_string()
+    } else {
+        format!("profile {profile_name}")
+    }
+}
+
+/// Locations of the AWS config and shared credentials files.
+#[derive(Debug, Clone)]
+pub struct ConfigPaths {
+    pub config: PathBuf,
+    pub credentials: PathBuf,
+}
+
+impl ConfigPaths {
+    /// Resolves each path from an explicit override, then the standard
+    /// `AWS_CONFIG_FILE` / `AWS_SHARED_CREDENTIALS_FILE` variables, then the
+    /// `~/.aws` defaults.
+    pub fn resolve(
+        config_override: Option<PathBuf>,
+        credentials_override: Option<PathBuf>,
+    ) -> Result<Self> {
+        let env = Environment::current()?;
+        let config = config_override.unwrap_or_else(|| env.config_file());
+        let credentials = credentials_override.unwrap_or_else(|| env.credentials_file());
+
+        tracing::debug!("Reading {} and {}", config.display(), credentials.display());
+        Ok(Self {
+            config,
+            credentials,
+        })
+    }
+
+    /// When the config and credentials files were last changed, `None` for
+    /// one that is missing.
+    pub fn modified(&self) -> [Option<SystemTime>; 2] {
+        [&self.config, &self.credentials].map(|path| {
+            fs::metadata(path)
+                .and_then(|metadata| metadata.modified())
+                .ok()
+        })
+    }
+}
+
+/// The parsed files at a pair of paths, read again only when one of them
+/// changes, so the commands that look at the config several times per run
+/// (and `serve socket`, for every request) parse it once.
+#[derive(Debug)]
+pub struct ConfigCache {
+    paths: ConfigPaths,
+    loaded: Mutex<Option<([Option<SystemTime>; 2], AwsConfig)>>,
+}
+
+impl ConfigCache {
+    pub fn new(paths: ConfigPaths) -> Self {
+        Self {
+            paths,
+            loaded: Mutex::new(None),
+        }
+    }
+
+    pub fn paths(&self) -> &ConfigPaths {
+        &self.paths
+    }
+
+    /// What [`read_aws_config`] would return now.
+    pub fn get(&self) -> Result<AwsConfig> {
+        let modified = self.paths.modified();
+        let mut loaded = self.loaded.lock().unwrap();
+        match &*loaded {
+            Some((at, config)) if *at == modified => Ok(config.clone()),
+            _ => {
+                let config = read_aws_config(&self.paths).map_err(Error::config)?;
+                *loaded = Some((modified, config.clone()));
+                Ok(config)
+            }
+        }
+    }
 }
 
-pub fn read_aws_config() -> Result<Vec<Profile>> {
-    let config_path = get_aws_config_path()?;
+/// The home directory the way the AWS SDKs find it, so `~/.aws` is the same
+/// directory they read: `HOME`, then on Windows `USERPROFILE` and
+/// `HOMEDRIVE` plus `HOMEPATH`, then the OS's own answer.
+pub fn home_dir() -> Result<PathBuf> {
+    sdk_home_dir(|var| std::env::var(var).ok(), cfg!(windows))
+        .or_else(dirs::home_dir)
+        .ok_or_else(|| anyhow::anyhow!("Unable to determine home directory"))
+}
+
+fn sdk_home_dir(var: impl Fn(&str) -> Option<String>, windows: bool) -> Option<PathBuf> {
+    let var = |name: &str| var(name).filter(|value| !value.is_empty());
+    if let Some(home) = var("HOME") {
+        return Some(PathBuf::from(home));
+    }
+    if !windows {
+        return None;
+    }
+    var("USERPROFILE").map(PathBuf::from).or_else(|| {
+        let (drive, path) = (var("HOMEDRIVE")?, var("HOMEPATH")?);
+        Some(PathBuf::from(format!("{drive}{path}")))
+    })
+}
 
-    if !config_path.exists() {
+/// The AWS CLI expands a leading `~` in these variables, so we do too.
+pub(crate) fn expand_tilde(path: &str, home_dir: &Path) -> PathBuf {
+    match path.strip_prefix("~") {
+        Some("") => home_dir.to_path_buf(),
+        Some(rest) if rest.starts_with(['/', '\\']) => home_dir.join(&rest[1..]),
+        _ => PathBuf::from(path),
+    }
+}
+
+pub fn read_aws_config(paths: &ConfigPaths) -> Result<AwsConfig> {
+    let config_path = &paths.config;
+    let credentials_path = &paths.credentials;
+
+    if !config_path.exists() && !credentials_path.exists() {
         return Err(anyhow::anyhow!(
             "AWS config file not found at {:?}",
             config_path
         ));
     }
 
-    let content = fs::read_to_string(&config_path)
-        .with_context(|| format!("Failed to read AWS config file: {config_path:?}"))?;
+    let config = if config_path.exists() {
+        let content = fs::read_to_string(config_path)
+            .with_context(|| format!("Failed to read AWS config file: {config_path:?}"))?;
+        parse_aws_config(&content)?
+    } else {
+        AwsConfig::default()
+    };
+
+    let credentials_profiles = if credentials_path.exists() {
+        let content = fs::read_to_string(credentials_path).with_context(|| {
+            format!("Failed to read AWS credentials file: {credentials_path:?}")
+        })?;
+        parse_aws_credentials(&content)?
+    } else {
+        Vec::new()
+    };
 
-    parse_aws_config(&content)
+    Ok(AwsConfig {
+        profiles: merge_profiles(config.profiles, credentials_profiles),
+        sso_sessions: config.sso_sessions,
+        services: config.services,
+    })
 }
 
-fn get_aws_config_path() -> Result<PathBuf> {
-    let home_dir =
-        dirs::home_dir().ok_or_else(|| anyhow::anyhow!("Unable to determine home directory"))?;
+pub(crate) fn parse_aws_config(content: &str) -> Result<AwsConfig> {
+    let mut config = AwsConfig::default();
+
+    for section in parse_sections(content) {
+        if let Some((name, section_style)) = config_profile_name(&section.header) {
+            // `[default]` and `[profile default]`, or headers spaced
+            // differently: one profile, later values winning.
+            match config
+                .profiles
+                .iter_mut()
+                .find(|profile| profile.name == name)
+            {
+                Some(existing) => {
+                    existing.attributes.extend(section.attributes);
+                    existing.subsections.extend(section.subsections);
+                }
+                None => config.profiles.push(Profile {
+                    name,
+                    attributes: section.attributes,
+                    subsections: section.subsections,
+                    section_style,
+                }),
+            }
+        } else if let Some(name) = strip_section_prefix(&section.header, "sso-session") {
+            config.sso_sessions.push(SsoSession {
+                name,
+                attributes: section.attributes,
+            });
+        } else if let Some(name) = strip_section_prefix(&section.header, "services") {
+            config.services.push(Services {
+                name,
+                services: section.subsections,
+            });
+        }
+    }
 
-    Ok(home_dir.join(".aws").join("config"))
+    config.sso_sessions.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(config)
 }
 
-fn parse_aws_config(content: &str) -> Result<Vec<Profile>> {
-    let section_regex = Regex::new(r"^\s*\[profile\s+([^\]]+)\]")?;
-    let key_value_regex = Regex::new(r"^\s*([^=]+?)\s*=\s*(.*?)\s*$")?;
+/// The profile a config file section header names, if any.
+fn config_profile_name(header: &str) -> Option<(String, SectionStyle)> {
+    match strip_section_prefix(header, "profile") {
+        Some(name) => Some((name, SectionStyle::Prefixed)),
+        None if header == "default" => Some((header.to_string(), SectionStyle::Bare)),
+        None => None,
+    }
+}
+
+/// Sections in the credentials file are named after the profile directly,
+/// without the `profile` prefix used in the config file.
+fn parse_aws_credentials(content: &str) -> Result<Vec<Profile>> {
+    Ok(parse_sections(content)
+        .into_iter()
+        .map(|section| Profile {
+            name: section.header,
+            attributes: section.attributes,
+            subsections: section.subsections,
+            section_style: SectionStyle::Bare,
+        })
+        .collect())
+}
 
-    let mut profiles = Vec::new();
-    let mut current_profile: Option<String> = None;
-    let mut current_attributes = HashMap::new();
+/// Returns the name from a `[<prefix> name]` section header.
+fn strip_section_prefix(header: &str, prefix: &str) -> Option<String> {
+    header
+        .strip_prefix(prefix)
+        .filter(|name| name.starts_with(char::is_whitespace))
+        .map(|name| name.trim().to_string())
+}
 
-    for line in content.lines() {
-        let line = line.trim();
+/// A profile defined in more than one place, where reading the files
+/// quietly picks one of the values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProfileConflict {
+    /// More than one section of a file defines the profile; their values are
+    /// merged, later ones winning.
+    Duplicate {
+        profile: String,
+        /// In the credentials file rather than the config file.
+        credentials: bool,
+        /// The distinct headers, in file order: `[default]` next to
+        /// `[profile default]`, or one header repeated.
+        headers: Vec<String>,
+    },
+    /// Both files set `key`, to different values. The credentials file wins
+    /// for credentials, the config file for the rest.
+    Shadowed { profile: String, key: String },
+}
 
-        if line.is_empty() || line.starts_with('#') {
-            continue;
+impl ProfileConflict {
+    pub fn profile(&self) -> &str {
+        match self {
+            Self::Duplicate { profile, .. } | Self::Shadowed { profile, .. } => profile,
+        }
+    }
+}
+
+impl fmt::Display for ProfileConflict {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Duplicate {
+                credentials,
+                headers,
+                ..
+            } => {
+                let file = if *credentials {
+                    "credentials"
+                } else {
+                    "config"
+                };
+                let headers: Vec<String> =
+                    headers.iter().map(|header| format!("[{header}]")).collect();
+                match headers.as_slice() {
+                    [header] => write!(
+                        f,
+                        "{header} appears more than once in the {file} file; later values win"
+                    ),
+                    _ => write!(
+                        f,
+                        "{} in the {file} file all define it; later values win",
+                        headers.join(" and ")
+                    ),
+                }
+            }
+            Self::Shadowed { key, .. } => {
+                let winner = match credentials_file_wins(key) {
+                    true => "credentials",
+                    false => "config",
+                };
+                write!(
+                    f,
+                    "{key} differs between the config and credentials files; the {winner} file wins"
+                )
+            }
+        }
+    }
+}
+
+/// The duplicated and shadowed profiles in the two files.
+pub fn profile_conflicts(paths: &ConfigPaths) -> Result<Vec<ProfileConflict>> {
+    let read = |path: &Path| -> Result<String> {
+        match path.exists() {
+            true => fs::read_to_string(path)
+                .with_context(|| format!("Failed to read {}", path.display())),
+            false => Ok(String::new()),
         }
+    };
+    Ok(find_conflicts(
+        &read(&paths.config)?,
+        &read(&paths.credentials)?,
+    ))
+}
 
-        if let Some(captures) = section_regex.captures(line) {
-            if let Some(profile_name) = current_profile.take() {
-                profiles.push(Profile {
-                    name: profile_name,
-                    attributes: current_attributes.clone(),
+fn find_conflicts(config: &str, credentials: &str) -> Vec<ProfileConflict> {
+    let mut conflicts = Vec::new();
+    let by_name = |content: &str, credentials: bool| {
+        let mut profiles: Vec<(String, Vec<Section>)> = Vec::new();
+        for section in parse_sections(content) {
+            let name = match credentials {
+                true => Some(section.header.clone()),
+                false => config_profile_name(&section.header).map(|(name, _)| name),
+            };
+            let Some(name) = name else {
+                continue;
+            };
+            match profiles.iter_mut().find(|(known, _)| *known == name) {
+                Some((_, sections)) => sections.push(section),
+                None => profiles.push((name, vec![section])),
+            }
+        }
+        profiles
+    };
+
+    let config_profiles = by_name(config, false);
+    let credentials_profiles = by_name(credentials, true);
+    for (profiles, credentials) in [(&config_profiles, false), (&credentials_profiles, true)] {
+        for (name, sections) in profiles {
+            if sections
+                .iter()
+                .map(|section| section.occurrences)
+                .sum::<usize>()
+                > 1
+            {
+                conflicts.push(ProfileConflict::Duplicate {
+                    profile: name.clone(),
+                    credentials,
+                    headers: sections
+                        .iter()
+                        .map(|section| section.header.clone())
+                        .collect(),
                 });
-                current_attributes.clear();
             }
+        }
+    }
 
-            current_profile = Some(captures[1].trim().to_string());
+    let merged = |sections: &[Section]| -> HashMap<String, String> {
+        sections
+            .iter()
+            .flat_map(|section| section.attributes.clone())
+            .collect()
+    };
+    for (name, sections) in &credentials_profiles {
+        let Some((_, config_sections)) = config_profiles.iter().find(|(known, _)| known == name)
+        else {
             continue;
+        };
+        let config_values = merged(config_sections);
+        let mut keys: Vec<String> = merged(sections)
+            .into_iter()
+            .filter(|(key, value)| config_values.get(key).is_some_and(|other| other != value))
+            .map(|(key, _)| key)
+            .collect();
+        keys.sort();
+        conflicts.extend(keys.into_iter().map(|key| ProfileConflict::Shadowed {
+            profile: name.clone(),
+            key,
+        }));
+    }
+    conflicts
+}
+
+struct Section {
+    header: String,
+    attributes: HashMap<String, String>,
+    subsections: HashMap<String, HashMap<String, String>>,
+    /// How many times the header appears in the file.
+    occurrences: usize,
+}
+
+impl Section {
+    fn new(header: String) -> Self {
+        Self {
+            header,
+            attributes: HashMap::new(),
+            subsections: HashMap::new(),
+            occurrences: 1,
         }
+    }
+}
+
+/// Splits an INI file into sections in file order, following the AWS
+/// CLI's reading of it:
+///
+/// - `#` and `;` start a comment at the beginning of a line or after a
+///   section header, and inside a value only after whitespace, so
+///   `token = abc#1` keeps its `#`.
+/// - A value runs from the first `=` to the end of the line, so later `=`s
+///   are part of it. Quotes are kept, as the AWS CLI keeps them.
+/// - Keys are case-insensitive and stored in lowercase; section names are
+///   case-sensitive. A section that appears twice is merged, later keys
+///   winning.
+/// - An indented line after a key with a value continues that value on a
+///   new line. A key with an empty value followed by indented `key = value`
+///   lines is AWS CLI nested-subsection syntax (e.g. `s3 =` then
+///   `max_concurrent_requests = 20`); those lines are collected into
+///   `subsections` rather than flattened into the section's attributes.
+///
+/// Lines that are none of these are skipped.
+fn parse_sections(content: &str) -> Vec<Section> {
+    let mut sections: Vec<Section> = Vec::new();
+    // The last key read in the current section, and whether it opened a
+    // subsection, for the indented lines that may follow it.
+    let mut last_key: Option<(String, bool)> = None;
 
-        if current_profile.is_some() {
-            if let Some(captures) = key_value_regex.captures(line) {
-                let key = captures[1].trim().to_string();
-                let value = captures[2].trim().to_string();
-                current_attributes.insert(key, value);
+    for raw_line in content.lines() {
+        let indented = raw_line.starts_with(char::is_whitespace);
+        let line = raw_line.trim();
+        if line.is_empty() || line.starts_with(['#', ';']) {
+            continue;
+        }
+
+        if let Some(header) = section_header(line) {
+            last_key = None;
+            match sections.iter().position(|section| section.header == header) {
+                // Moved to the end, so the indented lines below land in it.
+                Some(index) => {
+                    let mut section = sections.remove(index);
+                    section.occurrences += 1;
+                    sections.push(section);
+                }
+                None => sections.push(Section::new(header.to_string())),
             }
+            continue;
+        }
+
+        let Some(section) = sections.last_mut() else {
+            continue;
+        };
+
+        if indented && let Some((key, opened_subsection)) = &last_key {
+            if *opened_subsection {
+                if let Some((sub_key, value)) = property(line) {
+                    section
+                        .subsections
+                        .entry(key.clone())
+                        .or_default()
+                        .insert(sub_key, value.to_string());
+                }
+            } else if let Some(value) = section.attributes.get_mut(key) {
+                value.push('\n');
+                value.push_str(strip_value_comment(line));
+            }
+            continue;
+        }
+
+        let Some((key, value)) = property(line) else {
+            last_key = None;
+            continue;
+        };
+        if value.is_empty() {
+            section.subsections.entry(key.clone()).or_default();
+            last_key = Some((key, true));
+        } else {
+            section.attributes.insert(key.clone(), value.to_string());
+            last_key = Some((key, false));
         }
     }
 
-    if let Some(profile_name) = current_profile {
-        profiles.push(Profile {
-            name: profile_name,
-            attributes: current_attributes,
-        });
+    sections
+}
+
+/// The name in a `[name]` line, ignoring a comment after the `]`.
+fn section_header(line: &str) -> Option<&str> {
+    let (header, rest) = line.strip_prefix('[')?.split_once(']')?;
+    let rest = rest.trim_start();
+    let header = header.trim();
+    (!header.is_empty() && (rest.is_empty() || rest.starts_with(['#', ';']))).then_some(header)
+}
+
+/// The lowercased key and the value of a `key = value` line.
+fn property(line: &str) -> Option<(String, &str)> {
+    let (key, value) = line.split_once('=')?;
+    let key = key.trim();
+    if key.is_empty() {
+        return None;
     }
+    Some((key.to_ascii_lowercase(), strip_value_comment(value)))
+}
 
-    profiles.sort_by(|a, b| a.name.cmp(&b.name));
-    Ok(profiles)
+/// `value # comment` without the comment; a `#` or `;` with no whitespace
+/// before it is part of the value.
+fn strip_value_comment(value: &str) -> &str {
+    let end = value
+        .char_indices()
+        .find(|&(index, c)| matches!(c, '#' | ';') && value[..index].ends_with(char::is_whitespace))
+        .map_or(value.len(), |(index, _)| index);
+    value[..end].trim()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::test_support::temp_dir;
 
     #[test]
     fn test_parse_aws_config() {
@@ -118,7 +960,7 @@ sso_role_name = ReadOnlyAccess
 region = us-east-1
 "#;
 
-        let profiles = parse_aws_config(config_content).unwrap();
+        let profiles = parse_aws_config(config_content).unwrap().profiles;
 
         assert_eq!(profiles.len(), 3);
         assert_eq!(profiles[0].name, "default");
@@ -128,4 +970,461 @@ region = us-east-1
         assert_eq!(profiles[1].get_account_id().unwrap(), "123456789012");
         assert_eq!(profiles[1].get_role_name().unwrap(), "DeveloperAccess");
     }
+
+    #[test]
+    fn test_parse_bare_default_section() {
+        let config_content = r#"
+[default]
+region = eu-west-1
+
+[sso-session corp]
+sso_region = eu-west-1
+
+[profile dev]
+region = us-west-2
+"#;
+
+        let profiles = parse_aws_config(config_content).unwrap().profiles;
+
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[0].name, "default");
+        assert_eq!(profiles[0].section_style, SectionStyle::Bare);
+        assert_eq!(profiles[0].get_region().unwrap(), "eu-west-1");
+        assert!(!profiles[0].attributes.contains_key("sso_region"));
+        assert_eq!(profiles[1].name, "dev");
+        assert_eq!(profiles[1].section_style, SectionStyle::Prefixed);
+    }
+
+    #[test]
+    fn test_expand_tilde() {
+        let home_dir = Path::new("/home/user");
+
+        assert_eq!(
+            expand_tilde("~/aws/config", home_dir),
+            PathBuf::from("/home/user/aws/config")
+        );
+        assert_eq!(expand_tilde("~", home_dir), PathBuf::from("/home/user"));
+        assert_eq!(
+            expand_tilde("/etc/aws/config", home_dir),
+            PathBuf::from("/etc/aws/config")
+        );
+        assert_eq!(
+            expand_tilde("~other/config", home_dir),
+            PathBuf::from("~other/config")
+        );
+    }
+
+    #[test]
+    fn test_sdk_home_dir_on_windows() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(key, _)| *key == name)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+
+        assert_eq!(
+            sdk_home_dir(
+                env(&[("HOME", "/home/user"), ("USERPROFILE", r"C:\Users\user")]),
+                true
+            ),
+            Some(PathBuf::from("/home/user"))
+        );
+        assert_eq!(
+            sdk_home_dir(
+                env(&[("HOME", ""), ("USERPROFILE", r"C:\Users\user")]),
+                true
+            ),
+            Some(PathBuf::from(r"C:\Users\user"))
+        );
+        assert_eq!(
+            sdk_home_dir(
+                env(&[("HOMEDRIVE", "D:"), ("HOMEPATH", r"\Users\user")]),
+                true
+            ),
+            Some(PathBuf::from(r"D:\Users\user"))
+        );
+        assert_eq!(
+            sdk_home_dir(env(&[("USERPROFILE", r"C:\Users\user")]), false),
+            None
+        );
+    }
+
+    #[test]
+    fn test_find_conflicts() {
+        let config = "[default]\nregion = us-east-1\n\n[profile default]\nregion = eu-west-1\n\n\
+                      [profile dev]\nregion = us-west-2\noutput = json\n";
+        let credentials = "[dev]\nregion = eu-west-1\noutput = json\n\n[ci]\n[ci]\n";
+
+        let parsed = parse_aws_config(config).unwrap();
+        assert_eq!(parsed.profiles.len(), 2);
+        assert_eq!(parsed.profiles[0].get_region(), Some("eu-west-1"));
+
+        let conflicts = find_conflicts(config, credentials);
+        assert_eq!(
+            conflicts,
+            vec![
+                ProfileConflict::Duplicate {
+                    profile: "default".to_string(),
+                    credentials: false,
+                    headers: vec!["default".to_string(), "profile default".to_string()],
+                },
+                ProfileConflict::Duplicate {
+                    profile: "ci".to_string(),
+                    credentials: true,
+                    headers: vec!["ci".to_string()],
+                },
+                ProfileConflict::Shadowed {
+                    profile: "dev".to_string(),
+                    key: "region".to_string(),
+                },
+            ]
+        );
+        assert_eq!(
+            conflicts[0].to_string(),
+            "[default] and [profile default] in the config file all define it; later values win"
+        );
+        assert_eq!(
+            conflicts[1].to_string(),
+            "[ci] appears more than once in the credentials file; later values win"
+        );
+        assert_eq!(
+            conflicts[2].to_string(),
+            "region differs between the config and credentials files; the config file wins"
+        );
+        assert!(find_conflicts("[profile a]\n", "[a]\nregion = x\n").is_empty());
+    }
+
+    #[test]
+    fn test_endpoint_overrides() {
+        let config = parse_aws_config(
+            r#"
+[profile local]
+services = localstack
+endpoint_url = http://localhost:4566
+
+[profile ignored]
+services = localstack
+ignore_configured_endpoint_urls = true
+
+[services localstack]
+sqs =
+  endpoint_url = http://localhost:4576
+dynamodb =
+  endpoint_url = http://localhost:8000
+s3 =
+  addressing_style = path
+"#,
+        )
+        .unwrap();
+
+        let local = config.find_profile("local").unwrap();
+        assert_eq!(config.services_for(local).unwrap().name, "localstack");
+        let overrides: Vec<_> = config
+            .endpoint_overrides(local)
+            .into_iter()
+            .map(|endpoint| (endpoint.service, endpoint.url))
+            .collect();
+        assert_eq!(
+            overrides,
+            [
+                (None, "http://localhost:4566".to_string()),
+                (
+                    Some("dynamodb".to_string()),
+                    "http://localhost:8000".to_string()
+                ),
+                (Some("sqs".to_string()), "http://localhost:4576".to_string()),
+            ]
+        );
+        let ignored = config.find_profile("ignored").unwrap();
+        assert!(config.endpoint_overrides(ignored).is_empty());
+    }
+
+    #[test]
+    fn test_parse_sso_sessions() {
+        let config_content = r#"
+[profile modern]
+sso_session = corp
+sso_account_id = 123456789012
+sso_role_name = DeveloperAccess
+
+[sso-session corp]
+sso_start_url = https://corp.awsapps.com/start
+sso_region = eu-west-1
+
+[profile legacy]
+sso_start_url = https://legacy.awsapps.com/start
+sso_region = us-east-1
+"#;
+
+        let config = parse_aws_config(config_content).unwrap();
+
+        assert_eq!(config.profiles.len(), 2);
+        assert_eq!(config.sso_sessions.len(), 1);
+        assert_eq!(config.sso_sessions[0].name, "corp");
+
+        let modern = &config.profiles[0];
+        let legacy = &config.profiles[1];
+
+        assert_eq!(config.sso_session_for(modern).unwrap().name, "corp");
+        assert_eq!(
+            config.sso_start_url_for(modern).unwrap(),
+            "https://corp.awsapps.com/start"
+        );
+        assert_eq!(config.sso_region_for(modern).unwrap(), "eu-west-1");
+
+        assert!(config.sso_session_for(legacy).is_none());
+        assert_eq!(
+            config.sso_start_url_for(legacy).unwrap(),
+            "https://legacy.awsapps.com/start"
+        );
+    }
+
+    #[test]
+    fn test_parse_nested_subsections() {
+        let config_content = r#"
+[profile dev]
+region = us-west-2
+s3 =
+    max_concurrent_requests = 20
+    addressing_style = path
+output = json
+
+[profile prod]
+s3 =
+  use_accelerate_endpoint = true
+"#;
+
+        let profiles = parse_aws_config(config_content).unwrap().profiles;
+
+        let dev = &profiles[0];
+        assert_eq!(dev.attributes.len(), 2);
+        assert_eq!(dev.get_region().unwrap(), "us-west-2");
+        assert_eq!(dev.attributes.get("output").unwrap(), "json");
+        assert!(!dev.attributes.contains_key("s3"));
+        assert!(!dev.attributes.contains_key("max_concurrent_requests"));
+
+        let s3 = dev.subsections.get("s3").unwrap();
+        assert_eq!(s3.get("max_concurrent_requests").unwrap(), "20");
+        assert_eq!(s3.get("addressing_style").unwrap(), "path");
+
+        let prod = &profiles[1];
+        assert_eq!(
+            prod.subsections["s3"]
+                .get("use_accelerate_endpoint")
+                .unwrap(),
+            "true"
+        );
+    }
+
+    /// Tricky files, each checked against how the AWS CLI reads it.
+    #[test]
+    fn test_parse_fixture_corpus() {
+        let parse = |content: &str| parse_aws_config(content).unwrap();
+        let attribute = |config: &AwsConfig, profile: &str, key: &str| {
+            config
+                .find_profile(profile)
+                .unwrap()
+                .attributes
+                .get(key)
+                .cloned()
+        };
+
+        let config = parse(include_str!("config/fixtures/comments.ini"));
+        assert_eq!(config.profiles.len(), 2);
+        assert_eq!(attribute(&config, "dev", "region").unwrap(), "us-west-2");
+        assert_eq!(attribute(&config, "dev", "output").unwrap(), "json");
+        assert_eq!(
+            attribute(&config, "dev", "sso_role_name").unwrap(),
+            "Dev#Ops"
+        );
+        assert_eq!(attribute(&config, "prod", "region").unwrap(), "eu-west-1");
+
+        let config = parse(include_str!("config/fixtures/values.ini"));
+        assert_eq!(
+            attribute(&config, "values", "credential_process").unwrap(),
+            "/usr/bin/helper --arg=a=b --profile values"
+        );
+        assert_eq!(
+            attribute(&config, "values", "external_id").unwrap(),
+            "\"quoted"
+        );
+        assert_eq!(
+            attribute(&config, "values", "role_session_name").unwrap(),
+            "\"kept-quotes\""
+        );
+        assert_eq!(
+            attribute(&config, "values", "empty_then_value").unwrap(),
+            "no-spaces"
+        );
+        assert_eq!(
+            attribute(&config, "values", "description").unwrap(),
+            "first line\nsecond line"
+        );
+        let values = config.find_profile("values").unwrap();
+        assert_eq!(values.subsections["s3"].len(), 2);
+        assert_eq!(values.get_region(), Some("us-east-1"));
+
+        let config = parse(include_str!("config/fixtures/case.ini"));
+        assert_eq!(config.profiles.len(), 2);
+        let mixed = config.find_profile("Mixed").unwrap();
+        assert_eq!(mixed.get_region(), Some("ap-southeast-2"));
+        assert_eq!(mixed.get_account_id(), Some("111122223333"));
+        assert_eq!(attribute(&config, "mixed", "region").unwrap(), "us-east-1");
+
+        let config = parse(include_str!("config/fixtures/duplicates.ini"));
+        assert_eq!(config.profiles.len(), 2);
+        assert_eq!(config.sso_sessions.len(), 1);
+        let dev = config.find_profile("dev").unwrap();
+        assert_eq!(dev.get_region(), Some("eu-central-1"));
+        assert_eq!(dev.attributes.get("output").unwrap(), "json");
+        assert_eq!(dev.attributes.len(), 2);
+        assert_eq!(
+            attribute(&config, "indented", "region").unwrap(),
+            "sa-east-1"
+        );
+    }
+
+    #[test]
+    fn test_config_cache_rereads_changed_files() {
+        let root = temp_dir("config-cache");
+        let paths = ConfigPaths {
+            config: root.join("config"),
+            credentials: root.join("credentials"),
+        };
+        fs::write(&paths.config, "[profile dev]\n").unwrap();
+
+        let cache = ConfigCache::new(paths.clone());
+        assert_eq!(cache.get().unwrap().profiles.len(), 1);
+
+        // Behind the cache's back, with the old times put back: not seen.
+        let modified = fs::metadata(&paths.config).unwrap().modified().unwrap();
+        fs::write(&paths.config, "[profile dev]\n[profile prod]\n").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&paths.config)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        assert_eq!(cache.get().unwrap().profiles.len(), 1);
+
+        fs::write(&paths.credentials, "[ci]\n").unwrap();
+        assert_eq!(cache.get().unwrap().profiles.len(), 3);
+    }
+
+    #[test]
+    fn test_credential_type() {
+        let config_content = r#"
+[default]
+region = eu-west-1
+
+[profile sso]
+sso_session = corp
+
+[profile legacy-sso]
+sso_start_url = https://corp.awsapps.com/start
+
+[profile admin]
+role_arn = arn:aws:iam::123456789012:role/Admin
+source_profile = sso
+
+[profile ci]
+role_arn = arn:aws:iam::123456789012:role/Deploy
+web_identity_token_file = /var/run/token
+
+[profile vault]
+credential_process = vault-aws-creds
+
+[profile keys]
+aws_access_key_id = AKIA
+"#;
+
+        let config = parse_aws_config(config_content).unwrap();
+        let credential_type = |name: &str| config.find_profile(name).unwrap().credential_type();
+
+        assert_eq!(credential_type("default"), None);
+        assert_eq!(credential_type("sso"), Some(CredentialType::Sso));
+        assert_eq!(credential_type("legacy-sso"), Some(CredentialType::Sso));
+        assert_eq!(credential_type("admin"), Some(CredentialType::AssumeRole));
+        assert_eq!(credential_type("ci"), Some(CredentialType::WebIdentity));
+        assert_eq!(
+            credential_type("vault"),
+            Some(CredentialType::CredentialProcess)
+        );
+        assert_eq!(credential_type("keys"), Some(CredentialType::StaticKeys));
+
+        let role_arn = |name: &str| config.find_profile(name).unwrap().role_arn_parts();
+        assert_eq!(role_arn("admin"), Some(("123456789012", "Admin")));
+        assert_eq!(role_arn("sso"), None);
+
+        let problem = |name: &str| config.find_profile(name).unwrap().credential_problem();
+        assert_eq!(
+            problem("ci").as_deref(),
+            Some("web_identity_token_file /var/run/token doesn't exist")
+        );
+        assert_eq!(
+            problem("vault").as_deref(),
+            Some("credential_process runs vault-aws-creds, which isn't installed")
+        );
+        assert_eq!(problem("keys"), None);
+        assert_eq!(
+            command_program("'/opt/my tools/creds' --profile ci"),
+            Some("/opt/my tools/creds")
+        );
+        assert_eq!(command_program("sh -c 'echo'"), Some("sh"));
+    }
+
+    #[test]
+    fn test_role_chain() {
+        let config = parse_aws_config(
+            r#"
+[profile base]
+aws_access_key_id = AKIA
+
+[profile security-audit]
+role_arn = arn:aws:iam::123456789012:role/SecurityAudit
+source_profile = base
+
+[profile target-admin]
+role_arn = arn:aws:iam::987654321098:role/Admin
+source_profile = security-audit
+
+[profile orphan]
+role_arn = arn:aws:iam::987654321098:role/Admin
+source_profile = gone
+
+[profile ping]
+source_profile = pong
+
+[profile pong]
+source_profile = ping
+"#,
+        )
+        .unwrap();
+        let chain = |name: &str| config.role_chain(name).map(|chain| chain.to_string());
+
+        assert_eq!(
+            chain("target-admin").unwrap(),
+            "base → security-audit → target-admin"
+        );
+        assert_eq!(chain("orphan").unwrap(), "gone (missing) → orphan");
+        assert_eq!(chain("ping").unwrap(), "ping (cycle) → pong → ping");
+        assert_eq!(chain("base"), None);
+
+        let broken: Vec<&str> = config
+            .broken_chains()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        assert_eq!(broken, vec!["orphan", "ping", "pong"]);
+        assert_eq!(
+            config
+                .role_chain("pong")
+                .unwrap()
+                .problem_description()
+                .unwrap(),
+            "source_profile chain pong (cycle) → ping → pong loops at 'pong'"
+        );
+    }
 }
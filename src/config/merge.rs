@@ -0,0 +1,106 @@
+//! How a profile defined in both the config and the credentials file is put
+//! together, following the AWS CLI: the credentials file wins for the access
+//! keys and session token, the config file for everything else. Keys only one
+//! file sets are taken from it either way.
+
+use super::Profile;
+
+/// The keys the credentials file is the authority on.
+const CREDENTIAL_KEYS: [&str; 5] = [
+    "aws_access_key_id",
+    "aws_secret_access_key",
+    "aws_session_token",
+    "aws_security_token",
+    "aws_credential_expiration",
+];
+
+/// Whether the credentials file's value of `key` beats the config file's.
+pub fn credentials_file_wins(key: &str) -> bool {
+    CREDENTIAL_KEYS.contains(&key)
+}
+
+/// Profiles keep the order of the config file, followed by those only in the
+/// credentials file; `--sort` orders them from there.
+pub fn merge_profiles(
+    config_profiles: Vec<Profile>,
+    credentials_profiles: Vec<Profile>,
+) -> Vec<Profile> {
+    let mut profiles = config_profiles;
+
+    for credentials_profile in credentials_profiles {
+        match profiles
+            .iter_mut()
+            .find(|profile| profile.name == credentials_profile.name)
+        {
+            Some(existing) => merge_profile(existing, credentials_profile),
+            None => profiles.push(credentials_profile),
+        }
+    }
+    profiles
+}
+
+fn merge_profile(profile: &mut Profile, credentials_profile: Profile) {
+    for (key, value) in credentials_profile.attributes {
+        if credentials_file_wins(&key) || !profile.attributes.contains_key(&key) {
+            profile.attributes.insert(key, value);
+        }
+    }
+    // Nested settings such as `s3 =` are never credentials.
+    for (name, settings) in credentials_profile.subsections {
+        let existing = profile.subsections.entry(name).or_default();
+        for (key, value) in settings {
+            existing.entry(key).or_insert(value);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{parse_aws_config, parse_aws_credentials};
+
+    #[test]
+    fn test_merge_profiles() {
+        let config = r#"
+[profile dev]
+region = us-west-2
+output = json
+aws_access_key_id = AKIACONFIG
+s3 =
+  max_concurrent_requests = 10
+"#;
+        let credentials = r#"
+[dev]
+aws_access_key_id = AKIADEV
+aws_secret_access_key = devsecret
+output = text
+cli_pager = less
+s3 =
+  max_concurrent_requests = 20
+  multipart_threshold = 64MB
+
+[legacy]
+aws_access_key_id = AKIALEGACY
+aws_secret_access_key = legacysecret
+"#;
+
+        let profiles = merge_profiles(
+            parse_aws_config(config).unwrap().profiles,
+            parse_aws_credentials(credentials).unwrap(),
+        );
+
+        assert_eq!(profiles.len(), 2);
+        assert_eq!(profiles[0].name, "dev");
+        assert_eq!(profiles[1].name, "legacy");
+
+        let dev = &profiles[0];
+        assert_eq!(dev.get_region(), Some("us-west-2"));
+        assert_eq!(dev.attributes["aws_access_key_id"], "AKIADEV");
+        assert_eq!(dev.attributes["aws_secret_access_key"], "devsecret");
+        assert_eq!(dev.attributes["output"], "json");
+        assert_eq!(dev.attributes["cli_pager"], "less");
+        assert_eq!(dev.subsections["s3"]["max_concurrent_requests"], "10");
+        assert_eq!(dev.subsections["s3"]["multipart_threshold"], "64MB");
+        assert_eq!(profiles[1].attributes["aws_access_key_id"], "AKIALEGACY");
+    }
+}
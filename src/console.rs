@@ -0,0 +1,105 @@
+//! Federated sign-in URLs for the AWS web console.
+//!
+//! See <https://docs.aws.amazon.com/IAM/latest/UserGuide/id_roles_providers_enable-console-custom-url.html>.
+
+use crate::session::SessionDuration;
+use anyhow::{Context, Result};
+use aws_credential_types::Credentials;
+use serde::Deserialize;
+use url::Url;
+
+const ISSUER: &str = "aws-profile-selector";
+
+/// Sign-in and console host names for the partition a region belongs to.
+fn partition_hosts(region: Option<&str>) -> (&'static str, &'static str) {
+    match region {
+        Some(region) if region.starts_with("cn-") => {
+            ("signin.amazonaws.cn", "console.amazonaws.cn")
+        }
+        Some(region) if region.starts_with("us-gov-") => (
+            "signin.amazonaws-us-gov.com",
+            "console.amazonaws-us-gov.com",
+        ),
+        _ => ("signin.aws.amazon.com", "console.aws.amazon.com"),
+    }
+}
+
+#[derive(Deserialize)]
+struct SigninTokenResponse {
+    #[serde(rename = "SigninToken")]
+    signin_token: String,
+}
+
+/// Exchanges temporary credentials for a sign-in token and returns the
+/// console login URL. The credentials must include a session token; the
+/// console session lasts `session_duration` when they're from AssumeRole.
+pub fn signin_url(
+    credentials: &Credentials,
+    region: Option<&str>,
+    session_duration: Option<SessionDuration>,
+) -> Result<Url> {
+    let session_token = credentials.session_token().ok_or_else(|| {
+        anyhow::anyhow!("Console sign-in requires temporary credentials with a session token")
+    })?;
+
+    let (signin_host, console_host) = partition_hosts(region);
+    let federation_endpoint = format!("https://{signin_host}/federation");
+
+    let session = serde_json::json!({
+        "sessionId": credentials.access_key_id(),
+        "sessionKey": credentials.secret_access_key(),
+        "sessionToken": session_token,
+    });
+
+    let mut token_url = Url::parse(&federation_endpoint)?;
+    token_url
+        .query_pairs_mut()
+        .append_pair("Action", "getSigninToken")
+        .append_pair("Session", &session.to_string());
+    if let Some(duration) = session_duration {
+        token_url
+            .query_pairs_mut()
+            .append_pair("SessionDuration", &duration.seconds().to_string());
+    }
+
+    let response: SigninTokenResponse = ureq::get(token_url.as_str())
+        .call()
+        .context("Failed to request a console sign-in token")?
+        .body_mut()
+        .read_json()
+        .context("Unexpected response from the federation endpoint")?;
+
+    let destination = match region {
+        Some(region) => format!("https://{console_host}/console/home?region={region}"),
+        None => format!("https://{console_host}/console/home"),
+    };
+
+    let mut login_url = Url::parse(&federation_endpoint)?;
+    login_url
+        .query_pairs_mut()
+        .append_pair("Action", "login")
+        .append_pair("Issuer", ISSUER)
+        .append_pair("Destination", &destination)
+        .append_pair("SigninToken", &response.signin_token);
+
+    Ok(login_url)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_partition_hosts() {
+        assert_eq!(partition_hosts(None).0, "signin.aws.amazon.com");
+        assert_eq!(
+            partition_hosts(Some("eu-west-1")).1,
+            "console.aws.amazon.com"
+        );
+        assert_eq!(partition_hosts(Some("cn-north-1")).0, "signin.amazonaws.cn");
+        assert_eq!(
+            partition_hosts(Some("us-gov-west-1")).1,
+            "console.amazonaws-us-gov.com"
+        );
+    }
+}
@@ -0,0 +1,48 @@
+//! The optional background process started with `serve socket`. It keeps the
+//! parsed AWS config in memory and answers the `serve stdio` requests on a
+//! Unix socket, so commands that run on every prompt render can ask it
+//! instead of reading the config themselves. When nothing is listening they
+//! do the work directly, as before.
+
+use crate::cache::get_cache_dir;
+use anyhow::Result;
+use serde_json::Value;
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// A daemon slower than this to answer counts as not running.
+#[cfg_attr(not(unix), allow(dead_code))]
+const TIMEOUT: Duration = Duration::from_millis(200);
+
+/// `$XDG_RUNTIME_DIR/aws-profile-selector.sock`, or `daemon.sock` in the
+/// cache directory without one.
+pub fn socket_path() -> Result<PathBuf> {
+    match std::env::var_os("XDG_RUNTIME_DIR").filter(|path| !path.is_empty()) {
+        Some(dir) => Ok(PathBuf::from(dir).join("aws-profile-selector.sock")),
+        None => Ok(get_cache_dir()?.join("daemon.sock")),
+    }
+}
+
+/// Sends one request to the daemon and returns its result, or `None` when no
+/// daemon answers or it answers with an error.
+#[cfg(unix)]
+pub fn request(method: &str, params: Value) -> Option<Value> {
+    use std::io::{BufRead, BufReader, Write};
+    use std::os::unix::net::UnixStream;
+
+    let mut stream = UnixStream::connect(socket_path().ok()?).ok()?;
+    stream.set_read_timeout(Some(TIMEOUT)).ok()?;
+    stream.set_write_timeout(Some(TIMEOUT)).ok()?;
+    let request = serde_json::json!({ "method": method, "params": params });
+    writeln!(stream, "{request}").ok()?;
+
+    let mut line = String::new();
+    BufReader::new(stream).read_line(&mut line).ok()?;
+    let mut response: Value = serde_json::from_str(&line).ok()?;
+    Some(response.get_mut("result")?.take())
+}
+
+#[cfg(not(unix))]
+pub fn request(_method: &str, _params: Value) -> Option<Value> {
+    None
+}
@@ -0,0 +1,204 @@
+//! Line diffs for previewing config rewrites before they are written, and
+//! the [`Preview`] that commands changing the AWS files show and ask about.
+
+use crate::config::mask_secrets;
+use crate::dry_run::dry_run;
+use crate::error::Error;
+use crate::theme::{no_color_env, paint};
+use crate::ui::answer;
+use anyhow::Result;
+use inquire::Confirm;
+use ratatui::style::{Color, Modifier, Style};
+use std::io::IsTerminal;
+use std::path::{Path, PathBuf};
+
+/// How many unchanged lines to show around each change.
+const CONTEXT: usize = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Line<'a> {
+    Same(&'a str),
+    Removed(&'a str),
+    Added(&'a str),
+}
+
+/// Renders the changes from `old` to `new` as `-`/`+` lines with a little
+/// surrounding context. Returns an empty string when nothing changed.
+pub fn line_diff(old: &str, new: &str) -> String {
+    let lines = diff_lines(
+        &old.lines().collect::<Vec<_>>(),
+        &new.lines().collect::<Vec<_>>(),
+    );
+
+    let changed: Vec<usize> = lines
+        .iter()
+        .enumerate()
+        .filter(|(_, line)| !matches!(line, Line::Same(_)))
+        .map(|(index, _)| index)
+        .collect();
+
+    let mut output = Vec::new();
+    let mut last_shown: Option<usize> = None;
+
+    for (index, line) in lines.iter().enumerate() {
+        let near_change = changed
+            .iter()
+            .any(|&change| index + CONTEXT >= change && index <= change + CONTEXT);
+        if !near_change {
+            continue;
+        }
+
+        if last_shown.is_some_and(|last| index > last + 1) {
+            output.push("...".to_string());
+        }
+        last_shown = Some(index);
+
+        let rendered = match line {
+            Line::Same(text) => format!("  {text}"),
+            Line::Removed(text) => format!("- {text}"),
+            Line::Added(text) => format!("+ {text}"),
+        };
+        output.push(rendered.trim_end().to_string());
+    }
+
+    output.join("\n")
+}
+
+/// The files a command is about to rewrite, as they are and as they will
+/// be.
+#[derive(Debug, Default)]
+pub struct Preview {
+    files: Vec<(PathBuf, String)>,
+}
+
+impl Preview {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds the change from `old` to `new` in `path`. Files left as they are
+    /// aren't shown.
+    pub fn file(&mut self, path: &Path, old: &str, new: &str) {
+        let diff = line_diff(old, new);
+        if !diff.is_empty() {
+            self.files.push((path.to_path_buf(), mask_secrets(&diff)));
+        }
+    }
+
+    /// Shows the diffs on stderr and asks `question`, unless `yes` is given.
+    /// Answering no cancels. A dry run shows and asks nothing, as each write
+    /// is described in its place.
+    pub fn confirm(&self, question: Confirm<'_>, yes: bool) -> Result<()> {
+        if dry_run() {
+            return Ok(());
+        }
+        let color = std::io::stderr().is_terminal() && !no_color_env();
+        eprintln!("{}", self.render(color));
+        if yes || answer(question.prompt())? {
+            Ok(())
+        } else {
+            Err(Error::cancelled().into())
+        }
+    }
+
+    /// Each file's name and diff, with removed lines red and added ones
+    /// green when `color` is set.
+    fn render(&self, color: bool) -> String {
+        let style = |style: Style| if color { style } else { Style::new() };
+        self.files
+            .iter()
+            .map(|(path, diff)| {
+                let lines: Vec<String> = diff
+                    .lines()
+                    .map(|line| {
+                        let line_style = match line {
+                            "..." => Style::new().add_modifier(Modifier::DIM),
+                            _ if line.starts_with('-') => Style::new().fg(Color::Red),
+                            _ if line.starts_with('+') => Style::new().fg(Color::Green),
+                            _ => Style::new(),
+                        };
+                        paint(line, style(line_style))
+                    })
+                    .collect();
+                let header = format!("{}:", path.display());
+                format!(
+                    "{}\n{}\n",
+                    paint(&header, style(Style::new().add_modifier(Modifier::BOLD))),
+                    lines.join("\n")
+                )
+            })
+            .collect::<Vec<_>>()
+            .join("\n")
+    }
+}
+
+/// Longest-common-subsequence diff; config files are small enough that the
+/// quadratic table is not a concern.
+fn diff_lines<'a>(old: &[&'a str], new: &[&'a str]) -> Vec<Line<'a>> {
+    let mut table = vec![vec![0usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            table[i][j] = if old[i] == new[j] {
+                table[i + 1][j + 1] + 1
+            } else {
+                table[i + 1][j].max(table[i][j + 1])
+            };
+        }
+    }
+
+    let (mut i, mut j) = (0, 0);
+    let mut lines = Vec::new();
+    while i < old.len() && j < new.len() {
+        if old[i] == new[j] {
+            lines.push(Line::Same(old[i]));
+            i += 1;
+            j += 1;
+        } else if table[i + 1][j] >= table[i][j + 1] {
+            lines.push(Line::Removed(old[i]));
+            i += 1;
+        } else {
+            lines.push(Line::Added(new[j]));
+            j += 1;
+        }
+    }
+    lines.extend(old[i..].iter().map(|line| Line::Removed(line)));
+    lines.extend(new[j..].iter().map(|line| Line::Added(line)));
+    lines
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_line_diff_shows_changes_with_context() {
+        let old = "a\nb\nc\nd\ne\nf\ng\nh\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\nh\ni\n";
+
+        assert_eq!(
+            line_diff(old, new),
+            "  a\n- b\n+ B\n  c\n  d\n...\n  g\n  h\n+ i"
+        );
+        assert_eq!(line_diff(old, old), "");
+    }
+
+    #[test]
+    fn test_preview_masks_and_colors_the_diff() {
+        let mut preview = Preview::new();
+        preview.file(
+            Path::new("credentials"),
+            "[dev]\naws_secret_access_key = old\n",
+            "[dev]\naws_secret_access_key = new\n",
+        );
+        preview.file(Path::new("config"), "same\n", "same\n");
+        assert_eq!(
+            preview.render(false),
+            "credentials:\n  [dev]\n- aws_secret_access_key = ********\n+ aws_secret_access_key = ********\n"
+        );
+        assert!(
+            preview
+                .render(true)
+                .contains("\x1b[32m+ aws_secret_access_key = ********\x1b[0m")
+        );
+    }
+}
@@ -0,0 +1,98 @@
+//! `--dry-run`: the writes to the AWS config and credentials, the state
+//! files, kubeconfig, the keychain, and the calling shell, and the hooks and
+//! notifications, all ask here first. In a dry run each is described on
+//! stderr instead (file writes as a diff) and skipped.
+//!
+//! `--read-only` asks here too: writes the user asked for fail with
+//! [`ensure_writable`], and bookkeeping (history, caches, the selector's
+//! position) is quietly left unwritten.
+
+use crate::config::mask_secrets;
+use crate::diff::line_diff;
+use anyhow::Result;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static DRY_RUN: AtomicBool = AtomicBool::new(false);
+
+pub fn set_dry_run(enabled: bool) {
+    DRY_RUN.store(enabled, Ordering::Relaxed);
+}
+
+pub fn dry_run() -> bool {
+    DRY_RUN.load(Ordering::Relaxed)
+}
+
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+
+pub fn set_read_only(enabled: bool) {
+    READ_ONLY.store(enabled, Ordering::Relaxed);
+}
+
+pub fn read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+/// Fails with `--read-only`, naming the `action` it won't take.
+pub fn ensure_writable(action: impl fmt::Display) -> Result<()> {
+    match read_only() {
+        true => Err(anyhow::anyhow!("Read-only mode: not going to {action}")),
+        false => Ok(()),
+    }
+}
+
+/// In a dry run, says `Would <action>` and returns true for the caller to
+/// skip it.
+pub fn would(action: impl fmt::Display) -> bool {
+    if dry_run() {
+        eprintln!("Would {action}");
+    }
+    dry_run()
+}
+
+/// In a dry run, shows what writing `contents` to `path` would change and
+/// returns true for the caller to skip the write.
+pub fn would_write(path: &Path, contents: &str) -> bool {
+    if dry_run() {
+        let existing = fs::read_to_string(path).ok();
+        eprintln!("{}", describe_write(path, existing.as_deref(), contents));
+    }
+    dry_run()
+}
+
+fn describe_write(path: &Path, existing: Option<&str>, contents: &str) -> String {
+    let diff = mask_secrets(&line_diff(existing.unwrap_or_default(), contents));
+    match existing {
+        None => format!("Would create {}:\n{diff}", path.display()),
+        Some(_) if diff.is_empty() => format!("Would leave {} unchanged", path.display()),
+        Some(_) => format!("Would write {}:\n{diff}", path.display()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_write() {
+        let path = Path::new("/home/me/.aws/credentials");
+        assert_eq!(
+            describe_write(
+                path,
+                Some("[dev]\nregion = eu-west-1\n"),
+                "[dev]\nregion = eu-west-1\n\n[ci]\naws_secret_access_key = secret\n"
+            ),
+            "Would write /home/me/.aws/credentials:\n  [dev]\n  region = eu-west-1\n+\n+ [ci]\n+ aws_secret_access_key = ********"
+        );
+        assert_eq!(
+            describe_write(path, None, "dev"),
+            "Would create /home/me/.aws/credentials:\n+ dev"
+        );
+        assert_eq!(
+            describe_write(path, Some("dev\n"), "dev"),
+            "Would leave /home/me/.aws/credentials unchanged"
+        );
+    }
+}
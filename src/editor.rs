@@ -0,0 +1,459 @@
+//! Format-preserving editing of INI files such as `~/.aws/config`.
+//!
+//! The document keeps every original line verbatim, including its line
+//! ending. Mutations only touch the lines they need to, so comments, blank
+//! lines, key ordering, and unrelated sections survive a read → mutate →
+//! write cycle byte-for-byte.
+
+use crate::backup::before_write;
+use crate::dry_run::{ensure_writable, would_write};
+use crate::files::{Access, write_atomically};
+use anyhow::{Context, Result};
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IniDocument {
+    lines: Vec<String>,
+}
+
+/// What a single line of the document contains, ignoring its layout.
+enum LineKind<'a> {
+    Header(&'a str),
+    Entry { key: &'a str, indented: bool },
+    Other,
+}
+
+fn classify(line: &str) -> LineKind<'_> {
+    let indented = line.starts_with(char::is_whitespace);
+    let trimmed = line.trim();
+
+    if trimmed.is_empty() || trimmed.starts_with('#') || trimmed.starts_with(';') {
+        return LineKind::Other;
+    }
+
+    if let Some(rest) = trimmed.strip_prefix('[')
+        && let Some(end) = rest.find(']')
+    {
+        return LineKind::Header(rest[..end].trim());
+    }
+
+    match trimmed.split_once('=') {
+        Some((key, _)) => LineKind::Entry {
+            key: key.trim(),
+            indented,
+        },
+        None => LineKind::Other,
+    }
+}
+
+impl IniDocument {
+    pub fn parse(content: &str) -> Self {
+        Self {
+            lines: content.split_inclusive('\n').map(str::to_string).collect(),
+        }
+    }
+
+    /// Reads the file at `path`, treating a missing file as empty.
+    pub fn load(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::parse(""));
+        }
+
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?;
+        Ok(Self::parse(&content))
+    }
+
+    /// Writes the document to `path` atomically, creating its directory if
+    /// needed. An existing file keeps its permissions; a new one is only
+    /// readable by its owner, as the AWS CLI creates them.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        ensure_writable(format_args!("write {}", path.display()))?;
+        if would_write(path, &self.to_string()) {
+            return Ok(());
+        }
+        before_write(path)?;
+        write_atomically(path, self.to_string(), Access::KeepOrPrivate)
+    }
+
+    /// Section headers in file order, e.g. `profile dev` or `default`.
+    pub fn sections(&self) -> Vec<&str> {
+        self.lines
+            .iter()
+            .filter_map(|line| match classify(line) {
+                LineKind::Header(header) => Some(header),
+                _ => None,
+            })
+            .collect()
+    }
+
+    pub fn has_section(&self, header: &str) -> bool {
+        self.find_section(header).is_some()
+    }
+
+    /// Returns the top-level value of `key` in `header`, if present.
+    pub fn get(&self, header: &str, key: &str) -> Option<&str> {
+        let index = self.find_key(header, key)?;
+        let (_, value) = self.lines[index].split_once('=')?;
+        Some(value.trim())
+    }
+
+    /// The verbatim text of a section, from its header up to the next one.
+    pub fn section_text(&self, header: &str) -> Option<String> {
+        let (start, end) = self.find_section(header)?;
+        Some(self.lines[start..end].concat())
+    }
+
+    /// Sets `key` in `header`, replacing an existing value in place or
+    /// appending a new entry after the section's last entry. The section is
+    /// created at the end of the document if it doesn't exist.
+    pub fn set(&mut self, header: &str, key: &str, value: &str) {
+        if let Some(index) = self.find_key(header, key) {
+            let line = &self.lines[index];
+            let ending = line_ending(line);
+            let (prefix, _) = line.split_once('=').unwrap_or((line, ""));
+            let separator = if prefix.ends_with(' ') { " " } else { "" };
+            self.lines[index] = format!("{prefix}={separator}{value}{ending}");
+            return;
+        }
+
+        if !self.has_section(header) {
+            self.append_section(header, &[]);
+        }
+
+        let (start, end) = self.find_section(header).unwrap_or_default();
+        let insert_at = self.lines[start..end]
+            .iter()
+            .rposition(|line| matches!(classify(line), LineKind::Entry { .. }))
+            .map_or(start + 1, |offset| start + offset + 1);
+
+        self.ensure_terminated(insert_at);
+        let newline = self.newline();
+        self.lines
+            .insert(insert_at, format!("{key} = {value}{newline}"));
+    }
+
+    /// Replaces the `key` entry in `header` with `new_key = value` on the same
+    /// line, keeping its position in the section.
+    pub fn replace_key(&mut self, header: &str, key: &str, new_key: &str, value: &str) -> bool {
+        let Some(index) = self.find_key(header, key) else {
+            return false;
+        };
+
+        let ending = line_ending(&self.lines[index]);
+        self.lines[index] = format!("{new_key} = {value}{ending}");
+        true
+    }
+
+    /// Removes `key` (and any nested lines belonging to it) from `header`.
+    pub fn remove_key(&mut self, header: &str, key: &str) -> bool {
+        let Some(index) = self.find_key(header, key) else {
+            return false;
+        };
+
+        let (_, end) = self.find_section(header).unwrap_or_default();
+        let nested = self.lines[index + 1..end]
+            .iter()
+            .take_while(|line| matches!(classify(line), LineKind::Entry { indented: true, .. }))
+            .count();
+
+        self.lines.drain(index..=index + nested);
+        true
+    }
+
+    /// Removes a whole section, including its header and entries.
+    pub fn remove_section(&mut self, header: &str) -> bool {
+        let Some((start, end)) = self.find_section(header) else {
+            return false;
+        };
+
+        self.lines.drain(start..end);
+        true
+    }
+
+    /// Rewrites a section header, leaving its entries untouched.
+    pub fn rename_section(&mut self, header: &str, new_header: &str) -> bool {
+        let Some((start, _)) = self.find_section(header) else {
+            return false;
+        };
+
+        let line = &self.lines[start];
+        let indent: String = line.chars().take_while(|c| c.is_whitespace()).collect();
+        let ending = line_ending(line);
+        self.lines[start] = format!("{indent}[{new_header}]{ending}");
+        true
+    }
+
+    /// Appends a new section at the end of the document, separated from the
+    /// previous content by a blank line.
+    pub fn append_section(&mut self, header: &str, entries: &[(&str, &str)]) {
+        let newline = self.newline();
+
+        if !self.lines.is_empty() {
+            self.ensure_terminated(self.lines.len());
+            if !self.lines.last().is_some_and(|line| line.trim().is_empty()) {
+                self.lines.push(newline.to_string());
+            }
+        }
+
+        self.lines.push(format!("[{header}]{newline}"));
+        for (key, value) in entries {
+            self.lines.push(format!("{key} = {value}{newline}"));
+        }
+    }
+
+    /// Replaces the text of an existing section with `text`, or appends it
+    /// when the section does not exist.
+    pub fn replace_section_text(&mut self, header: &str, text: &str) {
+        let mut replacement: Vec<String> = text.split_inclusive('\n').map(str::to_string).collect();
+
+        match self.find_section(header) {
+            Some((start, end)) => {
+                if end < self.lines.len()
+                    && let Some(last) = replacement.last_mut()
+                    && line_ending(last).is_empty()
+                {
+                    last.push_str(self.newline());
+                }
+                self.lines.splice(start..end, replacement);
+            }
+            None => {
+                if !self.lines.is_empty() {
+                    self.ensure_terminated(self.lines.len());
+                    if !self.lines.last().is_some_and(|line| line.trim().is_empty()) {
+                        replacement.insert(0, self.newline().to_string());
+                    }
+                }
+                self.lines.extend(replacement);
+            }
+        }
+    }
+
+    /// Returns the `[start, end)` line range of a section.
+    fn find_section(&self, header: &str) -> Option<(usize, usize)> {
+        let start = self
+            .lines
+            .iter()
+            .position(|line| matches!(classify(line), LineKind::Header(h) if h == header))?;
+
+        let end = self.lines[start + 1..]
+            .iter()
+            .position(|line| matches!(classify(line), LineKind::Header(_)))
+            .map_or(self.lines.len(), |offset| start + 1 + offset);
+
+        Some((start, end))
+    }
+
+    fn find_key(&self, header: &str, key: &str) -> Option<usize> {
+        let (start, end) = self.find_section(header)?;
+
+        (start + 1..end).find(|&index| {
+            matches!(
+                classify(&self.lines[index]),
+                LineKind::Entry { key: k, indented: false } if k == key
+            )
+        })
+    }
+
+    /// Makes sure the line before `index` ends with a newline so content
+    /// inserted at `index` starts on its own line.
+    fn ensure_terminated(&mut self, index: usize) {
+        let newline = self.newline();
+
+        if let Some(previous) = index.checked_sub(1).and_then(|i| self.lines.get_mut(i))
+            && line_ending(previous).is_empty()
+        {
+            previous.push_str(newline);
+        }
+    }
+
+    /// The line ending used by the document, so inserted lines match it.
+    fn newline(&self) -> &'static str {
+        if self.lines.iter().any(|line| line.ends_with("\r\n")) {
+            "\r\n"
+        } else {
+            "\n"
+        }
+    }
+}
+
+fn line_ending(line: &str) -> &'static str {
+    if line.ends_with("\r\n") {
+        "\r\n"
+    } else if line.ends_with('\n') {
+        "\n"
+    } else {
+        ""
+    }
+}
+
+impl fmt::Display for IniDocument {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for line in &self.lines {
+            f.write_str(line)?;
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CONFIG: &str = r#"# Managed by hand
+[default]
+region = us-east-1
+
+; legacy comment style
+[profile dev]
+sso_account_id = 123456789012
+# inline note
+region=us-west-2
+s3 =
+    max_concurrent_requests = 20
+
+[sso-session corp]
+sso_start_url = https://corp.awsapps.com/start
+"#;
+
+    #[test]
+    fn test_round_trip_is_byte_for_byte() {
+        let document = IniDocument::parse(CONFIG);
+        assert_eq!(document.to_string(), CONFIG);
+
+        let crlf = CONFIG.replace('\n', "\r\n");
+        assert_eq!(IniDocument::parse(&crlf).to_string(), crlf);
+
+        let unterminated = "[default]\nregion = us-east-1";
+        assert_eq!(IniDocument::parse(unterminated).to_string(), unterminated);
+    }
+
+    #[test]
+    fn test_set_existing_key_preserves_layout() {
+        let mut document = IniDocument::parse(CONFIG);
+        document.set("profile dev", "region", "eu-west-1");
+
+        assert_eq!(
+            document.to_string(),
+            CONFIG.replace("region=us-west-2", "region=eu-west-1")
+        );
+        assert_eq!(document.get("profile dev", "region"), Some("eu-west-1"));
+        assert_eq!(document.get("default", "region"), Some("us-east-1"));
+    }
+
+    #[test]
+    fn test_set_new_key_appends_after_last_entry() {
+        let mut document = IniDocument::parse(CONFIG);
+        document.set("profile dev", "output", "json");
+
+        assert_eq!(
+            document.to_string(),
+            CONFIG.replace(
+                "    max_concurrent_requests = 20\n",
+                "    max_concurrent_requests = 20\noutput = json\n"
+            )
+        );
+    }
+
+    #[test]
+    fn test_set_ignores_nested_keys() {
+        let mut document = IniDocument::parse(CONFIG);
+        assert_eq!(document.get("profile dev", "max_concurrent_requests"), None);
+
+        document.set("profile dev", "max_concurrent_requests", "5");
+        assert!(
+            document
+                .to_string()
+                .contains("    max_concurrent_requests = 20\n")
+        );
+        assert_eq!(
+            document.get("profile dev", "max_concurrent_requests"),
+            Some("5")
+        );
+    }
+
+    #[test]
+    fn test_replace_key_keeps_position() {
+        let mut document = IniDocument::parse(CONFIG);
+        assert!(document.replace_key("profile dev", "sso_account_id", "sso_session", "corp"));
+        assert!(!document.replace_key("profile dev", "missing", "x", "y"));
+
+        assert_eq!(
+            document.to_string(),
+            CONFIG.replace("sso_account_id = 123456789012", "sso_session = corp")
+        );
+    }
+
+    #[test]
+    fn test_append_section() {
+        let mut document = IniDocument::parse("[default]\nregion = us-east-1");
+        document.append_section("profile new", &[("region", "eu-west-1")]);
+
+        assert_eq!(
+            document.to_string(),
+            "[default]\nregion = us-east-1\n\n[profile new]\nregion = eu-west-1\n"
+        );
+
+        let mut empty = IniDocument::parse("");
+        empty.set("profile new", "region", "eu-west-1");
+        assert_eq!(empty.to_string(), "[profile new]\nregion = eu-west-1\n");
+    }
+
+    #[test]
+    fn test_remove_key_and_section() {
+        let mut document = IniDocument::parse(CONFIG);
+
+        assert!(document.remove_key("profile dev", "s3"));
+        assert!(!document.to_string().contains("max_concurrent_requests"));
+        assert!(!document.remove_key("profile dev", "s3"));
+
+        assert!(document.remove_section("profile dev"));
+        assert_eq!(
+            document.to_string(),
+            "# Managed by hand\n\
+[default]\n\
+region = us-east-1\n\
+\n\
+; legacy comment style\n\
+[sso-session corp]\n\
+sso_start_url = https://corp.awsapps.com/start\n"
+        );
+        assert_eq!(document.sections(), vec!["default", "sso-session corp"]);
+    }
+
+    #[test]
+    fn test_rename_and_section_text() {
+        let mut document = IniDocument::parse(CONFIG);
+
+        assert!(document.rename_section("profile dev", "profile development"));
+        assert!(!document.has_section("profile dev"));
+        assert_eq!(
+            document.section_text("profile development").unwrap(),
+            r#"[profile development]
+sso_account_id = 123456789012
+# inline note
+region=us-west-2
+s3 =
+    max_concurrent_requests = 20
+
+"#
+        );
+    }
+
+    #[test]
+    fn test_replace_section_text() {
+        let mut document = IniDocument::parse(CONFIG);
+        let text = document
+            .section_text("default")
+            .unwrap()
+            .replace("us-east-1", "eu-central-1");
+        document.replace_section_text("default", &text);
+
+        assert_eq!(
+            document.to_string(),
+            CONFIG.replace("region = us-east-1", "region = eu-central-1")
+        );
+    }
+}
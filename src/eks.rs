@@ -0,0 +1,248 @@
+//! Keeps kubectl pointed at the active profile's EKS clusters: after a
+//! switch, each cluster gets a kubeconfig entry (like
+//! `aws eks update-kubeconfig`) whose credentials come from that profile, and
+//! the first one becomes the current context.
+
+use crate::aws::{EksCluster, block_on, describe_eks_cluster, list_eks_clusters, load_sdk_config};
+use crate::check::DEFAULT_TIMEOUT;
+use crate::config::{ConfigPaths, home_dir};
+use crate::dry_run::{ensure_writable, would_write};
+use crate::files::{Access, write_atomically};
+use crate::filter::glob_match;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use serde_yaml::{Mapping, Value};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct EksSettings {
+    /// Update the kubeconfig whenever a profile is activated.
+    pub sync: bool,
+    /// Clusters by profile name or glob pattern; other profiles get every
+    /// cluster ListClusters returns in their region.
+    pub clusters: BTreeMap<String, Vec<String>>,
+}
+
+impl EksSettings {
+    /// The configured clusters for `profile_name`, or `None` to list them.
+    fn clusters_for(&self, profile_name: &str) -> Option<Vec<String>> {
+        let mut clusters: Vec<String> = self
+            .clusters
+            .iter()
+            .filter(|(pattern, _)| glob_match(pattern, profile_name))
+            .flat_map(|(_, clusters)| clusters.iter().cloned())
+            .collect();
+        if clusters.is_empty() {
+            return None;
+        }
+        clusters.sort_unstable();
+        clusters.dedup();
+        Some(clusters)
+    }
+}
+
+/// The first file in `$KUBECONFIG`, falling back to `~/.kube/config`.
+pub fn get_kubeconfig_path() -> Result<PathBuf> {
+    if let Some(path) = std::env::var_os("KUBECONFIG")
+        .and_then(|paths| std::env::split_paths(&paths).find(|path| !path.as_os_str().is_empty()))
+    {
+        return Ok(path);
+    }
+
+    Ok(home_dir()?.join(".kube").join("config"))
+}
+
+/// Writes the profile's clusters into the kubeconfig, returning the context
+/// made current (`None` when the profile has no clusters).
+pub fn sync_kubeconfig(
+    paths: &ConfigPaths,
+    settings: &EksSettings,
+    profile_name: &str,
+) -> Result<Option<String>> {
+    let lookup = async {
+        let sdk_config = load_sdk_config(paths, profile_name).await;
+        let region = sdk_config
+            .region()
+            .map(|region| region.to_string())
+            .ok_or_else(|| anyhow::anyhow!("'{profile_name}' has no region"))?;
+
+        let names = match settings.clusters_for(profile_name) {
+            Some(names) => names,
+            None => list_eks_clusters(&sdk_config).await?,
+        };
+        let mut clusters = Vec::new();
+        for name in &names {
+            // One cluster that can't be described leaves the others in sync.
+            match describe_eks_cluster(&sdk_config, name).await {
+                Ok(cluster) => clusters.push(cluster),
+                Err(e) => tracing::warn!("Skipping EKS cluster '{name}': {e:#}"),
+            }
+        }
+        anyhow::Ok((region, clusters))
+    };
+    let (region, clusters) =
+        block_on(async { tokio::time::timeout(DEFAULT_TIMEOUT, lookup).await })?
+            .map_err(|_| anyhow::anyhow!("No answer from EKS in time"))??;
+
+    if clusters.is_empty() {
+        return Ok(None);
+    }
+
+    let path = get_kubeconfig_path()?;
+    let mut kubeconfig = match fs::read_to_string(&path) {
+        Ok(content) => serde_yaml::from_str(&content)
+            .with_context(|| format!("Failed to parse {}", path.display()))?,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Value::Null,
+        Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+    };
+    update_kubeconfig(&mut kubeconfig, &clusters, profile_name, &region)?;
+
+    let contents = serde_yaml::to_string(&kubeconfig)?;
+    ensure_writable(format_args!("write {}", path.display()))?;
+    if !would_write(&path, &contents) {
+        // The kubeconfig may hold other clusters' credentials; kubectl warns
+        // about one others can read.
+        write_atomically(&path, contents, Access::Private)?;
+    }
+
+    Ok(Some(clusters[0].arn.clone()))
+}
+
+/// Adds or replaces the cluster, user, and context entries (all named after
+/// the cluster ARN, as the AWS CLI does) and selects the first cluster.
+fn update_kubeconfig(
+    kubeconfig: &mut Value,
+    clusters: &[EksCluster],
+    profile_name: &str,
+    region: &str,
+) -> Result<()> {
+    if kubeconfig.is_null() {
+        *kubeconfig = serde_yaml::from_str("apiVersion: v1\nkind: Config\npreferences: {}")?;
+    }
+    let config = kubeconfig
+        .as_mapping_mut()
+        .ok_or_else(|| anyhow::anyhow!("The kubeconfig is not a YAML mapping"))?;
+
+    for cluster in clusters {
+        let cluster_entry = serde_yaml::to_value(BTreeMap::from([
+            ("certificate-authority-data", &cluster.certificate_authority),
+            ("server", &cluster.endpoint),
+        ]))?;
+        let context_entry = serde_yaml::to_value(BTreeMap::from([
+            ("cluster", &cluster.arn),
+            ("user", &cluster.arn),
+        ]))?;
+        let user_entry = serde_yaml::to_value(serde_json::json!({
+            "exec": {
+                "apiVersion": "client.authentication.k8s.io/v1beta1",
+                "command": "aws",
+                "args": [
+                    "--region", region, "eks", "get-token",
+                    "--cluster-name", cluster.name, "--output", "json",
+                ],
+                "env": [{"name": "AWS_PROFILE", "value": profile_name}],
+            }
+        }))?;
+
+        upsert(config, "clusters", &cluster.arn, "cluster", cluster_entry)?;
+        upsert(config, "users", &cluster.arn, "user", user_entry)?;
+        upsert(config, "contexts", &cluster.arn, "context", context_entry)?;
+    }
+
+    config.insert(
+        Value::from("current-context"),
+        Value::from(clusters[0].arn.as_str()),
+    );
+    Ok(())
+}
+
+/// Replaces the `{name, <key>}` item called `name` in the `section` list, or
+/// appends one.
+fn upsert(config: &mut Mapping, section: &str, name: &str, key: &str, entry: Value) -> Result<()> {
+    let items = config
+        .entry(Value::from(section))
+        .or_insert_with(|| Value::Sequence(Vec::new()));
+    if items.is_null() {
+        *items = Value::Sequence(Vec::new());
+    }
+    let items = items
+        .as_sequence_mut()
+        .ok_or_else(|| anyhow::anyhow!("The kubeconfig's `{section}` is not a list"))?;
+
+    let mut item = Mapping::new();
+    item.insert(Value::from(key), entry);
+    item.insert(Value::from("name"), Value::from(name));
+
+    match items
+        .iter_mut()
+        .find(|existing| existing.get("name").and_then(Value::as_str) == Some(name))
+    {
+        Some(existing) => *existing = Value::Mapping(item),
+        None => items.push(Value::Mapping(item)),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_kubeconfig_replaces_own_entries() {
+        let arn = "arn:aws:eks:eu-west-1:987654321098:cluster/payments";
+        let mut kubeconfig: Value = serde_yaml::from_str(&format!(
+            r#"
+apiVersion: v1
+kind: Config
+current-context: minikube
+clusters:
+- name: minikube
+  cluster: {{server: "https://127.0.0.1:8443"}}
+- name: {arn}
+  cluster: {{server: "https://old.example.com"}}
+contexts:
+- name: minikube
+  context: {{cluster: minikube, user: minikube}}
+users:
+"#
+        ))
+        .unwrap();
+        let cluster = EksCluster {
+            name: "payments".to_string(),
+            arn: arn.to_string(),
+            endpoint: "https://new.example.com".to_string(),
+            certificate_authority: "Q0E=".to_string(),
+        };
+
+        update_kubeconfig(&mut kubeconfig, &[cluster], "prod", "eu-west-1").unwrap();
+
+        assert_eq!(kubeconfig["current-context"], arn);
+        let clusters = kubeconfig["clusters"].as_sequence().unwrap();
+        assert_eq!(clusters.len(), 2);
+        assert_eq!(clusters[1]["cluster"]["server"], "https://new.example.com");
+        assert_eq!(kubeconfig["contexts"].as_sequence().unwrap().len(), 2);
+        let exec = &kubeconfig["users"][0]["user"]["exec"];
+        assert_eq!(exec["env"][0]["value"], "prod");
+        assert_eq!(exec["args"][1], "eu-west-1");
+    }
+
+    #[test]
+    fn test_configured_clusters_win_over_listing() {
+        let settings = EksSettings {
+            sync: true,
+            clusters: BTreeMap::from([
+                ("*prod*".to_string(), vec!["payments".to_string()]),
+                ("prod".to_string(), vec!["ledger".to_string()]),
+            ]),
+        };
+
+        assert_eq!(
+            settings.clusters_for("prod"),
+            Some(vec!["ledger".to_string(), "payments".to_string()])
+        );
+        assert_eq!(settings.clusters_for("dev"), None);
+    }
+}
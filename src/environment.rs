@@ -0,0 +1,288 @@
+//! What a run of the tool takes from outside the process: the home
+//! directory, the environment variables (which also place the AWS files, the
+//! settings, the state, the caches, and temporary files), and the clock.
+//! Every command finds its files through [`Environment::current`], so the
+//! paths here are the ones the tool uses.
+//!
+//! [`Environment::isolated`] builds one under a directory of its own, so the
+//! binary can be run end to end without touching the real `~/.aws`:
+//!
+//! ```no_run
+//! use aws_profile_selector::Environment;
+//!
+//! let root = std::env::temp_dir().join("aws-profile-selector-demo");
+//! let env = Environment::isolated(&root)?.with_clock("2026-01-05T09:00:00Z".parse()?);
+//! std::fs::write(env.config_file(), "[profile dev]\nregion = eu-west-1\n")?;
+//! let output = env
+//!     .command("aws-profile-selector")
+//!     .args(["activate", "dev"])
+//!     .output()?;
+//! assert!(output.status.success());
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+
+use crate::config::{expand_tilde, home_dir};
+use crate::settings::SETTINGS_PATH_VAR;
+use crate::state::{STATE_DIR_VAR, STATE_FILES};
+use anyhow::{Context, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use std::collections::BTreeMap;
+use std::ffi::OsStr;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::sync::OnceLock;
+use std::time::Instant;
+
+/// An RFC 3339 time the clock starts from instead of the system's.
+pub const CLOCK_VAR: &str = "AWS_PROFILE_SELECTOR_NOW";
+
+/// The variables an isolated environment keeps from the calling process, so
+/// hooks and editors can still be found.
+const INHERITED: [&str; 3] = ["PATH", "SYSTEMROOT", "LANG"];
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Environment {
+    home: PathBuf,
+    vars: BTreeMap<String, String>,
+}
+
+impl Environment {
+    /// The process's own environment.
+    pub fn current() -> Result<Self> {
+        Ok(Self {
+            home: home_dir()?,
+            vars: std::env::vars().collect(),
+        })
+    }
+
+    /// A fresh environment under `root`: a home with an empty `.aws`, and
+    /// the settings, state, cache, and temporary directories beside it.
+    /// Only `PATH` and a few other variables are kept from this process;
+    /// `AWS_PROFILE` and the like are not.
+    pub fn isolated(root: &Path) -> Result<Self> {
+        let home = root.join("home");
+        let dir = |name: &str| -> Result<String> {
+            let dir = root.join(name);
+            fs::create_dir_all(&dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+            Ok(dir.to_string_lossy().to_string())
+        };
+        dir("home/.aws")?;
+
+        let mut vars: BTreeMap<String, String> = INHERITED
+            .into_iter()
+            .filter_map(|name| Some((name.to_string(), std::env::var(name).ok()?)))
+            .collect();
+        let path = |path: PathBuf| path.to_string_lossy().to_string();
+        vars.extend([
+            ("HOME".to_string(), path(home.clone())),
+            ("USERPROFILE".to_string(), path(home.clone())),
+            ("XDG_CONFIG_HOME".to_string(), dir("config")?),
+            ("XDG_STATE_HOME".to_string(), dir("state")?),
+            ("XDG_CACHE_HOME".to_string(), dir("cache")?),
+            ("TMPDIR".to_string(), dir("tmp")?),
+            (
+                "AWS_CONFIG_FILE".to_string(),
+                path(home.join(".aws/config")),
+            ),
+            (
+                "AWS_SHARED_CREDENTIALS_FILE".to_string(),
+                path(home.join(".aws/credentials")),
+            ),
+            // Nothing to ask for credentials on a test machine.
+            ("AWS_EC2_METADATA_DISABLED".to_string(), "true".to_string()),
+        ]);
+        Ok(Self { home, vars })
+    }
+
+    /// The same environment with `name` set to `value`.
+    pub fn with_var(mut self, name: &str, value: impl Into<String>) -> Self {
+        self.vars.insert(name.to_string(), value.into());
+        self
+    }
+
+    /// The same environment without `name`.
+    pub fn without_var(mut self, name: &str) -> Self {
+        self.vars.remove(name);
+        self
+    }
+
+    /// The same environment with its clock starting at `time`.
+    pub fn with_clock(self, time: DateTime<Utc>) -> Self {
+        self.with_var(CLOCK_VAR, time.to_rfc3339())
+    }
+
+    pub fn home(&self) -> &Path {
+        &self.home
+    }
+
+    pub fn var(&self, name: &str) -> Option<&str> {
+        self.vars
+            .get(name)
+            .map(String::as_str)
+            .filter(|value| !value.is_empty())
+    }
+
+    pub fn vars(&self) -> &BTreeMap<String, String> {
+        &self.vars
+    }
+
+    /// `AWS_CONFIG_FILE` (a leading `~` is the home directory, as the AWS
+    /// CLI has it), else `~/.aws/config`.
+    pub fn config_file(&self) -> PathBuf {
+        self.aws_path_var("AWS_CONFIG_FILE")
+            .unwrap_or_else(|| self.home.join(".aws").join("config"))
+    }
+
+    /// `AWS_SHARED_CREDENTIALS_FILE`, else `~/.aws/credentials`.
+    pub fn credentials_file(&self) -> PathBuf {
+        self.aws_path_var("AWS_SHARED_CREDENTIALS_FILE")
+            .unwrap_or_else(|| self.home.join(".aws").join("credentials"))
+    }
+
+    /// `$AWS_PROFILE_SELECTOR_CONFIG`, else `$XDG_CONFIG_HOME` or `~/.config`
+    /// followed by `aws-profile-selector/config.toml`.
+    pub fn settings_file(&self) -> PathBuf {
+        self.path_var(SETTINGS_PATH_VAR).unwrap_or_else(|| {
+            self.path_var("XDG_CONFIG_HOME")
+                .unwrap_or_else(|| self.home.join(".config"))
+                .join("aws-profile-selector")
+                .join("config.toml")
+        })
+    }
+
+    /// Where the current profile and the state file are kept:
+    ///
+    /// 1. `$AWS_PROFILE_SELECTOR_STATE_DIR`, or
+    ///    `$XDG_STATE_HOME/aws-profile-selector`.
+    /// 2. `~/.aws`, while it has the files older versions kept there and
+    ///    neither is set.
+    /// 3. `~/.local/state/aws-profile-selector`.
+    ///
+    /// A run moves files left in `~/.aws` to the directory chosen in 1 (see
+    /// [`crate::state::get_state_dir`]), except with `--read-only`.
+    pub fn state_dir(&self) -> PathBuf {
+        self.chosen_state_dir().unwrap_or_else(|| {
+            let legacy = self.legacy_state_dir();
+            match STATE_FILES.iter().any(|name| legacy.join(name).exists()) {
+                true => legacy,
+                false => self.home.join(".local/state/aws-profile-selector"),
+            }
+        })
+    }
+
+    /// The state directory a variable picks, if one does.
+    pub(crate) fn chosen_state_dir(&self) -> Option<PathBuf> {
+        self.path_var(STATE_DIR_VAR).or_else(|| {
+            self.path_var("XDG_STATE_HOME")
+                .map(|dir| dir.join("aws-profile-selector"))
+        })
+    }
+
+    /// `~/.aws`, where older versions kept the state files.
+    pub(crate) fn legacy_state_dir(&self) -> PathBuf {
+        self.home.join(".aws")
+    }
+
+    /// `$XDG_CACHE_HOME` or `~/.cache`, followed by `aws-profile-selector`.
+    pub fn cache_dir(&self) -> PathBuf {
+        self.path_var("XDG_CACHE_HOME")
+            .unwrap_or_else(|| self.home.join(".cache"))
+            .join("aws-profile-selector")
+    }
+
+    /// `program`, to be run with exactly these variables and from the home
+    /// directory.
+    pub fn command(&self, program: impl AsRef<OsStr>) -> Command {
+        let mut command = Command::new(program);
+        command.env_clear().envs(&self.vars).current_dir(&self.home);
+        command
+    }
+
+    fn path_var(&self, name: &str) -> Option<PathBuf> {
+        self.var(name).map(PathBuf::from)
+    }
+
+    fn aws_path_var(&self, name: &str) -> Option<PathBuf> {
+        self.var(name).map(|value| expand_tilde(value, &self.home))
+    }
+}
+
+/// The time the tool goes by: the system clock, or one running from
+/// `$AWS_PROFILE_SELECTOR_NOW` when that is set.
+pub fn now() -> DateTime<Utc> {
+    static START: OnceLock<Option<(DateTime<Utc>, Instant)>> = OnceLock::new();
+    let start = START.get_or_init(|| {
+        let time = std::env::var(CLOCK_VAR).ok()?;
+        match DateTime::parse_from_rfc3339(&time) {
+            Ok(time) => Some((time.with_timezone(&Utc), Instant::now())),
+            Err(e) => {
+                tracing::warn!("Ignoring {CLOCK_VAR}={time}: {e}");
+                None
+            }
+        }
+    });
+    match start {
+        Some((time, started)) => *time + TimeDelta::from_std(started.elapsed()).unwrap_or_default(),
+        None => Utc::now(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_isolated_environment() {
+        let root = temp_dir("environment");
+        let env = Environment::isolated(&root)
+            .unwrap()
+            .with_var("AWS_PROFILE", "dev")
+            .with_clock("2026-01-05T09:00:00Z".parse().unwrap());
+
+        assert_eq!(env.home(), root.join("home"));
+        assert!(root.join("home/.aws").is_dir());
+        assert_eq!(env.config_file(), root.join("home/.aws/config"));
+        assert_eq!(
+            env.settings_file(),
+            root.join("config/aws-profile-selector/config.toml")
+        );
+        assert_eq!(env.state_dir(), root.join("state/aws-profile-selector"));
+        assert_eq!(
+            env.clone()
+                .with_var("AWS_CONFIG_FILE", "~/aws")
+                .config_file(),
+            root.join("home/aws")
+        );
+
+        // Without a variable choosing one, files in ~/.aws keep it in use.
+        let unchosen = env.clone().without_var("XDG_STATE_HOME");
+        assert_eq!(
+            unchosen.state_dir(),
+            root.join("home/.local/state/aws-profile-selector")
+        );
+        fs::write(root.join("home/.aws/current-profile"), "dev").unwrap();
+        assert_eq!(unchosen.state_dir(), root.join("home/.aws"));
+        assert_eq!(env.state_dir(), root.join("state/aws-profile-selector"));
+        assert_eq!(env.var(CLOCK_VAR), Some("2026-01-05T09:00:00+00:00"));
+        assert_eq!(
+            env.clone().without_var("AWS_PROFILE").var("AWS_PROFILE"),
+            None
+        );
+
+        #[cfg(unix)]
+        {
+            let output = env
+                .command("sh")
+                .args(["-c", "echo \"$AWS_PROFILE $HOME\""])
+                .output()
+                .unwrap();
+            assert_eq!(
+                String::from_utf8_lossy(&output.stdout).trim(),
+                format!("dev {}", root.join("home").display())
+            );
+        }
+    }
+}
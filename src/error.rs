@@ -0,0 +1,128 @@
+//! The failures scripts need to tell apart, each with its own exit status.
+//! Commands return them through `anyhow` like any other error; `main` looks
+//! for one in the chain when choosing the status to exit with.
+//!
+//! | Status | Meaning                                                |
+//! |--------|--------------------------------------------------------|
+//! | 0      | Success                                                |
+//! | 1      | Any other failure                                      |
+//! | 2      | Cancelled: Esc, Ctrl-C, or nothing picked              |
+//! | 3      | Not found: no such profile, no single one, or none set |
+//! | 4      | Config error: the AWS config or settings can't be used |
+//! | 64     | Usage error: a flag or argument clap rejected          |
+
+use serde::Serialize;
+use std::fmt;
+
+pub const EXIT_FAILURE: i32 = 1;
+pub const EXIT_CANCELLED: i32 = 2;
+pub const EXIT_NOT_FOUND: i32 = 3;
+pub const EXIT_CONFIG: i32 = 4;
+/// `EX_USAGE`, in place of clap's 2, which means cancelled here.
+pub const EXIT_USAGE: i32 = 64;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Error {
+    /// The user backed out of a prompt; the message says of which.
+    Cancelled(&'static str),
+    /// A profile, file, or other named thing that isn't there.
+    NotFound(String),
+    /// The AWS config or the settings file is missing or can't be parsed.
+    Config(String),
+}
+
+impl Error {
+    /// The prompt was dismissed.
+    pub fn cancelled() -> Self {
+        Self::Cancelled("Cancelled")
+    }
+
+    /// `Profile 'name' not found in AWS config`.
+    pub fn profile_not_found(profile_name: &str) -> Self {
+        Self::NotFound(format!("Profile '{profile_name}' not found in AWS config"))
+    }
+
+    /// Keeps the whole chain of `error` as the message.
+    pub fn config(error: anyhow::Error) -> Self {
+        Self::Config(format!("{error:#}"))
+    }
+
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            Self::Cancelled(_) => EXIT_CANCELLED,
+            Self::NotFound(_) => EXIT_NOT_FOUND,
+            Self::Config(_) => EXIT_CONFIG,
+        }
+    }
+
+    fn kind(&self) -> &'static str {
+        match self {
+            Self::Cancelled(_) => "cancelled",
+            Self::NotFound(_) => "not_found",
+            Self::Config(_) => "config",
+        }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Cancelled(message) => f.write_str(message),
+            Self::NotFound(message) | Self::Config(message) => f.write_str(message),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
+
+/// What `--output json` prints on stderr for a failed command.
+#[derive(Debug, Serialize)]
+pub struct ErrorReport {
+    pub kind: &'static str,
+    pub message: String,
+    pub exit_code: i32,
+}
+
+impl ErrorReport {
+    pub fn new(error: &anyhow::Error) -> Self {
+        let typed = error.downcast_ref::<Error>();
+        Self {
+            kind: typed.map_or("error", Error::kind),
+            message: format!("{error:#}"),
+            exit_code: exit_code(error),
+        }
+    }
+}
+
+/// The status to exit with after `error`.
+pub fn exit_code(error: &anyhow::Error) -> i32 {
+    error
+        .downcast_ref::<Error>()
+        .map_or(EXIT_FAILURE, Error::exit_code)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn test_exit_codes_survive_context() {
+        let error = anyhow::Error::new(Error::profile_not_found("dev"));
+        assert_eq!(exit_code(&error), EXIT_NOT_FOUND);
+
+        let error = Err::<(), _>(Error::cancelled())
+            .context("Failed to pick a region")
+            .unwrap_err();
+        assert_eq!(exit_code(&error), EXIT_CANCELLED);
+
+        let report = ErrorReport::new(&error);
+        assert_eq!(report.kind, "cancelled");
+        assert_eq!(report.message, "Failed to pick a region: Cancelled");
+        assert_eq!(report.exit_code, EXIT_CANCELLED);
+
+        let error = anyhow::anyhow!("Something else");
+        assert_eq!(exit_code(&error), EXIT_FAILURE);
+        assert_eq!(ErrorReport::new(&error).kind, "error");
+    }
+}
@@ -0,0 +1,206 @@
+//! Writing the files the tool changes. Every write goes to a temporary file
+//! next to the target that is then renamed over it, so other processes (and
+//! a crash halfway) see the old file or the new one, never part of one. The
+//! temporary file is created with `create_new`, which won't follow a symlink
+//! or open a file someone else left at its path, and is only readable by
+//! its owner until it has its final permissions.
+//!
+//! [`TempDir`] is for files that must exist only while a command runs: a
+//! directory with a random name that only its owner can enter, removed
+//! again when dropped.
+
+use anyhow::{Context, Result};
+use std::fs;
+use std::hash::{BuildHasher, RandomState};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Who may read a file [`write_atomically`] writes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Access {
+    /// Only its owner, whatever the file allowed before.
+    Private,
+    /// What the file allowed before; only its owner when it is new, as the
+    /// AWS CLI creates `~/.aws/credentials`.
+    KeepOrPrivate,
+    /// What the file allowed before; the usual permissions when it is new.
+    KeepOrDefault,
+}
+
+/// Replaces `path` with `contents`. When `path` is a symlink, the file it
+/// points to is replaced and the link kept.
+pub fn write_atomically(path: &Path, contents: impl AsRef<[u8]>, access: Access) -> Result<()> {
+    static WRITES: AtomicUsize = AtomicUsize::new(0);
+
+    let target = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_symlink() => path
+            .canonicalize()
+            .with_context(|| format!("Failed to follow the symlink {}", path.display()))?,
+        _ => path.to_path_buf(),
+    };
+    if let Some(parent) = target
+        .parent()
+        .filter(|parent| !parent.as_os_str().is_empty())
+    {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let existing = fs::metadata(&target)
+        .ok()
+        .map(|metadata| metadata.permissions());
+
+    let file_name = target
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temporary = target.with_file_name(format!(
+        ".{file_name}.{}.{}.tmp",
+        std::process::id(),
+        WRITES.fetch_add(1, Ordering::Relaxed)
+    ));
+    let private = match access {
+        Access::Private | Access::KeepOrPrivate => true,
+        Access::KeepOrDefault => existing.is_some(),
+    };
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, if private { 0o600 } else { 0o666 });
+
+    let result = options
+        .open(&temporary)
+        .and_then(|mut file| {
+            file.write_all(contents.as_ref())?;
+            file.sync_all()?;
+            match existing {
+                Some(permissions) if access != Access::Private => file.set_permissions(permissions),
+                _ => Ok(()),
+            }
+        })
+        .and_then(|()| fs::rename(&temporary, &target))
+        .with_context(|| format!("Failed to write {}", path.display()));
+    if result.is_err() {
+        let _ = fs::remove_file(&temporary);
+    }
+    result
+}
+
+/// Opens `path` to append to, creating it only readable by its owner.
+pub fn append_private(path: &Path) -> Result<fs::File> {
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)
+            .with_context(|| format!("Failed to create {}", parent.display()))?;
+    }
+    let mut options = fs::OpenOptions::new();
+    options.append(true).create(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+    options
+        .open(path)
+        .with_context(|| format!("Failed to open {}", path.display()))
+}
+
+/// A directory in the system's temporary directory with a fresh random name
+/// starting with `aws-profile-selector-<purpose>-`, which only its owner can
+/// enter. It is removed with everything in it when dropped.
+#[derive(Debug)]
+pub struct TempDir {
+    path: PathBuf,
+}
+
+impl TempDir {
+    pub fn new(purpose: &str) -> Result<Self> {
+        let mut builder = fs::DirBuilder::new();
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+
+        let mut attempts = 0;
+        loop {
+            let name = format!(
+                "aws-profile-selector-{purpose}-{:016x}",
+                RandomState::new().hash_one((std::process::id(), attempts))
+            );
+            let path = std::env::temp_dir().join(name);
+            // `create` fails on anything already there, a symlink included.
+            match builder.create(&path) {
+                Ok(()) => return Ok(Self { path }),
+                Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && attempts < 16 => {
+                    attempts += 1;
+                }
+                Err(e) => {
+                    return Err(e).with_context(|| format!("Failed to create {}", path.display()));
+                }
+            }
+        }
+    }
+}
+
+impl AsRef<Path> for TempDir {
+    fn as_ref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl std::ops::Deref for TempDir {
+    type Target = Path;
+
+    fn deref(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl Drop for TempDir {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[cfg(unix)]
+    fn mode(path: &Path) -> u32 {
+        use std::os::unix::fs::PermissionsExt;
+        fs::metadata(path).unwrap().permissions().mode() & 0o777
+    }
+
+    #[test]
+    fn test_writes_replace_the_file_with_its_permissions() {
+        let dir = TempDir::new("files").unwrap();
+        let others = TempDir::new("files").unwrap();
+        assert_ne!(&dir.path, &others.path);
+
+        let new = dir.join("nested/credentials");
+        write_atomically(&new, "[dev]\n", Access::KeepOrPrivate).unwrap();
+        assert_eq!(fs::read_to_string(&new).unwrap(), "[dev]\n");
+
+        // A symlink is followed, not replaced.
+        let target = others.join("config");
+        fs::write(&target, "old").unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            assert_eq!(mode(&new), 0o600);
+            assert_eq!(mode(&dir.path), 0o700);
+
+            fs::set_permissions(&target, fs::Permissions::from_mode(0o644)).unwrap();
+            let link = dir.join("config");
+            std::os::unix::fs::symlink(&target, &link).unwrap();
+            write_atomically(&link, "new", Access::KeepOrPrivate).unwrap();
+            assert!(fs::symlink_metadata(&link).unwrap().is_symlink());
+            assert_eq!(fs::read_to_string(&target).unwrap(), "new");
+            assert_eq!(mode(&target), 0o644);
+
+            write_atomically(&target, "secret", Access::Private).unwrap();
+            assert_eq!(mode(&target), 0o600);
+            // No temporary files left behind.
+            assert_eq!(fs::read_dir(&others.path).unwrap().count(), 1);
+        }
+
+        let path = &dir.path.to_path_buf();
+        drop(dir);
+        assert!(!path.exists());
+    }
+}
@@ -0,0 +1,439 @@
+use crate::accounts::{AccountNames, account_name};
+use crate::config::Profile;
+use anyhow::Result;
+use regex::Regex;
+use std::collections::BTreeMap;
+use std::str::FromStr;
+
+/// What the settings file (and account alias lookups) attach to profiles
+/// beyond the AWS config. Filters match these too.
+#[derive(Debug, Clone, Default)]
+pub struct Annotations {
+    pub account_names: AccountNames,
+    /// Tags by profile name or glob pattern.
+    pub tags: BTreeMap<String, Vec<String>>,
+}
+
+impl Annotations {
+    pub fn account_name(&self, profile: &Profile) -> Option<&str> {
+        account_name(&self.account_names, profile)
+    }
+
+    /// The tags of every pattern matching `profile_name`, sorted and without
+    /// duplicates.
+    pub fn tags_for(&self, profile_name: &str) -> Vec<&str> {
+        let mut tags: Vec<&str> = self
+            .tags
+            .iter()
+            .filter(|(pattern, _)| glob_match(pattern, profile_name))
+            .flat_map(|(_, tags)| tags.iter().map(String::as_str))
+            .collect();
+        tags.sort_unstable();
+        tags.dedup();
+        tags
+    }
+}
+
+/// A profile field that filters can be applied to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FilterField {
+    Name,
+    Region,
+    Account,
+    Role,
+    Tag,
+}
+
+impl FilterField {
+    /// Every value of the field that a filter may match: account filters
+    /// also match the account's name, account and role filters the account
+    /// and role in `role_arn`, and tag filters any of the tags.
+    fn values<'a>(&self, profile: &'a Profile, annotations: &'a Annotations) -> Vec<&'a str> {
+        match self {
+            FilterField::Name => vec![&profile.name],
+            FilterField::Region => profile.get_region().into_iter().collect(),
+            FilterField::Account => [
+                profile.get_account_id(),
+                profile.role_arn_parts().map(|(account_id, _)| account_id),
+                annotations.account_name(profile),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+            FilterField::Role => [
+                profile.get_role_name(),
+                profile.role_arn_parts().map(|(_, role)| role),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+            FilterField::Tag => annotations.tags_for(&profile.name),
+        }
+    }
+}
+
+impl FromStr for FilterField {
+    type Err = anyhow::Error;
+
+    fn from_str(field: &str) -> Result<Self> {
+        match field {
+            "name" => Ok(FilterField::Name),
+            "region" => Ok(FilterField::Region),
+            "account" | "account_id" => Ok(FilterField::Account),
+            "role" | "role_name" => Ok(FilterField::Role),
+            "tag" => Ok(FilterField::Tag),
+            _ => Err(anyhow::anyhow!(
+                "Unknown filter field '{field}' (expected name, region, account, role, or tag)"
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum FilterOp {
+    /// `field=value`: the field equals the value, which may be a glob.
+    Equals,
+    /// `field~value`: the field contains the value.
+    Contains,
+    /// `field:value` in an interactive query: the field contains the value,
+    /// ignoring case.
+    ContainsIgnoreCase,
+    /// Either of the first two with `--regex`: the value is a regex.
+    Regex(Pattern),
+}
+
+/// A single `field=value` or `field~value` filter expression.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ProfileFilter {
+    field: FilterField,
+    op: FilterOp,
+    value: String,
+}
+
+impl ProfileFilter {
+    /// A `field=value` filter.
+    pub fn equals(field: FilterField, value: &str) -> Self {
+        Self {
+            field,
+            op: FilterOp::Equals,
+            value: value.to_string(),
+        }
+    }
+
+    pub fn matches(&self, profile: &Profile, annotations: &Annotations) -> bool {
+        self.field
+            .values(profile, annotations)
+            .into_iter()
+            .any(|actual| self.matches_value(actual))
+    }
+
+    /// The same filter with its value as a regex: `field=value` has to match
+    /// the whole field, `field~value` any part of it.
+    pub fn into_regex(self) -> Result<Self> {
+        let pattern = match self.op {
+            FilterOp::Equals => {
+                // Checked on its own so errors show what was typed.
+                Pattern::new(&self.value, true)?;
+                Pattern::new(&format!("^(?:{})$", self.value), true)?
+            }
+            FilterOp::Contains => Pattern::new(&self.value, true)?,
+            FilterOp::ContainsIgnoreCase | FilterOp::Regex(_) => return Ok(self),
+        };
+        Ok(Self {
+            op: FilterOp::Regex(pattern),
+            ..self
+        })
+    }
+
+    fn matches_value(&self, actual: &str) -> bool {
+        match &self.op {
+            FilterOp::Equals => glob_match(&self.value, actual),
+            FilterOp::Contains => actual.contains(&self.value),
+            FilterOp::ContainsIgnoreCase => {
+                actual.to_lowercase().contains(&self.value.to_lowercase())
+            }
+            FilterOp::Regex(pattern) => pattern.matches(actual),
+        }
+    }
+}
+
+impl FromStr for ProfileFilter {
+    type Err = anyhow::Error;
+
+    fn from_str(expression: &str) -> Result<Self> {
+        let (index, op) = expression
+            .char_indices()
+            .find_map(|(index, c)| match c {
+                '=' => Some((index, FilterOp::Equals)),
+                '~' => Some((index, FilterOp::Contains)),
+                _ => None,
+            })
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Invalid filter '{expression}' (expected field=value or field~value)"
+                )
+            })?;
+
+        Ok(Self {
+            field: expression[..index].trim().parse()?,
+            op,
+            value: expression[index + 1..].trim().to_string(),
+        })
+    }
+}
+
+/// What was typed into the interactive filter: `field:value` tokens become
+/// attribute filters and the remaining words are matched against the
+/// profile's display string, e.g. `region:us-east-1 role:ReadOnly web`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Query {
+    pub filters: Vec<ProfileFilter>,
+    pub text: String,
+}
+
+impl Query {
+    /// Tokens whose prefix isn't a known field (or whose value is still
+    /// empty) are kept as search text, so typing `prod:` never hides
+    /// everything.
+    pub fn parse(input: &str) -> Self {
+        let mut filters = Vec::new();
+        let mut words = Vec::new();
+
+        for token in input.split_whitespace() {
+            let filter = token.split_once(':').and_then(|(field, value)| {
+                let field = field.parse().ok()?;
+                (!value.is_empty()).then(|| ProfileFilter {
+                    field,
+                    op: FilterOp::ContainsIgnoreCase,
+                    value: value.to_string(),
+                })
+            });
+            match filter {
+                Some(filter) => filters.push(filter),
+                None => words.push(token),
+            }
+        }
+
+        Self {
+            filters,
+            text: words.join(" "),
+        }
+    }
+
+    pub fn matches(&self, profile: &Profile, annotations: &Annotations) -> bool {
+        matches_all(&self.filters, profile, annotations)
+    }
+}
+
+/// How commands that target several profiles by name match them: a
+/// shell-style glob (see [`glob_match`]), or with `--regex` a regular
+/// expression, which may match anywhere in the name unless anchored.
+#[derive(Debug, Clone)]
+pub enum Pattern {
+    Glob(String),
+    Regex(Regex),
+}
+
+impl Pattern {
+    pub fn new(pattern: &str, regex: bool) -> Result<Self> {
+        match regex {
+            true => Regex::new(pattern)
+                .map(Pattern::Regex)
+                .map_err(|e| anyhow::anyhow!("Invalid regex '{pattern}': {e}")),
+            false => Ok(Pattern::Glob(pattern.to_string())),
+        }
+    }
+
+    /// Whether `text` has glob wildcards, so a name given on the command
+    /// line is taken as a pattern rather than a profile name.
+    pub fn has_wildcards(text: &str) -> bool {
+        text.contains(['*', '?'])
+    }
+
+    pub fn matches(&self, text: &str) -> bool {
+        match self {
+            Pattern::Glob(pattern) => glob_match(pattern, text),
+            Pattern::Regex(regex) => regex.is_match(text),
+        }
+    }
+}
+
+impl PartialEq for Pattern {
+    fn eq(&self, other: &Self) -> bool {
+        match (self, other) {
+            (Pattern::Glob(a), Pattern::Glob(b)) => a == b,
+            (Pattern::Regex(a), Pattern::Regex(b)) => a.as_str() == b.as_str(),
+            _ => false,
+        }
+    }
+}
+
+impl Eq for Pattern {}
+
+/// Matches `text` against a shell-style glob where `*` matches any run of
+/// characters and `?` matches exactly one.
+pub fn glob_match(pattern: &str, text: &str) -> bool {
+    let pattern: Vec<char> = pattern.chars().collect();
+    let text: Vec<char> = text.chars().collect();
+    let (mut p, mut t) = (0, 0);
+    // Where to resume after the last `*`: its position in the pattern and
+    // how much of the text it has absorbed so far.
+    let mut backtrack = None;
+
+    while t < text.len() {
+        match pattern.get(p) {
+            Some('*') => {
+                backtrack = Some((p, t));
+                p += 1;
+            }
+            Some(&c) if c == '?' || c == text[t] => {
+                p += 1;
+                t += 1;
+            }
+            _ => match backtrack {
+                Some((star, absorbed)) => {
+                    p = star + 1;
+                    t = absorbed + 1;
+                    backtrack = Some((star, absorbed + 1));
+                }
+                None => return false,
+            },
+        }
+    }
+
+    pattern[p..].iter().all(|&c| c == '*')
+}
+
+/// Returns whether a profile satisfies every filter.
+pub fn matches_all(
+    filters: &[ProfileFilter],
+    profile: &Profile,
+    annotations: &Annotations,
+) -> bool {
+    filters
+        .iter()
+        .all(|filter| filter.matches(profile, annotations))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::profile;
+
+    #[test]
+    fn test_parse_and_match_filters() {
+        let dev = profile(
+            "team-dev",
+            &[("region", "us-west-2"), ("sso_account_id", "123456789012")],
+        );
+
+        let region: ProfileFilter = "region=us-west-2".parse().unwrap();
+        let name: ProfileFilter = "name~dev".parse().unwrap();
+        let account: ProfileFilter = "account=999999999999".parse().unwrap();
+        let role: ProfileFilter = "role~Admin".parse().unwrap();
+
+        let none = Annotations::default();
+        assert!(region.matches(&dev, &none));
+        assert!(name.matches(&dev, &none));
+        assert!(!account.matches(&dev, &none));
+        assert!(!role.matches(&dev, &none));
+        assert!(matches_all(&[region.clone(), name], &dev, &none));
+        assert!(!matches_all(&[region, account], &dev, &none));
+        assert!(ProfileFilter::equals(FilterField::Region, "us-west-2").matches(&dev, &none));
+
+        let assumed = profile(
+            "team-admin",
+            &[("role_arn", "arn:aws:iam::123456789012:role/ops/Admin")],
+        );
+        assert!(role.matches(&assumed, &none));
+        assert!(
+            ProfileFilter::equals(FilterField::Account, "123456789012").matches(&assumed, &none)
+        );
+
+        let annotations = Annotations {
+            account_names: AccountNames::from([(
+                "123456789012".to_string(),
+                "payments-dev".to_string(),
+            )]),
+            tags: BTreeMap::from([
+                ("team-*".to_string(), vec!["sandbox".to_string()]),
+                (
+                    "*-dev".to_string(),
+                    vec!["client-x".to_string(), "sandbox".to_string()],
+                ),
+            ]),
+        };
+        let nickname: ProfileFilter = "account=payments-dev".parse().unwrap();
+        assert!(nickname.matches(&dev, &annotations));
+        assert!(!nickname.matches(&dev, &none));
+        assert!(Query::parse("account:payments").matches(&dev, &annotations));
+
+        assert_eq!(annotations.tags_for("team-dev"), ["client-x", "sandbox"]);
+        let tag: ProfileFilter = "tag=client-x".parse().unwrap();
+        assert!(tag.matches(&dev, &annotations));
+        assert!(!tag.matches(&dev, &none));
+        assert!(!Query::parse("tag:prod").matches(&dev, &annotations));
+    }
+
+    #[test]
+    fn test_parse_query() {
+        let query = Query::parse("region:us-east-1  role:readonly web prod:");
+        assert_eq!(query.text, "web prod:");
+        assert_eq!(query.filters.len(), 2);
+
+        let web = profile(
+            "web",
+            &[("region", "us-east-1"), ("sso_role_name", "ReadOnlyAccess")],
+        );
+        let admin = profile(
+            "web-admin",
+            &[("region", "us-east-1"), ("sso_role_name", "Admin")],
+        );
+        assert!(query.matches(&web, &Annotations::default()));
+        assert!(!query.matches(&admin, &Annotations::default()));
+        assert_eq!(Query::parse("colour:red").text, "colour:red");
+    }
+
+    #[test]
+    fn test_patterns() {
+        let glob = Pattern::new("team-*-prod", false).unwrap();
+        assert!(glob.matches("team-payments-prod"));
+        assert!(!glob.matches("team-payments-dev"));
+        assert!(Pattern::has_wildcards("dev-?"));
+        assert!(!Pattern::has_wildcards("dev"));
+
+        let regex = Pattern::new("^team-(web|api)-", true).unwrap();
+        assert!(regex.matches("team-api-prod"));
+        assert!(!regex.matches("team-db-prod"));
+        assert!(Pattern::new("team-(", true).is_err());
+
+        let none = Annotations::default();
+        let web = profile("team-web-prod", &[]);
+        let name: ProfileFilter = "name=team-*-prod".parse().unwrap();
+        assert!(name.matches(&web, &none));
+        let name: ProfileFilter = "name=team-w.b-prod".parse().unwrap();
+        assert!(!name.matches(&web, &none));
+        assert!(name.into_regex().unwrap().matches(&web, &none));
+        let name: ProfileFilter = "name=web".parse().unwrap();
+        assert!(!name.into_regex().unwrap().matches(&web, &none));
+        let name: ProfileFilter = "name~w[aeiou]b".parse().unwrap();
+        assert!(name.into_regex().unwrap().matches(&web, &none));
+    }
+
+    #[test]
+    fn test_glob_match() {
+        assert!(glob_match("generated-*", "generated-123456789012-Admin"));
+        assert!(glob_match("*-ReadOnly", "team-ReadOnly"));
+        assert!(glob_match("dev-?", "dev-1"));
+        assert!(glob_match("a*b*c", "axxbyyc"));
+        assert!(!glob_match("dev-?", "dev-10"));
+        assert!(!glob_match("prod", "prod-admin"));
+        assert!(glob_match("*", ""));
+    }
+
+    #[test]
+    fn test_invalid_filters() {
+        assert!("region".parse::<ProfileFilter>().is_err());
+        assert!("colour=red".parse::<ProfileFilter>().is_err());
+    }
+}
@@ -0,0 +1,124 @@
+//! Shell commands from the `[hooks]` table of the settings file, run around
+//! profile switches, e.g. to refresh a kubeconfig or restart a local proxy.
+//!
+//! Hooks run through `sh -c` (`cmd /C` on Windows) one after another. Their
+//! output goes to stderr so it never ends up in the commands `-c` prints for
+//! the shell to evaluate.
+
+use crate::dry_run::would;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct HookSettings {
+    /// Run before a profile is activated; a non-zero exit cancels the switch.
+    pub pre_activate: Vec<String>,
+    pub post_activate: Vec<String>,
+    pub post_deactivate: Vec<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Event {
+    PreActivate,
+    PostActivate,
+    PostDeactivate,
+}
+
+impl Event {
+    /// The settings key, also passed to hooks as `AWS_PROFILE_SELECTOR_HOOK`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Event::PreActivate => "pre-activate",
+            Event::PostActivate => "post-activate",
+            Event::PostDeactivate => "post-deactivate",
+        }
+    }
+}
+
+impl HookSettings {
+    pub fn commands(&self, event: Event) -> &[String] {
+        match event {
+            Event::PreActivate => &self.pre_activate,
+            Event::PostActivate => &self.post_activate,
+            Event::PostDeactivate => &self.post_deactivate,
+        }
+    }
+}
+
+/// Runs the hooks for `event` with `env` set (`None` removes a variable).
+/// A failing pre-activate hook is an error and stops the remaining hooks;
+/// other failures are reported and the rest still run.
+pub fn run_hooks(
+    settings: &HookSettings,
+    event: Event,
+    env: &[(&str, Option<String>)],
+) -> Result<()> {
+    for hook in settings.commands(event) {
+        if would(format_args!("run the {} hook `{hook}`", event.name())) {
+            continue;
+        }
+        let (shell, flag) = if cfg!(windows) {
+            ("cmd", "/C")
+        } else {
+            ("sh", "-c")
+        };
+        let mut command = Command::new(shell);
+        command
+            .args([flag, hook])
+            .env("AWS_PROFILE_SELECTOR_HOOK", event.name())
+            .stdout(Stdio::from(std::io::stderr()));
+        for (name, value) in env {
+            match value {
+                Some(value) => command.env(name, value),
+                None => command.env_remove(name),
+            };
+        }
+
+        let status = command
+            .status()
+            .with_context(|| format!("Failed to run {} hook `{hook}`", event.name()))?;
+        if status.success() {
+            continue;
+        }
+
+        let exit = status.code().map_or("was killed".to_string(), |code| {
+            format!("exited with {code}")
+        });
+        if event == Event::PreActivate {
+            return Err(anyhow::anyhow!("pre-activate hook `{hook}` {exit}"));
+        }
+        tracing::warn!("{} hook `{hook}` {exit}", event.name());
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[cfg(unix)]
+    fn test_failing_pre_activate_hook_stops_the_rest() {
+        let settings = HookSettings {
+            pre_activate: vec![
+                "test \"$AWS_PROFILE\" = prod".to_string(),
+                "exit 3".to_string(),
+                "echo unreachable".to_string(),
+            ],
+            post_activate: vec!["exit 1".to_string(), "true".to_string()],
+            ..HookSettings::default()
+        };
+        let env = [("AWS_PROFILE", Some("prod".to_string()))];
+
+        let error = run_hooks(&settings, Event::PreActivate, &env).unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "pre-activate hook `exit 3` exited with 3"
+        );
+        assert!(run_hooks(&settings, Event::PostActivate, &env).is_ok());
+        assert!(run_hooks(&settings, Event::PostDeactivate, &env).is_ok());
+    }
+}
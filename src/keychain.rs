@@ -0,0 +1,179 @@
+//! Static access keys kept in the OS credential store (Keychain on macOS,
+//! Credential Manager on Windows, the Secret Service elsewhere) instead of
+//! in plain text in `~/.aws/credentials`.
+//!
+//! A profile whose keys live there gets a `credential_process` line running
+//! `credential-process` for itself, so the AWS CLI, the SDKs, and this
+//! tool's own `env` and `exec` all read the keys back through this binary.
+
+use crate::config::Profile;
+use crate::dry_run::{ensure_writable, would};
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+
+/// The service the entries are stored under, one per profile name.
+const SERVICE: &str = "aws-profile-selector";
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StaticKeys {
+    pub access_key_id: String,
+    pub secret_access_key: String,
+}
+
+fn entry(profile_name: &str) -> Result<keyring::Entry> {
+    keyring::Entry::new(SERVICE, profile_name)
+        .map_err(|e| anyhow::anyhow!("The OS keychain isn't available: {e}"))
+}
+
+/// Stores the keys, then reads them back so a keychain that silently drops
+/// them is noticed before the plain-text copy is removed.
+pub fn store(profile_name: &str, keys: &StaticKeys) -> Result<()> {
+    ensure_writable(format_args!(
+        "store the keys for '{profile_name}' in the OS keychain"
+    ))?;
+    if would(format_args!(
+        "store the keys for '{profile_name}' in the OS keychain"
+    )) {
+        return Ok(());
+    }
+    entry(profile_name)?
+        .set_password(&serde_json::to_string(keys)?)
+        .map_err(|e| anyhow::anyhow!("Failed to store '{profile_name}' in the OS keychain: {e}"))?;
+
+    match load(profile_name)? {
+        Some(stored) if &stored == keys => Ok(()),
+        _ => Err(anyhow::anyhow!(
+            "The OS keychain didn't keep the keys for '{profile_name}'"
+        )),
+    }
+}
+
+/// The keys stored for a profile, or `None` when there are none.
+pub fn load(profile_name: &str) -> Result<Option<StaticKeys>> {
+    match entry(profile_name)?.get_password() {
+        Ok(secret) => Ok(Some(serde_json::from_str(&secret).map_err(|e| {
+            anyhow::anyhow!("The OS keychain entry for '{profile_name}' is malformed: {e}")
+        })?)),
+        Err(keyring::Error::NoEntry) => Ok(None),
+        Err(e) => Err(anyhow::anyhow!(
+            "Failed to read '{profile_name}' from the OS keychain: {e}"
+        )),
+    }
+}
+
+/// Removes a profile's keys, returning whether there were any.
+pub fn delete(profile_name: &str) -> Result<bool> {
+    ensure_writable(format_args!("remove '{profile_name}' from the OS keychain"))?;
+    if would(format_args!("remove '{profile_name}' from the OS keychain")) {
+        return Ok(load(profile_name)?.is_some());
+    }
+    match entry(profile_name)?.delete_credential() {
+        Ok(()) => Ok(true),
+        Err(keyring::Error::NoEntry) => Ok(false),
+        Err(e) => Err(anyhow::anyhow!(
+            "Failed to remove '{profile_name}' from the OS keychain: {e}"
+        )),
+    }
+}
+
+/// Whether the profile reads its keys from the keychain.
+pub fn uses_keychain(profile: &Profile) -> bool {
+    profile
+        .attributes
+        .get("credential_process")
+        .is_some_and(|command| runs_helper_for(command, &profile.name))
+}
+
+/// The `credential_process` line for a profile whose keys are stored here.
+/// The bare binary name keeps working across upgrades that move it.
+pub fn helper_command(profile_name: &str) -> String {
+    format!(
+        "aws-profile-selector credential-process --profile {}",
+        quote_word(profile_name)
+    )
+}
+
+/// Whether a `credential_process` command line runs `credential-process` for
+/// `profile_name`.
+pub fn runs_helper_for(command: &str, profile_name: &str) -> bool {
+    let words = command_words(command);
+    words.iter().any(|word| word == "credential-process")
+        && words.iter().enumerate().any(|(index, word)| {
+            word.strip_prefix("--profile=") == Some(profile_name)
+                || (word == "--profile"
+                    && words.get(index + 1).map(String::as_str) == Some(profile_name))
+        })
+}
+
+/// `word` as one word of a `credential_process` line: in double quotes, with
+/// `"` and `\` escaped, when it has whitespace or quotes. Both the AWS CLI's
+/// POSIX and Windows splitting read that back as the word.
+fn quote_word(word: &str) -> String {
+    if !word.is_empty() && !word.contains(|c: char| c.is_whitespace() || "\"'\\".contains(c)) {
+        return word.to_string();
+    }
+    let mut quoted = String::with_capacity(word.len() + 2);
+    quoted.push('"');
+    for c in word.chars() {
+        if matches!(c, '"' | '\\') {
+            quoted.push('\\');
+        }
+        quoted.push(c);
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Splits a `credential_process` line into words the way the AWS CLI does:
+/// at whitespace outside quotes, with `"..."` and `'...'` grouping and `\`
+/// escaping a quote or another backslash.
+fn command_words(command: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word: Option<String> = None;
+    let mut quote = None;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some('\''), '\'') | (Some('"'), '"') => quote = None,
+            (None, '"' | '\'') => {
+                quote = Some(c);
+                word.get_or_insert_default();
+            }
+            (None | Some('"'), '\\') if matches!(chars.peek(), Some('"' | '\\')) => {
+                word.get_or_insert_default().extend(chars.next());
+            }
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (_, c) => word.get_or_insert_default().push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_runs_helper_for() {
+        assert!(runs_helper_for(&helper_command("dev"), "dev"));
+        assert!(!runs_helper_for(&helper_command("dev"), "prod"));
+        assert!(runs_helper_for(
+            "/usr/local/bin/aws-profile-selector credential-process --profile=prod",
+            "prod"
+        ));
+        assert!(!runs_helper_for("vault-aws-creds --profile dev", "dev"));
+
+        let spaced = helper_command("team a \"b\"");
+        assert_eq!(
+            spaced,
+            r#"aws-profile-selector credential-process --profile "team a \"b\"""#
+        );
+        assert!(runs_helper_for(&spaced, "team a \"b\""));
+        assert!(!runs_helper_for(&spaced, "team"));
+        assert!(runs_helper_for(
+            "aws-profile-selector credential-process '--profile=team a'",
+            "team a"
+        ));
+    }
+}
@@ -0,0 +1,186 @@
+//! The `[keys]` table of the settings file: the keys the full-screen browser
+//! selects, cancels, favorites, hides, opens the details, edits, and copies
+//! with.
+//!
+//! A key is `enter`, `esc`, `tab`, `space`, `backspace`, `f1` to `f12`, or a
+//! single character, optionally after `ctrl-` or `alt-`: `ctrl-f`, `alt-h`,
+//! `tab`. Bound keys are looked at before the built-in ones, so binding
+//! `ctrl-n` takes it from moving down and binding a plain letter stops it
+//! being typed into the filter. Ctrl-C always cancels.
+//!
+//! The inline prompt is drawn by `inquire`, which keeps Enter and Esc.
+
+use anyhow::Result;
+use ratatui::crossterm::event::{KeyCode, KeyEvent, KeyModifiers};
+use serde::Deserialize;
+use std::fmt;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct KeySettings {
+    pub select: Key,
+    pub cancel: Key,
+    pub favorite: Key,
+    pub hide: Key,
+    /// Shows the highlighted profile's details in place of the list, and
+    /// back.
+    pub details: Key,
+    /// Asks for a new `region` or `output` for the highlighted profile.
+    pub edit: Key,
+    /// Copies the command that sets `AWS_PROFILE` to the highlighted
+    /// profile, as `--copy` does.
+    pub copy: Key,
+}
+
+impl Default for KeySettings {
+    fn default() -> Self {
+        Self {
+            select: Key::new(KeyCode::Enter),
+            cancel: Key::new(KeyCode::Esc),
+            favorite: Key::ctrl('f'),
+            hide: Key::ctrl('x'),
+            details: Key::ctrl('o'),
+            edit: Key::ctrl('e'),
+            copy: Key::ctrl('y'),
+        }
+    }
+}
+
+/// One key with its Ctrl and Alt modifiers. Shift is left out, as it is
+/// already in the character.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct Key {
+    code: KeyCode,
+    modifiers: KeyModifiers,
+}
+
+impl Key {
+    const fn new(code: KeyCode) -> Self {
+        Self {
+            code,
+            modifiers: KeyModifiers::NONE,
+        }
+    }
+
+    const fn ctrl(c: char) -> Self {
+        Self {
+            code: KeyCode::Char(c),
+            modifiers: KeyModifiers::CONTROL,
+        }
+    }
+
+    pub fn matches(&self, event: &KeyEvent) -> bool {
+        let modifiers = event
+            .modifiers
+            .intersection(KeyModifiers::CONTROL | KeyModifiers::ALT);
+        let code = match event.code {
+            // Terminals send Ctrl with a letter as the lowercase letter.
+            KeyCode::Char(c) if !modifiers.is_empty() => KeyCode::Char(c.to_ascii_lowercase()),
+            code => code,
+        };
+        code == self.code && modifiers == self.modifiers
+    }
+}
+
+impl FromStr for Key {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let mut modifiers = KeyModifiers::NONE;
+        let mut rest = text;
+        loop {
+            let lower = rest.to_lowercase();
+            if lower.starts_with("ctrl-") && rest.len() > "ctrl-".len() {
+                modifiers |= KeyModifiers::CONTROL;
+                rest = &rest["ctrl-".len()..];
+            } else if lower.starts_with("alt-") && rest.len() > "alt-".len() {
+                modifiers |= KeyModifiers::ALT;
+                rest = &rest["alt-".len()..];
+            } else {
+                break;
+            }
+        }
+
+        let mut chars = rest.chars();
+        let code = match (chars.next(), chars.next()) {
+            (Some(c), None) if modifiers.is_empty() => KeyCode::Char(c),
+            (Some(c), None) => KeyCode::Char(c.to_ascii_lowercase()),
+            _ => match rest.to_lowercase().as_str() {
+                "enter" | "return" => KeyCode::Enter,
+                "esc" | "escape" => KeyCode::Esc,
+                "tab" => KeyCode::Tab,
+                "space" => KeyCode::Char(' '),
+                "backspace" => KeyCode::Backspace,
+                name => match name.strip_prefix('f').and_then(|n| n.parse().ok()) {
+                    Some(n @ 1..=12) => KeyCode::F(n),
+                    _ => return Err(anyhow::anyhow!("Unknown key '{text}'")),
+                },
+            },
+        };
+        Ok(Self { code, modifiers })
+    }
+}
+
+impl TryFrom<String> for Key {
+    type Error = anyhow::Error;
+
+    fn try_from(text: String) -> Result<Self> {
+        text.parse()
+    }
+}
+
+impl fmt::Display for Key {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.contains(KeyModifiers::CONTROL) {
+            f.write_str("ctrl-")?;
+        }
+        if self.modifiers.contains(KeyModifiers::ALT) {
+            f.write_str("alt-")?;
+        }
+        match self.code {
+            KeyCode::Enter => f.write_str("enter"),
+            KeyCode::Esc => f.write_str("esc"),
+            KeyCode::Tab => f.write_str("tab"),
+            KeyCode::Backspace => f.write_str("backspace"),
+            KeyCode::Char(' ') => f.write_str("space"),
+            KeyCode::Char(c) => write!(f, "{c}"),
+            KeyCode::F(n) => write!(f, "f{n}"),
+            code => write!(f, "{code:?}"),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_keys() {
+        for text in [
+            "enter",
+            "esc",
+            "tab",
+            "space",
+            "f5",
+            "q",
+            "ctrl-f",
+            "ctrl-alt-h",
+        ] {
+            assert_eq!(text.parse::<Key>().unwrap().to_string(), text);
+        }
+        assert_eq!("Ctrl-F".parse::<Key>().unwrap(), Key::ctrl('f'));
+        assert_eq!("ctrl--".parse::<Key>().unwrap().to_string(), "ctrl--");
+        assert!("ctrl-".parse::<Key>().is_err());
+        assert!("f13".parse::<Key>().is_err());
+        assert!("hyper-x".parse::<Key>().is_err());
+
+        let key: Key = "ctrl-f".parse().unwrap();
+        assert!(key.matches(&KeyEvent::new(KeyCode::Char('f'), KeyModifiers::CONTROL)));
+        assert!(!key.matches(&KeyEvent::new(KeyCode::Char('f'), KeyModifiers::NONE)));
+        let key: Key = "Q".parse().unwrap();
+        assert!(key.matches(&KeyEvent::new(KeyCode::Char('Q'), KeyModifiers::SHIFT)));
+        assert!(!key.matches(&KeyEvent::new(KeyCode::Char('q'), KeyModifiers::NONE)));
+    }
+}
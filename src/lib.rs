@@ -0,0 +1,83 @@
+//! The AWS profile model behind the `aws-profile-selector` CLI, for tools
+//! that want to read the same profiles, emit the same shell commands, or
+//! share the selection history.
+//!
+//! - [`ProfileStore`] reads `~/.aws/config` and `~/.aws/credentials` (or the
+//!   files `AWS_CONFIG_FILE` and `AWS_SHARED_CREDENTIALS_FILE` name) into
+//!   [`Profile`]s.
+//! - [`shell`] builds the commands that set `AWS_PROFILE` and friends in
+//!   each supported shell.
+//! - [`SelectorState`] is the favorites, hide list, aliases, and activation
+//!   history the selector keeps in `profile-selector-state.json`.
+//! - [`Environment`] holds what a run reads from outside (the home
+//!   directory, the variables, and the clock), and builds isolated ones for
+//!   end-to-end tests of the binary.
+//!
+//! ```no_run
+//! use aws_profile_selector::ProfileStore;
+//!
+//! let store = ProfileStore::open_default()?;
+//! for profile in store.profiles() {
+//!     println!("{} {:?}", profile.name, profile.get_account_id());
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+//!
+//! The `cli` and `commands` modules are the binary's and aren't part of the
+//! stable API.
+
+mod accounts;
+mod audit;
+mod aws;
+mod backend;
+mod backup;
+mod cache;
+mod check;
+#[doc(hidden)]
+pub mod cli;
+mod clipboard;
+mod columns;
+#[doc(hidden)]
+pub mod commands;
+pub mod config;
+mod console;
+mod daemon;
+mod diff;
+mod dry_run;
+mod editor;
+mod eks;
+pub mod environment;
+mod error;
+mod files;
+mod filter;
+mod hooks;
+mod keychain;
+mod keys;
+mod logging;
+mod matcher;
+mod mfa;
+mod notify;
+mod onepassword;
+mod output;
+mod profile_templates;
+mod regions;
+mod session;
+mod settings;
+pub mod shell;
+mod sso;
+pub mod state;
+mod store;
+mod tasks;
+mod template;
+mod terminal;
+#[cfg(test)]
+mod test_support;
+mod theme;
+mod tui;
+mod ui;
+
+pub use config::{AwsConfig, ConfigPaths, CredentialType, Profile, SsoSession};
+pub use environment::Environment;
+pub use shell::Shell;
+pub use state::{Activation, SelectorState};
+pub use store::{ProfileChange, ProfileStore};
@@ -0,0 +1,189 @@
+//! `-v`, `-q`, and `--log-file`. Diagnostics go through `tracing` to stderr,
+//! or to a file, so they never mix with the output `-c` mode's callers
+//! evaluate. Warnings are shown by default and look as they always have
+//! (`Warning: ...`); `-v` adds what the tool decided and why, `-vv` the
+//! details, and `-vvv` the AWS SDK's own events.
+
+use anyhow::{Context, Result};
+use chrono::Utc;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::Path;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use tracing::field::{Field, Visit};
+use tracing::level_filters::LevelFilter;
+use tracing::span::{Attributes, Id, Record};
+use tracing::{Event, Level, Metadata, Subscriber};
+
+static QUIET: AtomicBool = AtomicBool::new(false);
+
+/// Whether `-q` asked for warnings and notes to be left out.
+pub fn quiet() -> bool {
+    QUIET.load(Ordering::Relaxed)
+}
+
+/// Starts logging for the rest of the process: `verbose` is the number of
+/// `-v`s, and `log_file` takes the place of stderr.
+pub fn init(verbose: u8, quiet: bool, log_file: Option<&Path>) -> Result<()> {
+    QUIET.store(quiet, Ordering::Relaxed);
+    let max_level = match (quiet, verbose) {
+        (true, _) => LevelFilter::OFF,
+        (false, 0) => LevelFilter::WARN,
+        (false, 1) => LevelFilter::INFO,
+        (false, 2) => LevelFilter::DEBUG,
+        (false, _) => LevelFilter::TRACE,
+    };
+    let output: Box<dyn Write + Send> = match log_file {
+        Some(path) => {
+            if let Some(parent) = path
+                .parent()
+                .filter(|parent| !parent.as_os_str().is_empty())
+            {
+                fs::create_dir_all(parent)?;
+            }
+            Box::new(
+                fs::File::options()
+                    .create(true)
+                    .append(true)
+                    .open(path)
+                    .with_context(|| format!("Failed to open {}", path.display()))?,
+            )
+        }
+        None => Box::new(std::io::stderr()),
+    };
+
+    let logger = Logger {
+        max_level,
+        sdk_events: verbose >= 3,
+        to_file: log_file.is_some(),
+        output: Mutex::new(output),
+        next_span: AtomicU64::new(1),
+    };
+    // Only the first call counts, which is all a process makes.
+    let _ = tracing::subscriber::set_global_default(logger);
+    Ok(())
+}
+
+struct Logger {
+    max_level: LevelFilter,
+    /// Whether events from other crates (the AWS SDK) are shown too.
+    sdk_events: bool,
+    to_file: bool,
+    output: Mutex<Box<dyn Write + Send>>,
+    next_span: AtomicU64,
+}
+
+impl Subscriber for Logger {
+    fn enabled(&self, metadata: &Metadata<'_>) -> bool {
+        *metadata.level() <= self.max_level
+            && (self.sdk_events || metadata.target().starts_with(env!("CARGO_CRATE_NAME")))
+    }
+
+    fn max_level_hint(&self) -> Option<LevelFilter> {
+        Some(self.max_level)
+    }
+
+    fn new_span(&self, _span: &Attributes<'_>) -> Id {
+        Id::from_u64(self.next_span.fetch_add(1, Ordering::Relaxed))
+    }
+
+    fn record(&self, _span: &Id, _values: &Record<'_>) {}
+
+    fn record_follows_from(&self, _span: &Id, _follows: &Id) {}
+
+    fn event(&self, event: &Event<'_>) {
+        let mut fields = Fields::default();
+        event.record(&mut fields);
+        let metadata = event.metadata();
+        let line = format_line(
+            *metadata.level(),
+            metadata.target(),
+            &fields.message,
+            &fields.rest,
+            self.to_file,
+        );
+        let mut output = self.output.lock().unwrap();
+        let _ = writeln!(output, "{line}");
+    }
+
+    fn enter(&self, _span: &Id) {}
+
+    fn exit(&self, _span: &Id) {}
+}
+
+/// An event as one line: warnings on stderr as `Warning: ...`, everything
+/// else with its level and where it came from, and a timestamp in files.
+fn format_line(level: Level, target: &str, message: &str, rest: &str, to_file: bool) -> String {
+    let mut line = match (level, to_file) {
+        (Level::ERROR, false) => format!("Error: {message}"),
+        (Level::WARN, false) => format!("Warning: {message}"),
+        (_, false) => format!("{} {target}: {message}", level.as_str().to_lowercase()),
+        (_, true) => format!(
+            "{} {:<5} {target}: {message}",
+            Utc::now().format("%Y-%m-%dT%H:%M:%S%.3fZ"),
+            level.as_str()
+        ),
+    };
+    line.push_str(rest);
+    line
+}
+
+/// An event's message and its other fields as ` key=value`.
+#[derive(Default)]
+struct Fields {
+    message: String,
+    rest: String,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        match field.name() {
+            "message" => self.message = value.to_string(),
+            name => {
+                let _ = write!(self.rest, " {name}={value}");
+            }
+        }
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        match field.name() {
+            "message" => self.message = format!("{value:?}"),
+            name => {
+                let _ = write!(self.rest, " {name}={value:?}");
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_line() {
+        assert_eq!(
+            format_line(
+                Level::WARN,
+                "aws_profile_selector::hooks",
+                "hook failed",
+                "",
+                false
+            ),
+            "Warning: hook failed"
+        );
+        assert_eq!(
+            format_line(
+                Level::DEBUG,
+                "aws_profile_selector::shell",
+                "detected shell",
+                " shell=Zsh",
+                false
+            ),
+            "debug aws_profile_selector::shell: detected shell shell=Zsh"
+        );
+        let line = format_line(Level::INFO, "aws_smithy", "sent", "", true);
+        assert!(line.ends_with("Z INFO  aws_smithy: sent"), "{line}");
+    }
+}
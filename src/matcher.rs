@@ -0,0 +1,102 @@
+//! Scoring of profiles against the text typed into the selector.
+
+use clap::ValueEnum;
+use fuzzy_matcher::FuzzyMatcher;
+use fuzzy_matcher::skim::SkimMatcherV2;
+use serde::Deserialize;
+
+/// How typed text is matched against profiles.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MatchMode {
+    /// Skim-style fuzzy matching: the typed characters must appear in order,
+    /// with consecutive runs and word starts scoring higher.
+    #[default]
+    Fuzzy,
+    /// Case-insensitive substring matching; keeps the list order.
+    Exact,
+}
+
+/// A successful match: higher scores sort first, and `indices` are the
+/// matched character positions, for highlighting.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Match {
+    pub score: i64,
+    pub indices: Vec<usize>,
+}
+
+pub struct Matcher {
+    mode: MatchMode,
+    skim: SkimMatcherV2,
+}
+
+impl Matcher {
+    pub fn new(mode: MatchMode) -> Self {
+        Self {
+            mode,
+            skim: SkimMatcherV2::default().ignore_case(),
+        }
+    }
+
+    /// Matches `query` against `candidate`. An empty query matches
+    /// everything with the same score, so the original order is kept.
+    pub fn matches(&self, candidate: &str, query: &str) -> Option<Match> {
+        if query.is_empty() {
+            return Some(Match {
+                score: 0,
+                indices: Vec::new(),
+            });
+        }
+
+        match self.mode {
+            MatchMode::Fuzzy => self
+                .skim
+                .fuzzy_indices(candidate, query)
+                .map(|(score, indices)| Match { score, indices }),
+            MatchMode::Exact => {
+                let candidate_lower = candidate.to_lowercase();
+                let query_lower = query.to_lowercase();
+                let byte_start = candidate_lower.find(&query_lower)?;
+                let start = candidate_lower[..byte_start].chars().count();
+                Some(Match {
+                    score: 0,
+                    indices: (start..start + query_lower.chars().count()).collect(),
+                })
+            }
+        }
+    }
+
+    pub fn score(&self, candidate: &str, query: &str) -> Option<i64> {
+        self.matches(candidate, query).map(|found| found.score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_fuzzy_matches_out_of_order_abbreviations() {
+        let matcher = Matcher::new(MatchMode::Fuzzy);
+
+        let found = matcher.matches("prod-read", "pdrd").unwrap();
+        assert_eq!(found.indices, vec![0, 3, 5, 8]);
+        assert!(matcher.matches("prod-read", "dp").is_none());
+
+        let tight = matcher.score("prod-read", "prod").unwrap();
+        let loose = matcher.score("p-r-o-d", "prod").unwrap();
+        assert!(tight > loose);
+    }
+
+    #[test]
+    fn test_exact_is_case_insensitive_substring() {
+        let matcher = Matcher::new(MatchMode::Exact);
+
+        assert_eq!(
+            matcher.matches("Prod-Read", "read").unwrap().indices,
+            vec![5, 6, 7, 8]
+        );
+        assert!(matcher.matches("prod-read", "pdrd").is_none());
+        assert_eq!(matcher.score("anything", ""), Some(0));
+    }
+}
@@ -0,0 +1,270 @@
+//! Profiles with `mfa_serial`. The SDK credential chain ignores the setting,
+//! so `whoami`, `env`, and `exec` ask for the code themselves, call STS with
+//! it, and cache the session until shortly before it expires, as the AWS CLI
+//! does.
+
+use crate::aws::{self, MfaRequest, block_on, get_mfa_credentials, with_credentials};
+use crate::cache::Cache;
+use crate::config::{ConfigPaths, Profile, read_aws_config};
+use crate::environment::now;
+use crate::ui::answer;
+use anyhow::Result;
+use aws_config::SdkConfig;
+use aws_credential_types::Credentials;
+use chrono::{DateTime, TimeDelta, Utc};
+use inquire::Text;
+use inquire::validator::Validation;
+use serde::{Deserialize, Serialize};
+
+/// GetSessionToken sessions last up to 36 hours.
+const CACHE_NAME: &str = "mfa";
+const CACHE_TTL: TimeDelta = TimeDelta::hours(36);
+
+/// Sessions with less than this left prompt for a new code.
+const EXPIRY_MARGIN: TimeDelta = TimeDelta::minutes(5);
+
+/// Used when the profile has no `role_session_name`.
+pub const DEFAULT_SESSION_NAME: &str = "aws-profile-selector";
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Session {
+    access_key_id: String,
+    secret_access_key: String,
+    session_token: String,
+    expiration: DateTime<Utc>,
+}
+
+impl Session {
+    fn credentials(&self) -> Credentials {
+        Credentials::new(
+            &self.access_key_id,
+            &self.secret_access_key,
+            Some(self.session_token.clone()),
+            Some(self.expiration.into()),
+            "MFA session cache",
+        )
+    }
+}
+
+/// What an MFA-protected profile's settings ask for.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct MfaProfile {
+    serial_number: String,
+    request: MfaRequest,
+    /// The profile whose credentials make the STS call: `source_profile`
+    /// for roles, the profile itself for GetSessionToken.
+    source_profile: String,
+    duration_seconds: Option<i32>,
+}
+
+pub fn requires_mfa(profile: &Profile) -> bool {
+    profile.attributes.contains_key("mfa_serial")
+}
+
+fn mfa_profile(profile: &Profile) -> Result<Option<MfaProfile>> {
+    let attribute = |key: &str| profile.attributes.get(key).cloned();
+    let Some(serial_number) = attribute("mfa_serial") else {
+        return Ok(None);
+    };
+    let duration_seconds = attribute("duration_seconds")
+        .map(|seconds| {
+            seconds.parse().map_err(|_| {
+                anyhow::anyhow!(
+                    "'{}' has an invalid duration_seconds: '{seconds}'",
+                    profile.name
+                )
+            })
+        })
+        .transpose()?;
+
+    let (request, source_profile) = match attribute("role_arn") {
+        Some(role_arn) => {
+            let source_profile = attribute("source_profile").ok_or_else(|| {
+                anyhow::anyhow!(
+                    "'{}' needs a source_profile to assume its role with MFA",
+                    profile.name
+                )
+            })?;
+            let request = MfaRequest::AssumeRole {
+                role_arn,
+                session_name: attribute("role_session_name")
+                    .unwrap_or_else(|| DEFAULT_SESSION_NAME.to_string()),
+                external_id: attribute("external_id"),
+            };
+            (request, source_profile)
+        }
+        None => (MfaRequest::SessionToken, profile.name.clone()),
+    };
+
+    Ok(Some(MfaProfile {
+        serial_number,
+        request,
+        source_profile,
+        duration_seconds,
+    }))
+}
+
+/// Loads SDK configuration for `profile_name` like [`aws::load_sdk_config`],
+/// with an MFA session's credentials for profiles that have `mfa_serial`.
+/// A broken `source_profile` chain, a missing web identity token file, or a
+/// `credential_process` that isn't installed is reported here rather than as
+/// whatever the SDK makes of it.
+pub fn load_sdk_config(paths: &ConfigPaths, profile_name: &str) -> Result<SdkConfig> {
+    load_sdk_config_for(paths, profile_name, None)
+}
+
+/// [`load_sdk_config`], with a new MFA session lasting `duration_seconds`
+/// rather than the profile's `duration_seconds`. A cached session is used
+/// however long it has left.
+pub fn load_sdk_config_for(
+    paths: &ConfigPaths,
+    profile_name: &str,
+    duration_seconds: Option<i32>,
+) -> Result<SdkConfig> {
+    let config = read_aws_config(paths)?;
+    if let Some(problem) = config
+        .role_chain(profile_name)
+        .and_then(|chain| chain.problem_description())
+        .or_else(|| config.credential_problem(profile_name))
+    {
+        return Err(anyhow::anyhow!(
+            "'{profile_name}' can't get credentials: {problem}"
+        ));
+    }
+    let mfa = match config.find_profile(profile_name) {
+        Some(profile) => mfa_profile(profile)?.map(|mut mfa| {
+            mfa.duration_seconds = duration_seconds.or(mfa.duration_seconds);
+            mfa
+        }),
+        None => None,
+    };
+    let sdk_config = block_on(aws::load_sdk_config(paths, profile_name))?;
+
+    match mfa {
+        Some(mfa) => Ok(with_credentials(
+            &sdk_config,
+            session_credentials(paths, profile_name, &mfa)?,
+        )),
+        None => Ok(sdk_config),
+    }
+}
+
+/// When the cached MFA session for `profile_name` runs out, if there is one.
+pub fn session_expiry(profile_name: &str) -> Result<Option<DateTime<Utc>>> {
+    let cache: Cache<Session> = Cache::load_private(CACHE_NAME, CACHE_TTL)?;
+    Ok(cache
+        .get(profile_name, now())
+        .map(|session| session.expiration))
+}
+
+/// The cached session, or a new one after prompting for a code.
+fn session_credentials(
+    paths: &ConfigPaths,
+    profile_name: &str,
+    mfa: &MfaProfile,
+) -> Result<Credentials> {
+    let now = now();
+    let mut cache: Cache<Session> = Cache::load_private(CACHE_NAME, CACHE_TTL)?;
+    if let Some(session) = cache
+        .get(profile_name, now)
+        .filter(|session| session.expiration - now > EXPIRY_MARGIN)
+    {
+        return Ok(session.credentials());
+    }
+
+    // inquire draws on stderr, so this works under `eval "$(... env)"`.
+    let code = answer(
+        Text::new(&format!("MFA code for {}:", mfa.serial_number))
+            .with_validator(totp_code)
+            .prompt(),
+    )?;
+    let credentials = block_on(async {
+        let source = aws::load_sdk_config(paths, &mfa.source_profile).await;
+        get_mfa_credentials(
+            &source,
+            &mfa.request,
+            &mfa.serial_number,
+            code.trim(),
+            mfa.duration_seconds,
+        )
+        .await
+    })??;
+
+    if let (Some(session_token), Some(expiry)) = (credentials.session_token(), credentials.expiry())
+    {
+        let session = Session {
+            access_key_id: credentials.access_key_id().to_string(),
+            secret_access_key: credentials.secret_access_key().to_string(),
+            session_token: session_token.to_string(),
+            expiration: expiry.into(),
+        };
+        cache.insert(profile_name, session, now);
+        // Failing to cache only means asking again next time.
+        let _ = cache.save(now);
+    }
+    Ok(credentials)
+}
+
+fn totp_code(input: &str) -> Result<Validation, inquire::CustomUserError> {
+    let input = input.trim();
+    Ok(
+        if input.len() == 6 && input.bytes().all(|b| b.is_ascii_digit()) {
+            Validation::Valid
+        } else {
+            Validation::Invalid("Enter the 6-digit code from your MFA device".into())
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::profile;
+
+    #[test]
+    fn test_mfa_profile_reads_role_and_session_token_settings() {
+        let serial = "arn:aws:iam::123456789012:mfa/alice";
+        let role = profile(
+            "admin",
+            &[
+                ("role_arn", "arn:aws:iam::987654321098:role/Admin"),
+                ("source_profile", "base"),
+                ("mfa_serial", serial),
+                ("duration_seconds", "7200"),
+            ],
+        );
+        assert_eq!(
+            mfa_profile(&role).unwrap(),
+            Some(MfaProfile {
+                serial_number: serial.to_string(),
+                request: MfaRequest::AssumeRole {
+                    role_arn: "arn:aws:iam::987654321098:role/Admin".to_string(),
+                    session_name: DEFAULT_SESSION_NAME.to_string(),
+                    external_id: None,
+                },
+                source_profile: "base".to_string(),
+                duration_seconds: Some(7200),
+            })
+        );
+
+        let keys = profile("admin", &[("mfa_serial", serial)]);
+        let mfa = mfa_profile(&keys).unwrap().unwrap();
+        assert_eq!(mfa.request, MfaRequest::SessionToken);
+        assert_eq!(mfa.source_profile, "admin");
+
+        assert_eq!(
+            mfa_profile(&profile("admin", &[("region", "eu-west-1")])).unwrap(),
+            None
+        );
+        assert!(
+            mfa_profile(&profile(
+                "admin",
+                &[
+                    ("role_arn", "arn:aws:iam::987654321098:role/Admin"),
+                    ("mfa_serial", serial),
+                ]
+            ))
+            .is_err()
+        );
+    }
+}
@@ -0,0 +1,95 @@
+//! Desktop notifications for profile switches (`notify = true` in the
+//! settings file), for activations made from hooks, hotkeys, or editors where
+//! no terminal output is seen. They go through the desktop's own tool:
+//! `osascript` on macOS, a PowerShell toast on Windows, and `notify-send`
+//! elsewhere.
+
+use crate::dry_run::would;
+use anyhow::{Context, Result};
+use std::process::{Command, Stdio};
+
+const TITLE: &str = "AWS profile";
+
+/// `AWS profile switched to payments-prod (123456789012)`.
+pub fn switch_message(profile_name: &str, account_id: Option<&str>) -> String {
+    match account_id {
+        Some(account_id) => format!("AWS profile switched to {profile_name} ({account_id})"),
+        None => format!("AWS profile switched to {profile_name}"),
+    }
+}
+
+/// Shows `message` without waiting for the notification to go away.
+pub fn notify(message: &str) -> Result<()> {
+    if would(format_args!("show the notification \"{message}\"")) {
+        return Ok(());
+    }
+    let mut command = notification_command(message);
+    let program = command.get_program().to_string_lossy().to_string();
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run '{program}'"))?;
+    Ok(())
+}
+
+#[cfg(target_os = "macos")]
+fn notification_command(message: &str) -> Command {
+    let mut command = Command::new("osascript");
+    command.args([
+        "-e",
+        &format!(
+            "display notification {} with title {}",
+            applescript_string(message),
+            applescript_string(TITLE)
+        ),
+    ]);
+    command
+}
+
+#[cfg(windows)]
+fn notification_command(message: &str) -> Command {
+    let quote = |text: &str| format!("'{}'", text.replace('\'', "''"));
+    let script = format!(
+        "[Windows.UI.Notifications.ToastNotificationManager, Windows.UI.Notifications, ContentType = WindowsRuntime] > $null; \
+         $xml = [Windows.UI.Notifications.ToastNotificationManager]::GetTemplateContent([Windows.UI.Notifications.ToastTemplateType]::ToastText02); \
+         $text = $xml.GetElementsByTagName('text'); \
+         $text.Item(0).AppendChild($xml.CreateTextNode({})) > $null; \
+         $text.Item(1).AppendChild($xml.CreateTextNode({})) > $null; \
+         [Windows.UI.Notifications.ToastNotificationManager]::CreateToastNotifier('aws-profile-selector').Show([Windows.UI.Notifications.ToastNotification]::new($xml))",
+        quote(TITLE),
+        quote(message)
+    );
+    let mut command = Command::new("powershell");
+    command.args(["-NoProfile", "-Command", &script]);
+    command
+}
+
+#[cfg(not(any(target_os = "macos", windows)))]
+fn notification_command(message: &str) -> Command {
+    let mut command = Command::new("notify-send");
+    command.args(["--app-name=aws-profile-selector", TITLE, message]);
+    command
+}
+
+/// A double-quoted AppleScript string literal.
+#[cfg_attr(not(target_os = "macos"), allow(dead_code))]
+fn applescript_string(text: &str) -> String {
+    format!("\"{}\"", text.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_switch_message() {
+        assert_eq!(
+            switch_message("payments-prod", Some("123456789012")),
+            "AWS profile switched to payments-prod (123456789012)"
+        );
+        assert_eq!(switch_message("dev", None), "AWS profile switched to dev");
+        assert_eq!(applescript_string(r#"a "b" \c"#), r#""a \"b\" \\c""#);
+    }
+}
@@ -0,0 +1,148 @@
+//! Static access keys kept in 1Password and read with its `op` CLI when
+//! they're needed, for profiles the settings file maps to an item:
+//!
+//! ```toml
+//! [credential-sources]
+//! legacy = "1password:AWS legacy keys"
+//! ci = "1password:Engineering/CI deploy user"
+//! ```
+//!
+//! The item's fields labelled "access key id" and "secret access key" (or
+//! `aws_access_key_id` and `aws_secret_access_key`) hold the keys. The
+//! profile still needs a section in the AWS config for its region.
+
+use crate::keychain::StaticKeys;
+use anyhow::{Context, Result};
+use serde::Deserialize;
+use std::process::{Command, Stdio};
+
+/// Where a profile's keys come from instead of the credentials file.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub enum CredentialSource {
+    /// `1password:<item>` or `1password:<vault>/<item>`.
+    OnePassword { vault: Option<String>, item: String },
+}
+
+impl TryFrom<String> for CredentialSource {
+    type Error = anyhow::Error;
+
+    fn try_from(text: String) -> Result<Self> {
+        let Some(reference) = text.strip_prefix("1password:").filter(|r| !r.is_empty()) else {
+            return Err(anyhow::anyhow!(
+                "Unknown credential source '{text}'; expected 1password:<item>"
+            ));
+        };
+        Ok(match reference.split_once('/') {
+            Some((vault, item)) => Self::OnePassword {
+                vault: Some(vault.to_string()),
+                item: item.to_string(),
+            },
+            None => Self::OnePassword {
+                vault: None,
+                item: reference.to_string(),
+            },
+        })
+    }
+}
+
+#[derive(Deserialize)]
+struct Item {
+    #[serde(default)]
+    fields: Vec<Field>,
+}
+
+#[derive(Deserialize)]
+struct Field {
+    #[serde(default)]
+    label: String,
+    #[serde(default)]
+    value: Option<String>,
+}
+
+impl CredentialSource {
+    /// Reads the keys, which may have `op` ask to unlock 1Password.
+    pub fn load(&self) -> Result<StaticKeys> {
+        let Self::OnePassword { vault, item } = self;
+        let mut command = Command::new("op");
+        command.args(["item", "get", item, "--format", "json", "--reveal"]);
+        if let Some(vault) = vault {
+            command.args(["--vault", vault]);
+        }
+        let output = command
+            .stdin(Stdio::inherit())
+            .stderr(Stdio::inherit())
+            .output()
+            .context("Failed to run the 1Password CLI (op)")?;
+        if !output.status.success() {
+            return Err(anyhow::anyhow!(
+                "op item get '{item}' failed with {}",
+                output.status
+            ));
+        }
+        let parsed: Item = serde_json::from_slice(&output.stdout)
+            .with_context(|| format!("op printed something other than the item '{item}'"))?;
+        keys_from(&parsed).ok_or_else(|| {
+            anyhow::anyhow!(
+                "1Password item '{item}' has no access key id and secret access key fields"
+            )
+        })
+    }
+}
+
+fn keys_from(item: &Item) -> Option<StaticKeys> {
+    let field = |names: [&str; 2]| {
+        item.fields
+            .iter()
+            .find(|field| names.contains(&normalized(&field.label).as_str()))
+            .and_then(|field| field.value.clone())
+            .filter(|value| !value.is_empty())
+    };
+    Some(StaticKeys {
+        access_key_id: field(["access key id", "aws access key id"])?,
+        secret_access_key: field(["secret access key", "aws secret access key"])?,
+    })
+}
+
+/// `AWS_Access-Key ID` → `aws access key id`.
+fn normalized(label: &str) -> String {
+    label
+        .to_lowercase()
+        .replace(['_', '-'], " ")
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_credential_source() {
+        assert_eq!(
+            CredentialSource::try_from("1password:Engineering/CI user".to_string()).unwrap(),
+            CredentialSource::OnePassword {
+                vault: Some("Engineering".to_string()),
+                item: "CI user".to_string()
+            }
+        );
+        assert!(CredentialSource::try_from("vault:ci".to_string()).is_err());
+
+        let item: Item = serde_json::from_str(
+            r#"{"fields": [
+                {"label": "notesPlain"},
+                {"label": "aws_access_key_id", "value": "AKIA"},
+                {"label": "Secret Access Key", "value": "secret"}
+            ]}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            keys_from(&item),
+            Some(StaticKeys {
+                access_key_id: "AKIA".to_string(),
+                secret_access_key: "secret".to_string(),
+            })
+        );
+    }
+}
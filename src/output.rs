@@ -0,0 +1,86 @@
+use anyhow::Result;
+use clap::ValueEnum;
+use serde::Serialize;
+
+/// Output formats for commands that report structured data.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum OutputFormat {
+    Table,
+    Json,
+    Yaml,
+}
+
+/// A column `list --fields` prints, named as in `list --output json`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+#[value(rename_all = "snake_case")]
+pub enum ListField {
+    Name,
+    /// The credential type's badge, as in the `TYPE` column.
+    Type,
+    #[value(alias = "account")]
+    AccountId,
+    AccountName,
+    Tags,
+    Region,
+    Role,
+    SsoSession,
+    SsoStartUrl,
+    SsoRegion,
+    /// When the cached SSO token expires, as an RFC 3339 time.
+    #[value(alias = "sso_token_expires_at")]
+    Expiry,
+    LastUsed,
+    Backend,
+    /// `ok`, `failed`, or `timeout` with `--check`.
+    Check,
+}
+
+/// Prints `value` as pretty JSON or YAML. Table output is command-specific,
+/// so callers handle `OutputFormat::Table` themselves.
+pub fn print_structured<T: Serialize>(value: &T, format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string_pretty(value)?),
+        OutputFormat::Yaml => print!("{}", serde_yaml::to_string(value)?),
+        OutputFormat::Table => unreachable!("table output is rendered by the caller"),
+    }
+
+    Ok(())
+}
+
+/// Prints rows as left-aligned columns separated by two spaces.
+pub fn print_table(headers: &[&str], rows: &[Vec<String>]) {
+    print_columns(headers, rows, true);
+}
+
+/// [`print_table`], leaving out the header row unless `header`.
+pub fn print_columns(headers: &[&str], rows: &[Vec<String>], header: bool) {
+    let mut widths: Vec<usize> = headers
+        .iter()
+        .map(|header| header.chars().count())
+        .map(|width| if header { width } else { 0 })
+        .collect();
+
+    for row in rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let format_row = |cells: Vec<&str>| {
+        cells
+            .iter()
+            .zip(&widths)
+            .map(|(cell, width)| format!("{cell:<width$}"))
+            .collect::<Vec<_>>()
+            .join("  ")
+            .trim_end()
+            .to_string()
+    };
+
+    if header {
+        println!("{}", format_row(headers.to_vec()));
+    }
+    for row in rows {
+        println!("{}", format_row(row.iter().map(String::as_str).collect()));
+    }
+}
@@ -0,0 +1,160 @@
+//! Profile templates from the `[templates]` table of the settings file, for
+//! organizations where every account gets the same profiles:
+//!
+//! ```toml
+//! [templates.eng-role]
+//! roles = ["ReadOnly", "PowerUser", "AdministratorAccess"]
+//! sso_session = "corp"
+//! sso_account_id = "{account_id}"
+//! sso_role_name = "{role}"
+//! region = "eu-west-1"
+//! ```
+//!
+//! `add --from-template eng-role --account 123456789012` writes one profile
+//! per role, or a single one when there are no `roles`. Every key other than
+//! `name` and `roles` is written to the profile. Values and the `name`
+//! (`{account_name}-{role}` by default, or `{account_name}` without roles)
+//! can use `{account_id}`, `{account_name}` (the account's nickname from
+//! `[accounts]`, else its ID), `{role}`, and any `--var KEY=VALUE`. `{{` and
+//! `}}` are literal braces.
+
+use crate::template::{Segment, parse_segments};
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+
+pub type Variables = BTreeMap<String, String>;
+
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct ProfileTemplate {
+    pub name: Option<String>,
+    #[serde(default)]
+    pub roles: Vec<String>,
+    #[serde(flatten)]
+    pub settings: BTreeMap<String, String>,
+}
+
+/// A profile filled in from a template, ready to be written to the config.
+#[derive(Debug, PartialEq, Eq)]
+pub struct StampedProfile {
+    pub name: String,
+    pub entries: Vec<(String, String)>,
+}
+
+impl ProfileTemplate {
+    /// The template's profiles with `variables` filled in, one per role.
+    pub fn stamp(&self, variables: &Variables) -> Result<Vec<StampedProfile>> {
+        let roles: Vec<Option<&String>> = match self.roles.is_empty() {
+            true => vec![None],
+            false => self.roles.iter().map(Some).collect(),
+        };
+        let name = self.name.as_deref().unwrap_or(match self.roles.is_empty() {
+            true => "{account_name}",
+            false => "{account_name}-{role}",
+        });
+
+        roles
+            .into_iter()
+            .map(|role| {
+                let mut variables = variables.clone();
+                if let Some(role) = role {
+                    variables.insert("role".to_string(), role.clone());
+                }
+                let entries = self
+                    .settings
+                    .iter()
+                    .map(|(key, value)| Ok((key.clone(), substitute(value, &variables)?)))
+                    .collect::<Result<_>>()?;
+                Ok(StampedProfile {
+                    name: clean_profile_name(&substitute(name, &variables)?),
+                    entries,
+                })
+            })
+            .collect()
+    }
+}
+
+/// `text` with each `{variable}` replaced by its value, read with the same
+/// rules as a display format (see [`parse_segments`]).
+fn substitute(text: &str, variables: &Variables) -> Result<String> {
+    let segments = parse_segments(text, &format!("template '{text}'"), |variable| {
+        variables.get(variable).ok_or_else(|| {
+            let hint = match variable {
+                "account_id" | "account_name" => "--account ID".to_string(),
+                _ => format!("--var {variable}=VALUE"),
+            };
+            anyhow::anyhow!("'{text}' in the template uses {{{variable}}}, which needs {hint}")
+        })
+    })?;
+    Ok(segments
+        .into_iter()
+        .map(|segment| match segment {
+            Segment::Literal(literal) => literal,
+            Segment::Placeholder(value) => value.clone(),
+        })
+        .collect())
+}
+
+/// Replaces characters that don't belong in a profile name with `-`.
+pub fn clean_profile_name(rendered: &str) -> String {
+    let mut name = String::with_capacity(rendered.len());
+    for c in rendered.chars() {
+        let c = if c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.') {
+            c
+        } else {
+            '-'
+        };
+        if !(c == '-' && name.ends_with('-')) {
+            name.push(c);
+        }
+    }
+
+    name.trim_matches('-').to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stamp_one_profile_per_role() {
+        let template: ProfileTemplate = toml::from_str(
+            r#"
+roles = ["ReadOnly", "Admin"]
+sso_session = "corp"
+sso_account_id = "{account_id}"
+sso_role_name = "{role}"
+region = "{region}"
+"#,
+        )
+        .unwrap();
+        let variables = Variables::from([
+            ("account_id".to_string(), "123456789012".to_string()),
+            ("account_name".to_string(), "payments prod".to_string()),
+            ("region".to_string(), "eu-west-1".to_string()),
+        ]);
+
+        let stamped = template.stamp(&variables).unwrap();
+        assert_eq!(stamped[0].name, "payments-prod-ReadOnly");
+        assert_eq!(
+            stamped[1],
+            StampedProfile {
+                name: "payments-prod-Admin".to_string(),
+                entries: vec![
+                    ("region".to_string(), "eu-west-1".to_string()),
+                    ("sso_account_id".to_string(), "123456789012".to_string()),
+                    ("sso_role_name".to_string(), "Admin".to_string()),
+                    ("sso_session".to_string(), "corp".to_string()),
+                ],
+            }
+        );
+
+        assert_eq!(
+            substitute("{{literal}} {account_id}", &variables).unwrap(),
+            "{literal} 123456789012"
+        );
+        assert!(substitute("{role}", &variables).is_err());
+        assert!(substitute("{account_id", &variables).is_err());
+        assert!(substitute("account_id}", &variables).is_err());
+    }
+}
@@ -0,0 +1,324 @@
+//! `--duration` for `console`, `env`, `exec`, and `creds write`, with
+//! defaults by profile name or glob from `[session-durations]` in the
+//! settings file:
+//!
+//! ```toml
+//! [session-durations]
+//! "*-admin" = "1h"
+//! ci = "8h"
+//! ```
+//!
+//! The SDK's credential chain always asks STS for its default hour, so a
+//! duration means making the call here instead: AssumeRole with
+//! `DurationSeconds` for `role_arn` profiles, and GetSessionToken for static
+//! keys (GetFederationToken for the console, which can't sign in with a
+//! session token). MFA profiles ask for it when they start a new session.
+//! SSO credentials last as long as the permission set says, whatever is
+//! asked for.
+
+use crate::aws::{
+    self, assume_role, block_on, get_federation_token, get_session_token, resolve_credentials,
+    with_credentials,
+};
+use crate::config::{ConfigPaths, CredentialType, read_aws_config};
+use crate::error::Error;
+use crate::filter::glob_match;
+use crate::mfa::{self, DEFAULT_SESSION_NAME, requires_mfa};
+use anyhow::Result;
+use aws_config::SdkConfig;
+use aws_credential_types::Credentials;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fmt;
+use std::str::FromStr;
+
+const MINUTE: u32 = 60;
+const HOUR: u32 = 60 * MINUTE;
+/// The shortest session STS hands out.
+const MIN_SECONDS: u32 = 15 * MINUTE;
+/// AssumeRole's longest. The role's MaxSessionDuration, an hour unless
+/// raised, may be lower, which STS enforces.
+const MAX_ROLE_SECONDS: u32 = 12 * HOUR;
+/// A role assumed with another role's credentials gets at most an hour.
+const MAX_CHAINED_ROLE_SECONDS: u32 = HOUR;
+/// GetSessionToken's and GetFederationToken's longest.
+const MAX_SESSION_SECONDS: u32 = 36 * HOUR;
+
+/// How long temporary credentials should last: `2h`, `90m`, `1h30m`, or
+/// plain seconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct SessionDuration {
+    seconds: u32,
+}
+
+impl SessionDuration {
+    pub fn seconds(self) -> i32 {
+        self.seconds as i32
+    }
+
+    /// Fails unless STS's `operation` can give a session this long.
+    fn check(self, operation: &str, max_seconds: u32) -> Result<()> {
+        let limit = |seconds| Self { seconds };
+        if self.seconds < MIN_SECONDS || self.seconds > max_seconds {
+            return Err(anyhow::anyhow!(
+                "{operation} sessions last from {} to {}, not {self}",
+                limit(MIN_SECONDS),
+                limit(max_seconds)
+            ));
+        }
+        Ok(())
+    }
+}
+
+impl FromStr for SessionDuration {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{text}' is not a duration like 2h, 90m, or 1h30m");
+        if let Ok(seconds) = text.parse::<u32>() {
+            return (seconds > 0)
+                .then_some(Self { seconds })
+                .ok_or_else(invalid);
+        }
+
+        let mut seconds: u32 = 0;
+        let mut rest = text;
+        while !rest.is_empty() {
+            let digits = rest
+                .find(|c: char| !c.is_ascii_digit())
+                .ok_or_else(invalid)?;
+            let amount: u32 = rest[..digits].parse().map_err(|_| invalid())?;
+            let unit = match rest[digits..].chars().next() {
+                Some('h') => HOUR,
+                Some('m') => MINUTE,
+                Some('s') => 1,
+                _ => return Err(invalid()),
+            };
+            seconds = amount
+                .checked_mul(unit)
+                .and_then(|part| seconds.checked_add(part))
+                .ok_or_else(invalid)?;
+            rest = &rest[digits + 1..];
+        }
+        match seconds {
+            0 => Err(invalid()),
+            seconds => Ok(Self { seconds }),
+        }
+    }
+}
+
+impl TryFrom<String> for SessionDuration {
+    type Error = String;
+
+    fn try_from(text: String) -> Result<Self, Self::Error> {
+        text.parse()
+    }
+}
+
+impl fmt::Display for SessionDuration {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let (hours, minutes, seconds) = (
+            self.seconds / HOUR,
+            self.seconds % HOUR / MINUTE,
+            self.seconds % MINUTE,
+        );
+        for (amount, unit) in [(hours, "h"), (minutes, "m"), (seconds, "s")] {
+            if amount > 0 {
+                write!(f, "{amount}{unit}")?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// The profile's `[session-durations]` entry: the one under its name, else
+/// the first pattern that matches it.
+pub fn configured_duration(
+    durations: &BTreeMap<String, SessionDuration>,
+    profile_name: &str,
+) -> Option<SessionDuration> {
+    durations.get(profile_name).copied().or_else(|| {
+        durations
+            .iter()
+            .find(|(pattern, _)| glob_match(pattern, profile_name))
+            .map(|(_, duration)| *duration)
+    })
+}
+
+/// Credentials made to last a given time, and where they came from.
+pub struct Session {
+    pub credentials: Credentials,
+    pub region: Option<String>,
+    /// Whether they're from AssumeRole, the only credentials the console
+    /// takes a session length for.
+    pub assumed_role: bool,
+}
+
+/// Temporary credentials for `profile_name` lasting `duration`, with the
+/// profile's region. Static keys get GetFederationToken rather than
+/// GetSessionToken credentials `for_console`.
+pub fn session_credentials(
+    paths: &ConfigPaths,
+    profile_name: &str,
+    duration: SessionDuration,
+    for_console: bool,
+) -> Result<Session> {
+    let config = read_aws_config(paths)?;
+    let profile = config
+        .find_profile(profile_name)
+        .ok_or_else(|| Error::profile_not_found(profile_name))?;
+    let role_arn = profile.attributes.get("role_arn");
+
+    if requires_mfa(profile) {
+        match role_arn {
+            Some(_) => duration.check("AssumeRole", MAX_ROLE_SECONDS)?,
+            None => duration.check("GetSessionToken", MAX_SESSION_SECONDS)?,
+        }
+        let sdk_config = mfa::load_sdk_config_for(paths, profile_name, Some(duration.seconds()))?;
+        return resolved(&sdk_config, role_arn.is_some());
+    }
+
+    match profile.credential_type() {
+        Some(CredentialType::AssumeRole) => {
+            let role_arn = role_arn.expect("assume-role profiles have a role_arn");
+            let source_profile = profile.attributes.get("source_profile").ok_or_else(|| {
+                anyhow::anyhow!(
+                    "'{profile_name}' needs a source_profile for a session of {duration}"
+                )
+            })?;
+            let chained = config
+                .find_profile(source_profile)
+                .and_then(|source| source.credential_type())
+                .is_some_and(|kind| {
+                    matches!(
+                        kind,
+                        CredentialType::AssumeRole
+                            | CredentialType::Sso
+                            | CredentialType::WebIdentity
+                    )
+                });
+            match chained {
+                true => duration.check(
+                    &format!("AssumeRole with '{source_profile}''s role credentials"),
+                    MAX_CHAINED_ROLE_SECONDS,
+                )?,
+                false => duration.check("AssumeRole", MAX_ROLE_SECONDS)?,
+            }
+
+            let source = mfa::load_sdk_config(paths, source_profile)?;
+            let session_name = profile
+                .attributes
+                .get("role_session_name")
+                .map_or(DEFAULT_SESSION_NAME, String::as_str);
+            let credentials = block_on(assume_role(
+                &source,
+                role_arn,
+                session_name,
+                profile.attributes.get("external_id").cloned(),
+                duration.seconds(),
+            ))??;
+            let sdk_config = block_on(aws::load_sdk_config(paths, profile_name))?;
+            Ok(Session {
+                credentials,
+                region: sdk_config.region().map(|region| region.to_string()),
+                assumed_role: true,
+            })
+        }
+        Some(CredentialType::StaticKeys) => {
+            let sdk_config = mfa::load_sdk_config(paths, profile_name)?;
+            Ok(Session {
+                credentials: exchange_keys(&sdk_config, duration, for_console)?,
+                region: sdk_config.region().map(|region| region.to_string()),
+                assumed_role: false,
+            })
+        }
+        kind => {
+            let source = kind.map_or("its credential chain", |kind| kind.badge());
+            tracing::warn!(
+                "'{profile_name}' gets its credentials from {source}, which sets how long they last; ignoring the {duration} duration"
+            );
+            resolved(&mfa::load_sdk_config(paths, profile_name)?, false)
+        }
+    }
+}
+
+/// Trades the long-lived keys in `sdk_config` for temporary ones lasting
+/// `duration`.
+pub fn exchange_keys(
+    sdk_config: &SdkConfig,
+    duration: SessionDuration,
+    for_console: bool,
+) -> Result<Credentials> {
+    let operation = match for_console {
+        true => "GetFederationToken",
+        false => "GetSessionToken",
+    };
+    duration.check(operation, MAX_SESSION_SECONDS)?;
+    block_on(async {
+        match for_console {
+            true => get_federation_token(sdk_config, Some(duration.seconds())).await,
+            false => get_session_token(sdk_config, duration.seconds()).await,
+        }
+    })?
+}
+
+/// [`exchange_keys`] for keys kept somewhere the SDK doesn't look.
+pub fn exchange_static_keys(
+    sdk_config: &SdkConfig,
+    keys: Credentials,
+    duration: SessionDuration,
+) -> Result<Credentials> {
+    exchange_keys(&with_credentials(sdk_config, keys), duration, false)
+}
+
+fn resolved(sdk_config: &SdkConfig, assumed_role: bool) -> Result<Session> {
+    Ok(Session {
+        credentials: block_on(resolve_credentials(sdk_config))??,
+        region: sdk_config.region().map(|region| region.to_string()),
+        assumed_role,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_session_duration() {
+        for (text, seconds, shown) in [
+            ("2h", 7200, "2h"),
+            ("90m", 5400, "1h30m"),
+            ("1h30m", 5400, "1h30m"),
+            ("3600", 3600, "1h"),
+            ("15m30s", 930, "15m30s"),
+        ] {
+            let duration: SessionDuration = text.parse().unwrap();
+            assert_eq!(duration.seconds(), seconds);
+            assert_eq!(duration.to_string(), shown);
+        }
+        for text in ["", "0", "0h", "2x", "h", "1h30", "99999999h"] {
+            assert!(text.parse::<SessionDuration>().is_err(), "{text}");
+        }
+
+        let duration: SessionDuration = "2h".parse().unwrap();
+        assert!(duration.check("AssumeRole", MAX_ROLE_SECONDS).is_ok());
+        assert_eq!(
+            duration
+                .check("AssumeRole", MAX_CHAINED_ROLE_SECONDS)
+                .unwrap_err()
+                .to_string(),
+            "AssumeRole sessions last from 15m to 1h, not 2h"
+        );
+
+        let durations = BTreeMap::from([
+            ("*-admin".to_string(), "1h".parse().unwrap()),
+            ("ci".to_string(), "8h".parse().unwrap()),
+        ]);
+        assert_eq!(
+            configured_duration(&durations, "prod-admin").map(|d| d.to_string()),
+            Some("1h".to_string())
+        );
+        assert_eq!(configured_duration(&durations, "dev"), None);
+    }
+}
@@ -0,0 +1,256 @@
+//! The tool's own configuration file,
+//! `~/.config/aws-profile-selector/config.toml`. Everything in it is
+//! optional; environment variables and command-line flags take precedence.
+
+use crate::accounts::AccountNames;
+use crate::audit::AuditSettings;
+use crate::eks::EksSettings;
+use crate::environment::Environment;
+use crate::filter::glob_match;
+use crate::hooks::HookSettings;
+use crate::keys::KeySettings;
+use crate::matcher::MatchMode;
+use crate::onepassword::CredentialSource;
+use crate::profile_templates::ProfileTemplate;
+use crate::session::SessionDuration;
+use crate::state::Scope;
+use crate::template::DisplayFormat;
+use crate::theme::ThemeSettings;
+use crate::ui::{PageSize, Picker, SortOrder};
+use anyhow::Result;
+use serde::Deserialize;
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::PathBuf;
+
+/// Overrides the location of the settings file.
+pub const SETTINGS_PATH_VAR: &str = "AWS_PROFILE_SELECTOR_CONFIG";
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct Settings {
+    /// Default for `--sort`, in `list` as well as the selector.
+    pub sort: Option<SortOrder>,
+    /// Default for `--match`.
+    #[serde(rename = "match")]
+    pub match_mode: Option<MatchMode>,
+    /// Default for `--page-size`.
+    pub page_size: Option<PageSize>,
+    /// Vim-style keys in the selectors: j/k to move, and in `--tui` gg/G to
+    /// jump and `/` to filter.
+    pub vim: bool,
+    /// Default for `--format`.
+    pub display_format: Option<DisplayFormat>,
+    /// Default for `statusline --format`.
+    pub statusline_format: Option<DisplayFormat>,
+    /// Always look up account aliases, as with `--account-aliases`.
+    pub account_aliases: bool,
+    /// Always mask account IDs, as with `--redact`.
+    pub redact: bool,
+    /// Always pick from numbered lists, as with `--accessible`.
+    pub accessible: bool,
+    /// Never write files, as with `--read-only`.
+    pub read_only: bool,
+    /// Default for `--scope`.
+    pub scope: Option<Scope>,
+    /// Glob patterns hidden from the selector, in addition to `hide add`.
+    pub hide: Vec<String>,
+    /// Glob patterns for profiles that must be confirmed by typing their name
+    /// before they are activated.
+    pub protected: Vec<String>,
+    /// Always ask before activating a profile picked in the selector, as
+    /// with `--confirm`.
+    pub confirm: bool,
+    /// The text of an entry at the top of the `activate` selector that
+    /// deactivates instead, e.g. `"<no profile>"`; none when unset.
+    pub deactivate_entry: Option<String>,
+    /// How many recent profiles `switch` offers.
+    pub switch_count: Option<usize>,
+    /// Default for `--picker`.
+    pub picker: Option<Picker>,
+    /// Aliases (alias → profile), in addition to `alias add`.
+    pub aliases: BTreeMap<String, String>,
+    /// Account nicknames (account ID → name), shown and matched wherever
+    /// account IDs are.
+    pub accounts: AccountNames,
+    /// Tags by profile name or glob pattern, shown next to the profiles and
+    /// matched by `--tag`.
+    pub tags: BTreeMap<String, Vec<String>>,
+    /// Store the static keys entered in `add` in the OS keychain rather than
+    /// in the credentials file.
+    pub keychain: bool,
+    /// Where `env`, `exec`, `shell`, and `credential-process` read static
+    /// keys from instead, by profile name, e.g. `"1password:<item>"`.
+    pub credential_sources: BTreeMap<String, CredentialSource>,
+    /// Always export the profile's region in `-c` mode, as with
+    /// `--with-region`.
+    pub with_region: bool,
+    /// Show a desktop notification for every profile switch.
+    pub notify: bool,
+    /// Shell commands run around activation and deactivation.
+    pub hooks: HookSettings,
+    /// kubeconfig updates for the active profile's EKS clusters.
+    pub eks: EksSettings,
+    /// Selector colors.
+    pub theme: ThemeSettings,
+    /// The full-screen browser's select, cancel, favorite, hide, details,
+    /// edit, and copy keys.
+    pub keys: KeySettings,
+    /// Warnings about credentials that are about to run out.
+    pub expiry: ExpirySettings,
+    /// The log of activations, `exec`, and `console` kept for auditing.
+    pub audit: AuditSettings,
+    /// How long the credentials `console`, `env`, `exec`, and `creds write`
+    /// make last unless `--duration` says, by profile name or glob.
+    pub session_durations: BTreeMap<String, SessionDuration>,
+    /// Profile templates for `add --from-template`, by name.
+    pub templates: BTreeMap<String, ProfileTemplate>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ExpirySettings {
+    /// Activation warns when the profile's SSO token or MFA session has less
+    /// than this many minutes left; 0 turns the warning off.
+    pub warn_minutes: u32,
+    /// Offer to log in to SSO again straight away after the warning.
+    pub prompt_login: bool,
+}
+
+impl Default for ExpirySettings {
+    fn default() -> Self {
+        Self {
+            warn_minutes: 30,
+            prompt_login: false,
+        }
+    }
+}
+
+/// `$AWS_PROFILE_SELECTOR_CONFIG`, else `$XDG_CONFIG_HOME` or `~/.config`
+/// followed by `aws-profile-selector/config.toml`.
+pub fn get_settings_path() -> Result<PathBuf> {
+    Ok(Environment::current()?.settings_file())
+}
+
+impl Settings {
+    pub fn is_protected(&self, profile_name: &str) -> bool {
+        self.protected
+            .iter()
+            .any(|pattern| glob_match(pattern, profile_name))
+    }
+
+    /// Reads the settings file, treating a missing one as empty.
+    pub fn load() -> Result<Self> {
+        let path = get_settings_path()?;
+
+        if !path.exists() {
+            tracing::debug!("No settings file at {}; using the defaults", path.display());
+            return Ok(Self::default());
+        }
+
+        tracing::debug!("Reading settings from {}", path.display());
+        let contents = fs::read_to_string(&path)?;
+        Self::parse(&contents)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    fn parse(contents: &str) -> Result<Self, toml::de::Error> {
+        toml::from_str(contents)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::theme::Preset;
+
+    #[test]
+    fn test_parse_settings() {
+        let settings = Settings::parse(
+            r#"
+sort = "frecency"
+match = "exact"
+page-size = 20
+scope = "terminal"
+with-region = true
+notify = true
+display-format = "{name:<30} {region}"
+hide = ["generated-*"]
+protected = ["*prod*"]
+
+[aliases]
+p = "mycorp-production-AdministratorAccess"
+
+[accounts]
+123456789012 = "payments-prod"
+
+[tags]
+"*prod*" = ["prod"]
+
+[hooks]
+post-activate = ["kubectx $AWS_PROFILE"]
+
+[eks]
+sync = true
+clusters = { "*prod*" = ["payments"] }
+
+[theme]
+preset = "light"
+region = "bold #ff8700"
+
+[expiry]
+prompt-login = true
+
+[keys]
+select = "tab"
+
+[templates.eng-role]
+roles = ["ReadOnly", "Admin"]
+sso_account_id = "{account_id}"
+"#,
+        )
+        .unwrap();
+
+        assert_eq!(settings.sort, Some(SortOrder::Frecency));
+        assert_eq!(settings.match_mode, Some(MatchMode::Exact));
+        assert_eq!(settings.page_size, Some(PageSize::Rows(20)));
+        assert_eq!(settings.scope, Some(Scope::Terminal));
+        assert!(settings.with_region);
+        assert!(settings.notify);
+        assert!(settings.display_format.is_some());
+        assert_eq!(settings.hide, vec!["generated-*"]);
+        assert!(settings.is_protected("payments-prod-Admin"));
+        assert!(!settings.is_protected("payments-dev-Admin"));
+        assert_eq!(
+            settings.aliases["p"],
+            "mycorp-production-AdministratorAccess"
+        );
+        assert_eq!(settings.accounts["123456789012"], "payments-prod");
+        assert_eq!(settings.tags["*prod*"], ["prod"]);
+        assert_eq!(settings.hooks.post_activate, ["kubectx $AWS_PROFILE"]);
+        assert!(settings.hooks.pre_activate.is_empty());
+        assert!(settings.eks.sync);
+        assert_eq!(settings.eks.clusters["*prod*"], ["payments"]);
+
+        assert_eq!(settings.theme.preset, Preset::Light);
+        assert!(settings.theme.region.is_some());
+        assert_eq!(settings.expiry.warn_minutes, 30);
+        assert!(settings.expiry.prompt_login);
+        assert_eq!(settings.keys.select.to_string(), "tab");
+        assert_eq!(settings.keys.cancel.to_string(), "esc");
+        assert_eq!(settings.templates["eng-role"].roles, ["ReadOnly", "Admin"]);
+
+        assert!(Settings::parse("").unwrap().sort.is_none());
+        assert!(Settings::parse("colour = \"red\"").is_err());
+        assert!(Settings::parse("sort = \"sideways\"").is_err());
+        assert!(Settings::parse("display-format = \"{colour}\"").is_err());
+        assert!(Settings::parse("[theme]\nregion = \"sparkly\"").is_err());
+        assert!(Settings::parse("[hooks]\non-activate = []").is_err());
+        assert!(Settings::parse("[keys]\nhide = \"ctrl-hyper-x\"").is_err());
+        assert_eq!(
+            Settings::parse("page-size = \"auto\"").unwrap().page_size,
+            Some(PageSize::Auto)
+        );
+        assert!(Settings::parse("page-size = 0").is_err());
+    }
+}
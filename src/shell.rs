@@ -0,0 +1,436 @@
+//! The commands that set and unset environment variables in the user's
+//! shell, used by `-c`, `env`, `hook-env`, and the `init` wrappers.
+
+use crate::dry_run::would;
+use std::sync::OnceLock;
+
+/// The shell syntaxes we know how to emit commands for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Shell {
+    Bash,
+    Zsh,
+    /// sh, dash, ksh, and the other POSIX shells.
+    Posix,
+    Fish,
+    Nu,
+    /// PowerShell.
+    Pwsh,
+    Cmd,
+    Elvish,
+    Xonsh,
+    /// tcsh and csh.
+    Tcsh,
+}
+
+/// The names `--shell` accepts, each mapped through [`Shell::from_name`].
+pub const SHELL_NAMES: [&str; 13] = [
+    "bash",
+    "zsh",
+    "sh",
+    "fish",
+    "nu",
+    "nushell",
+    "pwsh",
+    "powershell",
+    "cmd",
+    "elvish",
+    "xonsh",
+    "tcsh",
+    "csh",
+];
+
+static OVERRIDE: OnceLock<Shell> = OnceLock::new();
+
+/// Forces the syntax for the rest of the process (`--shell`); only the first
+/// call counts.
+pub fn set_shell(shell: Shell) {
+    let _ = OVERRIDE.set(shell);
+}
+
+impl Shell {
+    /// The shell named by `--shell`, else the shell running this process
+    /// (which is the one that evaluates `-c` output, even when it differs
+    /// from the login shell), else the one in the `SHELL` environment
+    /// variable. Windows shells don't set it, so there cmd is recognised by
+    /// the `PROMPT` variable it defines and PowerShell is assumed otherwise.
+    pub fn detect() -> Self {
+        if let Some(shell) = OVERRIDE.get() {
+            return *shell;
+        }
+        if let Some((name, shell)) = parent_process_name()
+            .and_then(|name| Self::from_program(&name).map(|shell| (name, shell)))
+        {
+            tracing::debug!("Detected the {shell:?} shell from the parent process '{name}'");
+            return shell;
+        }
+
+        let shell = std::env::var("SHELL").unwrap_or_default();
+        let windows_prompt = cfg!(windows) && std::env::var_os("PROMPT").is_some();
+        let detected = Self::from_path(&shell, cfg!(windows), windows_prompt);
+        tracing::debug!("Detected the {detected:?} shell from SHELL='{shell}'");
+        detected
+    }
+
+    /// The syntax of a shell given by name or path, such as `zsh` or
+    /// `/usr/bin/fish`.
+    pub fn from_name(shell: &str) -> Self {
+        Self::from_path(shell, false, false)
+    }
+
+    fn from_path(shell: &str, windows: bool, windows_prompt: bool) -> Self {
+        if let Some(shell) = Self::from_program(shell) {
+            return shell;
+        }
+
+        let shell = shell.to_lowercase();
+        if shell.contains("nu") || shell.contains("nushell") {
+            Self::Nu
+        } else if shell.contains("fish") {
+            Self::Fish
+        } else if windows && shell.is_empty() {
+            if windows_prompt {
+                Self::Cmd
+            } else {
+                Self::Pwsh
+            }
+        } else {
+            // Default to bash/zsh/POSIX syntax
+            Self::Posix
+        }
+    }
+
+    /// Recognises a shell program by its name or path, also as a login shell
+    /// (`-zsh`) or with a version (`bash5.2`); `None` for anything else.
+    fn from_program(program: &str) -> Option<Self> {
+        let program = program.trim().to_lowercase();
+        let program = program
+            .rsplit(['/', '\\'])
+            .next()
+            .unwrap_or_default()
+            .trim_end_matches(".exe")
+            .trim_start_matches('-')
+            .trim_end_matches(|c: char| c.is_ascii_digit() || c == '.');
+
+        Some(match program {
+            "bash" => Self::Bash,
+            "zsh" => Self::Zsh,
+            "sh" | "dash" | "ash" | "ksh" | "mksh" | "yash" => Self::Posix,
+            "fish" => Self::Fish,
+            "nu" | "nushell" => Self::Nu,
+            "pwsh" | "powershell" => Self::Pwsh,
+            "cmd" => Self::Cmd,
+            "elvish" => Self::Elvish,
+            "xonsh" => Self::Xonsh,
+            "tcsh" | "csh" => Self::Tcsh,
+            _ => return None,
+        })
+    }
+
+    pub fn set_env(self, key: &str, value: &str) -> String {
+        let value = self.quote(value);
+
+        match self {
+            Self::Bash | Self::Zsh | Self::Posix => format!("export {key}={value}"),
+            Self::Fish => format!("set -gx {key} {value}"),
+            Self::Nu => format!("$env.{key} = {value}"),
+            Self::Pwsh => format!("$env:{key} = {value}"),
+            Self::Cmd => format!("set \"{key}={value}\""),
+            Self::Elvish => format!("set-env {key} {value}"),
+            Self::Xonsh => format!("${key} = {value}"),
+            // `eval \`...\`` joins the lines into one, so each ends the
+            // command itself.
+            Self::Tcsh => format!("setenv {key} {value};"),
+        }
+    }
+
+    pub fn unset_env(self, key: &str) -> String {
+        match self {
+            Self::Bash | Self::Zsh | Self::Posix => format!("unset {key}"),
+            Self::Fish => format!("set -e {key}"),
+            Self::Nu => format!("hide-env {key}"),
+            Self::Pwsh => format!("Remove-Item Env:{key} -ErrorAction SilentlyContinue"),
+            Self::Cmd => format!("set {key}="),
+            Self::Elvish => format!("unset-env {key}"),
+            Self::Xonsh => format!("${{...}}.pop(\"{key}\", None)"),
+            Self::Tcsh => format!("unsetenv {key};"),
+        }
+    }
+
+    /// Quotes `value` so the shell takes it literally.
+    fn quote(self, value: &str) -> String {
+        let special: &[char] = match self {
+            Self::Bash | Self::Zsh | Self::Posix => &['\\', '"', '$', '`'],
+            Self::Fish => &['\\', '"', '$'],
+            // A Python string literal; `$` only expands in subprocess mode.
+            Self::Nu | Self::Xonsh => &['\\', '"'],
+            // Nothing expands inside single quotes; a quote is written twice.
+            Self::Pwsh | Self::Elvish => return format!("'{}'", value.replace('\'', "''")),
+            // `set "KEY=value"` quotes the whole assignment, which keeps `&`
+            // and `|` literal; cmd has no way to escape `"` itself.
+            Self::Cmd => return value.replace('"', ""),
+            // Single quotes stop everything but history expansion, and a
+            // quote has to be closed, escaped, and reopened.
+            Self::Tcsh => {
+                return format!("'{}'", value.replace('\'', "'\\''").replace('!', "\\!"));
+            }
+        };
+
+        let mut quoted = String::with_capacity(value.len() + 2);
+        quoted.push('"');
+        for c in value.chars() {
+            if special.contains(&c) {
+                quoted.push('\\');
+            }
+            quoted.push(c);
+        }
+        quoted.push('"');
+        quoted
+    }
+}
+
+/// The name of the process that started this one.
+#[cfg(target_os = "linux")]
+fn parent_process_name() -> Option<String> {
+    let parent = std::os::unix::process::parent_id();
+    std::fs::read_to_string(format!("/proc/{parent}/comm")).ok()
+}
+
+#[cfg(all(unix, not(target_os = "linux")))]
+fn parent_process_name() -> Option<String> {
+    let parent = std::os::unix::process::parent_id();
+    let output = std::process::Command::new("ps")
+        .args(["-o", "comm=", "-p", &parent.to_string()])
+        .output()
+        .ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+/// Windows has no cheap way to ask; `PROMPT` stands in for it.
+#[cfg(not(unix))]
+fn parent_process_name() -> Option<String> {
+    None
+}
+
+/// Builds the command that sets (or, with `None`, unsets) `AWS_PROFILE` in
+/// the user's shell, detected from the `SHELL` environment variable.
+pub fn shell_command(profile_name: Option<&str>) -> String {
+    let shell = Shell::detect();
+    match profile_name {
+        Some(name) => shell.set_env("AWS_PROFILE", name),
+        None => shell.unset_env("AWS_PROFILE"),
+    }
+}
+
+/// Builds one line per variable that exports it in the user's shell.
+pub fn export_commands(vars: &[(&str, String)]) -> String {
+    let shell = Shell::detect();
+
+    vars.iter()
+        .map(|(key, value)| shell.set_env(key, value))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Like [`export_commands`], but variables without a value are unset.
+pub fn env_commands(vars: &[(&str, Option<String>)]) -> String {
+    let shell = Shell::detect();
+
+    vars.iter()
+        .map(|(key, value)| match value {
+            Some(value) => shell.set_env(key, value),
+            None => shell.unset_env(key),
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+pub fn print_shell_command(profile_name: Option<&str>) {
+    print_for_shell(&shell_command(profile_name));
+}
+
+/// Prints commands for the calling shell to evaluate. A dry run shows them
+/// on stderr instead, where the shell leaves them alone.
+pub fn print_for_shell(commands: &str) {
+    if !would(format_args!("run in the shell:\n{}", commands.trim_end())) {
+        print!("{commands}");
+    }
+}
+
+/// The key `init --bind` opens the selector with: Ctrl or Alt and a letter
+/// or digit, written `ctrl-o` or `alt-p`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct KeyBinding {
+    alt: bool,
+    key: char,
+}
+
+impl KeyBinding {
+    /// The key as `bind` (bash), `bindkey` (zsh), or `bind` (fish) writes it.
+    pub fn sequence(self, shell: Shell) -> Option<String> {
+        let key = self.key;
+        Some(match (shell, self.alt) {
+            (Shell::Bash, false) => format!("\\C-{key}"),
+            (Shell::Zsh, false) => format!("^{}", key.to_ascii_uppercase()),
+            (Shell::Fish, false) => format!("\\c{key}"),
+            (Shell::Zsh, true) => format!("^[{key}"),
+            (Shell::Bash | Shell::Fish, true) => format!("\\e{key}"),
+            _ => return None,
+        })
+    }
+}
+
+impl std::fmt::Display for KeyBinding {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let modifier = if self.alt { "alt" } else { "ctrl" };
+        write!(f, "{modifier}-{}", self.key)
+    }
+}
+
+impl std::str::FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{text}' is not a key like ctrl-o or alt-p");
+        let lower = text.to_ascii_lowercase();
+        let (alt, key) = match lower.split_once('-') {
+            Some(("ctrl", key)) => (false, key),
+            Some(("alt", key)) => (true, key),
+            _ => return Err(invalid()),
+        };
+        let mut chars = key.chars();
+        match (chars.next(), chars.next()) {
+            // Ctrl with a digit isn't a key terminals send.
+            (Some(key), None) if key.is_ascii_lowercase() || (alt && key.is_ascii_digit()) => {
+                Ok(Self { alt, key })
+            }
+            _ => Err(invalid()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_set_and_unset_per_shell() {
+        let cases = [
+            (
+                Shell::Posix,
+                "export AWS_PROFILE=\"dev\"",
+                "unset AWS_PROFILE",
+            ),
+            (
+                Shell::Fish,
+                "set -gx AWS_PROFILE \"dev\"",
+                "set -e AWS_PROFILE",
+            ),
+            (
+                Shell::Nu,
+                "$env.AWS_PROFILE = \"dev\"",
+                "hide-env AWS_PROFILE",
+            ),
+            (
+                Shell::Pwsh,
+                "$env:AWS_PROFILE = 'dev'",
+                "Remove-Item Env:AWS_PROFILE -ErrorAction SilentlyContinue",
+            ),
+            (Shell::Cmd, "set \"AWS_PROFILE=dev\"", "set AWS_PROFILE="),
+            (
+                Shell::Elvish,
+                "set-env AWS_PROFILE 'dev'",
+                "unset-env AWS_PROFILE",
+            ),
+            (
+                Shell::Xonsh,
+                "$AWS_PROFILE = \"dev\"",
+                "${...}.pop(\"AWS_PROFILE\", None)",
+            ),
+            (
+                Shell::Tcsh,
+                "setenv AWS_PROFILE 'dev';",
+                "unsetenv AWS_PROFILE;",
+            ),
+        ];
+
+        for (shell, set, unset) in cases {
+            assert_eq!(shell.set_env("AWS_PROFILE", "dev"), set, "{shell:?}");
+            assert_eq!(shell.unset_env("AWS_PROFILE"), unset, "{shell:?}");
+        }
+    }
+
+    #[test]
+    fn test_shell_detection() {
+        let detect = |shell| Shell::from_path(shell, false, false);
+        assert_eq!(detect("/bin/zsh"), Shell::Zsh);
+        assert_eq!(detect("/bin/dash"), Shell::Posix);
+        assert_eq!(detect("/usr/bin/fish"), Shell::Fish);
+        assert_eq!(detect("/usr/bin/nu"), Shell::Nu);
+        assert_eq!(detect("/usr/local/bin/pwsh"), Shell::Pwsh);
+        assert_eq!(detect("/usr/bin/elvish"), Shell::Elvish);
+        assert_eq!(detect("/usr/bin/xonsh"), Shell::Xonsh);
+        assert_eq!(detect("/bin/tcsh"), Shell::Tcsh);
+        assert_eq!(detect("/bin/csh"), Shell::Tcsh);
+        assert_eq!(detect(""), Shell::Posix);
+        assert_eq!(detect("/opt/homebrew/bin/nushell-nightly"), Shell::Nu);
+        assert_eq!(Shell::from_name("bash"), Shell::Bash);
+        assert_eq!(Shell::from_name("sh"), Shell::Posix);
+        assert_eq!(Shell::from_name("cmd"), Shell::Cmd);
+        assert_eq!(Shell::from_name("nushell"), Shell::Nu);
+        assert_eq!(Shell::from_name("powershell"), Shell::Pwsh);
+        assert_eq!(Shell::from_name("csh"), Shell::Tcsh);
+
+        assert_eq!(
+            Shell::from_path(r"C:\Windows\System32\cmd.exe", true, true),
+            Shell::Cmd
+        );
+        assert_eq!(Shell::from_path("", true, false), Shell::Pwsh);
+        assert_eq!(Shell::from_path("", true, true), Shell::Cmd);
+        // Git Bash and MSYS set SHELL on Windows.
+        assert_eq!(Shell::from_path("/usr/bin/bash", true, true), Shell::Bash);
+    }
+
+    #[test]
+    fn test_parent_process_names() {
+        assert_eq!(Shell::from_program("zsh\n"), Some(Shell::Zsh));
+        assert_eq!(Shell::from_program("-zsh"), Some(Shell::Zsh));
+        assert_eq!(Shell::from_program("/bin/bash"), Some(Shell::Bash));
+        assert_eq!(Shell::from_program("bash5.2"), Some(Shell::Bash));
+        assert_eq!(Shell::from_program("pwsh.exe"), Some(Shell::Pwsh));
+        assert_eq!(Shell::from_program("nu"), Some(Shell::Nu));
+        assert_eq!(Shell::from_program("dash"), Some(Shell::Posix));
+        // Not shells: make, tmux, and a python running xonsh.
+        assert_eq!(Shell::from_program("make"), None);
+        assert_eq!(Shell::from_program("tmux: server"), None);
+        assert_eq!(Shell::from_program("python3"), None);
+    }
+
+    #[test]
+    fn test_quote_escapes_shell_specials() {
+        assert_eq!(Shell::Posix.quote("a$b`c\"d"), r#""a\$b\`c\"d""#);
+        assert_eq!(Shell::Fish.quote("a$b`c"), r#""a\$b`c""#);
+        assert_eq!(Shell::Nu.quote("a$b\\c"), r#""a$b\\c""#);
+        assert_eq!(Shell::Xonsh.quote("a$b\"c"), r#""a$b\"c""#);
+        assert_eq!(Shell::Pwsh.quote("it's $x"), "'it''s $x'");
+        assert_eq!(Shell::Elvish.quote("it's $x"), "'it''s $x'");
+        assert_eq!(Shell::Tcsh.quote("it's $x!"), r"'it'\''s $x\!'");
+    }
+
+    #[test]
+    fn test_key_binding_sequences() {
+        let ctrl_o: KeyBinding = "ctrl-o".parse().unwrap();
+        assert_eq!(ctrl_o.sequence(Shell::Bash).as_deref(), Some(r"\C-o"));
+        assert_eq!(ctrl_o.sequence(Shell::Zsh).as_deref(), Some("^O"));
+        assert_eq!(ctrl_o.sequence(Shell::Fish).as_deref(), Some(r"\co"));
+        assert_eq!(ctrl_o.sequence(Shell::Nu), None);
+        let alt_p: KeyBinding = "Alt-P".parse().unwrap();
+        assert_eq!(alt_p.sequence(Shell::Zsh).as_deref(), Some("^[p"));
+        assert_eq!(alt_p.sequence(Shell::Fish).as_deref(), Some(r"\ep"));
+        for text in ["o", "ctrl-", "ctrl-1", "shift-o", "ctrl-oo"] {
+            assert!(text.parse::<KeyBinding>().is_err(), "{text}");
+        }
+    }
+}
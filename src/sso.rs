@@ -0,0 +1,604 @@
+//! SSO token cache handling and the OIDC device-authorization login flow.
+//!
+//! Tokens are stored in `~/.aws/sso/cache` using the same file names and
+//! JSON layout as the AWS CLI, so a login performed here is picked up by the
+//! CLI and SDKs and vice versa.
+
+use crate::config::{AwsConfig, Profile, SsoSession, home_dir};
+use crate::dry_run::ensure_writable;
+use crate::environment::now;
+use crate::files::{Access, write_atomically};
+use crate::tasks::{Background, Update};
+use anyhow::{Context, Result};
+use aws_config::{BehaviorVersion, Region};
+use aws_sdk_ssooidc::error::DisplayErrorContext;
+use aws_sdk_ssooidc::operation::create_token::CreateTokenError;
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+use sha1::{Digest, Sha1};
+use std::collections::HashMap;
+use std::fs;
+use std::path::PathBuf;
+
+const CLIENT_NAME: &str = "aws-profile-selector";
+const DEVICE_CODE_GRANT: &str = "urn:ietf:params:oauth:grant-type:device_code";
+const REFRESH_TOKEN_GRANT: &str = "refresh_token";
+const DEFAULT_SESSION_SCOPES: &[&str] = &["sso:account:access"];
+
+/// The SSO portal a profile authenticates against.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SsoTarget {
+    pub start_url: String,
+    pub region: String,
+    /// Set for profiles using an `[sso-session]` block; legacy profiles with
+    /// inline `sso_start_url` have none.
+    pub session_name: Option<String>,
+    pub scopes: Vec<String>,
+}
+
+impl SsoTarget {
+    /// Returns the SSO target for a profile, or `None` when the profile does
+    /// not authenticate through SSO.
+    pub fn for_profile(config: &AwsConfig, profile: &Profile) -> Option<Self> {
+        let start_url = config.sso_start_url_for(profile)?.to_string();
+        let region = config.sso_region_for(profile)?.to_string();
+        let session = config.sso_session_for(profile);
+
+        Some(Self {
+            start_url,
+            region,
+            session_name: session.map(|session| session.name.clone()),
+            scopes: session.map(session_scopes).unwrap_or_default(),
+        })
+    }
+
+    /// Returns the SSO target of an `[sso-session]` block, or `None` when it
+    /// lacks a start URL or region.
+    pub fn for_session(session: &SsoSession) -> Option<Self> {
+        Some(Self {
+            start_url: session.get_start_url()?.to_string(),
+            region: session.get_region()?.to_string(),
+            session_name: Some(session.name.clone()),
+            scopes: session_scopes(session),
+        })
+    }
+
+    /// The cache file name the AWS CLI uses: the SHA-1 of the session name,
+    /// or of the start URL for legacy profiles.
+    pub fn cache_key(&self) -> String {
+        let input = self.session_name.as_deref().unwrap_or(&self.start_url);
+        hex::encode(Sha1::digest(input.as_bytes()))
+    }
+
+    /// A short label for messages: the session name or the start URL.
+    pub fn label(&self) -> &str {
+        self.session_name.as_deref().unwrap_or(&self.start_url)
+    }
+}
+
+/// A cached SSO access token in the AWS CLI's on-disk format.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CachedToken {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start_url: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    pub access_token: String,
+    #[serde(serialize_with = "serialize_timestamp")]
+    #[serde(deserialize_with = "deserialize_timestamp")]
+    pub expires_at: DateTime<Utc>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_id: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub client_secret: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[serde(serialize_with = "serialize_optional_timestamp")]
+    #[serde(deserialize_with = "deserialize_optional_timestamp")]
+    pub registration_expires_at: Option<DateTime<Utc>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub refresh_token: Option<String>,
+}
+
+impl CachedToken {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at <= now()
+    }
+
+    /// Whether [`refresh`] can renew the token without a browser login.
+    pub fn can_refresh(&self) -> bool {
+        self.refresh_token.is_some() && self.has_valid_registration()
+    }
+
+    fn has_valid_registration(&self) -> bool {
+        self.client_id.is_some()
+            && self.client_secret.is_some()
+            && self
+                .registration_expires_at
+                .is_some_and(|expires_at| expires_at > now())
+    }
+}
+
+/// Parses the timestamp formats found in SSO cache files: RFC 3339 as
+/// written by the CLI v2 and SDKs, and the `...UTC` suffix from older tools.
+fn parse_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+
+    let value = value.trim_end_matches("UTC").trim_end_matches('Z');
+    NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+        .ok()
+        .map(|timestamp| timestamp.and_utc())
+}
+
+fn format_timestamp(timestamp: &DateTime<Utc>) -> String {
+    timestamp.format("%Y-%m-%dT%H:%M:%SZ").to_string()
+}
+
+fn serialize_timestamp<S: Serializer>(
+    timestamp: &DateTime<Utc>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&format_timestamp(timestamp))
+}
+
+fn serialize_optional_timestamp<S: Serializer>(
+    timestamp: &Option<DateTime<Utc>>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match timestamp {
+        Some(timestamp) => serialize_timestamp(timestamp, serializer),
+        None => serializer.serialize_none(),
+    }
+}
+
+fn deserialize_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<DateTime<Utc>, D::Error> {
+    let value = String::deserialize(deserializer)?;
+    parse_timestamp(&value)
+        .ok_or_else(|| serde::de::Error::custom(format!("invalid timestamp '{value}'")))
+}
+
+fn deserialize_optional_timestamp<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Option<DateTime<Utc>>, D::Error> {
+    Ok(Option::<String>::deserialize(deserializer)?
+        .as_deref()
+        .and_then(parse_timestamp))
+}
+
+/// The registration scopes of a session, defaulting to the ones the CLI
+/// requests when `sso_registration_scopes` is not set.
+fn session_scopes(session: &SsoSession) -> Vec<String> {
+    session
+        .attributes
+        .get("sso_registration_scopes")
+        .map(|scopes| {
+            scopes
+                .split(',')
+                .map(|scope| scope.trim().to_string())
+                .filter(|scope| !scope.is_empty())
+                .collect()
+        })
+        .unwrap_or_else(|| {
+            DEFAULT_SESSION_SCOPES
+                .iter()
+                .map(|scope| scope.to_string())
+                .collect()
+        })
+}
+
+pub fn get_sso_cache_dir() -> Result<PathBuf> {
+    Ok(home_dir()?.join(".aws").join("sso").join("cache"))
+}
+
+pub fn cache_path(target: &SsoTarget) -> Result<PathBuf> {
+    Ok(get_sso_cache_dir()?.join(format!("{}.json", target.cache_key())))
+}
+
+/// Reads the cached token for a target. Unreadable or malformed cache files
+/// are treated as missing, just like the CLI does.
+pub fn read_cached_token(target: &SsoTarget) -> Result<Option<CachedToken>> {
+    let path = cache_path(target)?;
+
+    let Ok(content) = fs::read_to_string(&path) else {
+        return Ok(None);
+    };
+
+    Ok(serde_json::from_str(&content).ok())
+}
+
+/// When each SSO profile's cached token expires, or `None` when it has no
+/// token. Profiles sharing a session read the cache file once; non-SSO
+/// profiles are left out.
+pub fn token_expiries(config: &AwsConfig) -> Result<HashMap<String, Option<DateTime<Utc>>>> {
+    let mut by_cache_key: HashMap<String, Option<DateTime<Utc>>> = HashMap::new();
+    let mut expiries = HashMap::new();
+
+    for profile in &config.profiles {
+        let Some(target) = SsoTarget::for_profile(config, profile) else {
+            continue;
+        };
+        let expiry = match by_cache_key.get(&target.cache_key()) {
+            Some(expiry) => *expiry,
+            None => {
+                let expiry = read_cached_token(&target)?.map(|token| token.expires_at);
+                by_cache_key.insert(target.cache_key(), expiry);
+                expiry
+            }
+        };
+        expiries.insert(profile.name.clone(), expiry);
+    }
+
+    Ok(expiries)
+}
+
+/// Reads the token expiries on `background`, which reports them as one
+/// [`Update::TokenExpiries`]; a token cache that can't be read reports
+/// nothing.
+pub fn spawn_token_expiries(background: &Background, config: AwsConfig) -> Result<()> {
+    background.spawn(|sender| async move {
+        if let Ok(expiries) = token_expiries(&config) {
+            let _ = sender.send(Update::TokenExpiries(expiries));
+        }
+    })
+}
+
+/// `expires in 3h 12m`, or `expired` once `expires_at` has passed.
+pub fn describe_expiry(expires_at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    if expires_at <= now {
+        return "expired".to_string();
+    }
+    format!("expires in {}", time_left(expires_at, now))
+}
+
+/// `3h 12m`, `45m`, `2d 4h`, or `<1m`; `expires_at` should be ahead of `now`.
+pub fn time_left(expires_at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = (expires_at - now).num_minutes().max(0);
+
+    let (days, hours, minutes) = (minutes / (24 * 60), minutes / 60 % 24, minutes % 60);
+    match (days, hours) {
+        (0, 0) if minutes == 0 => "<1m".to_string(),
+        (0, 0) => format!("{minutes}m"),
+        (0, _) => format!("{hours}h {minutes}m"),
+        _ => format!("{days}d {hours}h"),
+    }
+}
+
+/// Deletes the cached token for a target, returning whether one existed.
+pub fn remove_cached_token(target: &SsoTarget) -> Result<bool> {
+    let path = cache_path(target)?;
+
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    ensure_writable(format_args!("remove {}", path.display()))?;
+    fs::remove_file(&path)
+        .with_context(|| format!("Failed to remove SSO token cache: {path:?}"))?;
+    Ok(true)
+}
+
+fn write_cached_token(target: &SsoTarget, token: &CachedToken) -> Result<()> {
+    let path = cache_path(target)?;
+    // The access and refresh tokens and the client secret are all in it.
+    write_atomically(&path, serde_json::to_string(token)?, Access::Private)
+        .context("Failed to write SSO token cache")
+}
+
+/// Runs the OIDC device-authorization flow for `target`, asking the user to
+/// approve the login in their browser, and caches the resulting token.
+///
+/// All prompts go to stderr so stdout stays clean for `-c` mode.
+pub async fn login(target: &SsoTarget) -> Result<CachedToken> {
+    ensure_writable(format_args!(
+        "log in to {}, which writes the SSO token cache",
+        target.label()
+    ))?;
+    let client = oidc_client(target).await;
+
+    let existing = read_cached_token(target)?;
+    let (client_id, client_secret, registration_expires_at) = match existing {
+        Some(token) if token.has_valid_registration() => (
+            token.client_id.unwrap_or_default(),
+            token.client_secret.unwrap_or_default(),
+            token.registration_expires_at,
+        ),
+        _ => {
+            let registration = client
+                .register_client()
+                .client_name(CLIENT_NAME)
+                .client_type("public")
+                .set_scopes((!target.scopes.is_empty()).then(|| target.scopes.clone()))
+                .send()
+                .await
+                .map_err(|e| {
+                    anyhow::anyhow!("RegisterClient failed: {}", DisplayErrorContext(e))
+                })?;
+
+            (
+                registration.client_id().unwrap_or_default().to_string(),
+                registration.client_secret().unwrap_or_default().to_string(),
+                DateTime::from_timestamp(registration.client_secret_expires_at(), 0),
+            )
+        }
+    };
+
+    let authorization = client
+        .start_device_authorization()
+        .client_id(&client_id)
+        .client_secret(&client_secret)
+        .start_url(&target.start_url)
+        .send()
+        .await
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "StartDeviceAuthorization failed: {}",
+                DisplayErrorContext(e)
+            )
+        })?;
+
+    let verification_uri = authorization
+        .verification_uri_complete()
+        .or(authorization.verification_uri())
+        .unwrap_or_default();
+
+    eprintln!("Attempting to open the SSO authorization page in your browser.");
+    eprintln!("If it does not open, visit: {verification_uri}");
+    if let Some(user_code) = authorization.user_code() {
+        eprintln!("and confirm the code: {user_code}");
+    }
+    let _ = open::that_detached(verification_uri);
+
+    let device_code = authorization.device_code().unwrap_or_default();
+    let deadline = now() + Duration::seconds(i64::from(authorization.expires_in()));
+    let mut interval = std::time::Duration::from_secs(authorization.interval().max(1) as u64);
+
+    let token = loop {
+        if now() >= deadline {
+            return Err(anyhow::anyhow!(
+                "SSO login timed out before it was approved"
+            ));
+        }
+
+        tokio::time::sleep(interval).await;
+
+        match client
+            .create_token()
+            .client_id(&client_id)
+            .client_secret(&client_secret)
+            .grant_type(DEVICE_CODE_GRANT)
+            .device_code(device_code)
+            .send()
+            .await
+        {
+            Ok(token) => break token,
+            Err(e) => match e.into_service_error() {
+                CreateTokenError::AuthorizationPendingException(_) => {}
+                CreateTokenError::SlowDownException(_) => {
+                    interval += std::time::Duration::from_secs(5);
+                }
+                error => {
+                    return Err(anyhow::anyhow!(
+                        "CreateToken failed: {}",
+                        DisplayErrorContext(error)
+                    ));
+                }
+            },
+        }
+    };
+
+    let cached_token = CachedToken {
+        start_url: Some(target.start_url.clone()),
+        region: Some(target.region.clone()),
+        access_token: token.access_token().unwrap_or_default().to_string(),
+        expires_at: now() + Duration::seconds(i64::from(token.expires_in())),
+        client_id: Some(client_id),
+        client_secret: Some(client_secret),
+        registration_expires_at,
+        refresh_token: token.refresh_token().map(str::to_string),
+    };
+
+    write_cached_token(target, &cached_token)?;
+    Ok(cached_token)
+}
+
+/// Trades the refresh token in `token` for a new access token, as the SDKs
+/// do near expiry, and caches the result. Needs no browser, but only works
+/// while the client registration and the refresh token are both valid.
+pub async fn refresh(target: &SsoTarget, token: &CachedToken) -> Result<CachedToken> {
+    let (Some(refresh_token), true) = (&token.refresh_token, token.can_refresh()) else {
+        return Err(anyhow::anyhow!(
+            "{} has no usable refresh token; log in again",
+            target.label()
+        ));
+    };
+
+    let client = oidc_client(target).await;
+    let response = client
+        .create_token()
+        .client_id(token.client_id.as_deref().unwrap_or_default())
+        .client_secret(token.client_secret.as_deref().unwrap_or_default())
+        .grant_type(REFRESH_TOKEN_GRANT)
+        .refresh_token(refresh_token)
+        .send()
+        .await
+        .map_err(|e| {
+            anyhow::anyhow!(
+                "Failed to refresh {}: {}",
+                target.label(),
+                DisplayErrorContext(e)
+            )
+        })?;
+
+    let refreshed = CachedToken {
+        access_token: response.access_token().unwrap_or_default().to_string(),
+        expires_at: now() + Duration::seconds(i64::from(response.expires_in())),
+        // Refresh tokens may be rotated; the old one stays good otherwise.
+        refresh_token: response
+            .refresh_token()
+            .map(str::to_string)
+            .or_else(|| token.refresh_token.clone()),
+        ..token.clone()
+    };
+    write_cached_token(target, &refreshed)?;
+    Ok(refreshed)
+}
+
+async fn oidc_client(target: &SsoTarget) -> aws_sdk_ssooidc::Client {
+    let sdk_config = aws_config::defaults(BehaviorVersion::latest())
+        .region(Region::new(target.region.clone()))
+        .no_credentials()
+        .load()
+        .await;
+    aws_sdk_ssooidc::Client::new(&sdk_config)
+}
+
+/// An account and role the signed-in user may assume through SSO.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountRole {
+    pub account_id: String,
+    pub account_name: String,
+    pub role_name: String,
+}
+
+/// Lists every account/role pair available to `access_token` through the
+/// SSO portal API.
+pub async fn list_account_roles(
+    target: &SsoTarget,
+    access_token: &str,
+) -> Result<Vec<AccountRole>> {
+    let sdk_config = aws_config::defaults(BehaviorVersion::latest())
+        .region(Region::new(target.region.clone()))
+        .no_credentials()
+        .load()
+        .await;
+    let client = aws_sdk_sso::Client::new(&sdk_config);
+
+    let accounts = client
+        .list_accounts()
+        .access_token(access_token)
+        .into_paginator()
+        .items()
+        .send()
+        .collect::<Result<Vec<_>, _>>()
+        .await
+        .map_err(|e| anyhow::anyhow!("ListAccounts failed: {}", DisplayErrorContext(e)))?;
+
+    let mut account_roles = Vec::new();
+    for account in accounts {
+        let account_id = account.account_id().unwrap_or_default().to_string();
+        let account_name = account.account_name().unwrap_or(&account_id).to_string();
+
+        let roles = client
+            .list_account_roles()
+            .access_token(access_token)
+            .account_id(&account_id)
+            .into_paginator()
+            .items()
+            .send()
+            .collect::<Result<Vec<_>, _>>()
+            .await
+            .map_err(|e| {
+                anyhow::anyhow!(
+                    "ListAccountRoles failed for {account_id}: {}",
+                    DisplayErrorContext(e)
+                )
+            })?;
+
+        account_roles.extend(roles.into_iter().filter_map(|role| {
+            Some(AccountRole {
+                account_id: account_id.clone(),
+                account_name: account_name.clone(),
+                role_name: role.role_name()?.to_string(),
+            })
+        }));
+    }
+
+    Ok(account_roles)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_cache_key_matches_aws_cli() {
+        let session = SsoTarget {
+            start_url: "https://corp.awsapps.com/start".to_string(),
+            region: "eu-west-1".to_string(),
+            session_name: Some("my-sso".to_string()),
+            scopes: Vec::new(),
+        };
+        let legacy = SsoTarget {
+            session_name: None,
+            ..session.clone()
+        };
+
+        // sha1("my-sso") and sha1("https://corp.awsapps.com/start")
+        assert_eq!(
+            session.cache_key(),
+            "0ad374308c5a4e22f723adf10145eafad7c4031c"
+        );
+        assert_eq!(
+            legacy.cache_key(),
+            hex::encode(Sha1::digest(b"https://corp.awsapps.com/start"))
+        );
+    }
+
+    #[test]
+    fn test_parse_cached_token() {
+        let token: CachedToken = serde_json::from_str(
+            r#"{
+                "startUrl": "https://corp.awsapps.com/start",
+                "region": "eu-west-1",
+                "accessToken": "token",
+                "expiresAt": "2020-01-01T00:00:00Z"
+            }"#,
+        )
+        .unwrap();
+
+        assert!(token.is_expired());
+        assert!(!token.can_refresh());
+        assert_eq!(format_timestamp(&token.expires_at), "2020-01-01T00:00:00Z");
+
+        let registered = CachedToken {
+            client_id: Some("client".to_string()),
+            client_secret: Some("secret".to_string()),
+            registration_expires_at: Some(Utc::now() + Duration::days(30)),
+            ..token
+        };
+        assert!(!registered.can_refresh());
+        let refreshable = CachedToken {
+            refresh_token: Some("refresh".to_string()),
+            ..registered
+        };
+        assert!(refreshable.can_refresh());
+
+        assert_eq!(
+            parse_timestamp("2030-01-01T08:30:00UTC"),
+            parse_timestamp("2030-01-01T08:30:00Z")
+        );
+    }
+
+    #[test]
+    fn test_describe_expiry() {
+        let now = Utc::now();
+        let describe = |delta: Duration| describe_expiry(now + delta, now);
+
+        assert_eq!(
+            describe(Duration::hours(3) + Duration::minutes(12)),
+            "expires in 3h 12m"
+        );
+        assert_eq!(describe(Duration::minutes(45)), "expires in 45m");
+        assert_eq!(describe(Duration::seconds(20)), "expires in <1m");
+        assert_eq!(
+            describe(Duration::days(2) + Duration::hours(5)),
+            "expires in 2d 5h"
+        );
+        assert_eq!(describe(Duration::zero()), "expired");
+        assert_eq!(describe(-Duration::hours(1)), "expired");
+    }
+}
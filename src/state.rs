@@ -0,0 +1,960 @@
+use crate::dry_run::{dry_run, ensure_writable, read_only, would, would_write};
+use crate::environment::Environment;
+use crate::files::{self, Access};
+use crate::filter::glob_match;
+use crate::settings::Settings;
+use crate::terminal;
+use anyhow::{Context, Result};
+use chrono::{DateTime, TimeDelta, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::fmt;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::OnceLock;
+
+/// Which current-profile file commands read and write.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Scope {
+    /// `current-profile` in the state directory, shared by every terminal.
+    #[default]
+    Global,
+    /// One file per tmux pane or tty under `current-profile.d/` in the state
+    /// directory.
+    Terminal,
+}
+
+static SCOPE: OnceLock<Scope> = OnceLock::new();
+
+/// Picks the scope for the rest of the process; only the first call counts.
+pub fn set_scope(scope: Scope) {
+    let _ = SCOPE.set(scope);
+}
+
+fn scope() -> Scope {
+    SCOPE.get().copied().unwrap_or_default()
+}
+
+/// Overrides the directory the current profile and the state file are kept
+/// in.
+pub const STATE_DIR_VAR: &str = "AWS_PROFILE_SELECTOR_STATE_DIR";
+
+/// The tool's own files, which older versions kept in `~/.aws`.
+pub(crate) const STATE_FILES: [&str; 3] = [
+    "current-profile",
+    "current-profile.d",
+    "profile-selector-state.json",
+];
+
+/// Where the current profile and the state file are kept, as
+/// [`Environment::state_dir`] has it. A directory chosen through
+/// `$AWS_PROFILE_SELECTOR_STATE_DIR` or `$XDG_STATE_HOME` is a choice made on
+/// purpose, so files an older version left in `~/.aws` are moved there;
+/// `~/.aws` stays in use otherwise, so prompts and scripts that read
+/// `~/.aws/current-profile` keep working.
+///
+/// With `--read-only` or `--dry-run` nothing is moved (a dry run says what
+/// would be), and `~/.aws` stays in use while it has the files.
+pub fn get_state_dir() -> Result<PathBuf> {
+    if let Some(dir) = STATE_DIR.get() {
+        return Ok(dir.clone());
+    }
+
+    let env = Environment::current()?;
+    let legacy = env.legacy_state_dir();
+    let has_legacy_files = || STATE_FILES.iter().any(|name| legacy.join(name).exists());
+    let dir = match env.chosen_state_dir() {
+        Some(dir) if read_only() || dry_run() => match has_legacy_files() {
+            true => {
+                for (source, target) in pending_moves(&legacy, &dir) {
+                    would(format_args!(
+                        "move {} to {}",
+                        source.display(),
+                        target.display()
+                    ));
+                }
+                legacy
+            }
+            false => dir,
+        },
+        Some(dir) => match migrate_state_files(&legacy, &dir) {
+            Ok(()) => dir,
+            Err(e) => {
+                tracing::warn!("{e:#}; still using {}", legacy.display());
+                legacy
+            }
+        },
+        None => env.state_dir(),
+    };
+    tracing::debug!("Keeping state in {}", dir.display());
+    Ok(STATE_DIR.get_or_init(|| dir).clone())
+}
+
+static STATE_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// The state files in `from` that `to` doesn't have yet, with where each
+/// would go.
+fn pending_moves(from: &Path, to: &Path) -> Vec<(PathBuf, PathBuf)> {
+    if from == to {
+        return Vec::new();
+    }
+    STATE_FILES
+        .iter()
+        .map(|name| (from.join(name), to.join(name)))
+        .filter(|(source, target)| source.exists() && !target.exists())
+        .collect()
+}
+
+/// Moves the state files from `from` to `to`, leaving any that `to` already
+/// has.
+fn migrate_state_files(from: &Path, to: &Path) -> Result<()> {
+    if from == to {
+        return Ok(());
+    }
+    for (source, target) in pending_moves(from, to) {
+        fs::create_dir_all(to).with_context(|| format!("Failed to create {}", to.display()))?;
+        fs::rename(&source, &target).with_context(|| {
+            format!(
+                "Failed to move {} to {}",
+                source.display(),
+                target.display()
+            )
+        })?;
+    }
+    // The lock is recreated next to the moved state file.
+    let _ = fs::remove_file(from.join("profile-selector-state.lock"));
+    Ok(())
+}
+
+pub fn get_current_profile_path() -> Result<PathBuf> {
+    match scope() {
+        Scope::Global => Ok(get_state_dir()?.join("current-profile")),
+        Scope::Terminal => Ok(get_state_dir()?
+            .join("current-profile.d")
+            .join(terminal::current()?.key)),
+    }
+}
+
+/// Returns the profile recorded in the current-profile file (or this
+/// terminal's file), if any.
+pub fn read_current_profile() -> Result<Option<String>> {
+    let path = match get_current_profile_path() {
+        Ok(path) => path,
+        // Outside a terminal nothing can have been activated for it.
+        Err(_) if scope() == Scope::Terminal => return Ok(None),
+        Err(e) => return Err(e),
+    };
+
+    if !path.exists() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(&path)?;
+    let mut lines = content.lines();
+    let profile_name = lines.next().unwrap_or_default().trim().to_string();
+
+    // A terminal file left by an earlier session on the same tty.
+    if scope() == Scope::Terminal
+        && lines.next().and_then(|line| line.trim().parse().ok())
+            != Some(terminal::current()?.session)
+    {
+        if !read_only() && !dry_run() {
+            fs::remove_file(&path)?;
+        }
+        return Ok(None);
+    }
+
+    Ok((!profile_name.is_empty()).then_some(profile_name))
+}
+
+/// A per-directory default profile, like `.nvmrc`: it applies in the
+/// directory holding it and everywhere below.
+pub const LOCAL_PROFILE_FILE: &str = ".aws-profile";
+
+/// The nearest `.aws-profile` in `dir` or one of its parents.
+pub fn find_local_profile_file(dir: &Path) -> Option<PathBuf> {
+    dir.ancestors()
+        .map(|dir| dir.join(LOCAL_PROFILE_FILE))
+        .find(|path| path.is_file())
+}
+
+/// The profile named by the nearest `.aws-profile` above the working
+/// directory, and that file's path.
+pub fn read_local_profile() -> Result<Option<(String, PathBuf)>> {
+    let Some(path) = std::env::current_dir()
+        .ok()
+        .and_then(|dir| find_local_profile_file(&dir))
+    else {
+        return Ok(None);
+    };
+
+    let profile_name = fs::read_to_string(&path)?.trim().to_string();
+    Ok((!profile_name.is_empty()).then_some((profile_name, path)))
+}
+
+/// The profile commands act on by default: the one in the nearest
+/// `.aws-profile`, then the current-profile file, falling back to
+/// `AWS_PROFILE` from the environment.
+pub fn active_profile() -> Result<Option<String>> {
+    if let Some((profile_name, _)) = read_local_profile()? {
+        return Ok(Some(profile_name));
+    }
+    if let Some(profile_name) = read_current_profile()? {
+        return Ok(Some(profile_name));
+    }
+
+    Ok(std::env::var("AWS_PROFILE")
+        .ok()
+        .filter(|profile_name| !profile_name.is_empty()))
+}
+
+/// Records `profile_name` as the current profile, with the region
+/// `activate --region` gave it in place of its own.
+pub fn write_current_profile(profile_name: &str, region: Option<&str>) -> Result<()> {
+    let path = get_current_profile_path()?;
+    ensure_writable(format_args!(
+        "write {} (-c sets the profile in this shell instead)",
+        path.display()
+    ))?;
+
+    let region_line = region
+        .map(|region| format!("{REGION_PREFIX}{region}\n"))
+        .unwrap_or_default();
+    tracing::info!("Writing '{profile_name}' to {}", path.display());
+    match scope() {
+        Scope::Global if region.is_some() => {
+            write_atomically(&path, &format!("{profile_name}\n{region_line}"))?
+        }
+        Scope::Global => write_atomically(&path, profile_name)?,
+        Scope::Terminal => {
+            let session = terminal::current()?.session;
+            write_atomically(&path, &format!("{profile_name}\n{session}\n{region_line}"))?;
+            if !dry_run() {
+                remove_stale_terminal_files(&path)?;
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Marks the current-profile file's region override line.
+const REGION_PREFIX: &str = "region=";
+
+/// The region override recorded with the current profile, if any.
+pub fn read_current_region() -> Result<Option<String>> {
+    if read_current_profile()?.is_none() {
+        return Ok(None);
+    }
+
+    let content = fs::read_to_string(get_current_profile_path()?)?;
+    Ok(recorded_region(&content).map(str::to_string))
+}
+
+/// The region line of a current-profile file's content: after the name, and
+/// after the session in a terminal file.
+fn recorded_region(content: &str) -> Option<&str> {
+    content
+        .lines()
+        .skip(1)
+        .find_map(|line| line.trim().strip_prefix(REGION_PREFIX))
+        .filter(|region| !region.is_empty())
+}
+
+/// The region override recorded for `profile_name`, when it is the current
+/// profile.
+pub fn region_override(profile_name: &str) -> Result<Option<String>> {
+    match read_current_profile()? {
+        Some(current) if current == profile_name => read_current_region(),
+        _ => Ok(None),
+    }
+}
+
+/// Deletes the other terminals' files whose sessions have ended.
+fn remove_stale_terminal_files(own: &Path) -> Result<()> {
+    let Some(dir) = own.parent() else {
+        return Ok(());
+    };
+
+    for entry in fs::read_dir(dir)?.flatten() {
+        let path = entry.path();
+        // Another terminal's file that is still being written.
+        if path == own || entry.file_name().to_string_lossy().starts_with('.') {
+            continue;
+        }
+        let session = fs::read_to_string(&path)
+            .ok()
+            .and_then(|content| content.lines().nth(1)?.trim().parse().ok());
+        if !session.is_some_and(terminal::is_session_alive) {
+            let _ = fs::remove_file(&path);
+        }
+    }
+    Ok(())
+}
+
+/// Writes a state file atomically (see [`crate::files`]), creating the
+/// state directory if needed; only the owner can read it.
+fn write_atomically(path: &Path, contents: &str) -> Result<()> {
+    if would_write(path, contents) {
+        return Ok(());
+    }
+    ensure_in_state_dir(path)?;
+    files::write_atomically(path, contents, Access::Private)
+}
+
+/// Fails when `path`, a file in the state directory, is a symlink or sits
+/// in a symlinked directory that leads out of it, so a link planted there
+/// can't redirect writes to another of the user's files.
+fn ensure_in_state_dir(path: &Path) -> Result<()> {
+    match STATE_DIR.get() {
+        Some(dir) if path.starts_with(dir) => ensure_inside(dir, path),
+        _ => Ok(()),
+    }
+}
+
+fn ensure_inside(dir: &Path, path: &Path) -> Result<()> {
+    let (Ok(root), Some(parent), Some(name)) =
+        (dir.canonicalize(), path.parent(), path.file_name())
+    else {
+        return Ok(());
+    };
+    let resolved = match fs::symlink_metadata(path) {
+        Ok(metadata) if metadata.is_symlink() => path
+            .canonicalize()
+            .unwrap_or_else(|_| fs::read_link(path).unwrap_or_default()),
+        _ => match parent.canonicalize() {
+            Ok(parent) => parent.join(name),
+            // Not created yet, so nothing in it can be a link.
+            Err(_) => return Ok(()),
+        },
+    };
+    if resolved.starts_with(&root) {
+        return Ok(());
+    }
+    Err(anyhow::anyhow!(
+        "Not writing {}: it leads to {}, outside {}",
+        path.display(),
+        resolved.display(),
+        dir.display()
+    ))
+}
+
+/// Holds an exclusive advisory lock on `<path>.lock` until dropped, so one
+/// process's read-modify-write of `path` can't interleave with another's.
+fn lock(path: &Path) -> Result<fs::File> {
+    let lock_path = path.with_extension("lock");
+    ensure_in_state_dir(&lock_path)?;
+    if let Some(parent) = lock_path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let file = fs::File::options()
+        .create(true)
+        .truncate(false)
+        .write(true)
+        .open(&lock_path)
+        .with_context(|| format!("Failed to open {}", lock_path.display()))?;
+    file.lock()
+        .with_context(|| format!("Failed to lock {}", lock_path.display()))?;
+    Ok(file)
+}
+
+/// Removes the current-profile file, returning whether one existed.
+pub fn clear_current_profile() -> Result<bool> {
+    let path = get_current_profile_path()?;
+
+    if !path.exists() {
+        return Ok(false);
+    }
+
+    ensure_writable(format_args!("remove {}", path.display()))?;
+    if !would(format_args!("remove {}", path.display())) {
+        fs::remove_file(&path)?;
+    }
+    Ok(true)
+}
+
+pub fn get_state_path() -> Result<PathBuf> {
+    Ok(get_state_dir()?.join("profile-selector-state.json"))
+}
+
+/// The tool's own bookkeeping, kept in `profile-selector-state.json` next to
+/// the current-profile file (see [`get_state_dir`]).
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct SelectorState {
+    /// Profiles pinned to the top of the selector, in the order they were
+    /// added.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub favorites: Vec<String>,
+    /// Glob patterns for profiles left out of the interactive selectors.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub hidden: Vec<String>,
+    /// Short names for profiles, alias → profile.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub aliases: BTreeMap<String, String>,
+    /// Recent activations, oldest first, capped at [`HISTORY_LIMIT`].
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub history: Vec<Activation>,
+    /// Where `--tui` was left, per scope: `global`, or the terminal's key.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub selectors: BTreeMap<String, SelectorPosition>,
+    /// Hide patterns from the settings file; consulted alongside `hidden`
+    /// but never saved.
+    #[serde(skip)]
+    pub configured_hidden: Vec<String>,
+    /// Aliases from the settings file; the saved ones take precedence.
+    #[serde(skip)]
+    pub configured_aliases: BTreeMap<String, String>,
+}
+
+/// How many activations the history keeps; enough for frecency to reflect a
+/// few weeks of regular use.
+pub const HISTORY_LIMIT: usize = 500;
+
+/// A stretch of the history to count activations in: `24h`, `7d`, or `4w`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Window {
+    amount: i64,
+    unit: char,
+}
+
+impl Window {
+    pub fn length(self) -> TimeDelta {
+        match self.unit {
+            'h' => TimeDelta::hours(self.amount),
+            'd' => TimeDelta::days(self.amount),
+            _ => TimeDelta::weeks(self.amount),
+        }
+    }
+}
+
+impl FromStr for Window {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        let invalid = || format!("'{text}' is not a window like 24h, 7d, or 4w");
+        let unit = text.chars().last().filter(|unit| "hdw".contains(*unit));
+        let amount = text[..text.len() - unit.map_or(0, char::len_utf8)]
+            .parse::<i64>()
+            .ok()
+            .filter(|&amount| amount > 0);
+        match (amount, unit) {
+            (Some(amount), Some(unit)) => Ok(Self { amount, unit }),
+            _ => Err(invalid()),
+        }
+    }
+}
+
+impl fmt::Display for Window {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}{}", self.amount, self.unit)
+    }
+}
+
+/// The filter and highlighted profile `--tui` was closed with, and the
+/// profile that was active afterwards.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SelectorPosition {
+    #[serde(default, skip_serializing_if = "String::is_empty")]
+    pub filter: String,
+    pub highlighted: Option<String>,
+    pub active: Option<String>,
+}
+
+/// Which of [`SelectorState::selectors`] this process uses.
+fn selector_key() -> String {
+    match scope() {
+        Scope::Global => "global".to_string(),
+        Scope::Terminal => terminal::current().map_or_else(|_| "global".to_string(), |t| t.key),
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Activation {
+    pub profile: String,
+    pub at: DateTime<Utc>,
+}
+
+/// `just now`, `5m ago`, `3h ago`, `2d ago`, `6w ago`, `4mo ago`, or
+/// `2y ago`.
+pub fn time_ago(at: DateTime<Utc>, now: DateTime<Utc>) -> String {
+    let minutes = (now - at).num_minutes().max(0);
+    let days = minutes / (24 * 60);
+    match minutes {
+        0 => "just now".to_string(),
+        1..60 => format!("{minutes}m ago"),
+        60..1440 => format!("{}h ago", minutes / 60),
+        _ if days < 14 => format!("{days}d ago"),
+        _ if days < 60 => format!("{}w ago", days / 7),
+        _ if days < 365 => format!("{}mo ago", days / 30),
+        _ => format!("{}y ago", days / 365),
+    }
+}
+
+impl SelectorState {
+    /// Reads the state file, treating a missing one as empty.
+    pub fn load() -> Result<Self> {
+        Self::load_from(&get_state_path()?)
+    }
+
+    /// Overwrites the state file with this state. Changes other processes
+    /// made since it was loaded are lost; [`SelectorState::update`] keeps
+    /// them.
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&get_state_path()?)
+    }
+
+    /// Applies `change` to the state file as it is now, holding a lock
+    /// so concurrent invocations each see the other's change. The file is
+    /// only written when `change` changed something.
+    pub fn update<T>(change: impl FnOnce(&mut Self) -> T) -> Result<T> {
+        Self::update_at(&get_state_path()?, change)
+    }
+
+    fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+
+        let contents = fs::read_to_string(path)?;
+        serde_json::from_str(&contents)
+            .map_err(|e| anyhow::anyhow!("Failed to parse {}: {}", path.display(), e))
+    }
+
+    fn save_to(&self, path: &Path) -> Result<()> {
+        ensure_writable(format_args!("write {}", path.display()))?;
+        write_atomically(path, &(serde_json::to_string_pretty(self)? + "\n"))
+    }
+
+    fn update_at<T>(path: &Path, change: impl FnOnce(&mut Self) -> T) -> Result<T> {
+        // Read-only and dry runs won't change the file, so needn't create
+        // the lock.
+        let _lock = match read_only() || dry_run() {
+            true => None,
+            false => Some(lock(path)?),
+        };
+        let mut state = Self::load_from(path)?;
+        let original = state.clone();
+        let result = change(&mut state);
+        if state != original {
+            state.save_to(path)?;
+        }
+        Ok(result)
+    }
+
+    /// Layers the hide patterns and aliases from the settings file under the
+    /// saved ones.
+    pub fn with_settings(mut self, settings: &Settings) -> Self {
+        self.configured_hidden = settings.hide.clone();
+        self.configured_aliases = settings.aliases.clone();
+        self
+    }
+
+    pub fn is_favorite(&self, profile_name: &str) -> bool {
+        self.favorites.iter().any(|name| name == profile_name)
+    }
+
+    /// Adds a favorite, returning `false` when it already was one.
+    pub fn add_favorite(&mut self, profile_name: &str) -> bool {
+        if self.is_favorite(profile_name) {
+            return false;
+        }
+        self.favorites.push(profile_name.to_string());
+        true
+    }
+
+    /// Removes a favorite, returning whether it was one.
+    pub fn remove_favorite(&mut self, profile_name: &str) -> bool {
+        let before = self.favorites.len();
+        self.favorites.retain(|name| name != profile_name);
+        self.favorites.len() != before
+    }
+
+    pub fn is_hidden(&self, profile_name: &str) -> bool {
+        self.hidden
+            .iter()
+            .chain(&self.configured_hidden)
+            .any(|pattern| glob_match(pattern, profile_name))
+    }
+
+    /// Adds a hide pattern, returning `false` when it was already present.
+    pub fn add_hidden(&mut self, pattern: &str) -> bool {
+        if self.hidden.iter().any(|existing| existing == pattern) {
+            return false;
+        }
+        self.hidden.push(pattern.to_string());
+        true
+    }
+
+    /// Removes a hide pattern, returning whether it was present.
+    pub fn remove_hidden(&mut self, pattern: &str) -> bool {
+        let before = self.hidden.len();
+        self.hidden.retain(|existing| existing != pattern);
+        self.hidden.len() != before
+    }
+
+    /// Saved and configured aliases together, alias → profile.
+    pub fn all_aliases(&self) -> BTreeMap<String, String> {
+        let mut aliases = self.configured_aliases.clone();
+        aliases.extend(self.aliases.clone());
+        aliases
+    }
+
+    /// The profile `name` refers to: an alias's target, or `name` itself.
+    pub fn resolve_alias<'a>(&'a self, name: &'a str) -> &'a str {
+        self.aliases
+            .get(name)
+            .or_else(|| self.configured_aliases.get(name))
+            .map_or(name, String::as_str)
+    }
+
+    /// Aliases pointing at `profile_name`, sorted.
+    pub fn aliases_for(&self, profile_name: &str) -> Vec<&str> {
+        let mut aliases: Vec<&str> = self
+            .aliases
+            .iter()
+            .chain(&self.configured_aliases)
+            .filter(|(alias, _)| self.resolve_alias(alias) == profile_name)
+            .map(|(alias, _)| alias.as_str())
+            .collect();
+        aliases.sort_unstable();
+        aliases.dedup();
+        aliases
+    }
+
+    /// Drops everything that refers to a removed profile by name: its
+    /// favorite and aliases. Returns whether anything changed.
+    pub fn forget_profile(&mut self, profile_name: &str) -> bool {
+        let aliases = self.aliases.len();
+        self.aliases.retain(|_, target| target != profile_name);
+        let favorite = self.remove_favorite(profile_name);
+        favorite || self.aliases.len() != aliases
+    }
+
+    pub fn record_activation(&mut self, profile_name: &str, at: DateTime<Utc>) {
+        self.history.push(Activation {
+            profile: profile_name.to_string(),
+            at,
+        });
+        let excess = self.history.len().saturating_sub(HISTORY_LIMIT);
+        self.history.drain(..excess);
+    }
+
+    /// Where this scope's `--tui` was left, unless the active profile has
+    /// changed some other way since, which makes it stale.
+    pub fn last_selector(&self, active: Option<&str>) -> Option<&SelectorPosition> {
+        self.selectors
+            .get(&selector_key())
+            .filter(|position| position.active.as_deref() == active)
+    }
+
+    pub fn remember_selector(&mut self, position: SelectorPosition) {
+        self.selectors.insert(selector_key(), position);
+    }
+
+    /// When each profile in the history was last activated.
+    pub fn last_used(&self) -> HashMap<String, DateTime<Utc>> {
+        self.history
+            .iter()
+            .map(|activation| (activation.profile.clone(), activation.at))
+            .collect()
+    }
+
+    /// Profile names from the history, most recently used first, each listed
+    /// once.
+    pub fn recent_profiles(&self) -> Vec<&str> {
+        let mut recent: Vec<&str> = Vec::new();
+        for activation in self.history.iter().rev() {
+            if !recent.contains(&activation.profile.as_str()) {
+                recent.push(&activation.profile);
+            }
+        }
+        recent
+    }
+
+    /// Frecency per profile: every activation counts, weighted by how long
+    /// ago it happened, so frequently used profiles stay ahead of ones that
+    /// were only used once recently.
+    pub fn frecency(&self, now: DateTime<Utc>) -> HashMap<&str, f64> {
+        let mut scores: HashMap<&str, f64> = HashMap::new();
+        for activation in &self.history {
+            let age = now - activation.at;
+            let weight = if age < TimeDelta::hours(1) {
+                2.0
+            } else if age < TimeDelta::days(1) {
+                1.5
+            } else if age < TimeDelta::weeks(1) {
+                1.0
+            } else {
+                0.5
+            };
+            *scores.entry(&activation.profile).or_default() += weight;
+        }
+        scores
+    }
+
+    /// The most recently used profile that isn't `current`, for switching
+    /// back and forth.
+    pub fn previous_profile(&self, current: Option<&str>) -> Option<&str> {
+        self.recent_profiles()
+            .into_iter()
+            .find(|name| Some(*name) != current)
+    }
+
+    /// Points favorites, aliases, and history at a profile's new name after
+    /// a rename, returning whether anything changed.
+    pub fn rename_profile(&mut self, old_name: &str, new_name: &str) -> bool {
+        let names = self
+            .favorites
+            .iter_mut()
+            .chain(self.aliases.values_mut())
+            .chain(
+                self.history
+                    .iter_mut()
+                    .map(|activation| &mut activation.profile),
+            );
+
+        let mut changed = false;
+        for name in names {
+            if name == old_name {
+                *name = new_name.to_string();
+                changed = true;
+            }
+        }
+        changed
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::temp_dir;
+
+    #[test]
+    fn test_recorded_region() {
+        assert_eq!(recorded_region("dev"), None);
+        assert_eq!(
+            recorded_region("dev\nregion=ap-southeast-2\n"),
+            Some("ap-southeast-2")
+        );
+        assert_eq!(
+            recorded_region("dev\n4242\nregion=eu-west-1\n"),
+            Some("eu-west-1")
+        );
+        assert_eq!(recorded_region("dev\n4242\n"), None);
+    }
+
+    #[test]
+    fn test_local_profile_file_is_found_upward() {
+        let root = temp_dir("local");
+        let nested = root.join("service").join("src");
+        fs::create_dir_all(&nested).unwrap();
+        assert_eq!(find_local_profile_file(&nested), None);
+
+        fs::write(root.join(LOCAL_PROFILE_FILE), "dev\n").unwrap();
+        assert_eq!(
+            find_local_profile_file(&nested),
+            Some(root.join(LOCAL_PROFILE_FILE))
+        );
+        fs::write(root.join("service").join(LOCAL_PROFILE_FILE), "prod").unwrap();
+        assert_eq!(
+            find_local_profile_file(&nested),
+            Some(root.join("service").join(LOCAL_PROFILE_FILE))
+        );
+    }
+
+    #[test]
+    fn test_migrate_state_files() {
+        let root = temp_dir("migrate");
+        let (legacy, state_dir) = (root.join(".aws"), root.join("state"));
+        fs::create_dir_all(legacy.join("current-profile.d")).unwrap();
+        fs::write(legacy.join("current-profile"), "dev").unwrap();
+        fs::write(legacy.join("config"), "[default]\n").unwrap();
+        fs::create_dir_all(&state_dir).unwrap();
+        fs::write(state_dir.join("profile-selector-state.json"), "{}\n").unwrap();
+        fs::write(legacy.join("profile-selector-state.json"), "old").unwrap();
+
+        migrate_state_files(&legacy, &state_dir).unwrap();
+
+        assert_eq!(
+            fs::read_to_string(state_dir.join("current-profile")).unwrap(),
+            "dev"
+        );
+        assert!(state_dir.join("current-profile.d").is_dir());
+        assert!(!legacy.join("current-profile").exists());
+        // What the new directory already has wins; the AWS files stay put.
+        assert_eq!(
+            fs::read_to_string(state_dir.join("profile-selector-state.json")).unwrap(),
+            "{}\n"
+        );
+        assert!(legacy.join("config").exists());
+    }
+
+    #[test]
+    fn test_parse_window() {
+        assert_eq!(
+            "24h".parse::<Window>().unwrap().length(),
+            TimeDelta::hours(24)
+        );
+        assert_eq!("4w".parse::<Window>().unwrap().to_string(), "4w");
+        assert!("0d".parse::<Window>().is_err());
+        assert!("7".parse::<Window>().is_err());
+        assert!("7y".parse::<Window>().is_err());
+    }
+
+    #[test]
+    fn test_concurrent_updates_are_not_lost() {
+        let root = temp_dir("lock");
+        let path = root.join("profile-selector-state.json");
+
+        std::thread::scope(|scope| {
+            for thread in 0..8 {
+                let path = &path;
+                scope.spawn(move || {
+                    for activation in 0..10 {
+                        SelectorState::update_at(path, |state| {
+                            state.record_activation(&format!("p{thread}"), Utc::now());
+                            state.add_favorite(&format!("f{thread}-{activation}"));
+                        })
+                        .unwrap();
+                    }
+                });
+            }
+        });
+
+        let state = SelectorState::load_from(&path).unwrap();
+        assert_eq!(state.history.len(), 80);
+        assert_eq!(state.favorites.len(), 80);
+        // Only the state file and its lock; no temporary files left behind.
+        assert_eq!(fs::read_dir(&root).unwrap().count(), 2);
+
+        let changed = SelectorState::update_at(&path, |state| state.add_favorite("f0-0")).unwrap();
+        assert!(!changed);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_state_writes_stay_private_and_inside() {
+        use std::os::unix::fs::{PermissionsExt, symlink};
+
+        let root = temp_dir("private");
+        let (state_dir, elsewhere) = (root.join("state"), root.join("elsewhere"));
+        fs::create_dir_all(&state_dir).unwrap();
+        fs::create_dir_all(&elsewhere).unwrap();
+
+        let path = state_dir.join("current-profile");
+        write_atomically(&path, "dev").unwrap();
+        let mode = fs::metadata(&path).unwrap().permissions().mode();
+        assert_eq!(mode & 0o777, 0o600);
+        assert!(ensure_inside(&state_dir, &path).is_ok());
+        assert!(ensure_inside(&state_dir, &state_dir.join("not-yet/current")).is_ok());
+
+        symlink(elsewhere.join("bashrc"), state_dir.join("linked")).unwrap();
+        symlink(&elsewhere, state_dir.join("current-profile.d")).unwrap();
+        symlink(&path, state_dir.join("inside")).unwrap();
+        for outside in ["linked", "current-profile.d/tty1"] {
+            assert!(ensure_inside(&state_dir, &state_dir.join(outside)).is_err());
+        }
+        assert!(ensure_inside(&state_dir, &state_dir.join("inside")).is_ok());
+    }
+
+    #[test]
+    fn test_favorites() {
+        let mut state = SelectorState::default();
+        assert!(state.add_favorite("prod"));
+        assert!(!state.add_favorite("prod"));
+        assert!(state.add_favorite("dev"));
+        assert!(state.rename_profile("prod", "production"));
+        assert_eq!(state.favorites, vec!["production", "dev"]);
+        assert!(state.remove_favorite("dev"));
+        assert!(!state.remove_favorite("dev"));
+
+        let json = serde_json::to_string(&state).unwrap();
+        assert_eq!(json, r#"{"favorites":["production"]}"#);
+        assert!(state.add_hidden("generated-*"));
+        assert!(!state.add_hidden("generated-*"));
+        assert!(state.is_hidden("generated-1234"));
+        assert!(!state.is_hidden("production"));
+        assert!(state.remove_hidden("generated-*"));
+
+        state
+            .aliases
+            .insert("p".to_string(), "production".to_string());
+        assert_eq!(state.resolve_alias("p"), "production");
+        assert_eq!(state.resolve_alias("dev"), "dev");
+        assert_eq!(state.aliases_for("production"), vec!["p"]);
+        let configured = SelectorState::default().with_settings(&Settings {
+            hide: vec!["old-*".to_string()],
+            aliases: BTreeMap::from([
+                ("p".to_string(), "staging".to_string()),
+                ("s".to_string(), "staging".to_string()),
+            ]),
+            ..Settings::default()
+        });
+        assert!(configured.is_hidden("old-account"));
+        assert_eq!(configured.resolve_alias("p"), "staging");
+        let mut layered = configured;
+        layered.aliases = state.aliases.clone();
+        assert_eq!(layered.resolve_alias("p"), "production");
+        assert_eq!(layered.aliases_for("staging"), vec!["s"]);
+
+        assert!(state.forget_profile("production"));
+        assert!(state.aliases.is_empty() && state.favorites.is_empty());
+
+        let empty: SelectorState = serde_json::from_str("{}").unwrap();
+        assert!(empty.favorites.is_empty());
+    }
+
+    #[test]
+    fn test_history() {
+        let mut state = SelectorState::default();
+        for name in ["dev", "prod", "dev", "sandbox"] {
+            state.record_activation(name, Utc::now());
+        }
+        assert_eq!(state.recent_profiles(), vec!["sandbox", "dev", "prod"]);
+        assert_eq!(state.previous_profile(Some("sandbox")), Some("dev"));
+        assert_eq!(state.previous_profile(Some("other")), Some("sandbox"));
+
+        state.rename_profile("dev", "development");
+        assert_eq!(
+            state.recent_profiles(),
+            vec!["sandbox", "development", "prod"]
+        );
+
+        let now = Utc::now();
+        let mut frecent = SelectorState::default();
+        for _ in 0..5 {
+            frecent.record_activation("daily", now - TimeDelta::days(2));
+        }
+        frecent.record_activation("rare", now);
+        let scores = frecent.frecency(now);
+        assert!(scores["daily"] > scores["rare"]);
+        assert_eq!(frecent.recent_profiles()[0], "rare");
+        let last_used = frecent.last_used();
+        assert_eq!(time_ago(last_used["daily"], now), "2d ago");
+        assert_eq!(time_ago(last_used["rare"], now), "just now");
+        assert_eq!(time_ago(now - TimeDelta::minutes(90), now), "1h ago");
+        assert_eq!(time_ago(now - TimeDelta::days(100), now), "3mo ago");
+
+        for _ in 0..HISTORY_LIMIT {
+            state.record_activation("prod", Utc::now());
+        }
+        assert_eq!(state.history.len(), HISTORY_LIMIT);
+        assert_eq!(state.recent_profiles(), vec!["prod"]);
+    }
+
+    #[test]
+    fn test_last_selector_goes_stale() {
+        let mut state = SelectorState::default();
+        assert_eq!(state.last_selector(None), None);
+        let position = SelectorPosition {
+            filter: "prod".to_string(),
+            highlighted: Some("prod-read".to_string()),
+            active: Some("prod-admin".to_string()),
+        };
+        state.remember_selector(position.clone());
+        assert_eq!(state.last_selector(Some("prod-admin")), Some(&position));
+        assert_eq!(state.last_selector(Some("dev")), None);
+    }
+}
@@ -0,0 +1,540 @@
+//! [`ProfileStore`]: the profiles of one pair of AWS config and credentials
+//! files, for library users and for the subcommands that change them.
+//! Changes go through the format-preserving [`IniDocument`] writer, so
+//! comments, ordering, and unrelated sections survive, and are announced to
+//! [`ProfileStore::subscribe`]rs.
+
+use crate::config::{
+    AwsConfig, ConfigPaths, Profile, SsoSession, config_section_header, read_aws_config,
+};
+use crate::editor::IniDocument;
+use crate::error::Error;
+use crate::keychain;
+use anyhow::Result;
+use std::path::Path;
+use std::sync::mpsc::{Receiver, Sender, channel};
+
+/// A change a [`ProfileStore`] wrote to the files.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum ProfileChange {
+    Added(String),
+    Updated(String),
+    Renamed { from: String, to: String },
+    Removed(String),
+}
+
+/// The profiles and sso-sessions read from an AWS config file and its
+/// shared credentials file, merged the way the AWS CLI merges them.
+#[derive(Debug, Clone)]
+pub struct ProfileStore {
+    paths: ConfigPaths,
+    config: AwsConfig,
+    subscribers: Vec<Sender<ProfileChange>>,
+}
+
+impl ProfileStore {
+    /// Reads the files at `paths`. Missing files count as empty.
+    pub fn open(paths: ConfigPaths) -> Result<Self> {
+        let config = read(&paths)?;
+        Ok(Self::with_config(paths, config))
+    }
+
+    /// A store over `config`, already read from `paths`.
+    pub(crate) fn with_config(paths: ConfigPaths, config: AwsConfig) -> Self {
+        Self {
+            paths,
+            config,
+            subscribers: Vec::new(),
+        }
+    }
+
+    /// Reads the files the AWS CLI would: `AWS_CONFIG_FILE` and
+    /// `AWS_SHARED_CREDENTIALS_FILE`, or `~/.aws/config` and
+    /// `~/.aws/credentials`.
+    pub fn open_default() -> Result<Self> {
+        Self::open(ConfigPaths::resolve(None, None)?)
+    }
+
+    /// Reads the files again, picking up changes made since.
+    pub fn reload(&mut self) -> Result<()> {
+        self.config = read(&self.paths)?;
+        Ok(())
+    }
+
+    pub fn paths(&self) -> &ConfigPaths {
+        &self.paths
+    }
+
+    /// Every profile, in the order the files list them.
+    pub fn profiles(&self) -> &[Profile] {
+        &self.config.profiles
+    }
+
+    pub fn sso_sessions(&self) -> &[SsoSession] {
+        &self.config.sso_sessions
+    }
+
+    /// The profile called `name`, without resolving aliases.
+    pub fn get(&self, name: &str) -> Option<&Profile> {
+        self.config.find_profile(name)
+    }
+
+    /// The whole parsed config, for the helpers on [`AwsConfig`] that look
+    /// across profiles (sso-sessions, `source_profile` chains).
+    pub fn config(&self) -> &AwsConfig {
+        &self.config
+    }
+
+    /// A channel that receives every change this store writes from now on.
+    /// Changes made by other processes aren't seen.
+    pub fn subscribe(&mut self) -> Receiver<ProfileChange> {
+        let (sender, receiver) = channel();
+        self.subscribers.push(sender);
+        receiver
+    }
+
+    /// The profile's sections as the files have them, config file first,
+    /// with the file each came from.
+    pub fn section_texts(&self, name: &str) -> Result<Vec<(&Path, String)>> {
+        let profile = self.existing(name)?;
+        let (config_doc, credentials_doc) = self.documents()?;
+        let mut texts = Vec::new();
+        if let Some(text) = config_doc.section_text(&profile.section_header()) {
+            texts.push((self.paths.config.as_path(), text));
+        }
+        if let Some(text) = credentials_doc.section_text(name) {
+            texts.push((self.paths.credentials.as_path(), text));
+        }
+        Ok(texts)
+    }
+
+    /// Appends a profile with the given entries. Entries for a file that has
+    /// none aren't written there.
+    pub fn add_profile(
+        &mut self,
+        name: &str,
+        config_entries: &[(&str, &str)],
+        credential_entries: &[(&str, &str)],
+    ) -> Result<()> {
+        if self.get(name).is_some() {
+            return Err(anyhow::anyhow!("Profile '{name}' already exists"));
+        }
+
+        let (mut config_doc, mut credentials_doc) = self.documents()?;
+        if !config_entries.is_empty() {
+            config_doc.append_section(&config_section_header(name), config_entries);
+            config_doc.save(&self.paths.config)?;
+        }
+        if !credential_entries.is_empty() {
+            credentials_doc.append_section(name, credential_entries);
+            credentials_doc.save(&self.paths.credentials)?;
+        }
+        self.changed(ProfileChange::Added(name.to_string()))
+    }
+
+    /// Appends an `[sso-session]` block for profiles to point at.
+    pub fn add_sso_session(&mut self, name: &str, entries: &[(&str, &str)]) -> Result<()> {
+        if self
+            .sso_sessions()
+            .iter()
+            .any(|session| session.name == name)
+        {
+            return Err(anyhow::anyhow!("sso-session '{name}' already exists"));
+        }
+
+        let mut config_doc = IniDocument::load(&self.paths.config)?;
+        config_doc.append_section(&format!("sso-session {name}"), entries);
+        config_doc.save(&self.paths.config)?;
+        self.reload()
+    }
+
+    /// Sets (`Some`) or removes (`None`) keys in the profile's config
+    /// section, creating the section if the profile only has credentials.
+    /// Returns whether anything changed.
+    pub fn update_profile(&mut self, name: &str, changes: &[(&str, Option<&str>)]) -> Result<bool> {
+        let (original, config_doc) = self.updated_config(name, changes)?;
+        if config_doc == original {
+            return Ok(false);
+        }
+
+        config_doc.save(&self.paths.config)?;
+        self.changed(ProfileChange::Updated(name.to_string()))?;
+        Ok(true)
+    }
+
+    /// The config file as it is and as [`Self::update_profile`] would write
+    /// it, for previewing the change.
+    pub(crate) fn updated_config(
+        &self,
+        name: &str,
+        changes: &[(&str, Option<&str>)],
+    ) -> Result<(IniDocument, IniDocument)> {
+        let profile = self.existing(name)?;
+        let original = IniDocument::load(&self.paths.config)?;
+        let header = match original.has_section(&profile.section_header()) {
+            true => profile.section_header(),
+            false => config_section_header(name),
+        };
+
+        let mut config_doc = original.clone();
+        for (key, value) in changes {
+            match value {
+                Some(value) => config_doc.set(&header, key, value),
+                None => {
+                    config_doc.remove_key(&header, key);
+                }
+            }
+        }
+        Ok((original, config_doc))
+    }
+
+    /// Replaces the profile's section with `text`, which has to be a single
+    /// section under the same header. The config file's section is the one
+    /// replaced, or the credentials file's for a credentials-only profile.
+    pub fn replace_profile_text(&mut self, name: &str, text: &str) -> Result<()> {
+        let profile = self.existing(name)?;
+        let (config_doc, credentials_doc) = self.documents()?;
+        let (path, header, mut document) = match config_doc.has_section(&profile.section_header()) {
+            true => (&self.paths.config, profile.section_header(), config_doc),
+            false => (&self.paths.credentials, name.to_string(), credentials_doc),
+        };
+        if !document.has_section(&header) {
+            return Err(anyhow::anyhow!(
+                "Section [{header}] not found in {}",
+                path.display()
+            ));
+        }
+        if IniDocument::parse(text).sections() != [header.as_str()] {
+            return Err(anyhow::anyhow!(
+                "The edited text must contain exactly one section, [{header}]; nothing was written"
+            ));
+        }
+
+        document.replace_section_text(&header, text);
+        document.save(path)?;
+        self.changed(ProfileChange::Updated(name.to_string()))
+    }
+
+    /// Renames a profile in both files, along with its keychain entry, and
+    /// repoints the profiles that use it as their `source_profile`. Returns
+    /// the headers of those.
+    pub fn rename_profile(&mut self, from: &str, to: &str) -> Result<Vec<String>> {
+        let profile = self.source_and_target(from, to)?.clone();
+        let (mut config_doc, mut credentials_doc) = self.documents()?;
+        let original = (config_doc.clone(), credentials_doc.clone());
+
+        let updated = rename_sections(&mut config_doc, &mut credentials_doc, &profile, to);
+        // Keychain entries are stored by profile name, and the helper is told
+        // which one to read.
+        let keychain_keys = match keychain::uses_keychain(&profile) {
+            true => keychain::load(from)?,
+            false => None,
+        };
+        if let Some(keys) = &keychain_keys {
+            keychain::store(to, keys)?;
+            config_doc.set(
+                &config_section_header(to),
+                "credential_process",
+                &keychain::helper_command(to),
+            );
+        }
+        if config_doc != original.0 {
+            config_doc.save(&self.paths.config)?;
+        }
+        if credentials_doc != original.1 {
+            credentials_doc.save(&self.paths.credentials)?;
+        }
+        if keychain_keys.is_some() {
+            keychain::delete(from)?;
+        }
+
+        self.changed(ProfileChange::Renamed {
+            from: from.to_string(),
+            to: to.to_string(),
+        })?;
+        Ok(updated)
+    }
+
+    /// Duplicates a profile's sections under a new name, then applies
+    /// `overrides` to the copy's config section.
+    pub fn copy_profile(&mut self, from: &str, to: &str, overrides: &[(&str, &str)]) -> Result<()> {
+        let profile = self.source_and_target(from, to)?;
+        let (mut config_doc, mut credentials_doc) = self.documents()?;
+
+        let target_header = config_section_header(to);
+        copy_section(&mut config_doc, &profile.section_header(), &target_header);
+        copy_section(&mut credentials_doc, from, to);
+        for (key, value) in overrides {
+            config_doc.set(&target_header, key, value);
+        }
+
+        if config_doc.has_section(&target_header) {
+            config_doc.save(&self.paths.config)?;
+        }
+        if credentials_doc.has_section(to) {
+            credentials_doc.save(&self.paths.credentials)?;
+        }
+        self.changed(ProfileChange::Added(to.to_string()))
+    }
+
+    /// Removes a profile's sections from both files and its keychain entry.
+    /// Profiles using it as their `source_profile` are left as they are.
+    pub fn remove_profile(&mut self, name: &str) -> Result<()> {
+        let profile = self.existing(name)?.clone();
+        let (mut config_doc, mut credentials_doc) = self.documents()?;
+
+        if config_doc.remove_section(&profile.section_header()) {
+            config_doc.save(&self.paths.config)?;
+        }
+        if credentials_doc.remove_section(name) {
+            credentials_doc.save(&self.paths.credentials)?;
+        }
+        if keychain::uses_keychain(&profile) {
+            keychain::delete(name)?;
+        }
+        self.changed(ProfileChange::Removed(name.to_string()))
+    }
+
+    fn existing(&self, name: &str) -> Result<&Profile> {
+        self.get(name)
+            .ok_or_else(|| Error::profile_not_found(name).into())
+    }
+
+    /// Checks that the source profile exists and the target name is free.
+    fn source_and_target(&self, source: &str, target: &str) -> Result<&Profile> {
+        let profile = self.existing(source)?;
+        if self.get(target).is_some() {
+            return Err(anyhow::anyhow!("Profile '{target}' already exists"));
+        }
+        Ok(profile)
+    }
+
+    fn documents(&self) -> Result<(IniDocument, IniDocument)> {
+        Ok((
+            IniDocument::load(&self.paths.config)?,
+            IniDocument::load(&self.paths.credentials)?,
+        ))
+    }
+
+    /// Rereads the files after a write and tells the subscribers, forgetting
+    /// the ones that hung up.
+    fn changed(&mut self, change: ProfileChange) -> Result<()> {
+        self.reload()?;
+        self.subscribers
+            .retain(|subscriber| subscriber.send(change.clone()).is_ok());
+        Ok(())
+    }
+}
+
+/// The files' profiles, or none when neither file exists yet.
+fn read(paths: &ConfigPaths) -> Result<AwsConfig> {
+    if paths.config.exists() || paths.credentials.exists() {
+        read_aws_config(paths)
+    } else {
+        Ok(AwsConfig::default())
+    }
+}
+
+/// Renames the profile's sections in both documents and repoints
+/// `source_profile` references, returning the headers that were updated.
+fn rename_sections(
+    config: &mut IniDocument,
+    credentials: &mut IniDocument,
+    profile: &Profile,
+    new_name: &str,
+) -> Vec<String> {
+    config.rename_section(&profile.section_header(), &config_section_header(new_name));
+    credentials.rename_section(&profile.name, new_name);
+
+    let mut updated = Vec::new();
+    for document in [config, credentials] {
+        let headers: Vec<String> = document.sections().into_iter().map(String::from).collect();
+        for header in headers {
+            if document.get(&header, "source_profile") == Some(profile.name.as_str()) {
+                document.set(&header, "source_profile", new_name);
+                updated.push(header);
+            }
+        }
+    }
+    updated
+}
+
+/// Appends a duplicate of `source`'s section under `target`, if the source
+/// section exists in this document.
+fn copy_section(document: &mut IniDocument, source: &str, target: &str) {
+    let Some(text) = document.section_text(source) else {
+        return;
+    };
+
+    let mut section = IniDocument::parse(&text);
+    section.rename_section(source, target);
+    document.replace_section_text(target, &section.to_string());
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::profile;
+    use crate::test_support::temp_dir;
+    use std::fs;
+
+    #[test]
+    fn test_open_and_reload() {
+        let root = temp_dir("store");
+        let paths = ConfigPaths {
+            config: root.join("config"),
+            credentials: root.join("credentials"),
+        };
+        fs::write(&paths.config, "[profile dev]\nregion = eu-west-1\n").unwrap();
+
+        let mut store = ProfileStore::open(paths.clone()).unwrap();
+        assert_eq!(store.profiles().len(), 1);
+        assert_eq!(store.get("dev").unwrap().get_region(), Some("eu-west-1"));
+
+        fs::write(&paths.credentials, "[ci]\naws_access_key_id = AKIA\n").unwrap();
+        store.reload().unwrap();
+        assert!(store.get("ci").is_some());
+    }
+
+    #[test]
+    fn test_mutations_preserve_formatting_and_notify() {
+        let root = temp_dir("store-write");
+        let paths = ConfigPaths {
+            config: root.join("config"),
+            credentials: root.join("credentials"),
+        };
+
+        let mut store = ProfileStore::open(paths.clone()).unwrap();
+        assert!(store.profiles().is_empty());
+        let changes = store.subscribe();
+
+        store
+            .add_profile("base", &[], &[("aws_access_key_id", "AKIA")])
+            .unwrap();
+        store
+            .add_profile(
+                "admin",
+                &[("source_profile", "base"), ("region", "us-east-1")],
+                &[],
+            )
+            .unwrap();
+        assert!(store.add_profile("admin", &[("region", "x")], &[]).is_err());
+
+        // A comment of the user's, which every later write keeps.
+        let config = fs::read_to_string(&paths.config).unwrap();
+        fs::write(&paths.config, format!("# work accounts\n{config}")).unwrap();
+
+        assert!(
+            store
+                .update_profile("admin", &[("region", None), ("output", Some("json"))])
+                .unwrap()
+        );
+        assert!(
+            !store
+                .update_profile("admin", &[("output", Some("json"))])
+                .unwrap()
+        );
+        assert_eq!(
+            store.rename_profile("base", "root").unwrap(),
+            vec!["profile admin"]
+        );
+        store
+            .copy_profile("admin", "admin-eu", &[("region", "eu-west-1")])
+            .unwrap();
+        store.remove_profile("admin").unwrap();
+
+        assert_eq!(
+            fs::read_to_string(&paths.config).unwrap(),
+            "# work accounts\n[profile admin-eu]\nsource_profile = root\noutput = json\nregion = eu-west-1\n"
+        );
+        assert_eq!(
+            fs::read_to_string(&paths.credentials).unwrap(),
+            "[root]\naws_access_key_id = AKIA\n"
+        );
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&paths.credentials)
+                .unwrap()
+                .permissions()
+                .mode();
+            assert_eq!(mode & 0o777, 0o600);
+        }
+        assert_eq!(
+            store.get("admin-eu").unwrap().get_region(),
+            Some("eu-west-1")
+        );
+        assert_eq!(
+            changes.try_iter().collect::<Vec<_>>(),
+            vec![
+                ProfileChange::Added("base".to_string()),
+                ProfileChange::Added("admin".to_string()),
+                ProfileChange::Updated("admin".to_string()),
+                ProfileChange::Renamed {
+                    from: "base".to_string(),
+                    to: "root".to_string()
+                },
+                ProfileChange::Added("admin-eu".to_string()),
+                ProfileChange::Removed("admin".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_rename_updates_source_profile_references() {
+        let mut config = IniDocument::parse(
+            r#"[profile base]
+region = us-east-1
+
+[profile admin]
+role_arn = arn:aws:iam::123456789012:role/Admin
+source_profile = base
+"#,
+        );
+        let mut credentials = IniDocument::parse("[base]\naws_access_key_id = AKIA\n");
+
+        let updated = rename_sections(&mut config, &mut credentials, &profile("base", &[]), "root");
+
+        assert_eq!(updated, vec!["profile admin"]);
+        assert_eq!(
+            config.to_string(),
+            r#"[profile root]
+region = us-east-1
+
+[profile admin]
+role_arn = arn:aws:iam::123456789012:role/Admin
+source_profile = root
+"#
+        );
+        assert_eq!(
+            credentials.to_string(),
+            "[root]\naws_access_key_id = AKIA\n"
+        );
+    }
+
+    #[test]
+    fn test_copy_section_duplicates_under_new_header() {
+        let mut document = IniDocument::parse(
+            "[profile dev]\nsso_role_name = Dev\n\n[profile other]\nregion = us-east-1\n",
+        );
+        copy_section(&mut document, "profile dev", "profile dev-admin");
+        document.set("profile dev-admin", "sso_role_name", "Admin");
+
+        assert_eq!(
+            document.to_string(),
+            r#"[profile dev]
+sso_role_name = Dev
+
+[profile other]
+region = us-east-1
+
+[profile dev-admin]
+sso_role_name = Admin
+
+"#
+        );
+
+        copy_section(&mut document, "profile missing", "profile new");
+        assert!(!document.has_section("profile new"));
+    }
+}
@@ -0,0 +1,154 @@
+//! Background work that fills in the selector after it is on screen.
+//!
+//! Tasks run on the shared runtime ([`crate::aws::runtime`]) and report
+//! [`Update`]s over a channel that the selector drains between frames, so the
+//! list renders straight away and network results stream in as they arrive.
+
+use crate::aws::runtime;
+use crate::check::CheckResult;
+use crate::config::{AwsConfig, ConfigPaths, read_aws_config};
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+use std::future::Future;
+use std::sync::mpsc::{Receiver, Sender, channel};
+use std::time::Duration;
+use tokio::task::JoinSet;
+
+/// How often an open selector looks for changes to the config files.
+const RELOAD_INTERVAL: Duration = Duration::from_millis(500);
+
+/// Something a background task found out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Update {
+    /// A `--check` result for one profile.
+    Checked {
+        profile_name: String,
+        result: CheckResult,
+    },
+    /// An account's IAM alias.
+    AccountAlias { account_id: String, alias: String },
+    /// The SSO token expiry of every SSO profile listed (see
+    /// [`crate::sso::token_expiries`]).
+    TokenExpiries(HashMap<String, Option<DateTime<Utc>>>),
+}
+
+/// The tasks feeding one selector.
+pub struct Background {
+    sender: Sender<Update>,
+    receiver: Receiver<Update>,
+}
+
+impl Default for Background {
+    fn default() -> Self {
+        let (sender, receiver) = channel();
+        Self { sender, receiver }
+    }
+}
+
+impl Background {
+    /// Starts `task` with a sender of its own to report through.
+    pub fn spawn<F, Fut>(&self, task: F) -> Result<()>
+    where
+        F: FnOnce(Sender<Update>) -> Fut,
+        Fut: Future<Output = ()> + Send + 'static,
+    {
+        runtime()?.spawn(task(self.sender.clone()));
+        Ok(())
+    }
+
+    /// The channel the updates arrive on. It disconnects once every spawned
+    /// task has finished (straight away when none were).
+    pub fn into_updates(self) -> Receiver<Update> {
+        self.receiver
+    }
+}
+
+/// Runs `task` on each of `items`, at most `limit` at a time, and hands
+/// each output to `on_done` as it finishes, fastest first.
+pub async fn run_bounded<I, F, Fut>(
+    items: I,
+    limit: usize,
+    mut task: F,
+    mut on_done: impl FnMut(Fut::Output),
+) -> Result<()>
+where
+    I: IntoIterator,
+    F: FnMut(I::Item) -> Fut,
+    Fut: Future + Send + 'static,
+    Fut::Output: Send + 'static,
+{
+    let mut queue = items.into_iter();
+    let mut running = JoinSet::new();
+    loop {
+        while running.len() < limit.max(1)
+            && let Some(item) = queue.next()
+        {
+            running.spawn(task(item));
+        }
+        let Some(finished) = running.join_next().await else {
+            return Ok(());
+        };
+        on_done(finished?);
+    }
+}
+
+/// Reads the AWS config again whenever one of its files changes and sends
+/// what `narrow` keeps of it, until the receiver is dropped. A file caught
+/// halfway through being written is read again on the next look.
+pub fn watch_config(
+    paths: ConfigPaths,
+    narrow: impl Fn(AwsConfig) -> AwsConfig + Send + 'static,
+) -> Receiver<AwsConfig> {
+    let (sender, receiver) = channel();
+    std::thread::spawn(move || {
+        let mut last_modified = paths.modified();
+        loop {
+            std::thread::sleep(RELOAD_INTERVAL);
+            let modified = paths.modified();
+            if modified == last_modified {
+                continue;
+            }
+            let Ok(config) = read_aws_config(&paths) else {
+                continue;
+            };
+            last_modified = modified;
+            if sender.send(narrow(config)).is_err() {
+                return;
+            }
+        }
+    });
+    receiver
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::aws::block_on;
+    use std::sync::Arc;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_run_bounded_keeps_to_the_limit() {
+        let running = Arc::new(AtomicUsize::new(0));
+        let most = Arc::new(AtomicUsize::new(0));
+        let mut done = Vec::new();
+        let task = |n: u64| {
+            let (running, most) = (running.clone(), most.clone());
+            async move {
+                let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+                most.fetch_max(now, Ordering::SeqCst);
+                tokio::time::sleep(Duration::from_millis(5 * (6 - n))).await;
+                running.fetch_sub(1, Ordering::SeqCst);
+                n
+            }
+        };
+
+        block_on(run_bounded(1..=5, 2, task, |n| done.push(n)))
+            .unwrap()
+            .unwrap();
+        done.sort_unstable();
+        assert_eq!(done, [1, 2, 3, 4, 5]);
+        assert_eq!(most.load(Ordering::SeqCst), 2);
+    }
+}
@@ -0,0 +1,513 @@
+//! User-defined display format for profile lines, e.g.
+//! `"{name:<30} {account_id} {region} {role}"`.
+//!
+//! Placeholders take an optional spec after `:` in the style of Rust's
+//! `format!`: an alignment (`<`, `>`, or `^`) with a minimum width, and/or a
+//! `.N` maximum width that truncates with `…`. `{{` and `}}` are literal
+//! braces.
+
+use crate::config::{AwsConfig, Profile};
+use crate::environment::now;
+use crate::state::time_ago;
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use std::str::FromStr;
+
+/// A profile value that can appear in a display format or be styled by a
+/// theme.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Field {
+    Name,
+    AccountId,
+    /// The account's alias, or its ID when it has none.
+    AccountName,
+    Region,
+    Role,
+    SsoSession,
+    SsoStartUrl,
+    SsoRegion,
+    Aliases,
+    CredentialType,
+    /// How long ago the profile was last activated, e.g. `2d ago`.
+    LastUsed,
+}
+
+impl Field {
+    const NAMES: &[(&str, Field)] = &[
+        ("name", Field::Name),
+        ("account_id", Field::AccountId),
+        ("account_name", Field::AccountName),
+        ("region", Field::Region),
+        ("role", Field::Role),
+        ("sso_session", Field::SsoSession),
+        ("sso_start_url", Field::SsoStartUrl),
+        ("sso_region", Field::SsoRegion),
+        ("aliases", Field::Aliases),
+        ("type", Field::CredentialType),
+        ("last_used", Field::LastUsed),
+    ];
+
+    fn value(
+        &self,
+        profile: &Profile,
+        config: &AwsConfig,
+        aliases: &[&str],
+        account_name: Option<&str>,
+        last_used: Option<DateTime<Utc>>,
+    ) -> Option<String> {
+        let value = match self {
+            Field::Name => Some(profile.name.as_str()),
+            Field::AccountId => profile.get_account_id(),
+            Field::AccountName => account_name.or(profile.get_account_id()),
+            Field::Region => profile.get_region(),
+            Field::Role => profile.get_role_name(),
+            Field::SsoSession => profile.get_sso_session(),
+            Field::SsoStartUrl => config.sso_start_url_for(profile),
+            Field::SsoRegion => config.sso_region_for(profile),
+            Field::Aliases => return (!aliases.is_empty()).then(|| aliases.join(",")),
+            Field::CredentialType => profile.credential_type().map(|kind| kind.badge()),
+            Field::LastUsed => return last_used.map(|at| time_ago(at, now())),
+        };
+        value.map(str::to_string)
+    }
+}
+
+impl FromStr for Field {
+    type Err = anyhow::Error;
+
+    fn from_str(name: &str) -> Result<Self> {
+        Field::NAMES
+            .iter()
+            .find(|(candidate, _)| *candidate == name)
+            .map(|(_, field)| *field)
+            .ok_or_else(|| {
+                let known: Vec<&str> = Field::NAMES.iter().map(|(name, _)| *name).collect();
+                anyhow::anyhow!(
+                    "Unknown field '{name}' in display format (expected one of {})",
+                    known.join(", ")
+                )
+            })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Align {
+    Left,
+    Right,
+    Center,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct Placeholder {
+    field: Field,
+    align: Align,
+    width: usize,
+    max_width: Option<usize>,
+}
+
+impl Placeholder {
+    fn parse(text: &str) -> Result<Self> {
+        let (name, spec) = text.split_once(':').unwrap_or((text, ""));
+        let mut placeholder = Self {
+            field: name.trim().parse()?,
+            align: Align::Left,
+            width: 0,
+            max_width: None,
+        };
+
+        let invalid = || anyhow::anyhow!("Invalid format spec '{spec}' for '{name}'");
+        let (width, max_width) = spec.split_once('.').unwrap_or((spec, ""));
+        let width = match width.chars().next() {
+            Some('<') => &width[1..],
+            Some('>') => {
+                placeholder.align = Align::Right;
+                &width[1..]
+            }
+            Some('^') => {
+                placeholder.align = Align::Center;
+                &width[1..]
+            }
+            _ => width,
+        };
+        if !width.is_empty() {
+            placeholder.width = width.parse().map_err(|_| invalid())?;
+        }
+        if spec.contains('.') {
+            placeholder.max_width = Some(max_width.parse().map_err(|_| invalid())?);
+        }
+
+        Ok(placeholder)
+    }
+
+    fn render(&self, value: &str) -> String {
+        let mut value: String = value.to_string();
+        if let Some(max_width) = self.max_width
+            && value.chars().count() > max_width
+        {
+            value = match max_width {
+                0 => String::new(),
+                _ => value.chars().take(max_width - 1).chain(['…']).collect(),
+            };
+        }
+
+        let padding = self.width.saturating_sub(value.chars().count());
+        let (before, after) = match self.align {
+            Align::Left => (0, padding),
+            Align::Right => (padding, 0),
+            Align::Center => (padding / 2, padding - padding / 2),
+        };
+        format!("{}{value}{}", " ".repeat(before), " ".repeat(after))
+    }
+}
+
+/// A piece of a parsed template (see [`parse_segments`]).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) enum Segment<P> {
+    Literal(String),
+    Placeholder(P),
+}
+
+/// Markers the selectors add after a profile's name.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Tag {
+    /// How the profile authenticates, shown before the name.
+    Badge,
+    /// The `[default]` profile.
+    Default,
+    /// The currently active profile.
+    Active,
+    /// `AWS_PROFILE` in the environment, when it disagrees with the
+    /// current-profile file.
+    Env,
+    /// Time left on a profile's SSO token.
+    Expiry,
+    /// An SSO profile that needs a login first.
+    Expired,
+    /// A credential check that succeeded.
+    CheckOk,
+    /// A credential check that failed or timed out.
+    CheckFailed,
+    /// A tag from the settings file.
+    Chip,
+    /// A `source_profile` chain that never reaches credentials.
+    BrokenChain,
+    /// A profile defined more than once (see
+    /// [`crate::config::ProfileConflict`]).
+    Conflict,
+    /// A profile whose requests go to an `endpoint_url` rather than AWS.
+    EndpointOverride,
+    /// Spaces and ellipses added by [`crate::columns::Columns`].
+    Padding,
+}
+
+/// A piece of a profile line, tagged with the field it shows (if any) so the
+/// selectors can style it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LabelPart {
+    pub field: Option<Field>,
+    pub tag: Option<Tag>,
+    pub text: String,
+}
+
+/// A profile line as a sequence of parts.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Label {
+    pub parts: Vec<LabelPart>,
+    /// The profile is protected, so its name gets the warning style.
+    pub protected: bool,
+}
+
+impl Label {
+    pub fn push(&mut self, field: Option<Field>, text: impl Into<String>) {
+        self.parts.push(LabelPart {
+            field,
+            tag: None,
+            text: text.into(),
+        });
+    }
+
+    /// Puts `text` in front of the line, padded so badges line up.
+    pub fn add_badge(&mut self, text: &str) {
+        let parts = [
+            LabelPart {
+                field: None,
+                tag: Some(Tag::Badge),
+                text: format!("{text:<4}"),
+            },
+            LabelPart {
+                field: None,
+                tag: Some(Tag::Badge),
+                text: " ".to_string(),
+            },
+        ];
+        self.parts.splice(0..0, parts);
+    }
+
+    /// Inserts `text` after the profile name and any earlier tags, or at the
+    /// end when the line doesn't show the name. Tags aren't part of what
+    /// filters match.
+    pub fn add_tag(&mut self, tag: Tag, text: impl Into<String>) {
+        let position = match self
+            .parts
+            .iter()
+            .position(|part| part.field == Some(Field::Name))
+        {
+            Some(name) => {
+                let tags = self.parts[name + 1..]
+                    .iter()
+                    .take_while(|part| part.tag.is_some())
+                    .count();
+                name + 1 + tags
+            }
+            None => self.parts.len(),
+        };
+        let parts = [
+            LabelPart {
+                field: None,
+                tag: Some(tag),
+                text: " ".to_string(),
+            },
+            LabelPart {
+                field: None,
+                tag: Some(tag),
+                text: text.into(),
+            },
+        ];
+        self.parts.splice(position..position, parts);
+    }
+
+    /// The text without styling or tags; what filters match against.
+    pub fn plain(&self) -> String {
+        self.parts
+            .iter()
+            .filter(|part| part.tag.is_none())
+            .map(|part| part.text.as_str())
+            .collect()
+    }
+
+    /// The whole text without styling, tags included.
+    pub fn text(&self) -> String {
+        self.parts.iter().map(|part| part.text.as_str()).collect()
+    }
+
+    /// Drops trailing whitespace, which padding on the last column leaves
+    /// behind.
+    fn trim_end(&mut self) {
+        while let Some(part) = self.parts.last_mut() {
+            let trimmed = part.text.trim_end().len();
+            part.text.truncate(trimmed);
+            if !part.text.is_empty() {
+                break;
+            }
+            self.parts.pop();
+        }
+    }
+}
+
+/// A parsed display format, rendered once per profile.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct DisplayFormat {
+    segments: Vec<Segment<Placeholder>>,
+    template: String,
+}
+
+impl DisplayFormat {
+    /// The template this was parsed from.
+    pub fn template(&self) -> &str {
+        &self.template
+    }
+
+    /// Fields without a value render as `-`.
+    pub fn render(
+        &self,
+        profile: &Profile,
+        config: &AwsConfig,
+        aliases: &[&str],
+        account_name: Option<&str>,
+        last_used: Option<DateTime<Utc>>,
+    ) -> String {
+        self.label(profile, config, aliases, account_name, last_used)
+            .plain()
+    }
+
+    pub fn label(
+        &self,
+        profile: &Profile,
+        config: &AwsConfig,
+        aliases: &[&str],
+        account_name: Option<&str>,
+        last_used: Option<DateTime<Utc>>,
+    ) -> Label {
+        let mut label = Label::default();
+        for segment in &self.segments {
+            match segment {
+                Segment::Literal(text) => label.push(None, text.as_str()),
+                Segment::Placeholder(placeholder) => {
+                    let value =
+                        placeholder
+                            .field
+                            .value(profile, config, aliases, account_name, last_used);
+                    let text = placeholder.render(value.as_deref().unwrap_or("-"));
+                    label.push(Some(placeholder.field), text);
+                }
+            }
+        }
+        label.trim_end();
+        label
+    }
+}
+
+impl FromStr for DisplayFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(template: &str) -> Result<Self> {
+        Ok(Self {
+            segments: parse_segments(template, "display format", Placeholder::parse)?,
+            template: template.to_string(),
+        })
+    }
+}
+
+/// Splits `template` into literal text and `{...}` placeholders, each read
+/// with `parse`. `{{` and `}}` are literal braces; `what` names the template
+/// in errors.
+pub(crate) fn parse_segments<P>(
+    template: &str,
+    what: &str,
+    mut parse: impl FnMut(&str) -> Result<P>,
+) -> Result<Vec<Segment<P>>> {
+    let mut segments = Vec::new();
+    let mut literal = String::new();
+    let mut chars = template.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            '{' if chars.peek() == Some(&'{') => {
+                chars.next();
+                literal.push('{');
+            }
+            '}' if chars.peek() == Some(&'}') => {
+                chars.next();
+                literal.push('}');
+            }
+            '{' => {
+                let mut placeholder = String::new();
+                loop {
+                    match chars.next() {
+                        Some('}') => break,
+                        Some(c) => placeholder.push(c),
+                        None => return Err(anyhow::anyhow!("Unclosed '{{' in {what}")),
+                    }
+                }
+                if !literal.is_empty() {
+                    segments.push(Segment::Literal(std::mem::take(&mut literal)));
+                }
+                segments.push(Segment::Placeholder(parse(&placeholder)?));
+            }
+            '}' => return Err(anyhow::anyhow!("Unmatched '}}' in {what}")),
+            c => literal.push(c),
+        }
+    }
+
+    if !literal.is_empty() {
+        segments.push(Segment::Literal(literal));
+    }
+    Ok(segments)
+}
+
+impl TryFrom<String> for DisplayFormat {
+    type Error = anyhow::Error;
+
+    fn try_from(template: String) -> Result<Self> {
+        template.parse()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support::profile;
+
+    fn production() -> Profile {
+        profile(
+            "mycorp-production",
+            &[("sso_account_id", "123456789012"), ("region", "eu-west-1")],
+        )
+    }
+
+    fn render(template: &str) -> String {
+        let format: DisplayFormat = template.parse().unwrap();
+        format.render(&production(), &AwsConfig::default(), &["p"], None, None)
+    }
+
+    #[test]
+    fn test_render_with_alignment_and_truncation() {
+        assert_eq!(
+            render("{name:<20}|{account_id:>14}|{region:^11}|{role}"),
+            "mycorp-production   |  123456789012| eu-west-1 |-"
+        );
+        assert_eq!(render("{name:.8} ({aliases})"), "mycorp-… (p)");
+        assert_eq!(render("{name:<10.6}|"), "mycor…    |");
+        assert_eq!(
+            render("{{{region}}} {name:<30}"),
+            "{eu-west-1} mycorp-production"
+        );
+        assert_eq!(render("{account_name}"), "123456789012");
+
+        let format: DisplayFormat = "{account_name} ({account_id})".parse().unwrap();
+        assert_eq!(
+            format.render(
+                &production(),
+                &AwsConfig::default(),
+                &[],
+                Some("payments-prod"),
+                None
+            ),
+            "payments-prod (123456789012)"
+        );
+
+        assert_eq!(render("{name} {last_used}"), "mycorp-production -");
+        let format: DisplayFormat = "{name} {last_used}".parse().unwrap();
+        let last_used = Utc::now() - chrono::TimeDelta::days(2);
+        assert_eq!(
+            format.render(
+                &production(),
+                &AwsConfig::default(),
+                &[],
+                None,
+                Some(last_used)
+            ),
+            "mycorp-production 2d ago"
+        );
+    }
+
+    #[test]
+    fn test_label_parts_carry_fields() {
+        let format: DisplayFormat = "{name} @ {region:<12}".parse().unwrap();
+        let label = format.label(&production(), &AwsConfig::default(), &[], None, None);
+        let fields: Vec<Option<Field>> = label.parts.iter().map(|part| part.field).collect();
+        assert_eq!(fields, [Some(Field::Name), None, Some(Field::Region)]);
+        assert_eq!(label.plain(), "mycorp-production @ eu-west-1");
+    }
+
+    #[test]
+    fn test_tags_follow_the_name_and_are_not_matched() {
+        let format: DisplayFormat = "{region} {name} {role}".parse().unwrap();
+        let mut label = format.label(&production(), &AwsConfig::default(), &[], None, None);
+        label.add_tag(Tag::Active, "(active)");
+        label.add_tag(Tag::Env, "(env)");
+        let text: String = label.parts.iter().map(|part| part.text.as_str()).collect();
+        assert_eq!(text, "eu-west-1 mycorp-production (active) (env) -");
+        assert_eq!(label.plain(), "eu-west-1 mycorp-production -");
+    }
+
+    #[test]
+    fn test_invalid_formats() {
+        assert!("{colour}".parse::<DisplayFormat>().is_err());
+        assert!("{name".parse::<DisplayFormat>().is_err());
+        assert!("name}".parse::<DisplayFormat>().is_err());
+        assert!("{name:<wide}".parse::<DisplayFormat>().is_err());
+    }
+}
@@ -0,0 +1,106 @@
+//! Telling terminals apart, for `--scope terminal`: each tmux pane, or else
+//! each tty, keeps its own current profile.
+//!
+//! A tty is reused once its terminal closes, so entries also record the
+//! terminal's session (the process group led by its first shell) and are
+//! ignored once that session has ended.
+
+use anyhow::Result;
+use std::fs;
+use std::path::Path;
+use std::process::{Command, Stdio};
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Terminal {
+    /// Safe to use as a file name: `tmux-3`, `pts-4`, `ttys002`.
+    pub key: String,
+    pub session: u32,
+}
+
+/// The terminal this process runs in.
+pub fn current() -> Result<Terminal> {
+    let key = match std::env::var("TMUX_PANE") {
+        Ok(pane) if !pane.is_empty() => format!("tmux-{}", pane.trim_start_matches('%')),
+        _ => tty_name()
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "Can't tell which terminal this is (no $TMUX_PANE or tty); use --scope global"
+                )
+            })?
+            .trim_start_matches("/dev/")
+            .replace('/', "-"),
+    };
+    let session = session_id(std::process::id())
+        .ok_or_else(|| anyhow::anyhow!("Unable to determine the terminal's session"))?;
+
+    Ok(Terminal { key, session })
+}
+
+/// Whether the session that wrote an entry is still running.
+pub fn is_session_alive(session: u32) -> bool {
+    if Path::new("/proc/self").exists() {
+        return Path::new(&format!("/proc/{session}")).exists();
+    }
+    Command::new("kill")
+        .args(["-0", &session.to_string()])
+        .stderr(Stdio::null())
+        .status()
+        .is_ok_and(|status| status.success())
+}
+
+/// The device of whichever standard stream is a terminal.
+fn tty_name() -> Option<String> {
+    for fd in 0..3 {
+        if let Ok(target) = fs::read_link(format!("/proc/self/fd/{fd}")) {
+            let target = target.display().to_string();
+            if target.starts_with("/dev/pts/") || target.starts_with("/dev/tty") {
+                return Some(target);
+            }
+        }
+    }
+
+    // No /proc (macOS, BSD): ask `tty` about the inherited stdin.
+    let output = Command::new("tty")
+        .stdin(Stdio::inherit())
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    let name = String::from_utf8(output.stdout).ok()?.trim().to_string();
+    (output.status.success() && name.starts_with("/dev/")).then_some(name)
+}
+
+fn session_id(pid: u32) -> Option<u32> {
+    if let Ok(stat) = fs::read_to_string(format!("/proc/{pid}/stat")) {
+        return parse_session(&stat);
+    }
+
+    let output = Command::new("ps")
+        .args(["-o", "sess=", "-p", &pid.to_string()])
+        .stderr(Stdio::null())
+        .output()
+        .ok()?;
+    String::from_utf8(output.stdout).ok()?.trim().parse().ok()
+}
+
+/// The session field of `/proc/<pid>/stat`. The command name before it is in
+/// parentheses and may itself contain spaces and parentheses.
+fn parse_session(stat: &str) -> Option<u32> {
+    let (_, fields) = stat.rsplit_once(')')?;
+    // state, ppid, pgrp, session
+    fields.split_whitespace().nth(3)?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_session() {
+        assert_eq!(
+            parse_session("4242 (zsh) S 4241 4242 4242 34816 4300 4194304 2068"),
+            Some(4242)
+        );
+        assert_eq!(parse_session("77 (my (odd) cmd) R 1 70 71 0 -1"), Some(71));
+        assert_eq!(parse_session("garbage"), None);
+    }
+}
@@ -0,0 +1,32 @@
+//! Fixtures the unit tests share.
+
+use crate::config::{AwsConfig, Profile, SectionStyle};
+use crate::files::TempDir;
+use std::collections::HashMap;
+
+/// A `[profile name]` section with `attributes`.
+pub fn profile(name: &str, attributes: &[(&str, &str)]) -> Profile {
+    Profile {
+        name: name.to_string(),
+        attributes: attributes
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect(),
+        subsections: HashMap::new(),
+        section_style: SectionStyle::Prefixed,
+    }
+}
+
+/// A config of just `profiles`.
+pub fn config(profiles: Vec<Profile>) -> AwsConfig {
+    AwsConfig {
+        profiles,
+        ..AwsConfig::default()
+    }
+}
+
+/// A fresh directory for one test, removed with everything in it when the
+/// test ends, however it ends.
+pub fn temp_dir(purpose: &str) -> TempDir {
+    TempDir::new(purpose).expect("a temporary directory")
+}
@@ -0,0 +1,432 @@
+//! Colors for the selectors: a built-in preset with optional per-field
+//! overrides from the `[theme]` table of the settings file.
+//!
+//! `--no-color` or a non-empty `NO_COLOR` turns all of it off; matched
+//! characters are then shown bold and underlined instead.
+
+use crate::template::{Field, Label, LabelPart, Tag};
+use anyhow::Result;
+use ratatui::style::{Color, Modifier, Style};
+use ratatui::text::{Line, Span};
+use serde::Deserialize;
+use std::str::FromStr;
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Preset {
+    /// For terminals with a dark background.
+    #[default]
+    Dark,
+    /// For terminals with a light background.
+    Light,
+    /// No colors; only the overrides apply.
+    None,
+}
+
+/// The `[theme]` table of the settings file.
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields, rename_all = "kebab-case")]
+pub struct ThemeSettings {
+    pub preset: Preset,
+    pub name: Option<StyleSpec>,
+    pub account_id: Option<StyleSpec>,
+    pub region: Option<StyleSpec>,
+    pub role: Option<StyleSpec>,
+    /// Characters matched by the filter (full-screen browser only).
+    pub matched: Option<StyleSpec>,
+    /// The favorite star and the `*` on the default profile.
+    pub marker: Option<StyleSpec>,
+    /// The `(active)` tag on the current profile.
+    pub active: Option<StyleSpec>,
+    /// The `(env)` tag on a profile selected by `AWS_PROFILE` instead of the
+    /// current-profile file, and `(endpoint override)`.
+    pub env: Option<StyleSpec>,
+    /// The credential type badge in front of each profile.
+    pub badge: Option<StyleSpec>,
+    /// Time left on an SSO token.
+    pub expiry: Option<StyleSpec>,
+    /// `(expired)` and `(not logged in)` on SSO profiles.
+    pub expired: Option<StyleSpec>,
+    /// A passed `--check`.
+    pub check_ok: Option<StyleSpec>,
+    /// A failed or timed out `--check`.
+    pub check_failed: Option<StyleSpec>,
+    /// The names of protected profiles.
+    pub protected: Option<StyleSpec>,
+    /// Profile tags from the settings file.
+    pub chip: Option<StyleSpec>,
+}
+
+/// A style written as words, e.g. `"bold yellow"`, `"#ff8700 on black"`, or
+/// `"underline 208"`. Colors are names, 256-color indices, or `#rrggbb`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct StyleSpec(Style);
+
+impl FromStr for StyleSpec {
+    type Err = anyhow::Error;
+
+    fn from_str(spec: &str) -> Result<Self> {
+        let mut style = Style::new();
+        let mut words = spec.split_whitespace();
+
+        while let Some(word) = words.next() {
+            style = match word.to_lowercase().as_str() {
+                "bold" => style.add_modifier(Modifier::BOLD),
+                "dim" => style.add_modifier(Modifier::DIM),
+                "italic" => style.add_modifier(Modifier::ITALIC),
+                "underline" | "underlined" => style.add_modifier(Modifier::UNDERLINED),
+                "reversed" => style.add_modifier(Modifier::REVERSED),
+                "on" => {
+                    let color = words
+                        .next()
+                        .ok_or_else(|| anyhow::anyhow!("Missing color after 'on' in '{spec}'"))?;
+                    style.bg(parse_color(color)?)
+                }
+                _ => style.fg(parse_color(word)?),
+            };
+        }
+
+        Ok(Self(style))
+    }
+}
+
+impl TryFrom<String> for StyleSpec {
+    type Error = anyhow::Error;
+
+    fn try_from(spec: String) -> Result<Self> {
+        spec.parse()
+    }
+}
+
+fn parse_color(text: &str) -> Result<Color> {
+    Color::from_str(text).map_err(|_| anyhow::anyhow!("Unknown color '{text}'"))
+}
+
+/// `NO_COLOR` is set to something non-empty (<https://no-color.org>).
+pub fn no_color_env() -> bool {
+    std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty())
+}
+
+/// Resolved styles for each part of a profile line.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Theme {
+    name: Style,
+    account_id: Style,
+    region: Style,
+    role: Style,
+    matched: Style,
+    marker: Style,
+    active: Style,
+    env: Style,
+    badge: Style,
+    expiry: Style,
+    expired: Style,
+    check_ok: Style,
+    check_failed: Style,
+    protected: Style,
+    chip: Style,
+}
+
+impl Default for Theme {
+    /// No colors at all.
+    fn default() -> Self {
+        Self {
+            name: Style::new(),
+            account_id: Style::new(),
+            region: Style::new(),
+            role: Style::new(),
+            matched: Style::new()
+                .add_modifier(Modifier::BOLD)
+                .add_modifier(Modifier::UNDERLINED),
+            marker: Style::new(),
+            active: Style::new().add_modifier(Modifier::BOLD),
+            env: Style::new().add_modifier(Modifier::BOLD),
+            badge: Style::new(),
+            expiry: Style::new(),
+            expired: Style::new(),
+            check_ok: Style::new(),
+            check_failed: Style::new(),
+            protected: Style::new(),
+            chip: Style::new(),
+        }
+    }
+}
+
+impl Theme {
+    /// The configured preset and overrides, or no colors when `no_color`.
+    pub fn new(settings: &ThemeSettings, no_color: bool) -> Self {
+        if no_color {
+            return Self::default();
+        }
+
+        let mut theme = Self::preset(settings.preset);
+        let overrides = [
+            (&mut theme.name, settings.name),
+            (&mut theme.account_id, settings.account_id),
+            (&mut theme.region, settings.region),
+            (&mut theme.role, settings.role),
+            (&mut theme.matched, settings.matched),
+            (&mut theme.marker, settings.marker),
+            (&mut theme.active, settings.active),
+            (&mut theme.env, settings.env),
+            (&mut theme.badge, settings.badge),
+            (&mut theme.expiry, settings.expiry),
+            (&mut theme.expired, settings.expired),
+            (&mut theme.check_ok, settings.check_ok),
+            (&mut theme.check_failed, settings.check_failed),
+            (&mut theme.protected, settings.protected),
+            (&mut theme.chip, settings.chip),
+        ];
+        for (style, spec) in overrides {
+            if let Some(StyleSpec(spec)) = spec {
+                *style = spec;
+            }
+        }
+        theme
+    }
+
+    pub fn preset(preset: Preset) -> Self {
+        let bold = Style::new().add_modifier(Modifier::BOLD);
+        match preset {
+            Preset::Dark => Self {
+                name: bold,
+                account_id: Style::new().fg(Color::Yellow),
+                region: Style::new().fg(Color::Cyan),
+                role: Style::new().fg(Color::Green),
+                matched: bold.fg(Color::LightYellow),
+                marker: Style::new().fg(Color::Yellow),
+                active: bold.fg(Color::LightGreen),
+                env: bold.fg(Color::LightRed),
+                badge: Style::new().fg(Color::DarkGray),
+                expiry: Style::new().fg(Color::DarkGray),
+                expired: Style::new().fg(Color::LightRed),
+                check_ok: Style::new().fg(Color::LightGreen),
+                check_failed: Style::new().fg(Color::LightRed),
+                protected: bold.fg(Color::LightRed),
+                chip: Style::new().fg(Color::LightMagenta),
+            },
+            Preset::Light => Self {
+                name: bold,
+                account_id: Style::new().fg(Color::Blue),
+                region: Style::new().fg(Color::Magenta),
+                role: Style::new().fg(Color::Green),
+                matched: bold.fg(Color::Red),
+                marker: Style::new().fg(Color::Red),
+                active: bold.fg(Color::Cyan),
+                env: bold.fg(Color::Red),
+                badge: Style::new().fg(Color::Gray),
+                expiry: Style::new().fg(Color::Gray),
+                expired: Style::new().fg(Color::Red),
+                check_ok: Style::new().fg(Color::Green),
+                check_failed: Style::new().fg(Color::Red),
+                protected: bold.fg(Color::Red),
+                chip: Style::new().fg(Color::Magenta),
+            },
+            Preset::None => Self::default(),
+        }
+    }
+
+    fn style(&self, label: &Label, part: &LabelPart) -> Style {
+        if label.protected && part.tag.is_none() && part.field == Some(Field::Name) {
+            return self.name.patch(self.protected);
+        }
+
+        match (part.tag, part.field) {
+            (Some(Tag::Badge), _) => self.badge,
+            (Some(Tag::Default), _) => self.marker,
+            (Some(Tag::Active), _) => self.active,
+            (Some(Tag::Env | Tag::EndpointOverride), _) => self.env,
+            (Some(Tag::Expiry), _) => self.expiry,
+            (Some(Tag::Expired | Tag::BrokenChain | Tag::Conflict), _) => self.expired,
+            (Some(Tag::CheckOk), _) => self.check_ok,
+            (Some(Tag::CheckFailed), _) => self.check_failed,
+            (Some(Tag::Chip), _) => self.chip,
+            (_, Some(Field::Name)) => self.name,
+            (_, Some(Field::AccountId | Field::AccountName)) => self.account_id,
+            (_, Some(Field::Region | Field::SsoRegion)) => self.region,
+            (_, Some(Field::Role)) => self.role,
+            _ => Style::new(),
+        }
+    }
+
+    pub fn marker(&self) -> Style {
+        self.marker
+    }
+
+    /// `label` with ANSI escapes, for the inline prompt.
+    pub fn ansi(&self, label: &Label) -> String {
+        label
+            .parts
+            .iter()
+            .map(|part| paint(&part.text, self.style(label, part)))
+            .collect()
+    }
+
+    /// `label` as styled spans for the full-screen browser, with the
+    /// characters at `matched` (positions in [`Label::plain`]) emphasised.
+    pub fn line(&self, label: &Label, matched: &[usize]) -> Line<'static> {
+        let mut spans: Vec<Span> = Vec::new();
+        let mut position = 0;
+
+        for part in &label.parts {
+            let base = self.style(label, part);
+            for c in part.text.chars() {
+                let style = if part.tag.is_none() && matched.contains(&position) {
+                    base.patch(self.matched)
+                } else {
+                    base
+                };
+                if part.tag.is_none() {
+                    position += 1;
+                }
+
+                match spans.last_mut() {
+                    Some(span) if span.style == style => span.content.to_mut().push(c),
+                    _ => spans.push(Span::styled(c.to_string(), style)),
+                }
+            }
+        }
+
+        Line::from(spans)
+    }
+}
+
+/// Wraps `text` in the SGR codes for `style`, leaving it untouched when the
+/// style or the text is empty.
+pub fn paint(text: &str, style: Style) -> String {
+    let mut codes: Vec<String> = Vec::new();
+
+    for (modifier, code) in [
+        (Modifier::BOLD, "1"),
+        (Modifier::DIM, "2"),
+        (Modifier::ITALIC, "3"),
+        (Modifier::UNDERLINED, "4"),
+        (Modifier::REVERSED, "7"),
+    ] {
+        if style.add_modifier.contains(modifier) {
+            codes.push(code.to_string());
+        }
+    }
+    if let Some(color) = style.fg {
+        codes.push(color_code(color, false));
+    }
+    if let Some(color) = style.bg {
+        codes.push(color_code(color, true));
+    }
+
+    if codes.is_empty() || text.trim().is_empty() {
+        return text.to_string();
+    }
+    format!("\x1b[{}m{text}\x1b[0m", codes.join(";"))
+}
+
+fn color_code(color: Color, background: bool) -> String {
+    let base = match color {
+        Color::Reset => return if background { "49" } else { "39" }.to_string(),
+        Color::Indexed(index) => {
+            return format!("{};5;{index}", if background { 48 } else { 38 });
+        }
+        Color::Rgb(r, g, b) => {
+            return format!("{};2;{r};{g};{b}", if background { 48 } else { 38 });
+        }
+        Color::Black => 30,
+        Color::Red => 31,
+        Color::Green => 32,
+        Color::Yellow => 33,
+        Color::Blue => 34,
+        Color::Magenta => 35,
+        Color::Cyan => 36,
+        Color::Gray => 37,
+        Color::DarkGray => 90,
+        Color::LightRed => 91,
+        Color::LightGreen => 92,
+        Color::LightYellow => 93,
+        Color::LightBlue => 94,
+        Color::LightMagenta => 95,
+        Color::LightCyan => 96,
+        Color::White => 97,
+    };
+    (if background { base + 10 } else { base }).to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_style_spec_parsing() {
+        let spec: StyleSpec = "bold #ff8700 on dark-gray".parse().unwrap();
+        assert_eq!(
+            spec.0,
+            Style::new()
+                .add_modifier(Modifier::BOLD)
+                .fg(Color::Rgb(255, 135, 0))
+                .bg(Color::DarkGray)
+        );
+        let spec: StyleSpec = "underline 208".parse().unwrap();
+        assert_eq!(
+            spec.0,
+            Style::new()
+                .add_modifier(Modifier::UNDERLINED)
+                .fg(Color::Indexed(208))
+        );
+        assert!("blinking".parse::<StyleSpec>().is_err());
+        assert!("red on".parse::<StyleSpec>().is_err());
+    }
+
+    #[test]
+    fn test_ansi_rendering() {
+        let mut label = Label::default();
+        label.push(Some(Field::Name), "dev");
+        label.push(None, " ");
+        label.push(Some(Field::Region), "[us-west-2]");
+
+        assert_eq!(
+            Theme::preset(Preset::Dark).ansi(&label),
+            "\x1b[1mdev\x1b[0m \x1b[36m[us-west-2]\x1b[0m"
+        );
+        assert_eq!(Theme::default().ansi(&label), "dev [us-west-2]");
+
+        let settings = ThemeSettings {
+            region: Some("bold 208".parse().unwrap()),
+            ..ThemeSettings::default()
+        };
+        assert_eq!(
+            Theme::new(&settings, false).ansi(&label),
+            "\x1b[1mdev\x1b[0m \x1b[1;38;5;208m[us-west-2]\x1b[0m"
+        );
+        assert_eq!(Theme::new(&settings, true), Theme::default());
+
+        label.protected = true;
+        assert_eq!(
+            Theme::preset(Preset::Dark).ansi(&label),
+            "\x1b[1;91mdev\x1b[0m \x1b[36m[us-west-2]\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_line_merges_spans_and_marks_matches() {
+        let mut label = Label::default();
+        label.push(Some(Field::Name), "dev");
+        label.push(None, " x");
+        label.add_tag(Tag::Active, "(active)");
+
+        let theme = Theme::default();
+        let line = theme.line(&label, &[0, 1]);
+        let spans: Vec<(&str, Style)> = line
+            .spans
+            .iter()
+            .map(|span| (span.content.as_ref(), span.style))
+            .collect();
+        assert_eq!(
+            spans,
+            [
+                ("de", theme.matched),
+                ("v", Style::new()),
+                (" (active)", theme.active),
+                (" x", Style::new()),
+            ]
+        );
+    }
+}
@@ -0,0 +1,1372 @@
+//! Full-screen profile browser: a filtered list on the left and the
+//! highlighted profile's details on the right.
+//!
+//! Everything is drawn on stderr so stdout stays free for the shell command
+//! printed in `-c` mode.
+
+use crate::accounts::redact;
+use crate::clipboard::copy;
+use crate::columns::Columns;
+use crate::config::{AwsConfig, ConfigPaths, Profile, SECRET_ATTRIBUTES, command_program};
+use crate::dry_run::read_only;
+use crate::filter::Query;
+use crate::keys::KeySettings;
+use crate::matcher::{MatchMode, Matcher};
+use crate::regions::is_known_region;
+use crate::shell::shell_command;
+use crate::sso::{SsoTarget, read_cached_token};
+use crate::state::{SelectorPosition, SelectorState};
+use crate::store::ProfileStore;
+use crate::tasks::Update;
+use crate::template::{DisplayFormat, Label};
+use crate::theme::Theme;
+use crate::ui::{FAVORITE_MARKER, Markers, profile_label};
+use anyhow::Result;
+use chrono::Local;
+use ratatui::Frame;
+use ratatui::Terminal;
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    EnterAlternateScreen, LeaveAlternateScreen, disable_raw_mode, enable_raw_mode,
+};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style, Stylize};
+use ratatui::text::{Line, Span};
+use ratatui::widgets::{Block, List, ListState, Paragraph, Wrap};
+use std::collections::HashMap;
+use std::io::{Stderr, stderr};
+use std::sync::mpsc::{Receiver, TryRecvError};
+use std::time::Duration;
+
+const PAGE: usize = 10;
+
+const UPDATE_POLL_INTERVAL: Duration = Duration::from_millis(100);
+
+/// The attributes the edit key can change, in the order Tab goes through
+/// them.
+const EDITABLE: [&str; 2] = ["region", "output"];
+
+/// Runs the browser and returns the chosen profile name, or `None` when the
+/// user backs out. The highlight starts on the active profile when it is
+/// listed. Without a starting `filter`, the filter and highlight the browser
+/// was last closed with in this scope come back, unless the active profile
+/// changed some other way since.
+/// Favorites toggled with Ctrl-F and profiles hidden with Ctrl-X (or the
+/// `keys` bound to them) are saved to `selector_state` as they change, and
+/// a `region` or `output` changed with Ctrl-E to the files at `paths`.
+/// Ctrl-Y copies the command that selects the highlighted profile.
+/// Background results from `updates` (see [`crate::tasks`]) are shown as
+/// they arrive, as is the config each time `reloads` sends it again, and
+/// `filter` starts out typed. With `vim`, keys move the highlight until `/`
+/// starts filtering.
+#[allow(clippy::too_many_arguments)]
+pub fn select_profile(
+    config: &AwsConfig,
+    match_mode: MatchMode,
+    display_format: Option<&DisplayFormat>,
+    theme: Theme,
+    markers: &Markers,
+    updates: Receiver<Update>,
+    reloads: Receiver<AwsConfig>,
+    selector_state: SelectorState,
+    filter: &str,
+    vim: bool,
+    keys: &KeySettings,
+    paths: &ConfigPaths,
+) -> Result<Option<String>> {
+    if config.profiles.is_empty() {
+        return Ok(None);
+    }
+
+    let last = match filter.is_empty() {
+        true => selector_state
+            .last_selector(markers.active.as_deref())
+            .cloned(),
+        false => None,
+    };
+    let mut browser = Browser::new(config, match_mode, display_format, theme, selector_state)
+        .with_markers(markers)
+        .with_filter(last.as_ref().map_or(filter, |last| &last.filter))
+        .with_highlight(last.and_then(|last| last.highlighted).as_deref())
+        .with_vim(vim)
+        .with_keys(keys)
+        .with_paths(paths)
+        .with_updates(updates)
+        .with_reloads(reloads);
+
+    let chosen = {
+        let _guard = TerminalGuard::enter()?;
+        let mut terminal = Terminal::new(CrosstermBackend::new(stderr()))?;
+        browser.run(&mut terminal)?
+    };
+    let position = SelectorPosition {
+        filter: browser.filter.clone(),
+        highlighted: browser.selected().map(|profile| profile.name.clone()),
+        active: chosen.clone().or_else(|| markers.active.clone()),
+    };
+    if !read_only() {
+        SelectorState::update(|state| state.remember_selector(position))?;
+    }
+    Ok(chosen)
+}
+
+/// Puts the terminal into raw mode on an alternate screen and restores it
+/// when dropped, including when the browser returns early with an error.
+struct TerminalGuard;
+
+impl TerminalGuard {
+    fn enter() -> Result<Self> {
+        enable_raw_mode()?;
+        if let Err(e) = execute!(stderr(), EnterAlternateScreen) {
+            let _ = disable_raw_mode();
+            return Err(e.into());
+        }
+        Ok(Self)
+    }
+}
+
+impl Drop for TerminalGuard {
+    fn drop(&mut self) {
+        let _ = disable_raw_mode();
+        let _ = execute!(stderr(), LeaveAlternateScreen);
+    }
+}
+
+struct Browser {
+    config: AwsConfig,
+    /// Profile lines, one per profile.
+    labels: Vec<Label>,
+    /// The same lines as plain text, which the filter matches against.
+    plain: Vec<String>,
+    display_format: Option<DisplayFormat>,
+    matcher: Matcher,
+    theme: Theme,
+    /// What the labels are tagged with, updated as background results come
+    /// in.
+    markers: Markers,
+    selector_state: SelectorState,
+    /// Profiles hidden with Ctrl-X during this run.
+    hidden: Vec<String>,
+    filter: String,
+    /// Profiles matching the filter, best first, as indices into
+    /// `config.profiles` with the matched character positions.
+    visible: Vec<(usize, Vec<usize>)>,
+    state: ListState,
+    /// Credential status per profile name, computed when first shown.
+    statuses: HashMap<String, String>,
+    /// Where background results come from, until every task has finished.
+    updates: Option<Receiver<Update>>,
+    /// Where the config comes from again after its files change.
+    reloads: Option<Receiver<AwsConfig>>,
+    /// Vim-style keys: typed letters are commands until `/` starts filtering.
+    vim: bool,
+    /// With `vim`, whether typing goes to the filter.
+    filtering: bool,
+    /// With `vim`, whether the last key was the first `g` of `gg`.
+    pending_g: bool,
+    keys: KeySettings,
+    /// The details take the whole screen, with the list out of the way.
+    expanded: bool,
+    /// The files an edit is written to; editing is off without them.
+    paths: Option<ConfigPaths>,
+    /// The edit prompt, while it is open.
+    editing: Option<AttributeEdit>,
+    /// What the last edit did, shown in place of the help until the next key.
+    notice: Option<String>,
+}
+
+/// A new value being typed for one of the [`EDITABLE`] attributes.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct AttributeEdit {
+    profile: String,
+    key: &'static str,
+    input: String,
+}
+
+impl Browser {
+    fn new(
+        config: &AwsConfig,
+        match_mode: MatchMode,
+        display_format: Option<&DisplayFormat>,
+        theme: Theme,
+        selector_state: SelectorState,
+    ) -> Self {
+        let mut browser = Self {
+            config: config.clone(),
+            labels: Vec::new(),
+            plain: Vec::new(),
+            display_format: display_format.cloned(),
+            matcher: Matcher::new(match_mode),
+            theme,
+            markers: Markers::default(),
+            selector_state,
+            hidden: Vec::new(),
+            filter: String::new(),
+            visible: Vec::new(),
+            state: ListState::default(),
+            statuses: HashMap::new(),
+            updates: None,
+            reloads: None,
+            vim: false,
+            filtering: false,
+            pending_g: false,
+            keys: KeySettings::default(),
+            expanded: false,
+            paths: None,
+            editing: None,
+            notice: None,
+        };
+        browser.relabel();
+        browser
+    }
+
+    fn run(&mut self, terminal: &mut Terminal<CrosstermBackend<Stderr>>) -> Result<Option<String>> {
+        loop {
+            self.receive_updates();
+            self.receive_reloads();
+            self.draw(terminal)?;
+
+            // Wake up regularly while background results or a changed config
+            // may still come in.
+            let waiting = self.updates.is_some() || self.reloads.is_some();
+            if waiting && !event::poll(UPDATE_POLL_INTERVAL)? {
+                continue;
+            }
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match self.handle_key(key) {
+                Action::Continue => {}
+                Action::ToggleDetails => self.expanded = !self.expanded,
+                // Saved as single changes, so edits other processes made
+                // while the browser was open are kept.
+                Action::ToggleFavorite => {
+                    if let Some(name) = self.toggle_favorite() {
+                        let favorite = self.selector_state.is_favorite(&name);
+                        SelectorState::update(|state| match favorite {
+                            true => state.add_favorite(&name),
+                            false => state.remove_favorite(&name),
+                        })?;
+                    }
+                }
+                Action::Hide => {
+                    if let Some(name) = self.hide_selected() {
+                        SelectorState::update(|state| state.add_hidden(&name))?;
+                    }
+                }
+                Action::Edit => self.start_edit(),
+                Action::Copy => self.notice = self.copy_selected(),
+                Action::SaveEdit => {
+                    if let Some(edit) = self.editing.take() {
+                        self.notice = Some(self.save_edit(&edit));
+                    }
+                }
+                Action::Cancel => return Ok(None),
+                Action::Select => {
+                    if let Some(profile) = self.selected() {
+                        return Ok(Some(profile.name.clone()));
+                    }
+                }
+            }
+        }
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        if ctrl && key.code == KeyCode::Char('c') {
+            return Action::Cancel;
+        }
+        self.notice = None;
+        if let Some(edit) = &mut self.editing {
+            if key.code == KeyCode::Esc {
+                self.editing = None;
+                return Action::Continue;
+            }
+            return edit.handle_key(key, &self.config);
+        }
+        if self.vim && self.filtering && !ctrl && key.code == KeyCode::Esc {
+            self.filtering = false;
+            return Action::Continue;
+        }
+        let bound = [
+            (self.keys.select, Action::Select),
+            (self.keys.cancel, Action::Cancel),
+            (self.keys.favorite, Action::ToggleFavorite),
+            (self.keys.hide, Action::Hide),
+            (self.keys.details, Action::ToggleDetails),
+            (self.keys.edit, Action::Edit),
+            (self.keys.copy, Action::Copy),
+        ];
+        if let Some((_, action)) = bound.into_iter().find(|(bound, _)| bound.matches(&key)) {
+            return action;
+        }
+        if self.vim
+            && !ctrl
+            && !self.filtering
+            && let Some(action) = self.handle_vim_key(key.code)
+        {
+            return action;
+        }
+
+        match key.code {
+            KeyCode::Up => self.move_by(-1),
+            KeyCode::Char('p') if ctrl => self.move_by(-1),
+            KeyCode::Down => self.move_by(1),
+            KeyCode::Char('n') if ctrl => self.move_by(1),
+            KeyCode::PageUp => self.move_by(-(PAGE as isize)),
+            KeyCode::PageDown => self.move_by(PAGE as isize),
+            KeyCode::Home => self.state.select_first(),
+            KeyCode::End => self.state.select_last(),
+            KeyCode::Backspace => {
+                self.filter.pop();
+                self.apply_filter();
+            }
+            KeyCode::Char('u') if ctrl => {
+                self.filter.clear();
+                self.apply_filter();
+            }
+            KeyCode::Char(c) if !ctrl => {
+                self.filter.push(c);
+                self.apply_filter();
+            }
+            _ => {}
+        }
+
+        Action::Continue
+    }
+
+    /// Keys outside filtering with `vim`: j/k move, gg and G jump to the
+    /// first and last profile, `/` starts filtering (Esc stops), and q
+    /// cancels. Other letters do nothing; `None` leaves the key (Enter,
+    /// arrows, Esc, ...) to the usual handling.
+    fn handle_vim_key(&mut self, code: KeyCode) -> Option<Action> {
+        let pending_g = std::mem::take(&mut self.pending_g);
+        match code {
+            KeyCode::Char('j') => self.move_by(1),
+            KeyCode::Char('k') => self.move_by(-1),
+            KeyCode::Char('g') if pending_g => self.state.select(Some(0)),
+            KeyCode::Char('g') => self.pending_g = true,
+            KeyCode::Char('G') => self.state.select(self.visible.len().checked_sub(1)),
+            KeyCode::Char('/') => self.filtering = true,
+            KeyCode::Char('q') => return Some(Action::Cancel),
+            KeyCode::Char(_) => {}
+            _ => return None,
+        }
+        Some(Action::Continue)
+    }
+
+    /// Rebuilds every profile line from the markers and filters again.
+    fn relabel(&mut self) {
+        self.labels = self
+            .config
+            .profiles
+            .iter()
+            .map(|profile| {
+                let aliases = self.selector_state.aliases_for(&profile.name);
+                let mut label = profile_label(
+                    profile,
+                    &self.config,
+                    &aliases,
+                    self.markers.account_name(profile),
+                    self.markers.last_used(&profile.name),
+                    self.display_format.as_ref(),
+                );
+                self.markers.tag(&mut label, &profile.name);
+                label
+            })
+            .collect();
+        self.plain = self.labels.iter().map(Label::plain).collect();
+        self.apply_filter();
+    }
+
+    /// Tags the profiles in `markers` and moves the highlight to the active
+    /// one, if it is listed.
+    fn with_markers(mut self, markers: &Markers) -> Self {
+        self.markers = markers.clone();
+        self.relabel();
+
+        if let Some(position) = self.visible.iter().position(|(index, _)| {
+            markers.active.as_ref() == Some(&self.config.profiles[*index].name)
+        }) {
+            self.state.select(Some(position));
+        }
+        self
+    }
+
+    /// Moves the highlight to `name`, if it is listed.
+    fn with_highlight(mut self, name: Option<&str>) -> Self {
+        if let Some(position) = self
+            .visible
+            .iter()
+            .position(|(index, _)| name == Some(self.config.profiles[*index].name.as_str()))
+        {
+            self.state.select(Some(position));
+        }
+        self
+    }
+
+    /// Starts with `filter` already typed.
+    fn with_filter(mut self, filter: &str) -> Self {
+        self.filter = filter.to_string();
+        self.apply_filter();
+        self
+    }
+
+    fn with_vim(mut self, vim: bool) -> Self {
+        self.vim = vim;
+        self
+    }
+
+    fn with_keys(mut self, keys: &KeySettings) -> Self {
+        self.keys = *keys;
+        self
+    }
+
+    fn with_paths(mut self, paths: &ConfigPaths) -> Self {
+        self.paths = Some(paths.clone());
+        self
+    }
+
+    fn with_updates(mut self, updates: Receiver<Update>) -> Self {
+        self.updates = Some(updates);
+        self
+    }
+
+    fn with_reloads(mut self, reloads: Receiver<AwsConfig>) -> Self {
+        self.reloads = Some(reloads);
+        self
+    }
+
+    /// Swaps in the newest config `reloads` sent, keeping the filter and the
+    /// highlighted profile when it is still there.
+    fn receive_reloads(&mut self) {
+        let Some(reloads) = &self.reloads else {
+            return;
+        };
+        let Some(config) = reloads.try_iter().last() else {
+            return;
+        };
+
+        let highlighted = self.selected().map(|profile| profile.name.clone());
+        let position = self.state.selected();
+        self.config = config;
+        self.statuses.clear();
+        // The old highlight is an index into the old profiles.
+        self.state.select(None);
+        self.relabel();
+
+        let position = highlighted
+            .and_then(|name| {
+                self.visible
+                    .iter()
+                    .position(|(index, _)| self.config.profiles[*index].name == name)
+            })
+            .or(position.filter(|_| !self.visible.is_empty()))
+            .map(|position| position.min(self.visible.len().saturating_sub(1)));
+        self.state.select(position);
+    }
+
+    /// Whether `--check` results may still arrive.
+    fn checking(&self) -> bool {
+        self.markers.checking && self.updates.is_some()
+    }
+
+    /// Applies the background results that arrived since the last call.
+    fn receive_updates(&mut self) {
+        let Some(receiver) = self.updates.take() else {
+            return;
+        };
+
+        let mut changed = false;
+        let disconnected = loop {
+            match receiver.try_recv() {
+                Ok(update) => {
+                    self.markers.apply(update);
+                    changed = true;
+                }
+                Err(TryRecvError::Empty) => break false,
+                Err(TryRecvError::Disconnected) => break true,
+            }
+        };
+
+        if !disconnected {
+            self.updates = Some(receiver);
+        }
+        if changed {
+            self.relabel();
+        }
+    }
+
+    fn move_by(&mut self, delta: isize) {
+        if self.visible.is_empty() {
+            return;
+        }
+
+        let current = self.state.selected().unwrap_or(0) as isize;
+        let last = self.visible.len() as isize - 1;
+        self.state
+            .select(Some((current + delta).clamp(0, last) as usize));
+    }
+
+    /// Pins or unpins the highlighted profile, which moves it within the
+    /// list, returning its name.
+    fn toggle_favorite(&mut self) -> Option<String> {
+        let name = self.selected()?.name.clone();
+        if !self.selector_state.remove_favorite(&name) {
+            self.selector_state.add_favorite(&name);
+        }
+        self.apply_filter();
+        Some(name)
+    }
+
+    /// Adds the highlighted profile to the hide list and drops it from the
+    /// list, moving the highlight to its neighbour. Returns its name.
+    fn hide_selected(&mut self) -> Option<String> {
+        let name = self.selected()?.name.clone();
+        self.selector_state.add_hidden(&name);
+        self.hidden.push(name.clone());
+
+        let position = self.state.selected().unwrap_or(0);
+        self.apply_filter();
+        if !self.visible.is_empty() {
+            self.state
+                .select(Some(position.min(self.visible.len() - 1)));
+        }
+        Some(name)
+    }
+
+    /// Opens the edit prompt on the highlighted profile's `region`.
+    fn start_edit(&mut self) {
+        if self.paths.is_none() {
+            return;
+        }
+        if let Some(profile) = self.selected() {
+            self.editing = Some(AttributeEdit::new(profile, EDITABLE[0]));
+        }
+    }
+
+    /// Copies the command that selects the highlighted profile, and says how
+    /// that went.
+    fn copy_selected(&self) -> Option<String> {
+        let name = &self.selected()?.name;
+        let what = format!("the command that selects '{name}'");
+        Some(match copy(&shell_command(Some(name)), &what) {
+            Ok(()) => format!("Copied {what}"),
+            Err(e) => format!("Failed to copy: {e:#}"),
+        })
+    }
+
+    /// Writes the edit to the config file and into the listed profile, and
+    /// says how that went.
+    fn save_edit(&mut self, edit: &AttributeEdit) -> String {
+        let Some(paths) = &self.paths else {
+            return "Editing is not available here".to_string();
+        };
+        let value = edit.input.trim();
+        let written = ProfileStore::open(paths.clone()).and_then(|mut store| {
+            store.update_profile(
+                &edit.profile,
+                &[(edit.key, Some(value).filter(|value| !value.is_empty()))],
+            )
+        });
+        match written {
+            Ok(false) => return format!("{} of {} unchanged", edit.key, edit.profile),
+            Err(e) => return format!("Failed to update {}: {e:#}", edit.profile),
+            Ok(true) => {}
+        }
+
+        if let Some(profile) = self
+            .config
+            .profiles
+            .iter_mut()
+            .find(|profile| profile.name == edit.profile)
+        {
+            match value.is_empty() {
+                true => profile.attributes.remove(edit.key),
+                false => profile
+                    .attributes
+                    .insert(edit.key.to_string(), value.to_string()),
+            };
+        }
+        self.statuses.remove(&edit.profile);
+        self.relabel();
+        match value {
+            "" => format!("Removed {} from {}", edit.key, edit.profile),
+            region if edit.key == "region" && !is_known_region(region) => format!(
+                "Set region of {} to {region}, which is not a known region",
+                edit.profile
+            ),
+            _ => format!("Set {} of {} to {value}", edit.key, edit.profile),
+        }
+    }
+
+    /// Recomputes the visible profiles, keeping the highlighted one when it
+    /// still matches.
+    fn apply_filter(&mut self) {
+        let highlighted = self.selected().map(|profile| profile.name.clone());
+        let query = Query::parse(&self.filter);
+
+        let mut scored: Vec<(bool, i64, usize, Vec<usize>)> = self
+            .plain
+            .iter()
+            .enumerate()
+            .filter(|(index, _)| {
+                let profile = &self.config.profiles[*index];
+                query.matches(profile, &self.markers.annotations)
+                    && !self.hidden.contains(&profile.name)
+            })
+            .filter_map(|(index, label)| {
+                let found = self.matcher.matches(label, &query.text)?;
+                let favorite = self
+                    .selector_state
+                    .is_favorite(&self.config.profiles[index].name);
+                Some((favorite, found.score, index, found.indices))
+            })
+            .collect();
+        // Favorites first, then by score. Stable, so ties keep the config
+        // order.
+        scored.sort_by_key(|(favorite, score, _, _)| std::cmp::Reverse((*favorite, *score)));
+        self.visible = scored
+            .into_iter()
+            .map(|(_, _, index, indices)| (index, indices))
+            .collect();
+
+        let position = highlighted
+            .and_then(|name| {
+                self.visible
+                    .iter()
+                    .position(|(index, _)| self.config.profiles[*index].name == name)
+            })
+            .or((!self.visible.is_empty()).then_some(0));
+        self.state.select(position);
+    }
+
+    fn selected(&self) -> Option<&Profile> {
+        let (index, _) = self.visible.get(self.state.selected()?)?;
+        Some(&self.config.profiles[*index])
+    }
+
+    fn draw(&mut self, terminal: &mut Terminal<CrosstermBackend<Stderr>>) -> Result<()> {
+        if let Some(profile) = self.selected()
+            && !self.statuses.contains_key(&profile.name)
+        {
+            let status = credential_status(&self.config, profile);
+            self.statuses.insert(profile.name.clone(), status);
+        }
+
+        terminal.draw(|frame| self.render(frame))?;
+        Ok(())
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let [filter_area, main_area, help_area] = Layout::vertical([
+            Constraint::Length(1),
+            Constraint::Min(0),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let list_width = if self.expanded { 0 } else { 40 };
+        let [list_area, details_area] = Layout::horizontal([
+            Constraint::Percentage(list_width),
+            Constraint::Percentage(100 - list_width),
+        ])
+        .areas(main_area);
+
+        let prompt = match &self.editing {
+            Some(edit) => Line::from(vec![
+                Span::raw(format!("{} of {}: ", edit.key, edit.profile)).bold(),
+                Span::raw(edit.input.as_str()),
+            ]),
+            None => Line::from(vec![
+                Span::raw("Filter: ").bold(),
+                Span::raw(self.filter.as_str()),
+            ]),
+        };
+        frame.render_widget(Paragraph::new(prompt), filter_area);
+
+        // The borders, the highlight symbol, and the favorite marker.
+        let columns = self
+            .display_format
+            .is_none()
+            .then(|| Columns::new(&self.labels, usize::from(list_area.width).saturating_sub(6)));
+        let items: Vec<Line> = self
+            .visible
+            .iter()
+            .map(|(index, indices)| {
+                let name = &self.config.profiles[*index].name;
+                let mut line = match &columns {
+                    Some(columns) => {
+                        let (label, indices) = columns.fit(&self.labels[*index], indices);
+                        self.theme.line(&label, &indices)
+                    }
+                    None => self.theme.line(&self.labels[*index], indices),
+                };
+                if self.selector_state.is_favorite(name) {
+                    line.spans.insert(
+                        0,
+                        Span::styled(format!("{FAVORITE_MARKER} "), self.theme.marker()),
+                    );
+                }
+                line
+            })
+            .collect();
+        let mut title = format!(
+            " Profiles ({}/{}) ",
+            self.visible.len(),
+            self.config.profiles.len()
+        );
+        if self.checking() {
+            title.push_str(&format!(
+                "· checked {}/{} ",
+                self.markers.checks.len(),
+                self.config.profiles.len()
+            ));
+        }
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, list_area, &mut self.state);
+
+        let details = match self.selected() {
+            Some(profile) => {
+                let status = self.statuses.get(&profile.name).map(String::as_str);
+                let identity = match self.markers.checks.get(&profile.name) {
+                    Some(result) => Some(format!("{} {}", result.symbol(), result.detail())),
+                    None => self.checking().then(|| "checking…".to_string()),
+                };
+                let mut lines = detail_lines(&self.config, profile, status, identity);
+                if self.markers.redact {
+                    for span in lines.iter_mut().flat_map(|line| line.spans.iter_mut()) {
+                        span.content = redact(&span.content).into();
+                    }
+                }
+                lines
+            }
+            None => vec![Line::from("No profiles match the filter")],
+        };
+        frame.render_widget(
+            Paragraph::new(details)
+                .block(Block::bordered().title(" Details "))
+                .wrap(Wrap { trim: false }),
+            details_area,
+        );
+
+        let KeySettings {
+            select,
+            cancel,
+            favorite,
+            hide,
+            details,
+            edit,
+            copy,
+        } = self.keys;
+        let help = match (self.vim, self.filtering) {
+            _ if self.editing.is_some() => {
+                "tab region/output · enter save · esc cancel · empty removes it".to_string()
+            }
+            _ if self.notice.is_some() => self.notice.clone().unwrap_or_default(),
+            (false, _) => format!(
+                "↑↓/PgUp/PgDn move · type to filter · {favorite} favorite · {hide} hide · {edit} edit · {copy} copy · {details} details · {select} select · {cancel} cancel"
+            ),
+            (true, false) => format!(
+                "j/k move · gg/G first/last · / filter · {favorite} favorite · {hide} hide · {edit} edit · {copy} copy · {details} details · {select} select · q/{cancel} cancel"
+            ),
+            (true, true) => format!("type to filter · esc stop filtering · {select} select"),
+        };
+        frame.render_widget(Paragraph::new(help).dim(), help_area);
+    }
+}
+
+enum Action {
+    Continue,
+    ToggleDetails,
+    ToggleFavorite,
+    Hide,
+    Edit,
+    SaveEdit,
+    Copy,
+    Cancel,
+    Select,
+}
+
+impl AttributeEdit {
+    /// Starts out with the profile's current value.
+    fn new(profile: &Profile, key: &'static str) -> Self {
+        Self {
+            profile: profile.name.clone(),
+            key,
+            input: profile.attributes.get(key).cloned().unwrap_or_default(),
+        }
+    }
+
+    /// Enter saves and Tab goes on to the next attribute, dropping what was
+    /// typed. Esc is left to the browser, which closes the prompt.
+    fn handle_key(&mut self, key: KeyEvent, config: &AwsConfig) -> Action {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => return Action::SaveEdit,
+            KeyCode::Tab => {
+                let next = EDITABLE
+                    .iter()
+                    .position(|key| *key == self.key)
+                    .map_or(0, |index| (index + 1) % EDITABLE.len());
+                if let Some(profile) = config.find_profile(&self.profile) {
+                    *self = Self::new(profile, EDITABLE[next]);
+                }
+            }
+            KeyCode::Backspace => {
+                self.input.pop();
+            }
+            KeyCode::Char('u') if ctrl => self.input.clear(),
+            KeyCode::Char(c) if !ctrl => self.input.push(c),
+            _ => {}
+        }
+        Action::Continue
+    }
+}
+
+/// The details pane as plain text, for `show`.
+pub fn details_text(config: &AwsConfig, profile: &Profile) -> Vec<String> {
+    let status = credential_status(config, profile);
+    detail_lines(config, profile, Some(&status), None)
+        .iter()
+        .map(|line| {
+            line.spans
+                .iter()
+                .map(|span| span.content.as_ref())
+                .collect()
+        })
+        .collect()
+}
+
+fn detail_lines<'a>(
+    config: &'a AwsConfig,
+    profile: &'a Profile,
+    status: Option<&'a str>,
+    identity: Option<String>,
+) -> Vec<Line<'a>> {
+    let heading = |text: &'a str| Line::from(text.bold());
+    let entry =
+        |key: String, value: String| Line::from(vec![format!("{key}: ").dim(), value.into()]);
+
+    let endpoints = config.endpoint_overrides(profile);
+    let mut title = heading(profile.name.as_str());
+    if !endpoints.is_empty() {
+        title.push_span(" (endpoint override)".bold());
+    }
+    let mut lines = vec![title, Line::default()];
+
+    let chain = config.role_chain(&profile.name);
+    let has_status = status.is_some() || identity.is_some() || chain.is_some();
+    if let Some(status) = status {
+        lines.push(entry("credentials".to_string(), status.to_string()));
+    }
+    if let Some(chain) = chain {
+        lines.push(entry("chain".to_string(), chain.to_string()));
+    }
+    if let Some(identity) = identity {
+        lines.push(entry("identity".to_string(), identity));
+    }
+    if has_status {
+        lines.push(Line::default());
+    }
+
+    lines.push(heading("Attributes"));
+    for (key, value) in profile_attributes(profile) {
+        lines.push(entry(format!("  {key}"), value));
+    }
+
+    let mut subsections: Vec<_> = profile.subsections.iter().collect();
+    subsections.sort_by_key(|(name, _)| name.as_str());
+    for (name, values) in subsections {
+        lines.push(entry(format!("  {name}"), String::new()));
+        let mut values: Vec<_> = values.iter().collect();
+        values.sort();
+        for (key, value) in values {
+            lines.push(entry(format!("    {key}"), value.clone()));
+        }
+    }
+
+    if let Some(session) = config.sso_session_for(profile) {
+        lines.push(Line::default());
+        lines.push(Line::from(format!("sso-session {}", session.name).bold()));
+        let mut attributes: Vec<_> = session.attributes.iter().collect();
+        attributes.sort();
+        for (key, value) in attributes {
+            lines.push(entry(format!("  {key}"), value.clone()));
+        }
+    }
+
+    if !endpoints.is_empty() {
+        lines.push(Line::default());
+        let title = match config.services_for(profile) {
+            Some(services) => format!("Endpoints (services {})", services.name),
+            None => "Endpoints".to_string(),
+        };
+        lines.push(Line::from(title.bold()));
+        for endpoint in endpoints {
+            let service = endpoint.service.as_deref().unwrap_or("all services");
+            lines.push(entry(format!("  {service}"), endpoint.url));
+        }
+    }
+
+    lines
+}
+
+/// The profile's attributes sorted by key, with secrets masked.
+fn profile_attributes(profile: &Profile) -> Vec<(String, String)> {
+    let mut attributes: Vec<(String, String)> = profile
+        .attributes
+        .iter()
+        .map(|(key, value)| {
+            let value = if SECRET_ATTRIBUTES.contains(&key.as_str()) {
+                "********".to_string()
+            } else {
+                value.clone()
+            };
+            (key.clone(), value)
+        })
+        .collect();
+    attributes.sort();
+    attributes
+}
+
+/// A one-line summary of where the profile's credentials come from and,
+/// for SSO, whether the cached token is still valid. A token file or
+/// `credential_process` program that isn't there is shown instead.
+fn credential_status(config: &AwsConfig, profile: &Profile) -> String {
+    if let Some(target) = SsoTarget::for_profile(config, profile) {
+        return match read_cached_token(&target) {
+            Ok(Some(token)) => {
+                let expires_at = token
+                    .expires_at
+                    .with_timezone(&Local)
+                    .format("%Y-%m-%d %H:%M");
+                if token.is_expired() {
+                    format!("SSO token expired at {expires_at}")
+                } else {
+                    format!("SSO token valid until {expires_at}")
+                }
+            }
+            Ok(None) => "Not logged in to SSO".to_string(),
+            Err(e) => format!("Unable to read the SSO token cache: {e}"),
+        };
+    }
+
+    let attribute = |key: &str| profile.attributes.get(key);
+
+    if let Some(problem) = profile.credential_problem() {
+        problem
+    } else if let (Some(role_arn), Some(token_file)) =
+        (attribute("role_arn"), attribute("web_identity_token_file"))
+    {
+        format!("Assumes {role_arn} with the web identity token in {token_file}")
+    } else if let Some(role_arn) = attribute("role_arn") {
+        let source = attribute("source_profile")
+            .or(attribute("credential_source"))
+            .map_or(String::new(), |source| format!(" via {source}"));
+        format!("Assumes {role_arn}{source}")
+    } else if let Some(process) = attribute("credential_process") {
+        let program = command_program(process).unwrap_or_default();
+        format!("Provided by credential_process ({program})")
+    } else if attribute("aws_session_token").is_some() {
+        "Temporary access keys".to_string()
+    } else if attribute("aws_access_key_id").is_some() {
+        "Static access keys".to_string()
+    } else {
+        "No credentials configured".to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::check::CheckResult;
+    use crate::test_support::temp_dir;
+    use crate::test_support::{config, profile};
+
+    #[test]
+    fn test_filter_keeps_highlighted_profile() {
+        let config = config(vec![
+            profile("dev", &[("region", "us-east-1")]),
+            profile("prod", &[("region", "eu-west-1")]),
+            profile("prod-read", &[("region", "eu-west-1")]),
+        ]);
+        let mut browser = Browser::new(
+            &config,
+            MatchMode::Exact,
+            None,
+            Theme::default(),
+            SelectorState::default(),
+        );
+        browser.move_by(2);
+        assert_eq!(browser.selected().unwrap().name, "prod-read");
+
+        browser.filter = "eu-west".to_string();
+        browser.apply_filter();
+        assert_eq!(
+            browser
+                .visible
+                .iter()
+                .map(|(index, _)| *index)
+                .collect::<Vec<_>>(),
+            vec![1, 2]
+        );
+        assert_eq!(browser.selected().unwrap().name, "prod-read");
+
+        browser.filter = "nothing".to_string();
+        browser.apply_filter();
+        assert!(browser.selected().is_none());
+    }
+
+    #[test]
+    fn test_vim_keys() {
+        let mut browser = Browser::new(
+            &config(vec![
+                profile("dev", &[]),
+                profile("prod", &[]),
+                profile("prod-read", &[]),
+            ]),
+            MatchMode::Exact,
+            None,
+            Theme::default(),
+            SelectorState::default(),
+        )
+        .with_vim(true);
+        let mut press = |code| browser.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+
+        assert!(matches!(press(KeyCode::Char('j')), Action::Continue));
+        assert!(matches!(press(KeyCode::Char('G')), Action::Continue));
+        press(KeyCode::Char('g'));
+        press(KeyCode::Char('x'));
+        assert_eq!(browser.selected().unwrap().name, "prod-read");
+
+        let mut press = |code| browser.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        press(KeyCode::Char('g'));
+        press(KeyCode::Char('g'));
+        assert_eq!(browser.selected().unwrap().name, "dev");
+        assert_eq!(browser.filter, "");
+
+        let mut press = |code| browser.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        press(KeyCode::Char('/'));
+        press(KeyCode::Char('r'));
+        press(KeyCode::Char('e'));
+        assert!(matches!(press(KeyCode::Esc), Action::Continue));
+        press(KeyCode::Char('j'));
+        assert_eq!(browser.filter, "re");
+        assert_eq!(browser.selected().unwrap().name, "prod-read");
+
+        let mut press = |code| browser.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+        assert!(matches!(press(KeyCode::Char('q')), Action::Cancel));
+    }
+
+    #[test]
+    fn test_bound_keys() {
+        let keys = KeySettings {
+            select: "tab".parse().unwrap(),
+            cancel: "q".parse().unwrap(),
+            ..KeySettings::default()
+        };
+        let mut browser = Browser::new(
+            &config(vec![profile("dev", &[]), profile("prod", &[])]),
+            MatchMode::Exact,
+            None,
+            Theme::default(),
+            SelectorState::default(),
+        )
+        .with_keys(&keys);
+        let mut press = |code| browser.handle_key(KeyEvent::new(code, KeyModifiers::NONE));
+
+        assert!(matches!(press(KeyCode::Tab), Action::Select));
+        assert!(matches!(press(KeyCode::Char('q')), Action::Cancel));
+        assert!(matches!(press(KeyCode::Enter), Action::Continue));
+        assert!(matches!(press(KeyCode::Esc), Action::Continue));
+        let mut ctrl =
+            |c| browser.handle_key(KeyEvent::new(KeyCode::Char(c), KeyModifiers::CONTROL));
+        assert!(matches!(ctrl('c'), Action::Cancel));
+        assert!(matches!(ctrl('o'), Action::ToggleDetails));
+        assert_eq!(browser.filter, "");
+    }
+
+    #[test]
+    fn test_reload_keeps_filter_and_highlight() {
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut browser = Browser::new(
+            &config(vec![profile("dev", &[]), profile("prod", &[])]),
+            MatchMode::Exact,
+            None,
+            Theme::default(),
+            SelectorState::default(),
+        )
+        .with_filter("pro")
+        .with_reloads(receiver);
+        assert_eq!(browser.selected().unwrap().name, "prod");
+
+        sender
+            .send(config(vec![
+                profile("dev", &[]),
+                profile("preprod", &[]),
+                profile("prod", &[]),
+            ]))
+            .unwrap();
+        browser.receive_reloads();
+        assert_eq!(browser.filter, "pro");
+        assert_eq!(browser.visible.len(), 2);
+        assert_eq!(browser.selected().unwrap().name, "prod");
+        assert_eq!(browser.config.profiles.len(), 3);
+    }
+
+    #[test]
+    fn test_edit_key_writes_the_attribute() {
+        let root = temp_dir("tui");
+        let paths = ConfigPaths {
+            config: root.join("config"),
+            credentials: root.join("credentials"),
+        };
+        std::fs::write(
+            &paths.config,
+            "# work\n[profile dev]\nregion = us-east-1\noutput = json\n",
+        )
+        .unwrap();
+
+        let mut browser = Browser::new(
+            &crate::config::read_aws_config(&paths).unwrap(),
+            MatchMode::Exact,
+            None,
+            Theme::default(),
+            SelectorState::default(),
+        )
+        .with_paths(&paths);
+        // What `run` does with the edit actions.
+        let press = |browser: &mut Browser, code, modifiers| {
+            let action = browser.handle_key(KeyEvent::new(code, modifiers));
+            if matches!(action, Action::Edit) {
+                browser.start_edit();
+            }
+            if matches!(action, Action::SaveEdit) {
+                let edit = browser.editing.take().unwrap();
+                browser.notice = Some(browser.save_edit(&edit));
+            }
+            action
+        };
+
+        press(&mut browser, KeyCode::Char('e'), KeyModifiers::CONTROL);
+        press(&mut browser, KeyCode::Char('u'), KeyModifiers::CONTROL);
+        for c in "eu-west-1".chars() {
+            press(&mut browser, KeyCode::Char(c), KeyModifiers::NONE);
+        }
+        assert!(matches!(
+            press(&mut browser, KeyCode::Enter, KeyModifiers::NONE),
+            Action::SaveEdit
+        ));
+        assert_eq!(
+            browser.notice.as_deref(),
+            Some("Set region of dev to eu-west-1")
+        );
+
+        press(&mut browser, KeyCode::Char('e'), KeyModifiers::CONTROL);
+        press(&mut browser, KeyCode::Tab, KeyModifiers::NONE);
+        assert_eq!(browser.editing.as_ref().unwrap().input, "json");
+        press(&mut browser, KeyCode::Char('u'), KeyModifiers::CONTROL);
+        press(&mut browser, KeyCode::Enter, KeyModifiers::NONE);
+        assert_eq!(browser.notice.as_deref(), Some("Removed output from dev"));
+
+        press(&mut browser, KeyCode::Char('e'), KeyModifiers::CONTROL);
+        press(&mut browser, KeyCode::Char('x'), KeyModifiers::NONE);
+        press(&mut browser, KeyCode::Esc, KeyModifiers::NONE);
+        assert!(browser.editing.is_none());
+        assert_eq!(browser.filter, "");
+
+        assert_eq!(
+            std::fs::read_to_string(&paths.config).unwrap(),
+            "# work\n[profile dev]\nregion = eu-west-1\n"
+        );
+        assert_eq!(browser.selected().unwrap().get_region(), Some("eu-west-1"));
+    }
+
+    #[test]
+    fn test_favorites_are_pinned_to_the_top() {
+        let config = config(vec![
+            profile("dev", &[]),
+            profile("prod", &[]),
+            profile("sandbox", &[]),
+        ]);
+        let mut state = SelectorState::default();
+        state.add_favorite("sandbox");
+        let mut browser = Browser::new(&config, MatchMode::Fuzzy, None, Theme::default(), state);
+        let order = |browser: &Browser| -> Vec<usize> {
+            browser.visible.iter().map(|(index, _)| *index).collect()
+        };
+        assert_eq!(order(&browser), vec![2, 0, 1]);
+
+        browser.move_by(2);
+        browser.toggle_favorite();
+        assert_eq!(order(&browser), vec![1, 2, 0]);
+        assert_eq!(browser.selected().unwrap().name, "prod");
+        assert_eq!(browser.selector_state.favorites, vec!["sandbox", "prod"]);
+    }
+
+    #[test]
+    fn test_hide_drops_profile_and_keeps_position() {
+        let config = config(vec![
+            profile("dev", &[]),
+            profile("generated-1", &[]),
+            profile("prod", &[]),
+        ]);
+        let mut browser = Browser::new(
+            &config,
+            MatchMode::Fuzzy,
+            None,
+            Theme::default(),
+            SelectorState::default(),
+        );
+        browser.move_by(1);
+        browser.hide_selected();
+
+        assert_eq!(browser.visible.len(), 2);
+        assert_eq!(browser.selected().unwrap().name, "prod");
+        assert!(browser.selector_state.is_hidden("generated-1"));
+    }
+
+    #[test]
+    fn test_starts_on_active_profile() {
+        let config = config(vec![
+            profile("dev", &[]),
+            profile("prod", &[]),
+            profile("sandbox", &[]),
+        ]);
+        let browser = Browser::new(
+            &config,
+            MatchMode::Fuzzy,
+            None,
+            Theme::default(),
+            SelectorState::default(),
+        )
+        .with_markers(&Markers {
+            active: Some("prod".to_string()),
+            ..Markers::default()
+        });
+        assert_eq!(browser.selected().unwrap().name, "prod");
+
+        let browser = Browser::new(
+            &config,
+            MatchMode::Fuzzy,
+            None,
+            Theme::default(),
+            SelectorState::default(),
+        )
+        .with_markers(&Markers {
+            active: Some("deleted".to_string()),
+            ..Markers::default()
+        });
+        assert_eq!(browser.selected().unwrap().name, "dev");
+
+        let browser = browser.with_filter("o").with_highlight(Some("sandbox"));
+        assert_eq!(browser.selected().unwrap().name, "sandbox");
+    }
+
+    #[test]
+    fn test_check_results_tag_profiles_as_they_arrive() {
+        let config = config(vec![profile("dev", &[]), profile("prod", &[])]);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut browser = Browser::new(
+            &config,
+            MatchMode::Fuzzy,
+            None,
+            Theme::default(),
+            SelectorState::default(),
+        )
+        .with_updates(receiver);
+
+        sender
+            .send(Update::Checked {
+                profile_name: "prod".to_string(),
+                result: CheckResult::Timeout,
+            })
+            .unwrap();
+        browser.receive_updates();
+        assert!(browser.updates.is_some());
+        assert_eq!(browser.labels[1].parts.last().unwrap().text, "timeout");
+        assert_eq!(browser.labels[1].plain(), browser.plain[1]);
+
+        drop(sender);
+        browser.receive_updates();
+        assert!(browser.updates.is_none());
+        assert_eq!(browser.markers.checks.len(), 1);
+    }
+
+    #[test]
+    fn test_list_shows_before_token_expiries_arrive() {
+        let config = config(vec![profile("dev", &[]), profile("prod", &[])]);
+        let (sender, receiver) = std::sync::mpsc::channel();
+        let mut browser = Browser::new(
+            &config,
+            MatchMode::Fuzzy,
+            None,
+            Theme::default(),
+            SelectorState::default(),
+        )
+        .with_updates(receiver);
+        let logged_out = |browser: &Browser| {
+            browser.labels[1]
+                .parts
+                .iter()
+                .any(|part| part.text == "(not logged in)")
+        };
+        assert!(!logged_out(&browser));
+        assert!(!browser.checking());
+
+        sender
+            .send(Update::TokenExpiries(HashMap::from([(
+                "prod".to_string(),
+                None,
+            )])))
+            .unwrap();
+        drop(sender);
+        browser.receive_updates();
+        assert!(logged_out(&browser));
+        assert!(browser.updates.is_none());
+    }
+
+    #[test]
+    fn test_credential_status_and_masked_attributes() {
+        let static_keys = profile(
+            "static",
+            &[
+                ("aws_access_key_id", "AKIA"),
+                ("aws_secret_access_key", "secret"),
+            ],
+        );
+        let assume = profile(
+            "admin",
+            &[
+                ("role_arn", "arn:aws:iam::123456789012:role/Admin"),
+                ("source_profile", "static"),
+            ],
+        );
+        let config = config(vec![static_keys.clone(), assume.clone()]);
+
+        assert_eq!(
+            credential_status(&config, &static_keys),
+            "Static access keys"
+        );
+        assert_eq!(
+            credential_status(&config, &assume),
+            "Assumes arn:aws:iam::123456789012:role/Admin via static"
+        );
+        assert_eq!(
+            profile_attributes(&static_keys),
+            vec![
+                ("aws_access_key_id".to_string(), "AKIA".to_string()),
+                ("aws_secret_access_key".to_string(), "********".to_string()),
+            ]
+        );
+    }
+}
@@ -1,36 +1,337 @@
-use crate::config::Profile;
+use crate::accounts::{AccountNames, redact};
+use crate::check::CheckResult;
+use crate::columns::Columns;
+use crate::config::{AwsConfig, ChainProblem, Profile, ProfileConflict};
+use crate::environment::now;
+use crate::error::Error;
+use crate::filter::{Annotations, Query, glob_match};
+use crate::matcher::{MatchMode, Matcher};
+use crate::sso::describe_expiry;
+use crate::state::SelectorState;
+use crate::tasks::Update;
+use crate::template::{DisplayFormat, Field, Label, Tag};
+use crate::theme::{Theme, paint};
 use anyhow::Result;
-use inquire::{InquireError, Select};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use inquire::{InquireError, MultiSelect, Select};
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{BufRead, IsTerminal, Write};
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ACCESSIBLE: AtomicBool = AtomicBool::new(false);
+
+pub fn set_accessible(enabled: bool) {
+    ACCESSIBLE.store(enabled, Ordering::Relaxed);
+}
+
+/// `--accessible`, the `accessible` setting, or `TERM=dumb`: pick from
+/// plain numbered lists instead of prompts that move the cursor around, for
+/// screen readers and terminals that can't.
+pub fn accessible() -> bool {
+    ACCESSIBLE.load(Ordering::Relaxed)
+        || std::env::var_os("TERM").is_some_and(|term| term == "dumb")
+}
+
+/// Unwraps a prompt answer, turning Esc/Ctrl-C into a plain "Cancelled"
+/// error (exit status 2) instead of inquire's wording.
+pub fn answer<T>(result: Result<T, InquireError>) -> Result<T> {
+    match result {
+        Ok(value) => Ok(value),
+        Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => {
+            Err(Error::cancelled().into())
+        }
+        Err(e) => Err(anyhow::anyhow!("Prompt failed: {}", e)),
+    }
+}
+
+/// The terminal's width in characters, or no limit when it can't be told.
+pub fn terminal_width() -> usize {
+    ratatui::crossterm::terminal::size().map_or(usize::MAX, |(width, _)| usize::from(width))
+}
+
+/// Whether the inline prompt can be drawn: it reads keys from stdin and
+/// draws on stderr.
+pub fn has_terminal() -> bool {
+    std::io::stdin().is_terminal() && std::io::stderr().is_terminal()
+}
+
+/// Whether to draw the inline prompt rather than a [`numbered_choice`].
+pub fn draws_prompts() -> bool {
+    has_terminal() && !accessible()
+}
+
+/// Picks one of `options` with the inline prompt, or by number when it
+/// isn't drawn. Esc or an empty answer cancels.
+pub fn choose<T: std::fmt::Display>(prompt: &str, mut options: Vec<T>) -> Result<T> {
+    if draws_prompts() {
+        return answer(Select::new(&format!("{prompt}:"), options).prompt());
+    }
+    let lines: Vec<String> = options.iter().map(T::to_string).collect();
+    match numbered_choice(prompt, &lines)? {
+        Some(index) => Ok(options.swap_remove(index)),
+        None => Err(Error::cancelled().into()),
+    }
+}
+
+/// Stands in for the inline prompt when there is no terminal to draw it on
+/// (a dumb SSH session, another program's pipe): lists `options` numbered on
+/// stderr and reads the chosen number from stdin. An empty answer or the end
+/// of input picks nothing.
+pub fn numbered_choice(prompt: &str, options: &[String]) -> Result<Option<usize>> {
+    read_numbered_choice(prompt, options, std::io::stdin().lock(), std::io::stderr())
+}
+
+fn read_numbered_choice(
+    prompt: &str,
+    options: &[String],
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> Result<Option<usize>> {
+    let width = options.len().to_string().len();
+    for (index, option) in options.iter().enumerate() {
+        writeln!(output, "{:>width$}) {option}", index + 1)?;
+    }
+    loop {
+        write!(output, "{prompt} [1-{}]: ", options.len())?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            writeln!(output)?;
+            return Ok(None);
+        }
+        let answer = line.trim();
+        if answer.is_empty() {
+            return Ok(None);
+        }
+        match answer.parse::<usize>() {
+            Ok(number) if (1..=options.len()).contains(&number) => return Ok(Some(number - 1)),
+            _ => writeln!(output, "Enter a number from 1 to {}", options.len())?,
+        }
+    }
+}
+
+/// [`numbered_choice`] for several: numbers separated by spaces or commas.
+/// An empty answer or the end of input picks none.
+fn numbered_choices(prompt: &str, options: &[String]) -> Result<Vec<usize>> {
+    read_numbered_choices(prompt, options, std::io::stdin().lock(), std::io::stderr())
+}
+
+fn read_numbered_choices(
+    prompt: &str,
+    options: &[String],
+    mut input: impl BufRead,
+    mut output: impl Write,
+) -> Result<Vec<usize>> {
+    let width = options.len().to_string().len();
+    for (index, option) in options.iter().enumerate() {
+        writeln!(output, "{:>width$}) {option}", index + 1)?;
+    }
+    loop {
+        write!(
+            output,
+            "{prompt}, separated by spaces [1-{}]: ",
+            options.len()
+        )?;
+        output.flush()?;
+        let mut line = String::new();
+        if input.read_line(&mut line)? == 0 {
+            writeln!(output)?;
+            return Ok(Vec::new());
+        }
+        let picked: Option<Vec<usize>> = line
+            .split([' ', ','])
+            .filter(|word| !word.trim().is_empty())
+            .map(|word| match word.trim().parse::<usize>() {
+                Ok(number) if (1..=options.len()).contains(&number) => Some(number - 1),
+                _ => None,
+            })
+            .collect();
+        match picked {
+            Some(mut picked) => {
+                picked.sort_unstable();
+                picked.dedup();
+                return Ok(picked);
+            }
+            None => writeln!(output, "Enter numbers from 1 to {}", options.len())?,
+        }
+    }
+}
 
 pub struct ProfileSelector {
-    profiles: Vec<Profile>,
+    config: AwsConfig,
+    match_mode: MatchMode,
+    favorites: Vec<String>,
+    aliases: BTreeMap<String, String>,
+    page_size: usize,
+    display_format: Option<DisplayFormat>,
+    theme: Theme,
+    markers: Markers,
+    filter: String,
+    vim: bool,
 }
 
 impl ProfileSelector {
-    pub fn new(profiles: Vec<Profile>) -> Self {
-        Self { profiles }
+    pub fn new(config: AwsConfig) -> Self {
+        Self {
+            config,
+            match_mode: MatchMode::default(),
+            favorites: Vec::new(),
+            aliases: BTreeMap::new(),
+            page_size: DEFAULT_PAGE_SIZE,
+            display_format: None,
+            theme: Theme::default(),
+            markers: Markers::default(),
+            filter: String::new(),
+            vim: false,
+        }
+    }
+
+    /// j and k move the highlight instead of filtering.
+    pub fn with_vim(mut self, vim: bool) -> Self {
+        self.vim = vim;
+        self
+    }
+
+    pub fn with_match_mode(mut self, match_mode: MatchMode) -> Self {
+        self.match_mode = match_mode;
+        self
+    }
+
+    /// Pins these profiles to the top of the list with a star marker.
+    pub fn with_favorites(mut self, favorites: Vec<String>) -> Self {
+        self.favorites = favorites;
+        self
+    }
+
+    pub fn with_page_size(mut self, page_size: usize) -> Self {
+        self.page_size = page_size;
+        self
+    }
+
+    /// Renders each option with `display_format` instead of the default
+    /// layout.
+    pub fn with_display_format(mut self, display_format: Option<DisplayFormat>) -> Self {
+        self.display_format = display_format;
+        self
+    }
+
+    pub fn with_theme(mut self, theme: Theme) -> Self {
+        self.theme = theme;
+        self
+    }
+
+    /// Tags the default, active, and env-selected profiles and starts the
+    /// cursor on the active one.
+    pub fn with_markers(mut self, markers: Markers) -> Self {
+        self.markers = markers;
+        self
+    }
+
+    /// Shows these aliases (alias → profile) next to the profile names.
+    pub fn with_aliases(mut self, aliases: BTreeMap<String, String>) -> Self {
+        self.aliases = aliases;
+        self
+    }
+
+    /// Opens the prompt with `filter` already typed.
+    pub fn with_filter(mut self, filter: &str) -> Self {
+        self.filter = filter.to_string();
+        self
     }
 
     pub fn run(&mut self) -> Result<Option<String>> {
-        if self.profiles.is_empty() {
+        if self.config.profiles.is_empty() {
             return Ok(None);
         }
 
-        let options: Vec<String> = self.profiles.iter().map(format_profile_display).collect();
+        let labels = self.labels();
+        if !draws_prompts() {
+            let kept: Vec<usize> = (0..labels.len())
+                .filter(|&index| self.keeps(&self.config.profiles[index], &labels[index]))
+                .collect();
+            let lines: Vec<String> = kept.iter().map(|&index| labels[index].text()).collect();
+            return Ok(numbered_choice("Select AWS Profile", &lines)?
+                .map(|choice| self.config.profiles[kept[choice]].name.clone()));
+        }
+        let pinned = |profile: &Profile| self.favorites.contains(&profile.name);
 
-        let ans = Select::new("Select AWS Profile:", options)
-            .with_page_size(10)
-            .with_help_message("↑↓ to move, enter to select, type to filter")
-            .prompt();
+        // Filters match the plain text; the options carry the colors, laid
+        // out to fit the terminal.
+        let plain: Vec<String> = labels.iter().map(Label::plain).collect();
+        let shown: Vec<Label> = match self.display_format {
+            Some(_) => labels,
+            None => {
+                // Inquire's cursor, and the favorite marker.
+                let prefix = if self.favorites.is_empty() { 2 } else { 4 };
+                let columns = Columns::new(&labels, terminal_width().saturating_sub(prefix));
+                labels
+                    .iter()
+                    .map(|label| columns.fit(label, &[]).0)
+                    .collect()
+            }
+        };
+        let options: Vec<String> = self
+            .config
+            .profiles
+            .iter()
+            .zip(&shown)
+            .map(|(profile, label)| {
+                let display = self.theme.ansi(label);
+                match (pinned(profile), self.favorites.is_empty()) {
+                    (true, _) => {
+                        format!("{} {display}", paint(FAVORITE_MARKER, self.theme.marker()))
+                    }
+                    (false, false) => format!("  {display}"),
+                    (false, true) => display,
+                }
+            })
+            .collect();
+
+        let matcher = Matcher::new(self.match_mode);
+        let profiles = &self.config.profiles;
+        let annotations = &self.markers.annotations;
+        let scorer = |input: &str, _: &String, _: &str, index: usize| {
+            let query = Query::parse(input);
+            let profile = &profiles[index];
+            if !query.matches(profile, annotations) {
+                return None;
+            }
+            let score = matcher.score(&plain[index], &query.text)?;
+            Some(rank(score, pinned(profile), index, profiles.len()))
+        };
+
+        let starting_cursor = self
+            .markers
+            .active
+            .as_ref()
+            .and_then(|active| profiles.iter().position(|profile| profile.name == *active))
+            .unwrap_or(0);
+
+        let mut select = Select::new("Select AWS Profile:", options.clone())
+            .with_scorer(&scorer)
+            .with_starting_cursor(starting_cursor)
+            .with_page_size(self.page_size)
+            .with_vim_mode(self.vim)
+            .with_help_message(match self.vim {
+                true => {
+                    "j/k or ↑↓ to move, enter to select, type to filter (region:, account:, role:)"
+                }
+                false => "↑↓ to move, enter to select, type to filter (region:, account:, role:)",
+            });
+        if !self.filter.is_empty() {
+            select = select.with_starting_filter_input(&self.filter);
+        }
+        let ans = select.prompt();
 
         match ans {
             Ok(selected_display) => {
                 // Find the profile that matches the selected display string
-                let selected_profile = self
-                    .profiles
+                let selected_profile = options
                     .iter()
-                    .find(|profile| format_profile_display(profile) == selected_display)
-                    .map(|profile| profile.name.clone());
+                    .position(|option| *option == selected_display)
+                    .map(|index| self.config.profiles[index].name.clone());
 
                 Ok(selected_profile)
             }
@@ -39,22 +340,918 @@ impl ProfileSelector {
             Err(e) => Err(anyhow::anyhow!("Selection failed: {}", e)),
         }
     }
+
+    /// Each profile's name and line as plain text, in the order the selector
+    /// lists them, for an external picker such as fzf. Only the profiles the
+    /// starting filter keeps are included.
+    pub fn plain_lines(&mut self) -> Vec<(String, String)> {
+        let labels = self.labels();
+        self.config
+            .profiles
+            .iter()
+            .zip(labels)
+            .filter(|(profile, label)| self.keeps(profile, label))
+            .map(|(profile, label)| (profile.name.clone(), label.plain()))
+            .collect()
+    }
+
+    /// The profile the starting filter points at: the only one it keeps, or
+    /// of those the only one whose name starts with it, or else the only one
+    /// whose name it matches. The whole line decides what is kept, but `stat`
+    /// typed as a name means `static`, not every profile with `start` in its
+    /// URL.
+    pub fn sole_match(&mut self) -> Option<String> {
+        let query = Query::parse(&self.filter);
+        let prefix = query.text.to_lowercase();
+        let matcher = Matcher::new(self.match_mode);
+        let kept: Vec<String> = self
+            .plain_lines()
+            .into_iter()
+            .map(|(name, _)| name)
+            .collect();
+        let sole = |matches: &dyn Fn(&str) -> bool| {
+            let mut found = kept.iter().filter(|name| matches(name));
+            match (found.next(), found.next()) {
+                (Some(name), None) => Some(name.clone()),
+                _ => None,
+            }
+        };
+
+        sole(&|_| true)
+            .or_else(|| sole(&|name| name.to_lowercase().starts_with(&prefix)))
+            .or_else(|| sole(&|name| matcher.score(name, &query.text).is_some()))
+    }
+
+    /// Whether the starting filter keeps `profile`, as typing it into the
+    /// prompt would.
+    fn keeps(&self, profile: &Profile, label: &Label) -> bool {
+        let query = Query::parse(&self.filter);
+        query.matches(profile, &self.markers.annotations)
+            && Matcher::new(self.match_mode)
+                .score(&label.plain(), &query.text)
+                .is_some()
+    }
+
+    /// Pins the favorites, then labels every profile in order.
+    fn labels(&mut self) -> Vec<Label> {
+        pin_favorites(&mut self.config, &self.favorites);
+        self.config
+            .profiles
+            .iter()
+            .map(|profile| {
+                let aliases: Vec<&str> = self
+                    .aliases
+                    .iter()
+                    .filter(|(_, target)| **target == profile.name)
+                    .map(|(alias, _)| alias.as_str())
+                    .collect();
+                let mut label = profile_label(
+                    profile,
+                    &self.config,
+                    &aliases,
+                    self.markers.account_name(profile),
+                    self.markers.last_used(&profile.name),
+                    self.display_format.as_ref(),
+                );
+                self.markers.tag(&mut label, &profile.name);
+                label
+            })
+            .collect()
+    }
+}
+
+/// The profile a line picked from [`ProfileSelector::plain_lines`] stands
+/// for. Pickers may trim or re-space the line, so failing an exact match its
+/// first word is taken as the name.
+pub fn resolve_line<'a>(lines: &'a [(String, String)], selection: &str) -> Option<&'a str> {
+    let selection = selection.trim();
+    let by_line = lines.iter().find(|(_, line)| line.trim() == selection);
+    let by_name = || {
+        let first = selection.split_whitespace().next()?;
+        lines.iter().find(|(name, _)| name == first)
+    };
+    by_line.or_else(by_name).map(|(name, _)| name.as_str())
+}
+
+/// Shown before favorite profiles in both selectors.
+pub const FAVORITE_MARKER: &str = "★";
+
+/// Profiles singled out with a tag after their name in both selectors.
+#[derive(Debug, Clone, Default)]
+pub struct Markers {
+    /// The currently active profile; the cursor starts on it.
+    pub active: Option<String>,
+    /// `AWS_PROFILE` from the environment, set only when it differs from the
+    /// current-profile file.
+    pub env: Option<String>,
+    /// SSO token expiry per profile (see [`crate::sso::token_expiries`]).
+    pub token_expiries: HashMap<String, Option<DateTime<Utc>>>,
+    /// Results of `--check`, per profile.
+    pub checks: HashMap<String, CheckResult>,
+    /// Whether `--check` results are still to come as [`Update`]s.
+    pub checking: bool,
+    /// Account names (see [`crate::accounts`]), shown next to the bare ID,
+    /// and tags, shown as `#tag` chips.
+    pub annotations: Annotations,
+    /// Mask account IDs (`--redact`).
+    pub redact: bool,
+    /// Glob patterns of protected profiles, whose names are shown in the
+    /// warning style.
+    pub protected: Vec<String>,
+    /// Profiles whose `source_profile` chain is broken, and how.
+    pub broken_chains: HashMap<String, ChainProblem>,
+    /// Profiles defined more than once.
+    pub conflicts: Vec<ProfileConflict>,
+    /// Profiles with an `endpoint_url`, their own or from a `[services]`
+    /// block.
+    pub endpoint_overrides: HashSet<String>,
+    /// When each profile was last activated, for `{last_used}`.
+    pub last_used: HashMap<String, DateTime<Utc>>,
+}
+
+impl Markers {
+    /// Takes in what a background task found out.
+    pub fn apply(&mut self, update: Update) {
+        match update {
+            Update::Checked {
+                profile_name,
+                result,
+            } => {
+                self.checks.insert(profile_name, result);
+            }
+            Update::AccountAlias { account_id, alias } => {
+                self.annotations.account_names.insert(account_id, alias);
+            }
+            Update::TokenExpiries(expiries) => self.token_expiries.extend(expiries),
+        }
+    }
+
+    pub fn account_name(&self, profile: &Profile) -> Option<&str> {
+        self.annotations.account_name(profile)
+    }
+
+    pub fn last_used(&self, profile_name: &str) -> Option<DateTime<Utc>> {
+        self.last_used.get(profile_name).copied()
+    }
+
+    pub fn tag(&self, label: &mut Label, profile_name: &str) {
+        if profile_name == "default" {
+            label.add_tag(Tag::Default, "*");
+        }
+        if self.active.as_deref() == Some(profile_name) {
+            label.add_tag(Tag::Active, "(active)");
+        }
+        if self.env.as_deref() == Some(profile_name) {
+            label.add_tag(Tag::Env, "(env)");
+        }
+        match self.token_expiries.get(profile_name) {
+            Some(Some(expires_at)) if *expires_at > now() => label.add_tag(
+                Tag::Expiry,
+                format!("({})", describe_expiry(*expires_at, now())),
+            ),
+            Some(Some(_)) => label.add_tag(Tag::Expired, "(expired)"),
+            Some(None) => label.add_tag(Tag::Expired, "(not logged in)"),
+            None => {}
+        }
+        match self.broken_chains.get(profile_name) {
+            Some(ChainProblem::MissingSource) => {
+                label.add_tag(Tag::BrokenChain, "(⚠ missing source)");
+            }
+            Some(ChainProblem::Cycle) => label.add_tag(Tag::BrokenChain, "(⚠ source cycle)"),
+            None => {}
+        }
+        let conflicts = || {
+            self.conflicts
+                .iter()
+                .filter(|conflict| conflict.profile() == profile_name)
+        };
+        if conflicts().any(|conflict| matches!(conflict, ProfileConflict::Duplicate { .. })) {
+            label.add_tag(Tag::Conflict, "(⚠ duplicate)");
+        }
+        if conflicts().any(|conflict| matches!(conflict, ProfileConflict::Shadowed { .. })) {
+            label.add_tag(Tag::Conflict, "(⚠ shadowed)");
+        }
+        if self.endpoint_overrides.contains(profile_name) {
+            label.add_tag(Tag::EndpointOverride, "(endpoint override)");
+        }
+        if let Some(result) = self.checks.get(profile_name) {
+            tag_check(label, result);
+        }
+        for tag in self.annotations.tags_for(profile_name) {
+            label.add_tag(Tag::Chip, format!("#{tag}"));
+        }
+        label.protected = self
+            .protected
+            .iter()
+            .any(|pattern| glob_match(pattern, profile_name));
+        if self.redact {
+            for part in &mut label.parts {
+                part.text = redact(&part.text);
+            }
+        }
+    }
+}
+
+/// Adds the `✓`/`✗`/`timeout` tag for a credential check.
+pub fn tag_check(label: &mut Label, result: &CheckResult) {
+    let tag = if result.is_ok() {
+        Tag::CheckOk
+    } else {
+        Tag::CheckFailed
+    };
+    label.add_tag(tag, result.symbol());
+}
+
+pub const DEFAULT_PAGE_SIZE: usize = 10;
+
+/// Lines of the terminal the inline prompt needs besides its rows: the
+/// question, the help line, and the line the shell prompt returns to.
+const PROMPT_LINES: usize = 3;
+
+/// What shows the profiles to pick from: the built-in prompt, fzf, skim, or
+/// any other command, run with `sh -c`, that reads the lines on stdin and
+/// prints the chosen one.
+#[derive(Debug, Clone, PartialEq, Eq, Default, Deserialize)]
+#[serde(from = "String")]
+pub enum Picker {
+    #[default]
+    Builtin,
+    Fzf,
+    Skim,
+    Command(String),
+}
+
+impl Picker {
+    /// The command to run, or `None` for the built-in prompt.
+    pub fn command(&self) -> Option<&str> {
+        match self {
+            Self::Builtin => None,
+            Self::Fzf => Some("fzf --prompt='Select AWS Profile> ' --no-multi"),
+            Self::Skim => Some("sk --prompt='Select AWS Profile> ' --no-multi"),
+            Self::Command(command) => Some(command),
+        }
+    }
 }
 
-fn format_profile_display(profile: &Profile) -> String {
-    let mut parts = vec![profile.name.clone()];
+impl From<String> for Picker {
+    fn from(text: String) -> Self {
+        match text.trim() {
+            "" | "builtin" => Self::Builtin,
+            "fzf" => Self::Fzf,
+            "skim" | "sk" => Self::Skim,
+            command => Self::Command(command.to_string()),
+        }
+    }
+}
+
+impl std::str::FromStr for Picker {
+    type Err = std::convert::Infallible;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Ok(text.to_string().into())
+    }
+}
+
+/// Hands `lines` to an external picker's stdin and returns the line it
+/// printed. Its own interface is drawn on the terminal, as fzf and skim do.
+/// `None` when it exits 1 or 130, which is how they report no match and
+/// Esc/Ctrl-C, or prints nothing.
+pub fn external_pick(command: &str, lines: &[String]) -> Result<Option<String>> {
+    use std::process::{Command, Stdio};
+
+    let (shell, flag) = if cfg!(windows) {
+        ("cmd", "/C")
+    } else {
+        ("sh", "-c")
+    };
+    let mut child = Command::new(shell)
+        .args([flag, command])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .spawn()
+        .map_err(|e| anyhow::anyhow!("Failed to run picker `{command}`: {e}"))?;
+    let mut stdin = child.stdin.take().expect("stdin is piped");
+    // A picker that exits early closes its end; what it chose still counts.
+    let _ = stdin.write_all((lines.join("\n") + "\n").as_bytes());
+    drop(stdin);
+
+    let output = child.wait_with_output()?;
+    match output.status.code() {
+        Some(0) => {}
+        Some(1 | 130) => return Ok(None),
+        Some(127) => return Err(anyhow::anyhow!("Picker `{command}` isn't installed")),
+        code => {
+            let exit = code.map_or("was killed".to_string(), |code| {
+                format!("exited with {code}")
+            });
+            return Err(anyhow::anyhow!("Picker `{command}` {exit}"));
+        }
+    }
+    let chosen = String::from_utf8_lossy(&output.stdout);
+    Ok(chosen
+        .lines()
+        .next()
+        .filter(|line| !line.trim().is_empty())
+        .map(str::to_string))
+}
+
+/// How many rows the inline prompt shows at once: a number, or `auto` to
+/// fill the terminal's height.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(try_from = "PageSizeSetting")]
+pub enum PageSize {
+    Rows(usize),
+    Auto,
+}
+
+impl PageSize {
+    /// `auto` looks at the terminal each time, falling back to
+    /// [`DEFAULT_PAGE_SIZE`] when there isn't one.
+    pub fn rows(self) -> usize {
+        match self {
+            Self::Rows(rows) => rows,
+            Self::Auto => ratatui::crossterm::terminal::size()
+                .map_or(DEFAULT_PAGE_SIZE, |(_, height)| auto_rows(height)),
+        }
+    }
+}
+
+fn auto_rows(height: u16) -> usize {
+    usize::from(height).saturating_sub(PROMPT_LINES).max(1)
+}
+
+impl std::str::FromStr for PageSize {
+    type Err = anyhow::Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        if text.eq_ignore_ascii_case("auto") {
+            return Ok(Self::Auto);
+        }
+        match text.parse() {
+            Ok(0) => Err(anyhow::anyhow!("The page size must be at least 1")),
+            Ok(rows) => Ok(Self::Rows(rows)),
+            Err(_) => Err(anyhow::anyhow!(
+                "Invalid page size '{text}': expected a number or 'auto'"
+            )),
+        }
+    }
+}
+
+/// `page-size = 20` or `page-size = "auto"`.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum PageSizeSetting {
+    Rows(u64),
+    Text(String),
+}
+
+impl TryFrom<PageSizeSetting> for PageSize {
+    type Error = anyhow::Error;
+
+    fn try_from(setting: PageSizeSetting) -> Result<Self> {
+        match setting {
+            PageSizeSetting::Rows(rows) => rows.to_string().parse(),
+            PageSizeSetting::Text(text) => text.parse(),
+        }
+    }
+}
+
+/// How the selector and `list` order the profiles.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SortOrder {
+    /// Most recently activated first, then the rest in config order.
+    #[default]
+    Recent,
+    /// Often and recently activated first (see [`SelectorState::frecency`]),
+    /// then the rest in config order.
+    Frecency,
+    /// Alphabetically by profile name.
+    Name,
+    /// By account ID; profiles without one last.
+    Account,
+    /// By region; profiles without one last.
+    Region,
+    /// By SSO role name; profiles without one last.
+    Role,
+    /// As they appear in the config files.
+    Config,
+}
+
+/// Sorts the profiles by `order`, keeping the config order among equals,
+/// then turns the list around with `reverse`.
+pub fn sort_profiles(
+    config: &mut AwsConfig,
+    order: SortOrder,
+    reverse: bool,
+    state: &SelectorState,
+) {
+    let by = |key: fn(&Profile) -> Option<&str>| {
+        move |a: &Profile, b: &Profile| {
+            key(a)
+                .is_none()
+                .cmp(&key(b).is_none())
+                .then_with(|| key(a).cmp(&key(b)))
+        }
+    };
+    match order {
+        SortOrder::Recent => {
+            let recent = state.recent_profiles();
+            config.profiles.sort_by_key(|profile| {
+                recent
+                    .iter()
+                    .position(|name| *name == profile.name)
+                    .unwrap_or(usize::MAX)
+            });
+        }
+        SortOrder::Frecency => {
+            let scores = state.frecency(now());
+            let score = |profile: &Profile| scores.get(profile.name.as_str()).copied();
+            // Stable, so unused profiles keep the config order.
+            config
+                .profiles
+                .sort_by(|a, b| score(b).unwrap_or(0.0).total_cmp(&score(a).unwrap_or(0.0)));
+        }
+        SortOrder::Name => config.profiles.sort_by(|a, b| a.name.cmp(&b.name)),
+        SortOrder::Account => config.profiles.sort_by(by(Profile::get_account_id)),
+        SortOrder::Region => config.profiles.sort_by(by(Profile::get_region)),
+        SortOrder::Role => config.profiles.sort_by(by(Profile::get_role_name)),
+        SortOrder::Config => {}
+    }
+    if reverse {
+        config.profiles.reverse();
+    }
+}
+
+/// Moves favorite profiles to the front, otherwise keeping the config order.
+pub fn pin_favorites(config: &mut AwsConfig, favorites: &[String]) {
+    config
+        .profiles
+        .sort_by_key(|profile| !favorites.contains(&profile.name));
+}
+
+/// Folds a match score into a single key for inquire, which sorts options
+/// by score with an unstable sort: favorites come first, then better
+/// matches, then the list order.
+fn rank(score: i64, favorite: bool, index: usize, len: usize) -> i64 {
+    let len = len as i64;
+    let tie_break = len - 1 - index as i64;
+    ((favorite as i64) << 48) + score * len + tie_break
+}
+
+/// Profiles that share an AWS account, in config order.
+pub struct AccountGroup<'a> {
+    /// `None` collects profiles without an `sso_account_id`.
+    pub account_id: Option<&'a str>,
+    pub profiles: Vec<&'a Profile>,
+}
+
+impl AccountGroup<'_> {
+    /// Without a known account name, the group's profile names stand in for
+    /// one: their shared prefix (`acme-prod` for `acme-prod-Admin` and
+    /// `acme-prod-ReadOnly`), or the names themselves when nothing is shared.
+    fn alias(&self) -> String {
+        let names: Vec<&str> = self.profiles.iter().map(|p| p.name.as_str()).collect();
+        let prefix = names.iter().skip(1).fold(names[0], |prefix, name| {
+            let shared = prefix
+                .char_indices()
+                .zip(name.chars())
+                .take_while(|((_, a), b)| a == b)
+                .last()
+                .map_or(0, |((index, c), _)| index + c.len_utf8());
+            &prefix[..shared]
+        });
+        let prefix = prefix.trim_end_matches(['-', '_', '.', ' ']);
+
+        if prefix.is_empty() {
+            names.join(", ")
+        } else {
+            prefix.to_string()
+        }
+    }
+
+    fn label(&self, account_names: &AccountNames) -> String {
+        let roles = match self.profiles.len() {
+            1 => "1 profile".to_string(),
+            n => format!("{n} profiles"),
+        };
+        let name = self
+            .account_id
+            .and_then(|account_id| account_names.get(account_id))
+            .cloned()
+            .unwrap_or_else(|| self.alias());
+        match self.account_id {
+            Some(account_id) => format!("{account_id} {name} ({roles})"),
+            None => format!("(no account) {name} ({roles})"),
+        }
+    }
+}
+
+/// Groups profiles by account id, keeping accounts in the order they first
+/// appear and profiles without an account last.
+pub fn account_groups(config: &AwsConfig) -> Vec<AccountGroup<'_>> {
+    let mut groups: Vec<AccountGroup> = Vec::new();
+    for profile in &config.profiles {
+        let account_id = profile.get_account_id();
+        match groups
+            .iter_mut()
+            .find(|group| group.account_id == account_id)
+        {
+            Some(group) => group.profiles.push(profile),
+            None => groups.push(AccountGroup {
+                account_id,
+                profiles: vec![profile],
+            }),
+        }
+    }
+    groups.sort_by_key(|group| group.account_id.is_none());
+    groups
+}
+
+/// First stage of `--by-account`: picks an account and returns a copy of
+/// the config holding only that account's profiles. The prompt is skipped
+/// when there is only one account. `None` means the user cancelled.
+pub fn select_account(
+    config: &AwsConfig,
+    account_names: &AccountNames,
+    redact_ids: bool,
+    match_mode: MatchMode,
+    page_size: usize,
+) -> Result<Option<AwsConfig>> {
+    let groups = account_groups(config);
+    let chosen = match groups.len() {
+        0 => return Ok(None),
+        1 => &groups[0],
+        _ => {
+            let labels: Vec<String> = groups
+                .iter()
+                .map(|group| group.label(account_names))
+                .map(|label| if redact_ids { redact(&label) } else { label })
+                .collect();
+            if !draws_prompts() {
+                return Ok(
+                    numbered_choice("Select AWS Account", &labels)?.map(|index| {
+                        let mut narrowed = config.clone();
+                        narrowed
+                            .profiles
+                            .retain(|profile| profile.get_account_id() == groups[index].account_id);
+                        narrowed
+                    }),
+                );
+            }
+            let matcher = Matcher::new(match_mode);
+            let scorer =
+                |input: &str, _: &String, option: &str, _: usize| matcher.score(option, input);
+
+            let ans = Select::new("Select AWS Account:", labels.clone())
+                .with_scorer(&scorer)
+                .with_page_size(page_size)
+                .with_help_message("↑↓ to move, enter to select, type to filter")
+                .prompt();
+
+            match ans {
+                Ok(label) => {
+                    let index = labels
+                        .iter()
+                        .position(|candidate| *candidate == label)
+                        .expect("answer is one of the options");
+                    &groups[index]
+                }
+                Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => {
+                    return Ok(None);
+                }
+                Err(e) => return Err(anyhow::anyhow!("Selection failed: {}", e)),
+            }
+        }
+    };
+
+    let mut narrowed = config.clone();
+    narrowed
+        .profiles
+        .retain(|profile| profile.get_account_id() == chosen.account_id);
+    Ok(Some(narrowed))
+}
+
+/// Lets the user tick several profiles, for running a command under each.
+/// Esc picks none.
+pub fn select_profiles(config: &AwsConfig, page_size: usize) -> Result<Vec<String>> {
+    if !has_terminal() {
+        return Err(anyhow::anyhow!(
+            "Picking several profiles needs a terminal; pass a glob instead"
+        ));
+    }
+    let labels: Vec<String> = config
+        .profiles
+        .iter()
+        .map(|profile| profile_label(profile, config, &[], None, None, None).plain())
+        .collect();
+    if accessible() {
+        return Ok(numbered_choices("Select AWS Profiles", &labels)?
+            .into_iter()
+            .map(|index| config.profiles[index].name.clone())
+            .collect());
+    }
+
+    let ans = MultiSelect::new("Select AWS Profiles:", labels.clone())
+        .with_page_size(page_size)
+        .with_help_message("↑↓ to move, space to select, → all, ← none, type to filter")
+        .prompt();
+    match ans {
+        Ok(picked) => Ok(labels
+            .iter()
+            .zip(&config.profiles)
+            .filter(|(label, _)| picked.contains(label))
+            .map(|(_, profile)| profile.name.clone())
+            .collect()),
+        Err(InquireError::OperationCanceled | InquireError::OperationInterrupted) => Ok(Vec::new()),
+        Err(e) => Err(anyhow::anyhow!("Selection failed: {}", e)),
+    }
+}
+
+/// A profile's line in the selectors: `format` when one is configured,
+/// otherwise [`format_profile_display`], which leaves out `last_used`.
+pub fn profile_label(
+    profile: &Profile,
+    config: &AwsConfig,
+    aliases: &[&str],
+    account_name: Option<&str>,
+    last_used: Option<DateTime<Utc>>,
+    format: Option<&DisplayFormat>,
+) -> Label {
+    match format {
+        Some(format) => format.label(profile, config, aliases, account_name, last_used),
+        None => format_profile_display(profile, config, aliases, account_name),
+    }
+}
+
+/// The default layout: a credential type badge, the name, aliases, account
+/// (with its name when known), region, role, and SSO portal.
+pub fn format_profile_display(
+    profile: &Profile,
+    config: &AwsConfig,
+    aliases: &[&str],
+    account_name: Option<&str>,
+) -> Label {
+    let mut parts = vec![(Field::Name, profile.name.clone())];
+
+    if !aliases.is_empty() {
+        parts.push((Field::Aliases, format!("(alias: {})", aliases.join(", "))));
+    }
 
     if let Some(account_id) = profile.get_account_id() {
-        parts.push(format!("({account_id})"));
+        let account = match account_name {
+            Some(account_name) => format!("{account_name} ({account_id})"),
+            None => format!("({account_id})"),
+        };
+        parts.push((Field::AccountId, account));
     }
 
     if let Some(region) = profile.get_region() {
-        parts.push(format!("[{region}]"));
+        parts.push((Field::Region, format!("[{region}]")));
     }
 
     if let Some(role) = profile.get_role_name() {
-        parts.push(format!("{{{role}}}"));
+        parts.push((Field::Role, format!("{{{role}}}")));
+    }
+
+    let sso: Vec<&str> = [
+        config.sso_start_url_for(profile),
+        config.sso_region_for(profile),
+    ]
+    .into_iter()
+    .flatten()
+    .collect();
+
+    if !sso.is_empty() {
+        parts.push((Field::SsoStartUrl, format!("<{}>", sso.join(" "))));
+    }
+
+    let mut label = Label::default();
+    for (index, (field, text)) in parts.into_iter().enumerate() {
+        if index > 0 {
+            label.push(None, " ");
+        }
+        label.push(Some(field), text);
+    }
+    label.add_badge(profile.credential_type().map_or("", |kind| kind.badge()));
+    label
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::test_support;
+
+    /// An SSO profile of `account_id`, or one without an account.
+    fn profile(name: &str, account_id: Option<&str>) -> Profile {
+        let attributes: Vec<_> = account_id
+            .map(|id| ("sso_account_id", id))
+            .into_iter()
+            .collect();
+        test_support::profile(name, &attributes)
+    }
+
+    #[test]
+    fn test_rank_orders_favorites_then_score_then_position() {
+        let mut ranks = [
+            (rank(10, false, 0, 4), "best match"),
+            (rank(0, true, 1, 4), "favorite"),
+            (rank(5, false, 2, 4), "weaker match"),
+            (rank(5, false, 3, 4), "weaker match, later"),
+        ];
+        ranks.sort_by_key(|(rank, _)| std::cmp::Reverse(*rank));
+        let order: Vec<&str> = ranks.iter().map(|(_, label)| *label).collect();
+        assert_eq!(
+            order,
+            [
+                "favorite",
+                "best match",
+                "weaker match",
+                "weaker match, later"
+            ]
+        );
+    }
+
+    #[test]
+    fn test_sole_match() {
+        let config = AwsConfig {
+            profiles: vec![
+                profile("static", Some("111111111111")),
+                profile("dev", Some("222222222222")),
+                profile("default", Some("222222222222")),
+            ],
+            ..AwsConfig::default()
+        };
+        let sole_match = |filter: &str| {
+            ProfileSelector::new(config.clone())
+                .with_filter(filter)
+                .sole_match()
+        };
+
+        assert_eq!(sole_match("stat"), Some("static".to_string()));
+        assert_eq!(sole_match("1111"), Some("static".to_string()));
+        assert_eq!(sole_match("de"), None);
+        assert_eq!(sole_match("def"), Some("default".to_string()));
+        assert_eq!(sole_match("2222"), None);
+        assert_eq!(sole_match("nothing"), None);
+    }
+
+    #[test]
+    fn test_read_numbered_choice() {
+        let options = vec!["dev".to_string(), "prod".to_string()];
+        let choose = |input: &str| {
+            let mut output = Vec::new();
+            let choice =
+                read_numbered_choice("Select", &options, input.as_bytes(), &mut output).unwrap();
+            (choice, String::from_utf8(output).unwrap())
+        };
+
+        let (choice, output) = choose("3\nprod\n 2 \n");
+        assert_eq!(choice, Some(1));
+        assert!(output.starts_with("1) dev\n2) prod\nSelect [1-2]: "));
+        assert_eq!(output.matches("Enter a number from 1 to 2").count(), 2);
+        assert_eq!(choose("\n").0, None);
+        assert_eq!(choose("").0, None);
+
+        let mut output = Vec::new();
+        let picked =
+            read_numbered_choices("Select", &options, "1 x\n2, 1\n".as_bytes(), &mut output)
+                .unwrap();
+        assert_eq!(picked, vec![0, 1]);
+        assert!(
+            String::from_utf8(output)
+                .unwrap()
+                .contains("Enter numbers from 1 to 2")
+        );
+    }
+
+    #[test]
+    fn test_picker_setting() {
+        let picker = |text: &str| Picker::from(text.to_string());
+        assert_eq!(picker("builtin"), Picker::Builtin);
+        assert_eq!(picker("sk"), Picker::Skim);
+        assert!(picker("fzf").command().unwrap().starts_with("fzf "));
+        assert_eq!(
+            picker("peco --layout bottom-up").command(),
+            Some("peco --layout bottom-up")
+        );
+        assert_eq!(picker("builtin").command(), None);
     }
 
-    parts.join(" ")
+    #[test]
+    fn test_resolve_line() {
+        let lines = vec![
+            (
+                "dev".to_string(),
+                "dev (123456789012) [us-west-2]".to_string(),
+            ),
+            (
+                "dev-admin".to_string(),
+                "dev-admin [us-west-2]  ".to_string(),
+            ),
+        ];
+
+        assert_eq!(
+            resolve_line(&lines, "dev-admin [us-west-2]\n"),
+            Some("dev-admin")
+        );
+        assert_eq!(resolve_line(&lines, "dev  (123456789012)"), Some("dev"));
+        assert_eq!(resolve_line(&lines, "prod [eu-west-1]"), None);
+        assert_eq!(resolve_line(&lines, ""), None);
+    }
+
+    #[test]
+    fn test_page_size() {
+        assert_eq!("15".parse::<PageSize>().unwrap(), PageSize::Rows(15));
+        assert_eq!("auto".parse::<PageSize>().unwrap(), PageSize::Auto);
+        assert_eq!(PageSize::Rows(15).rows(), 15);
+        assert!("0".parse::<PageSize>().is_err());
+        assert!("tall".parse::<PageSize>().is_err());
+        assert_eq!(auto_rows(40), 37);
+        assert_eq!(auto_rows(2), 1);
+    }
+
+    #[test]
+    fn test_sort_profiles() {
+        let mut config = AwsConfig {
+            profiles: vec![
+                profile("sandbox", None),
+                profile("prod", None),
+                profile("dev", None),
+            ],
+            ..AwsConfig::default()
+        };
+        let names = |config: &AwsConfig| -> Vec<String> {
+            config.profiles.iter().map(|p| p.name.clone()).collect()
+        };
+
+        let mut state = SelectorState::default();
+        state.record_activation("dev", Utc::now());
+        sort_profiles(&mut config, SortOrder::Recent, false, &state);
+        assert_eq!(names(&config), ["dev", "sandbox", "prod"]);
+        sort_profiles(&mut config, SortOrder::Name, false, &state);
+        assert_eq!(names(&config), ["dev", "prod", "sandbox"]);
+        sort_profiles(&mut config, SortOrder::Name, true, &state);
+        assert_eq!(names(&config), ["sandbox", "prod", "dev"]);
+
+        config.profiles[0] = profile("sandbox", Some("222222222222"));
+        config.profiles[2] = profile("dev", Some("111111111111"));
+        sort_profiles(&mut config, SortOrder::Account, false, &state);
+        assert_eq!(names(&config), ["dev", "sandbox", "prod"]);
+    }
+
+    #[test]
+    fn test_account_groups_and_labels() {
+        let config = AwsConfig {
+            profiles: vec![
+                profile("default", None),
+                profile("acme-prod-Admin", Some("111111111111")),
+                profile("sandbox", Some("222222222222")),
+                profile("acme-prod-ReadOnly", Some("111111111111")),
+            ],
+            ..AwsConfig::default()
+        };
+
+        let account_names =
+            AccountNames::from([("222222222222".to_string(), "playground".to_string())]);
+        let labels: Vec<String> = account_groups(&config)
+            .iter()
+            .map(|group| group.label(&account_names))
+            .collect();
+        assert_eq!(
+            labels,
+            vec![
+                "111111111111 acme-prod (2 profiles)",
+                "222222222222 playground (1 profile)",
+                "(no account) default (1 profile)",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_default_layout_names_known_accounts() {
+        let markers = Markers {
+            annotations: Annotations {
+                account_names: AccountNames::from([(
+                    "222222222222".to_string(),
+                    "payments-prod".to_string(),
+                )]),
+                ..Annotations::default()
+            },
+            ..Markers::default()
+        };
+        let label = |profile: &Profile| {
+            let account_name = markers.account_name(profile);
+            format_profile_display(profile, &AwsConfig::default(), &[], account_name).plain()
+        };
+
+        assert_eq!(
+            label(&profile("sandbox", Some("222222222222"))),
+            "sandbox payments-prod (222222222222)"
+        );
+        assert_eq!(
+            label(&profile("acme", Some("111111111111"))),
+            "acme (111111111111)"
+        );
+    }
 }
@@ -0,0 +1,152 @@
+mod harness;
+
+use harness::{CLOCK, Harness};
+
+const CONFIG: &str = "\
+[profile dev]
+region = eu-west-1
+sso_session = corp
+sso_account_id = 111111111111
+sso_role_name = ReadOnly
+
+[profile dev-admin]
+region = eu-west-1
+sso_session = corp
+sso_account_id = 111111111111
+sso_role_name = Admin
+
+[profile prod]
+region = us-east-1
+sso_session = corp
+sso_account_id = 222222222222
+sso_role_name = Admin
+
+[sso-session corp]
+sso_start_url = https://corp.awsapps.com/start
+sso_region = eu-west-1
+";
+
+#[test]
+fn activate_writes_the_current_profile_in_the_state_dir() {
+    let harness = Harness::new("activate", CONFIG);
+
+    harness.run(&["activate", "dev"]).success();
+
+    let current_profile = harness.env.state_dir().join("current-profile");
+    assert!(current_profile.starts_with(harness.root()));
+    assert_eq!(harness.read(&current_profile), "dev");
+    assert_eq!(
+        harness.run(&["current"]).success().stdout.lines().next(),
+        Some("dev")
+    );
+}
+
+#[test]
+fn history_records_activations_on_the_clock() {
+    let harness = Harness::new("history", CONFIG);
+
+    harness.run(&["activate", "dev"]).success();
+    harness.run(&["activate", "prod"]).success();
+
+    let history: serde_json::Value = serde_json::from_str(
+        &harness
+            .run(&["history", "--output", "json"])
+            .success()
+            .stdout,
+    )
+    .unwrap();
+    let profiles: Vec<&str> = history
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|entry| entry["profile"].as_str().unwrap())
+        .collect();
+    assert_eq!(profiles, ["prod", "dev"]);
+    let day = &CLOCK[..10];
+    assert!(
+        history[0]["at"].as_str().unwrap().starts_with(day),
+        "{history}"
+    );
+
+    harness.run(&["activate", "-"]).success();
+    assert_eq!(
+        harness.read(&harness.env.state_dir().join("current-profile")),
+        "dev"
+    );
+
+    harness.run(&["history", "clear"]).success();
+    assert!(
+        harness
+            .run(&["history"])
+            .success()
+            .stdout
+            .contains("No activations recorded")
+    );
+}
+
+#[test]
+fn current_shell_mode_prints_commands_and_writes_nothing() {
+    let harness = Harness::new("current-shell", CONFIG);
+
+    let run = harness
+        .run(&["--shell", "bash", "-c", "activate", "prod"])
+        .success();
+    assert!(
+        run.stdout.contains("export AWS_PROFILE=\"prod\""),
+        "{run:#?}"
+    );
+    assert!(!harness.env.state_dir().join("current-profile").exists());
+}
+
+#[test]
+fn role_keeps_the_account() {
+    let harness = Harness::new("role", CONFIG);
+
+    harness.run(&["activate", "dev"]).success();
+    harness.run(&["role", "Admin"]).success();
+    assert_eq!(
+        harness.read(&harness.env.state_dir().join("current-profile")),
+        "dev-admin"
+    );
+    harness.run(&["role", "Nope"]).failure();
+}
+
+#[test]
+fn read_only_refuses_to_write() {
+    let mut harness = Harness::new("read-only", CONFIG);
+    harness.set_var("AWS_PROFILE_SELECTOR_READ_ONLY", "1");
+
+    let run = harness.run(&["activate", "dev"]).failure();
+    assert!(run.stderr.contains("Read-only mode"), "{run:#?}");
+    assert!(!harness.env.state_dir().join("current-profile").exists());
+}
+
+#[test]
+fn dry_run_moves_and_creates_nothing() {
+    let harness = Harness::new("dry-run", CONFIG);
+    let legacy = harness.root().join("home/.aws");
+    harness.write(&legacy.join("current-profile"), "prod");
+    harness.write(&legacy.join("profile-selector-state.lock"), "");
+    let state_dir = harness.env.state_dir();
+
+    let run = harness.run(&["--dry-run", "activate", "dev"]).success();
+    assert!(run.stderr.contains("Would move"), "{run:#?}");
+    assert_eq!(harness.read(&legacy.join("current-profile")), "prod");
+    assert!(legacy.join("profile-selector-state.lock").exists());
+    assert!(!state_dir.exists());
+}
+
+#[test]
+fn selections_that_pick_no_single_profile_exit_not_found() {
+    let harness = Harness::new("ambiguous", CONFIG);
+
+    for args in [
+        &["activate", "de", "--output", "json"][..],
+        &["activate", "--select-index", "9", "--output", "json"],
+        &["activate", "--select-match", "nope", "--output", "json"],
+    ] {
+        let run = harness.run(args).failure();
+        assert_eq!(run.status.code(), Some(3), "{run:#?}");
+        assert!(run.stderr.contains("\"kind\":\"not_found\""), "{run:#?}");
+    }
+}
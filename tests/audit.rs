@@ -0,0 +1,55 @@
+mod harness;
+
+use harness::{CLOCK, Harness};
+
+const CONFIG: &str = "\
+[profile dev]
+region = eu-west-1
+role_arn = arn:aws:iam::111111111111:role/Developer
+source_profile = base
+
+[profile base]
+region = eu-west-1
+";
+
+#[test]
+fn activations_are_logged_and_exported_when_enabled() {
+    let mut harness = Harness::new("audit", CONFIG);
+    harness.set_var("USER", "alice");
+
+    harness.run(&["activate", "dev"]).success();
+    assert!(!harness.env.state_dir().join("audit.jsonl").exists());
+
+    harness.settings("[audit]\nenabled = true\n");
+    harness.run(&["activate", "dev"]).success();
+    harness
+        .run(&["--shell", "bash", "-c", "activate", "base"])
+        .success();
+    harness.run(&["--dry-run", "activate", "dev"]).success();
+
+    let export = harness.run(&["audit", "export"]).success().stdout;
+    let entries: Vec<serde_json::Value> = export
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(entries.len(), 2, "{export}");
+    assert_eq!(entries[0]["event"], "activate");
+    assert_eq!(entries[0]["profile"], "dev");
+    assert_eq!(entries[0]["account_id"], "111111111111");
+    assert_eq!(entries[0]["role"], "Developer");
+    assert_eq!(entries[0]["user"], "alice");
+    assert_eq!(entries[0]["command"], "activate dev");
+    assert!(entries[0]["at"].as_str().unwrap().starts_with(&CLOCK[..10]));
+    assert_eq!(entries[1]["profile"], "base");
+
+    let base = harness
+        .run(&["audit", "export", "--profile", "b*", "--output", "json"])
+        .success()
+        .stdout;
+    let base: serde_json::Value = serde_json::from_str(&base).unwrap();
+    assert_eq!(base.as_array().unwrap().len(), 1);
+    let none = harness
+        .run(&["audit", "export", "--event", "console"])
+        .success();
+    assert_eq!(none.stdout, "");
+}
@@ -0,0 +1,61 @@
+mod harness;
+
+use harness::Harness;
+
+const CONFIG: &str = "\
+# Shared by the team.
+[profile base]
+region = eu-west-1
+aws_access_key_id = AKIABASE
+
+[profile deploy]
+role_arn = arn:aws:iam::111111111111:role/Deploy
+source_profile = base
+";
+
+#[test]
+fn rename_updates_references_and_keeps_comments() {
+    let harness = Harness::new("rename", CONFIG);
+
+    harness.run(&["rename", "base", "shared"]).success();
+
+    let config = harness.read(&harness.env.config_file());
+    assert!(
+        config.starts_with("# Shared by the team.\n[profile shared]\n"),
+        "{config}"
+    );
+    assert!(config.contains("source_profile = shared"), "{config}");
+}
+
+#[test]
+fn copy_overrides_settings() {
+    let harness = Harness::new("copy", CONFIG);
+
+    harness
+        .run(&["copy", "deploy", "deploy-us", "--set", "region=us-east-1"])
+        .success();
+
+    let list = harness.run(&["list", "--names"]).success().stdout;
+    assert_eq!(
+        list.lines().collect::<Vec<_>>(),
+        ["base", "deploy", "deploy-us"]
+    );
+    let config = harness.read(&harness.env.config_file());
+    assert!(config.contains("[profile deploy-us]\nrole_arn = arn:aws:iam::111111111111:role/Deploy\nsource_profile = base\nregion = us-east-1\n"), "{config}");
+}
+
+#[cfg(unix)]
+#[test]
+fn edit_saves_what_the_editor_wrote_and_backup_restore_undoes_it() {
+    let mut harness = Harness::new("edit", CONFIG);
+    harness.set_var("EDITOR", "sed -i s/eu-west-1/ap-south-1/");
+
+    harness.run(&["edit", "base", "--yes"]).success();
+    let config = harness.read(&harness.env.config_file());
+    assert!(config.contains("region = ap-south-1"), "{config}");
+
+    harness
+        .run(&["backup", "restore", "latest", "--yes"])
+        .success();
+    assert_eq!(harness.read(&harness.env.config_file()), CONFIG);
+}
@@ -0,0 +1,106 @@
+//! Runs the binary end to end in an [`Environment`] of its own, under the
+//! temporary directory, so nothing reads or writes the real `~/.aws`. The
+//! directory is removed when the [`Harness`] is dropped.
+
+#![allow(dead_code)]
+
+use aws_profile_selector::Environment;
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::process::{ExitStatus, Stdio};
+
+/// Every harness's clock starts here.
+pub const CLOCK: &str = "2026-01-05T09:00:00Z";
+
+pub struct Harness {
+    root: PathBuf,
+    pub env: Environment,
+}
+
+/// What a run of the binary printed and how it exited.
+#[derive(Debug)]
+pub struct Run {
+    pub status: ExitStatus,
+    pub stdout: String,
+    pub stderr: String,
+}
+
+impl Run {
+    /// The run, after checking it succeeded.
+    #[track_caller]
+    pub fn success(self) -> Self {
+        assert!(self.status.success(), "{self:#?}");
+        self
+    }
+
+    /// The run, after checking it failed.
+    #[track_caller]
+    pub fn failure(self) -> Self {
+        assert!(!self.status.success(), "{self:#?}");
+        self
+    }
+}
+
+impl Harness {
+    /// A fresh environment for the test `name`, with the AWS `config`
+    /// written.
+    pub fn new(name: &str, config: &str) -> Self {
+        let root = std::env::temp_dir().join(format!(
+            "aws-profile-selector-it-{name}-{}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&root);
+        let env = Environment::isolated(&root)
+            .unwrap()
+            .with_clock(CLOCK.parse().unwrap());
+        let harness = Self { root, env };
+        harness.write(&harness.env.config_file(), config);
+        harness
+    }
+
+    /// Sets `name` for every later run.
+    pub fn set_var(&mut self, name: &str, value: impl Into<String>) {
+        self.env = self.env.clone().with_var(name, value);
+    }
+
+    /// Writes the settings file.
+    pub fn settings(&self, contents: &str) {
+        self.write(&self.env.settings_file(), contents);
+    }
+
+    pub fn write(&self, path: &Path, contents: &str) {
+        fs::create_dir_all(path.parent().unwrap()).unwrap();
+        fs::write(path, contents).unwrap();
+    }
+
+    #[track_caller]
+    pub fn read(&self, path: &Path) -> String {
+        fs::read_to_string(path).unwrap_or_else(|e| panic!("{}: {e}", path.display()))
+    }
+
+    /// Runs the binary with `args` and no terminal.
+    pub fn run(&self, args: &[&str]) -> Run {
+        let output = self
+            .env
+            .command(env!("CARGO_BIN_EXE_aws-profile-selector"))
+            .args(args)
+            .stdin(Stdio::null())
+            .output()
+            .unwrap();
+        Run {
+            status: output.status,
+            stdout: String::from_utf8_lossy(&output.stdout).to_string(),
+            stderr: String::from_utf8_lossy(&output.stderr).to_string(),
+        }
+    }
+
+    pub fn root(&self) -> &Path {
+        &self.root
+    }
+}
+
+impl Drop for Harness {
+    fn drop(&mut self) {
+        let _ = fs::remove_dir_all(&self.root);
+    }
+}
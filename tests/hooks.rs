@@ -0,0 +1,49 @@
+mod harness;
+
+use harness::Harness;
+
+const CONFIG: &str = "\
+[profile dev]
+region = eu-west-1
+aws_access_key_id = AKIADEV
+
+[profile prod]
+region = us-east-1
+aws_access_key_id = AKIAPROD
+";
+
+#[test]
+fn hooks_see_the_profile_being_activated() {
+    let harness = Harness::new("hooks", CONFIG);
+    let log = harness.env.home().join("hooks.log");
+    harness.settings(&format!(
+        r#"
+[hooks]
+pre-activate = ['echo "pre $AWS_PROFILE ${{AWS_PROFILE_SELECTOR_PREVIOUS:--}}" >> {log}']
+post-activate = ['echo "post $AWS_PROFILE $AWS_REGION" >> {log}']
+"#,
+        log = log.display()
+    ));
+
+    harness.run(&["activate", "dev"]).success();
+    harness.run(&["activate", "prod"]).success();
+
+    assert_eq!(
+        harness.read(&log),
+        "pre dev -\npost dev eu-west-1\npre prod dev\npost prod us-east-1\n"
+    );
+}
+
+#[test]
+fn a_failing_pre_activate_hook_cancels_the_switch() {
+    let harness = Harness::new("hooks-cancel", CONFIG);
+    harness.settings("[hooks]\npre-activate = ['test \"$AWS_PROFILE\" != prod']\n");
+
+    harness.run(&["activate", "dev"]).success();
+    let run = harness.run(&["activate", "prod"]).failure();
+    assert!(run.stderr.contains("'prod' was not activated"), "{run:#?}");
+    assert_eq!(
+        harness.read(&harness.env.state_dir().join("current-profile")),
+        "dev"
+    );
+}